@@ -0,0 +1,55 @@
+-- Replace raw billing_cycle_days with a calendar-aware recurrence (unit + interval)
+ALTER TABLE subscriptions
+    ADD COLUMN billing_cycle_unit VARCHAR(10) NOT NULL DEFAULT 'month',
+    ADD COLUMN billing_cycle_interval INTEGER NOT NULL DEFAULT 1;
+
+-- Convert existing rows: 365-day multiples become years, 30-day multiples
+-- become months, 7-day multiples become weeks, anything else stays in days
+UPDATE subscriptions
+SET billing_cycle_unit = CASE
+        WHEN billing_cycle_days % 365 = 0 THEN 'year'
+        WHEN billing_cycle_days % 30 = 0 THEN 'month'
+        WHEN billing_cycle_days % 7 = 0 THEN 'week'
+        ELSE 'day'
+    END,
+    billing_cycle_interval = CASE
+        WHEN billing_cycle_days % 365 = 0 THEN billing_cycle_days / 365
+        WHEN billing_cycle_days % 30 = 0 THEN billing_cycle_days / 30
+        WHEN billing_cycle_days % 7 = 0 THEN billing_cycle_days / 7
+        ELSE billing_cycle_days
+    END
+WHERE billing_cycle_days > 0;
+
+-- Recalculate next_billing_date for subscriptions that already started, since
+-- the day-based calculation drifted away from the real charge date.
+-- PostgreSQL date + interval clamps month/year arithmetic to the end of month.
+UPDATE subscriptions s
+SET next_billing_date = next_dates.billing_date
+FROM (
+    SELECT sub.id, MIN(candidate.billing_date) AS billing_date
+    FROM subscriptions sub
+    CROSS JOIN LATERAL (
+        SELECT (sub.start_date + CASE sub.billing_cycle_unit
+                WHEN 'day' THEN make_interval(days => n * sub.billing_cycle_interval)
+                WHEN 'week' THEN make_interval(weeks => n * sub.billing_cycle_interval)
+                WHEN 'month' THEN make_interval(months => n * sub.billing_cycle_interval)
+                ELSE make_interval(years => n * sub.billing_cycle_interval)
+            END)::date AS billing_date
+        FROM generate_series(
+            1, (CURRENT_DATE - sub.start_date) / sub.billing_cycle_interval + 1
+        ) AS n
+    ) candidate
+    WHERE sub.start_date < CURRENT_DATE
+      AND candidate.billing_date > CURRENT_DATE
+    GROUP BY sub.id
+) next_dates
+WHERE s.id = next_dates.id;
+
+ALTER TABLE subscriptions
+    ALTER COLUMN billing_cycle_unit DROP DEFAULT,
+    ALTER COLUMN billing_cycle_interval DROP DEFAULT,
+    ADD CONSTRAINT subscriptions_billing_cycle_unit_check
+        CHECK (billing_cycle_unit IN ('day', 'week', 'month', 'year')),
+    ADD CONSTRAINT subscriptions_billing_cycle_interval_check
+        CHECK (billing_cycle_interval > 0),
+    DROP COLUMN billing_cycle_days;
@@ -0,0 +1,22 @@
+-- Create payment_records table
+CREATE TABLE IF NOT EXISTS payment_records (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    subscription_id UUID NOT NULL REFERENCES subscriptions(id) ON DELETE CASCADE,
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    amount DECIMAL(10, 2) NOT NULL CHECK (amount > 0),
+    currency VARCHAR(10) NOT NULL,
+    paid_date DATE NOT NULL,
+    cycle_start DATE NOT NULL,
+    cycle_end DATE NOT NULL,
+    note TEXT,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    CHECK (cycle_end > cycle_start)
+);
+
+-- Create indexes
+CREATE INDEX IF NOT EXISTS idx_payment_records_subscription_id ON payment_records(subscription_id);
+CREATE INDEX IF NOT EXISTS idx_payment_records_user_id_paid_date ON payment_records(user_id, paid_date);
+
+-- A billing cycle is paid at most once
+CREATE UNIQUE INDEX IF NOT EXISTS idx_payment_records_subscription_cycle
+    ON payment_records(subscription_id, cycle_start);
@@ -0,0 +1,16 @@
+-- Create subscription_events table
+CREATE TABLE IF NOT EXISTS subscription_events (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    subscription_id UUID NOT NULL REFERENCES subscriptions(id) ON DELETE CASCADE,
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    event_type VARCHAR(50) NOT NULL,
+    before JSONB,
+    after JSONB,
+    note TEXT,
+    -- clock_timestamp() keeps events written in one transaction in order
+    created_at TIMESTAMPTZ NOT NULL DEFAULT clock_timestamp()
+);
+
+-- Create indexes
+CREATE INDEX IF NOT EXISTS idx_subscription_events_subscription_id_created_at
+    ON subscription_events(subscription_id, created_at);
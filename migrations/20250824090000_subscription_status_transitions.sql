@@ -0,0 +1,27 @@
+-- Track the state behind pause/resume/cancel transitions
+--
+-- billing_anchor_date is the date billing cycles are counted from. It starts
+-- out as the start date and moves when a paused subscription is resumed.
+ALTER TABLE subscriptions
+    ADD COLUMN billing_anchor_date DATE,
+    ADD COLUMN paused_at DATE,
+    ADD COLUMN end_date DATE;
+
+UPDATE subscriptions SET billing_anchor_date = start_date;
+
+-- The exact dates of earlier status changes are unknown; the last update is
+-- the closest approximation
+UPDATE subscriptions
+SET paused_at = updated_at::date
+WHERE LOWER(status) = 'paused';
+
+UPDATE subscriptions
+SET end_date = updated_at::date
+WHERE LOWER(status) = 'cancelled';
+
+ALTER TABLE subscriptions
+    ALTER COLUMN billing_anchor_date SET NOT NULL,
+    ADD CONSTRAINT subscriptions_paused_at_check
+        CHECK ((LOWER(status) = 'paused') = (paused_at IS NOT NULL)),
+    ADD CONSTRAINT subscriptions_end_date_check
+        CHECK ((LOWER(status) = 'cancelled') = (end_date IS NOT NULL));
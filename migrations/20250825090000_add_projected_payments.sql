@@ -0,0 +1,4 @@
+-- Payments created by the renewal scheduler are projections of the expected
+-- charge, not confirmed payments
+ALTER TABLE payment_records
+    ADD COLUMN projected BOOLEAN NOT NULL DEFAULT FALSE;
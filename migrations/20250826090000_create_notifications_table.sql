@@ -0,0 +1,29 @@
+-- Optional end of a subscription's free trial
+ALTER TABLE subscriptions
+    ADD COLUMN trial_end_date DATE;
+
+-- Create notifications table
+CREATE TABLE IF NOT EXISTS notifications (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    subscription_id UUID REFERENCES subscriptions(id) ON DELETE CASCADE,
+    notification_type VARCHAR(30) NOT NULL
+        CHECK (notification_type IN ('renewal_upcoming', 'trial_ending', 'price_changed')),
+    title VARCHAR(255) NOT NULL,
+    message TEXT NOT NULL,
+    -- the date the notification is about, e.g. the renewal date
+    due_date DATE,
+    read_at TIMESTAMPTZ,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT clock_timestamp()
+);
+
+-- Create indexes
+CREATE INDEX IF NOT EXISTS idx_notifications_user_id_created_at
+    ON notifications(user_id, created_at DESC);
+CREATE INDEX IF NOT EXISTS idx_notifications_user_id_unread
+    ON notifications(user_id) WHERE read_at IS NULL;
+
+-- Reminders are generated repeatedly; only one per subscription and date
+CREATE UNIQUE INDEX IF NOT EXISTS idx_notifications_reminder
+    ON notifications(subscription_id, notification_type, due_date)
+    WHERE notification_type IN ('renewal_upcoming', 'trial_ending');
@@ -0,0 +1,31 @@
+-- Create notification_deliveries table, the outbox and log of notifications
+-- delivered outside the app, e.g. by email
+CREATE TABLE IF NOT EXISTS notification_deliveries (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    notification_id UUID NOT NULL REFERENCES notifications(id) ON DELETE CASCADE,
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    channel VARCHAR(20) NOT NULL CHECK (channel IN ('email')),
+    recipient VARCHAR(255) NOT NULL,
+    status VARCHAR(20) NOT NULL DEFAULT 'pending'
+        CHECK (status IN ('pending', 'sent', 'failed')),
+    attempts INTEGER NOT NULL DEFAULT 0,
+    last_error TEXT,
+    next_attempt_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    sent_at TIMESTAMPTZ,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    -- a notification is delivered at most once per channel
+    UNIQUE (notification_id, channel)
+);
+
+-- Create indexes
+CREATE INDEX IF NOT EXISTS idx_notification_deliveries_pending
+    ON notification_deliveries(next_attempt_at) WHERE status = 'pending';
+CREATE INDEX IF NOT EXISTS idx_notification_deliveries_user_id
+    ON notification_deliveries(user_id);
+
+-- Create trigger for updated_at
+CREATE TRIGGER update_notification_deliveries_updated_at
+BEFORE UPDATE ON notification_deliveries
+FOR EACH ROW
+EXECUTE FUNCTION update_updated_at_column();
@@ -0,0 +1,19 @@
+-- Create api_tokens table
+CREATE TABLE IF NOT EXISTS api_tokens (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    name VARCHAR(100) NOT NULL,
+    scope VARCHAR(20) NOT NULL CHECK (scope IN ('read', 'read_write')),
+    -- SHA-256 of the token; the token itself is only shown when it is created
+    token_hash VARCHAR(64) NOT NULL UNIQUE,
+    -- start of the token, so users can recognize it in the list
+    token_prefix VARCHAR(16) NOT NULL,
+    -- NULL for tokens that do not expire
+    expires_at TIMESTAMPTZ,
+    last_used_at TIMESTAMPTZ,
+    revoked_at TIMESTAMPTZ,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+-- Create indexes
+CREATE INDEX IF NOT EXISTS idx_api_tokens_user_id ON api_tokens(user_id);
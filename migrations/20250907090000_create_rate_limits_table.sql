@@ -0,0 +1,20 @@
+-- Create rate_limits table: counters of the postgres rate limit store
+--
+-- Times are seconds since the Unix epoch taken from the database clock.
+-- The table is unlogged: counters survive restarts but are emptied after a
+-- crash, which only forgets recent requests.
+CREATE UNLOGGED TABLE IF NOT EXISTS rate_limits (
+    key TEXT PRIMARY KEY,
+    -- Start of the current window (sliding window) or last refill (token bucket)
+    window_timestamp DOUBLE PRECISION NOT NULL DEFAULT 0,
+    -- Requests in the current window (sliding window) or tokens left (token bucket)
+    value DOUBLE PRECISION NOT NULL DEFAULT 0,
+    -- Requests in the previous window (sliding window)
+    previous DOUBLE PRECISION NOT NULL DEFAULT 0,
+    blocked_until DOUBLE PRECISION NOT NULL DEFAULT 0,
+    -- From then on the row limits nothing and is purged
+    expires_at DOUBLE PRECISION NOT NULL DEFAULT 0
+);
+
+-- Create indexes
+CREATE INDEX IF NOT EXISTS idx_rate_limits_expires_at ON rate_limits(expires_at);
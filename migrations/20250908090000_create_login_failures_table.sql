@@ -0,0 +1,11 @@
+-- Count failed logins to addresses without an account like those of
+-- accounts, so that a lock does not tell whether an account exists
+CREATE TABLE IF NOT EXISTS login_failures (
+    email VARCHAR(255) PRIMARY KEY,
+    failed_login_attempts INTEGER NOT NULL DEFAULT 0,
+    last_failed_login_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    locked_until TIMESTAMPTZ
+);
+
+-- Create indexes
+CREATE INDEX IF NOT EXISTS idx_login_failures_last_failed_login_at ON login_failures(last_failed_login_at);
@@ -0,0 +1,15 @@
+-- Whether the user chose a password; accounts created or taken over by an
+-- OIDC login have a random one until the user sets one through a reset
+ALTER TABLE users ADD COLUMN IF NOT EXISTS password_set BOOLEAN NOT NULL DEFAULT TRUE;
+
+-- Accounts created by an OIDC login got their identity along with the
+-- account; those that went through a password reset since may know theirs
+UPDATE users u
+SET password_set = FALSE
+FROM user_identities i
+WHERE i.user_id = u.id
+  AND i.created_at < u.created_at + INTERVAL '1 minute'
+  AND NOT EXISTS (
+      SELECT 1 FROM password_reset_tokens t
+      WHERE t.user_id = u.id AND t.used_at IS NOT NULL
+  );
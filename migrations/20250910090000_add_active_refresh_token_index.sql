@@ -0,0 +1,5 @@
+-- Every request with an access token checks that its session still has an
+-- unused, unrevoked refresh token
+CREATE INDEX IF NOT EXISTS idx_refresh_tokens_active_family_id
+    ON refresh_tokens(family_id)
+    WHERE used_at IS NULL AND revoked_at IS NULL;
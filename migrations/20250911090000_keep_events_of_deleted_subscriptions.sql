@@ -0,0 +1,9 @@
+-- Keep the history of a deleted subscription; its events lose the reference
+-- and the `deleted` event names the subscription instead
+ALTER TABLE subscription_events ALTER COLUMN subscription_id DROP NOT NULL;
+
+ALTER TABLE subscription_events
+    DROP CONSTRAINT IF EXISTS subscription_events_subscription_id_fkey;
+ALTER TABLE subscription_events
+    ADD CONSTRAINT subscription_events_subscription_id_fkey
+    FOREIGN KEY (subscription_id) REFERENCES subscriptions(id) ON DELETE SET NULL;
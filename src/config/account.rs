@@ -0,0 +1,46 @@
+use std::time::Duration;
+
+use super::Settings;
+
+/// Settings of account deletion and account notices
+#[derive(Debug, Clone)]
+pub struct AccountConfig {
+    /// Days a deleted account is kept before it is purged; 0 deletes at once
+    pub deletion_grace_days: u32,
+    /// Time between two runs purging accounts whose grace period ended
+    pub purge_interval: Duration,
+    /// Email users when they sign in from a device none of their sessions used
+    pub new_device_emails: bool,
+}
+
+impl Default for AccountConfig {
+    fn default() -> Self {
+        Self {
+            deletion_grace_days: 0,
+            purge_interval: Duration::from_secs(60 * 60), // hourly
+            new_device_emails: false,
+        }
+    }
+}
+
+impl AccountConfig {
+    /// Load the account settings
+    ///
+    /// - `ACCOUNT_DELETION_GRACE_DAYS`: days before a deleted account is purged, defaults to 0
+    /// - `ACCOUNT_PURGE_INTERVAL_SECS`: seconds between purge runs, defaults to an hour
+    /// - `ACCOUNT_NEW_DEVICE_EMAILS`: email users about logins from new devices, defaults to false
+    pub fn from_settings(settings: &Settings) -> Result<Self, String> {
+        let defaults = Self::default();
+        Ok(Self {
+            deletion_grace_days: settings
+                .parse("ACCOUNT_DELETION_GRACE_DAYS")?
+                .unwrap_or(defaults.deletion_grace_days),
+            purge_interval: settings
+                .secs("ACCOUNT_PURGE_INTERVAL_SECS")?
+                .unwrap_or(defaults.purge_interval),
+            new_device_emails: settings
+                .flag("ACCOUNT_NEW_DEVICE_EMAILS")?
+                .unwrap_or(defaults.new_device_emails),
+        })
+    }
+}
@@ -0,0 +1,197 @@
+use super::{
+    AccountConfig, Cli, CorsConfig, DatabaseConfig, JwtConfig, LoginLockoutConfig, MailConfig,
+    OidcConfig, ProxyConfig, RateLimitConfig, RenewalSchedulerConfig, ServerConfig, Settings,
+    WebhookConfig,
+};
+
+/// The complete configuration of the server
+///
+/// Built from the defaults of each section, overridden by a TOML file,
+/// environment variables and command line flags, in that order (see
+/// [`Settings`]). Every section is checked when it is loaded, so an invalid
+/// value stops the server at startup rather than when it is first used.
+#[derive(Debug, Clone)]
+pub struct AppConfig {
+    pub server: ServerConfig,
+    pub database: DatabaseConfig,
+    pub cors: CorsConfig,
+    pub jwt: JwtConfig,
+    pub lockout: LoginLockoutConfig,
+    pub account: AccountConfig,
+    pub mail: MailConfig,
+    /// Single sign-on, `None` when no provider is configured
+    pub oidc: Option<OidcConfig>,
+    pub webhook: WebhookConfig,
+    pub scheduler: RenewalSchedulerConfig,
+    pub rate_limit: RateLimitConfig,
+    pub proxy: ProxyConfig,
+}
+
+impl AppConfig {
+    /// Load the configuration from the sources named on the command line
+    pub fn load(cli: &Cli) -> Result<Self, Vec<String>> {
+        Self::from_settings(&Settings::load(cli)?)
+    }
+
+    /// Check and build every section, reporting the errors of all of them
+    pub fn from_settings(settings: &Settings) -> Result<Self, Vec<String>> {
+        let mut errors = Vec::new();
+
+        let server = check(ServerConfig::from_settings(settings), &mut errors);
+        let app_url = server
+            .as_ref()
+            .map(|server| server.app_url.clone())
+            .unwrap_or_else(|| ServerConfig::default().app_url);
+        let production = server.as_ref().is_some_and(|server| server.production);
+        let database = check(DatabaseConfig::from_settings(settings), &mut errors);
+        let cors = check(CorsConfig::from_settings(settings), &mut errors);
+        let jwt = check(JwtConfig::from_settings(settings, production), &mut errors);
+        let lockout = check(LoginLockoutConfig::from_settings(settings), &mut errors);
+        let account = check(AccountConfig::from_settings(settings), &mut errors);
+        let mail = check(MailConfig::from_settings(settings, &app_url), &mut errors);
+        let oidc = check(OidcConfig::from_settings(settings, &app_url), &mut errors);
+        let webhook = check(WebhookConfig::from_settings(settings), &mut errors);
+        let scheduler = check(RenewalSchedulerConfig::from_settings(settings), &mut errors);
+        let rate_limit = check(RateLimitConfig::from_settings(settings), &mut errors);
+        let proxy = check(ProxyConfig::from_settings(settings), &mut errors);
+
+        match (
+            server, database, cors, jwt, lockout, account, mail, oidc, webhook, scheduler,
+            rate_limit, proxy,
+        ) {
+            (
+                Some(server),
+                Some(database),
+                Some(cors),
+                Some(jwt),
+                Some(lockout),
+                Some(account),
+                Some(mail),
+                Some(oidc),
+                Some(webhook),
+                Some(scheduler),
+                Some(rate_limit),
+                Some(proxy),
+            ) => Ok(Self {
+                server,
+                database,
+                cors,
+                jwt,
+                lockout,
+                account,
+                mail,
+                oidc,
+                webhook,
+                scheduler,
+                rate_limit,
+                proxy,
+            }),
+            _ => Err(errors),
+        }
+    }
+}
+
+/// The value of a result, with its error collected
+fn check<T>(result: Result<T, String>, errors: &mut Vec<String>) -> Option<T> {
+    result.map_err(|e| errors.push(e)).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+    use std::path::Path;
+
+    fn load(toml: &str, env: &[(&str, &str)]) -> Result<AppConfig, Vec<String>> {
+        let env: HashMap<String, String> = env
+            .iter()
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect();
+        let settings = Settings::from_sources(
+            Some((Path::new("sub-pal.toml"), toml)),
+            &env,
+            &Cli::default(),
+        )?;
+        AppConfig::from_settings(&settings)
+    }
+
+    #[test]
+    fn test_defaults_apply_without_configuration() {
+        let config = load("", &[]).unwrap();
+        assert_eq!(config.server.port, 3000);
+        assert_eq!(config.server.app_url, "http://localhost:5173");
+        assert_eq!(config.database.max_connections, 5);
+        assert_eq!(config.cors.allowed_origins, ["http://localhost:5173"]);
+        assert!(config.jwt.keys.is_empty());
+        assert!(config.oidc.is_none());
+        assert!(config.scheduler.enabled);
+    }
+
+    #[test]
+    fn test_sections_are_read_from_the_file() {
+        let config = load(
+            r#"
+            [server]
+            port = 8080
+            environment = "production"
+            app_url = "https://subs.example.com/"
+
+            [database]
+            max_connections = 20
+
+            [cors]
+            allowed_origins = ["https://subs.example.com"]
+
+            [auth]
+            jwt_secret = "an HS256 secret from the configuration file"
+
+            [oidc]
+            issuer_url = "https://id.example.com"
+            client_id = "sub-pal"
+            scopes = ["email", "groups"]
+
+            [scheduler]
+            interval_secs = 3600
+            "#,
+            &[("RATE_LIMIT_STORE", "postgres")],
+        )
+        .unwrap();
+
+        assert_eq!(config.server.port, 8080);
+        assert!(config.server.production && config.jwt.production);
+        assert_eq!(config.mail.app_url, "https://subs.example.com");
+        assert_eq!(config.database.max_connections, 20);
+        assert_eq!(config.jwt.keys.len(), 1);
+        let oidc = config.oidc.unwrap();
+        assert_eq!(oidc.scopes, ["openid", "email", "groups"]);
+        assert_eq!(oidc.redirect_url, "https://subs.example.com/oidc/callback");
+        assert_eq!(config.scheduler.interval.as_secs(), 3600);
+        assert_eq!(
+            config.rate_limit.backend,
+            crate::config::rate_limit::RateLimitBackend::Postgres
+        );
+    }
+
+    #[test]
+    fn test_every_invalid_section_is_reported() {
+        let errors = load(
+            "[server]\nport = 70000\n[cors]\nallowed_origins = \"https://a.example/\"\n",
+            &[
+                ("RATE_LIMIT_STORE", "redis"),
+                ("MAIL_TRANSPORT", "pigeon"),
+                ("DATABASE_MIN_CONNECTIONS", "10"),
+            ],
+        )
+        .unwrap_err();
+
+        assert_eq!(errors.len(), 5, "{errors:?}");
+        assert!(errors[0].contains("server.port in sub-pal.toml"));
+        assert!(errors[1].contains("DATABASE_MIN_CONNECTIONS"));
+        assert!(errors[2].contains("cors.allowed_origins"));
+        assert!(errors[3].contains("MAIL_TRANSPORT"));
+        assert_eq!(
+            errors[4],
+            "REDIS_URL is required by environment variable RATE_LIMIT_STORE"
+        );
+    }
+}
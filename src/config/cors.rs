@@ -0,0 +1,70 @@
+use http::HeaderValue;
+
+use super::Settings;
+
+/// Settings of cross-origin requests from the web app
+#[derive(Debug, Clone)]
+pub struct CorsConfig {
+    /// Origins allowed to call the API with credentials, e.g. `https://app.example.com`
+    pub allowed_origins: Vec<HeaderValue>,
+}
+
+impl Default for CorsConfig {
+    fn default() -> Self {
+        Self {
+            allowed_origins: vec![HeaderValue::from_static("http://localhost:5173")],
+        }
+    }
+}
+
+impl CorsConfig {
+    /// Load the CORS settings
+    ///
+    /// - `ALLOWED_ORIGINS`: comma separated origins, defaults to the
+    ///   development server `http://localhost:5173`
+    pub fn from_settings(settings: &Settings) -> Result<Self, String> {
+        let origins = settings.list("ALLOWED_ORIGINS");
+        if origins.is_empty() {
+            return Ok(Self::default());
+        }
+
+        let allowed_origins = origins
+            .iter()
+            .map(|origin| {
+                check_origin(origin).map_err(|reason| {
+                    settings.invalid("ALLOWED_ORIGINS", format!("{origin} {reason}"))
+                })
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(Self { allowed_origins })
+    }
+}
+
+/// Check an origin is a scheme and host, as browsers send it
+fn check_origin(origin: &str) -> Result<HeaderValue, &'static str> {
+    let Some((_, host)) = origin
+        .split_once("://")
+        .filter(|(scheme, _)| matches!(*scheme, "http" | "https"))
+    else {
+        return Err("is not an http:// or https:// origin");
+    };
+    if host.is_empty() || host.contains('/') {
+        return Err("must not have a path, e.g. https://app.example.com");
+    }
+    HeaderValue::from_str(origin).map_err(|_| "is not a valid header value")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_origins_are_checked() {
+        assert!(check_origin("https://app.example.com").is_ok());
+        assert!(check_origin("http://localhost:5173").is_ok());
+        assert!(check_origin("https://app.example.com/").is_err());
+        assert!(check_origin("app.example.com").is_err());
+        assert!(check_origin("*").is_err());
+        assert!(check_origin("ftp://example.com").is_err());
+    }
+}
@@ -0,0 +1,123 @@
+use jsonwebtoken::Algorithm;
+use std::path::PathBuf;
+use std::str::FromStr;
+
+use super::Settings;
+
+/// Key id of the key made from `JWT_SECRET`
+pub const JWT_SECRET_KID: &str = "default";
+/// Shortest HMAC secret accepted in production, in bytes
+pub const MIN_JWT_SECRET_LEN: usize = 32;
+
+/// Where the material of a signing key comes from
+#[derive(Debug, Clone)]
+pub enum JwtKeySource {
+    /// Key material given directly: an HMAC secret or a PEM private key
+    Inline(String),
+    /// File with the HMAC secret, or the PEM private key of an asymmetric algorithm
+    File(PathBuf),
+}
+
+/// A key access tokens are signed or verified with
+#[derive(Debug, Clone)]
+pub struct JwtKeySpec {
+    /// Key id written to the `kid` header of tokens
+    pub kid: String,
+    pub algorithm: Algorithm,
+    pub source: JwtKeySource,
+}
+
+/// Settings of the keys of access tokens
+#[derive(Debug, Clone)]
+pub struct JwtConfig {
+    /// Configured keys; the first signs new tokens, all verify tokens, so an
+    /// old key is kept after a rotation until its tokens expired
+    pub keys: Vec<JwtKeySpec>,
+    /// Refuse to start without a real key (`APP_ENV=production`)
+    pub production: bool,
+}
+
+impl JwtConfig {
+    /// Load the key settings of a production deployment or not
+    ///
+    /// - `JWT_KEYS`: comma separated `kid:ALGORITHM:path` entries, e.g.
+    ///   `2025-10:EdDSA:/run/secrets/jwt.pem`; the first key signs
+    /// - `JWT_SECRET`: HS256 secret used when `JWT_KEYS` is not set, with key id `default`
+    ///
+    /// In production a missing or weak key is a startup error.
+    pub fn from_settings(settings: &Settings, production: bool) -> Result<Self, String> {
+        let entries = settings.list("JWT_KEYS");
+        let keys = if entries.is_empty() {
+            settings
+                .text("JWT_SECRET")
+                .map(|secret| JwtKeySpec {
+                    kid: JWT_SECRET_KID.to_string(),
+                    algorithm: Algorithm::HS256,
+                    source: JwtKeySource::Inline(secret),
+                })
+                .into_iter()
+                .collect()
+        } else {
+            entries
+                .iter()
+                .map(|entry| parse_key_spec(entry))
+                .collect::<Result<Vec<_>, _>>()
+                .map_err(|e| settings.invalid("JWT_KEYS", e))?
+        };
+
+        let mut kids: Vec<&str> = keys.iter().map(|key| key.kid.as_str()).collect();
+        kids.sort_unstable();
+        if let Some(pair) = kids.windows(2).find(|pair| pair[0] == pair[1]) {
+            return Err(format!("JWT key id {} is configured twice", pair[0]));
+        }
+
+        Ok(Self { keys, production })
+    }
+}
+
+/// Parse a `kid:ALGORITHM:path` entry of `JWT_KEYS`
+fn parse_key_spec(entry: &str) -> Result<JwtKeySpec, String> {
+    let mut parts = entry.splitn(3, ':');
+    let (Some(kid), Some(algorithm), Some(path)) = (parts.next(), parts.next(), parts.next())
+    else {
+        return Err(format!(
+            "invalid entry {entry}, expected kid:ALGORITHM:path"
+        ));
+    };
+    if kid.is_empty() || path.is_empty() {
+        return Err(format!(
+            "invalid entry {entry}, expected kid:ALGORITHM:path"
+        ));
+    }
+    let algorithm = Algorithm::from_str(algorithm)
+        .map_err(|_| format!("Unsupported JWT algorithm {algorithm} of key {kid}"))?;
+
+    Ok(JwtKeySpec {
+        kid: kid.to_string(),
+        algorithm,
+        source: JwtKeySource::File(PathBuf::from(path)),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_key_spec() {
+        let key = parse_key_spec("2025-10:EdDSA:/run/secrets/jwt:ed.pem").unwrap();
+        assert_eq!(key.kid, "2025-10");
+        assert_eq!(key.algorithm, Algorithm::EdDSA);
+        assert!(
+            matches!(key.source, JwtKeySource::File(path) if path.to_str() == Some("/run/secrets/jwt:ed.pem"))
+        );
+
+        assert_eq!(
+            parse_key_spec("old:RS256:keys/old.pem").unwrap().algorithm,
+            Algorithm::RS256
+        );
+        assert!(parse_key_spec("2025-10:/run/secrets/jwt.pem").is_err());
+        assert!(parse_key_spec(":RS256:key.pem").is_err());
+        assert!(parse_key_spec("a:XX256:key.pem").is_err());
+    }
+}
@@ -0,0 +1,99 @@
+use std::time::Duration;
+
+use super::Settings;
+use crate::utils::backoff::exponential_backoff;
+
+/// Settings of the lock put on an account after failed logins
+///
+/// Failed logins are counted per account, whatever address they come from.
+/// From `max_attempts` failures on, each failure locks the account for
+/// `base_lock`, doubled for every further failure up to `max_lock`.
+#[derive(Debug, Clone)]
+pub struct LoginLockoutConfig {
+    /// Failed logins before the account is locked; 0 disables the lockout
+    pub max_attempts: u32,
+    /// Lock after the `max_attempts`-th failure
+    pub base_lock: Duration,
+    /// Longest lock
+    pub max_lock: Duration,
+    /// Time without failures after which the count starts over
+    pub reset_after: Duration,
+}
+
+impl Default for LoginLockoutConfig {
+    fn default() -> Self {
+        Self {
+            max_attempts: 5,
+            base_lock: Duration::from_secs(60),
+            max_lock: Duration::from_secs(60 * 60),
+            reset_after: Duration::from_secs(24 * 60 * 60),
+        }
+    }
+}
+
+impl LoginLockoutConfig {
+    /// Load the lockout settings
+    ///
+    /// - `LOGIN_LOCKOUT_MAX_ATTEMPTS`: failures before the first lock, defaults to 5; 0 disables it
+    /// - `LOGIN_LOCKOUT_BASE_SECS`: seconds of the first lock, defaults to 60
+    /// - `LOGIN_LOCKOUT_MAX_SECS`: seconds of the longest lock, defaults to an hour
+    /// - `LOGIN_LOCKOUT_RESET_SECS`: seconds without failures before the count starts over, defaults to a day
+    pub fn from_settings(settings: &Settings) -> Result<Self, String> {
+        let defaults = Self::default();
+        let base_lock = settings
+            .secs("LOGIN_LOCKOUT_BASE_SECS")?
+            .unwrap_or(defaults.base_lock);
+        Ok(Self {
+            max_attempts: settings
+                .parse("LOGIN_LOCKOUT_MAX_ATTEMPTS")?
+                .unwrap_or(defaults.max_attempts),
+            base_lock,
+            max_lock: settings
+                .secs("LOGIN_LOCKOUT_MAX_SECS")?
+                .unwrap_or(defaults.max_lock)
+                .max(base_lock),
+            reset_after: settings
+                .secs("LOGIN_LOCKOUT_RESET_SECS")?
+                .unwrap_or(defaults.reset_after),
+        })
+    }
+
+    /// How long the account is locked after its `failures`-th failed login
+    /// in a row, `None` while it stays unlocked
+    pub fn lock_duration(&self, failures: u32) -> Option<Duration> {
+        if self.max_attempts == 0 || failures < self.max_attempts {
+            return None;
+        }
+        // The lock after the `max_attempts`-th failure is the first backoff step
+        let locks = i32::try_from(failures - self.max_attempts)
+            .unwrap_or(i32::MAX)
+            .saturating_add(1);
+        Some(exponential_backoff(self.base_lock, locks, self.max_lock))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lock_duration_doubles_up_to_the_maximum() {
+        let config = LoginLockoutConfig::default();
+        assert_eq!(config.lock_duration(0), None);
+        assert_eq!(config.lock_duration(4), None);
+        assert_eq!(config.lock_duration(5), Some(Duration::from_secs(60)));
+        assert_eq!(config.lock_duration(6), Some(Duration::from_secs(120)));
+        assert_eq!(config.lock_duration(7), Some(Duration::from_secs(240)));
+        assert_eq!(config.lock_duration(11), Some(Duration::from_secs(3600)));
+        assert_eq!(
+            config.lock_duration(u32::MAX),
+            Some(Duration::from_secs(3600))
+        );
+
+        let disabled = LoginLockoutConfig {
+            max_attempts: 0,
+            ..LoginLockoutConfig::default()
+        };
+        assert_eq!(disabled.lock_duration(100), None);
+    }
+}
@@ -0,0 +1,122 @@
+use std::time::Duration;
+
+use super::Settings;
+
+/// How outgoing email is delivered
+#[derive(Debug, Clone, PartialEq)]
+pub enum MailTransport {
+    /// No email is sent and no deliveries are queued
+    Disabled,
+    /// Emails are written to the log instead of being sent (development)
+    Log,
+    /// Emails are sent through an SMTP server
+    Smtp,
+}
+
+/// How the SMTP connection is secured
+#[derive(Debug, Clone, PartialEq)]
+pub enum SmtpSecurity {
+    /// Plain connection, e.g. for MailHog
+    None,
+    /// Upgrade a plain connection with STARTTLS
+    StartTls,
+    /// TLS from the start (SMTPS)
+    Tls,
+}
+
+/// Settings of outgoing email
+#[derive(Debug, Clone)]
+pub struct MailConfig {
+    pub transport: MailTransport,
+    pub smtp_host: String,
+    pub smtp_port: u16,
+    pub smtp_security: SmtpSecurity,
+    pub smtp_username: Option<String>,
+    pub smtp_password: Option<String>,
+    /// Sender address, e.g. `Sub-Pal <no-reply@example.com>`
+    pub from: String,
+    /// Base URL of the web app, used for links in emails
+    pub app_url: String,
+    /// Time between two runs of the delivery worker
+    pub delivery_interval: Duration,
+}
+
+impl Default for MailConfig {
+    fn default() -> Self {
+        Self {
+            transport: MailTransport::Disabled,
+            smtp_host: String::new(),
+            smtp_port: 587,
+            smtp_security: SmtpSecurity::StartTls,
+            smtp_username: None,
+            smtp_password: None,
+            from: "Sub-Pal <no-reply@localhost>".to_string(),
+            app_url: "http://localhost:5173".to_string(),
+            delivery_interval: Duration::from_secs(60),
+        }
+    }
+}
+
+impl MailConfig {
+    /// Load the email settings, with links to the web app at `app_url`
+    ///
+    /// - `MAIL_TRANSPORT`: `disabled`, `log` or `smtp`; defaults to `smtp` when
+    ///   `SMTP_HOST` is set and `disabled` otherwise
+    /// - `SMTP_HOST`, `SMTP_PORT` (default 587), `SMTP_SECURITY` (`none`,
+    ///   `starttls` or `tls`, default `starttls`), `SMTP_USERNAME`, `SMTP_PASSWORD`
+    /// - `MAIL_FROM`: sender address
+    /// - `MAIL_DELIVERY_INTERVAL_SECS`: seconds between delivery runs, default 60
+    pub fn from_settings(settings: &Settings, app_url: &str) -> Result<Self, String> {
+        let defaults = Self::default();
+        let smtp_host = settings.text("SMTP_HOST").unwrap_or_default();
+        let transport = match settings
+            .text("MAIL_TRANSPORT")
+            .unwrap_or_default()
+            .to_lowercase()
+            .as_str()
+        {
+            "smtp" => MailTransport::Smtp,
+            "log" => MailTransport::Log,
+            "disabled" => MailTransport::Disabled,
+            "" if !smtp_host.is_empty() => MailTransport::Smtp,
+            "" => MailTransport::Disabled,
+            _ => {
+                return Err(settings.invalid("MAIL_TRANSPORT", "expected disabled, log or smtp"));
+            }
+        };
+        if transport == MailTransport::Smtp && smtp_host.is_empty() {
+            return Err(format!(
+                "SMTP_HOST is required by {}",
+                settings.origin("MAIL_TRANSPORT")
+            ));
+        }
+
+        let smtp_security = match settings
+            .text("SMTP_SECURITY")
+            .unwrap_or_default()
+            .to_lowercase()
+            .as_str()
+        {
+            "none" => SmtpSecurity::None,
+            "tls" => SmtpSecurity::Tls,
+            "" | "starttls" => SmtpSecurity::StartTls,
+            _ => {
+                return Err(settings.invalid("SMTP_SECURITY", "expected none, starttls or tls"));
+            }
+        };
+
+        Ok(Self {
+            transport,
+            smtp_host,
+            smtp_port: settings.parse("SMTP_PORT")?.unwrap_or(defaults.smtp_port),
+            smtp_security,
+            smtp_username: settings.text("SMTP_USERNAME"),
+            smtp_password: settings.text("SMTP_PASSWORD"),
+            from: settings.text("MAIL_FROM").unwrap_or(defaults.from),
+            app_url: app_url.to_string(),
+            delivery_interval: settings
+                .secs("MAIL_DELIVERY_INTERVAL_SECS")?
+                .unwrap_or(defaults.delivery_interval),
+        })
+    }
+}
@@ -1,3 +1,29 @@
+pub mod account;
+pub mod app;
+pub mod cors;
 pub mod database;
+pub mod jwt;
+pub mod lockout;
+pub mod mail;
+pub mod oidc;
+pub mod proxy;
+pub mod rate_limit;
+pub mod scheduler;
+pub mod server;
+pub mod settings;
+pub mod webhook;
 
-pub use self::database::create_pool;
+pub use self::account::AccountConfig;
+pub use self::app::AppConfig;
+pub use self::cors::CorsConfig;
+pub use self::database::{DatabaseConfig, create_pool};
+pub use self::jwt::JwtConfig;
+pub use self::lockout::LoginLockoutConfig;
+pub use self::mail::MailConfig;
+pub use self::oidc::OidcConfig;
+pub use self::proxy::ProxyConfig;
+pub use self::rate_limit::RateLimitConfig;
+pub use self::scheduler::RenewalSchedulerConfig;
+pub use self::server::ServerConfig;
+pub use self::settings::{Cli, Settings};
+pub use self::webhook::WebhookConfig;
@@ -0,0 +1,75 @@
+use super::Settings;
+
+/// Settings of sign-in with an OpenID Connect identity provider
+#[derive(Debug, Clone)]
+pub struct OidcConfig {
+    /// Issuer URL; the provider metadata is discovered below it
+    pub issuer_url: String,
+    pub client_id: String,
+    /// Secret of a confidential client, `None` for a public client using PKCE only
+    pub client_secret: Option<String>,
+    /// Page of the web app the provider redirects back to
+    pub redirect_url: String,
+    /// Scopes requested from the provider, always including `openid`
+    pub scopes: Vec<String>,
+    /// Name of the provider shown on the login page
+    pub provider_name: String,
+    /// Whether users without an account get one on their first sign-in
+    pub allow_signup: bool,
+}
+
+impl OidcConfig {
+    /// Load the OIDC settings, `None` when
+    /// `OIDC_ISSUER_URL` is not set
+    ///
+    /// - `OIDC_ISSUER_URL`, `OIDC_CLIENT_ID` (required), `OIDC_CLIENT_SECRET`
+    /// - `OIDC_REDIRECT_URL`: defaults to `{app_url}/oidc/callback`
+    /// - `OIDC_SCOPES`: space separated, defaults to `openid email profile`
+    /// - `OIDC_PROVIDER_NAME`: defaults to `Single sign-on`
+    /// - `OIDC_ALLOW_SIGNUP`: create accounts for new users, defaults to true
+    pub fn from_settings(settings: &Settings, app_url: &str) -> Result<Option<Self>, String> {
+        let Some(issuer_url) = settings
+            .text("OIDC_ISSUER_URL")
+            .map(|url| url.trim_end_matches('/').to_string())
+        else {
+            return Ok(None);
+        };
+        if !issuer_url.starts_with("https://") && !issuer_url.starts_with("http://") {
+            return Err(settings.invalid("OIDC_ISSUER_URL", "expected an https:// URL"));
+        }
+        let client_id = settings.text("OIDC_CLIENT_ID").ok_or_else(|| {
+            format!(
+                "OIDC_CLIENT_ID is required by {}",
+                settings.origin("OIDC_ISSUER_URL")
+            )
+        })?;
+
+        // Scopes are space separated, or a list in the configuration file
+        let mut scopes: Vec<String> = match settings.text("OIDC_SCOPES") {
+            Some(_) => settings
+                .list("OIDC_SCOPES")
+                .iter()
+                .flat_map(|scopes| scopes.split_whitespace())
+                .map(str::to_string)
+                .collect(),
+            None => vec!["openid".into(), "email".into(), "profile".into()],
+        };
+        if !scopes.iter().any(|scope| scope == "openid") {
+            scopes.insert(0, "openid".to_string());
+        }
+
+        Ok(Some(Self {
+            issuer_url,
+            client_id,
+            client_secret: settings.text("OIDC_CLIENT_SECRET"),
+            redirect_url: settings
+                .text("OIDC_REDIRECT_URL")
+                .unwrap_or_else(|| format!("{app_url}/oidc/callback")),
+            scopes,
+            provider_name: settings
+                .text("OIDC_PROVIDER_NAME")
+                .unwrap_or_else(|| "Single sign-on".to_string()),
+            allow_signup: settings.flag("OIDC_ALLOW_SIGNUP")?.unwrap_or(true),
+        }))
+    }
+}
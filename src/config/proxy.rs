@@ -0,0 +1,102 @@
+use ipnet::IpNet;
+use std::net::IpAddr;
+
+use super::Settings;
+
+/// Header trusted proxies pass the client address in
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ClientIpHeader {
+    /// `X-Forwarded-For`, a list each proxy appends the address it saw to
+    XForwardedFor,
+    /// RFC 7239 `Forwarded`, the standard form of `X-Forwarded-For`
+    Forwarded,
+    /// `X-Real-IP`, the one address the last proxy saw
+    XRealIp,
+}
+
+/// Settings of the reverse proxies in front of the server
+#[derive(Debug, Clone)]
+pub struct ProxyConfig {
+    /// Networks of the proxies whose forwarding headers are believed; empty
+    /// to always use the address of the connection
+    pub trusted_proxies: Vec<IpNet>,
+    pub client_ip_header: ClientIpHeader,
+}
+
+impl Default for ProxyConfig {
+    fn default() -> Self {
+        Self {
+            trusted_proxies: Vec::new(),
+            client_ip_header: ClientIpHeader::XForwardedFor,
+        }
+    }
+}
+
+impl ProxyConfig {
+    /// Load the proxy settings
+    ///
+    /// - `TRUSTED_PROXIES`: comma separated networks in CIDR notation or
+    ///   single addresses, e.g. `10.0.0.0/8,192.168.1.5`; empty by default
+    /// - `CLIENT_IP_HEADER`: `x-forwarded-for` (default), `forwarded` or `x-real-ip`
+    pub fn from_settings(settings: &Settings) -> Result<Self, String> {
+        let trusted_proxies = settings
+            .list("TRUSTED_PROXIES")
+            .iter()
+            .map(|network| parse_network(network))
+            .collect::<Result<_, _>>()
+            .map_err(|e| settings.invalid("TRUSTED_PROXIES", e))?;
+
+        let client_ip_header = match settings
+            .text("CLIENT_IP_HEADER")
+            .unwrap_or_default()
+            .to_lowercase()
+            .as_str()
+        {
+            "" | "x-forwarded-for" => ClientIpHeader::XForwardedFor,
+            "forwarded" => ClientIpHeader::Forwarded,
+            "x-real-ip" => ClientIpHeader::XRealIp,
+            _ => {
+                return Err(settings.invalid(
+                    "CLIENT_IP_HEADER",
+                    "expected x-forwarded-for, forwarded or x-real-ip",
+                ));
+            }
+        };
+
+        Ok(Self {
+            trusted_proxies,
+            client_ip_header,
+        })
+    }
+}
+
+/// Parse a network in CIDR notation or a single address
+pub(super) fn parse_network(network: &str) -> Result<IpNet, String> {
+    network
+        .parse::<IpNet>()
+        .or_else(|_| network.parse::<IpAddr>().map(IpNet::from))
+        .map_err(|_| format!("{network} is not a network or address"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_networks_and_single_addresses_are_parsed() {
+        assert_eq!(
+            parse_network("10.0.0.0/8").unwrap(),
+            "10.0.0.0/8".parse::<IpNet>().unwrap()
+        );
+        assert_eq!(
+            parse_network("192.168.1.5").unwrap(),
+            "192.168.1.5/32".parse::<IpNet>().unwrap()
+        );
+        assert_eq!(
+            parse_network("fd00::1").unwrap(),
+            "fd00::1/128".parse::<IpNet>().unwrap()
+        );
+        assert!(parse_network("10.0.0.0/33").is_err());
+        assert!(parse_network("proxy.local").is_err());
+    }
+}
@@ -0,0 +1,404 @@
+use http::Method;
+use serde::Deserialize;
+use std::collections::HashSet;
+use std::str::FromStr;
+use std::time::Duration;
+
+use super::Settings;
+
+/// The built-in rate limit policies
+const DEFAULT_POLICIES: &str = include_str!("../../config/rate_limits.toml");
+
+/// Where rate limit counters are kept
+#[derive(Debug, Clone, PartialEq)]
+pub enum RateLimitBackend {
+    /// In this process; limits reset on restart and are not shared between instances
+    Memory,
+    /// In the `rate_limits` table of the application database
+    Postgres,
+    /// In a Redis server
+    Redis,
+}
+
+/// How requests are counted against a limit
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RateLimitAlgorithm {
+    /// A bucket of `limit` tokens refilled evenly over the window; allows
+    /// short bursts up to the full limit
+    TokenBucket,
+    /// The requests of the last window, estimated from the counts of the
+    /// current and the previous fixed window
+    SlidingWindow,
+}
+
+impl RateLimitAlgorithm {
+    /// Parse an algorithm name as used in configuration
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_lowercase().replace('-', "_").as_str() {
+            "token_bucket" => Some(Self::TokenBucket),
+            "sliding_window" => Some(Self::SlidingWindow),
+            _ => None,
+        }
+    }
+}
+
+/// What the requests counted together under a policy have in common
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RateLimitKey {
+    /// The address of the client
+    #[default]
+    Ip,
+    /// The authenticated user, whatever token they use
+    User,
+    /// The personal API token used
+    ApiToken,
+}
+
+/// One segment of a route pattern
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum PatternSegment {
+    Literal(String),
+    /// `*` or `{name}`: any one segment
+    Any,
+    /// A trailing `**`: any number of segments, including none
+    Rest,
+}
+
+/// A `[METHOD ]/path` pattern matching requests
+///
+/// `*` and `{name}` match one path segment and a trailing `**` matches any
+/// number of segments, so `POST /api/v1/users/{id}/**` matches a POST to
+/// `/api/v1/users/42` and to `/api/v1/users/42/tokens/7`.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(try_from = "String")]
+pub struct RoutePattern {
+    /// Method of matching requests, `None` for any method
+    method: Option<Method>,
+    segments: Vec<PatternSegment>,
+}
+
+impl RoutePattern {
+    /// Whether a request with the given method and path matches the pattern
+    pub fn matches(&self, method: &Method, path: &str) -> bool {
+        if self
+            .method
+            .as_ref()
+            .is_some_and(|expected| expected != method)
+        {
+            return false;
+        }
+
+        let mut parts = path.split('/').filter(|part| !part.is_empty());
+        for segment in &self.segments {
+            match segment {
+                PatternSegment::Rest => return true,
+                PatternSegment::Any => {
+                    if parts.next().is_none() {
+                        return false;
+                    }
+                }
+                PatternSegment::Literal(literal) => {
+                    if parts.next() != Some(literal.as_str()) {
+                        return false;
+                    }
+                }
+            }
+        }
+        parts.next().is_none()
+    }
+}
+
+impl FromStr for RoutePattern {
+    type Err = String;
+
+    fn from_str(pattern: &str) -> Result<Self, Self::Err> {
+        let (method, path) = match pattern.trim().split_once(char::is_whitespace) {
+            Some((method, path)) => (
+                Some(
+                    Method::from_bytes(method.to_uppercase().as_bytes())
+                        .map_err(|_| format!("Invalid method in route pattern: {pattern}"))?,
+                ),
+                path.trim(),
+            ),
+            None => (None, pattern.trim()),
+        };
+        if !path.starts_with('/') {
+            return Err(format!("Route pattern must start with /: {pattern}"));
+        }
+
+        let parts: Vec<&str> = path.split('/').filter(|part| !part.is_empty()).collect();
+        let segments = parts
+            .iter()
+            .enumerate()
+            .map(|(index, part)| match *part {
+                "**" if index + 1 == parts.len() => Ok(PatternSegment::Rest),
+                "**" => Err(format!("** must end the route pattern: {pattern}")),
+                "*" => Ok(PatternSegment::Any),
+                part if part.starts_with('{') && part.ends_with('}') => Ok(PatternSegment::Any),
+                part => Ok(PatternSegment::Literal(part.to_string())),
+            })
+            .collect::<Result<_, _>>()?;
+
+        Ok(Self { method, segments })
+    }
+}
+
+impl TryFrom<String> for RoutePattern {
+    type Error = String;
+
+    fn try_from(pattern: String) -> Result<Self, Self::Error> {
+        pattern.parse()
+    }
+}
+
+/// A limit on the requests to some routes
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RateLimitPolicy {
+    /// Names the policy in errors and keeps its counters apart from other policies
+    pub name: String,
+    pub routes: Vec<RoutePattern>,
+    #[serde(default)]
+    pub key: RateLimitKey,
+    /// Algorithm of the policy, `None` for the configured default
+    #[serde(default)]
+    pub algorithm: Option<RateLimitAlgorithm>,
+    /// Requests allowed per window
+    pub limit: u32,
+    pub window_secs: u64,
+    /// Seconds a key is refused after exceeding the limit
+    #[serde(default)]
+    pub block_secs: Option<u64>,
+}
+
+/// Rate limit policies as written in a policy file
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RateLimitPolicies {
+    /// Routes that are never limited
+    #[serde(default)]
+    pub exempt: Vec<RoutePattern>,
+    #[serde(default)]
+    pub policies: Vec<RateLimitPolicy>,
+}
+
+impl RateLimitPolicies {
+    /// Parse and check policies in TOML
+    pub fn parse(toml: &str) -> Result<Self, String> {
+        let policies: Self = toml::from_str(toml).map_err(|e| e.to_string())?;
+
+        let mut names = HashSet::new();
+        for policy in &policies.policies {
+            if policy.name.trim().is_empty() {
+                return Err("Rate limit policies need a name".to_string());
+            }
+            if !names.insert(policy.name.as_str()) {
+                return Err(format!("Duplicate rate limit policy: {}", policy.name));
+            }
+            if policy.routes.is_empty() {
+                return Err(format!("Rate limit policy {} has no routes", policy.name));
+            }
+            if policy.limit == 0 || policy.window_secs == 0 {
+                return Err(format!(
+                    "Rate limit policy {} needs a positive limit and window",
+                    policy.name
+                ));
+            }
+        }
+
+        Ok(policies)
+    }
+}
+
+impl Default for RateLimitPolicies {
+    fn default() -> Self {
+        Self::parse(DEFAULT_POLICIES).expect("built-in rate limit policies are valid")
+    }
+}
+
+/// Settings of rate limiting
+#[derive(Debug, Clone)]
+pub struct RateLimitConfig {
+    pub backend: RateLimitBackend,
+    /// Algorithm of the policies that do not name one
+    pub algorithm: RateLimitAlgorithm,
+    /// Connection URL of the Redis server, required by the Redis backend
+    pub redis_url: Option<String>,
+    /// Time between two purges of counters that no longer limit anything
+    pub cleanup_interval: Duration,
+    pub policies: RateLimitPolicies,
+}
+
+impl Default for RateLimitConfig {
+    fn default() -> Self {
+        Self {
+            backend: RateLimitBackend::Memory,
+            algorithm: RateLimitAlgorithm::SlidingWindow,
+            redis_url: None,
+            cleanup_interval: Duration::from_secs(300),
+            policies: RateLimitPolicies::default(),
+        }
+    }
+}
+
+impl RateLimitConfig {
+    /// Load the rate limit settings
+    ///
+    /// - `RATE_LIMIT_STORE`: `memory`, `postgres` or `redis`, defaults to `memory`
+    /// - `RATE_LIMIT_ALGORITHM`: `sliding_window` or `token_bucket`, defaults to `sliding_window`
+    /// - `REDIS_URL`: e.g. `redis://localhost:6379`, required by the `redis` store
+    /// - `RATE_LIMIT_CLEANUP_INTERVAL_SECS`: seconds between purges, defaults to 300
+    /// - `RATE_LIMIT_POLICIES_FILE`: TOML file replacing the built-in policies
+    ///   of `config/rate_limits.toml`
+    pub fn from_settings(settings: &Settings) -> Result<Self, String> {
+        let defaults = Self::default();
+
+        let backend = match settings
+            .text("RATE_LIMIT_STORE")
+            .unwrap_or_default()
+            .to_lowercase()
+            .as_str()
+        {
+            "" | "memory" => RateLimitBackend::Memory,
+            "postgres" => RateLimitBackend::Postgres,
+            "redis" => RateLimitBackend::Redis,
+            _ => {
+                return Err(
+                    settings.invalid("RATE_LIMIT_STORE", "expected memory, postgres or redis")
+                );
+            }
+        };
+        let algorithm = match settings.text("RATE_LIMIT_ALGORITHM") {
+            Some(value) => RateLimitAlgorithm::parse(&value).ok_or_else(|| {
+                settings.invalid(
+                    "RATE_LIMIT_ALGORITHM",
+                    "expected sliding_window or token_bucket",
+                )
+            })?,
+            None => defaults.algorithm,
+        };
+        let redis_url = settings.text("REDIS_URL");
+        if backend == RateLimitBackend::Redis && redis_url.is_none() {
+            return Err(format!(
+                "REDIS_URL is required by {}",
+                settings.origin("RATE_LIMIT_STORE")
+            ));
+        }
+
+        let policies = match settings.text("RATE_LIMIT_POLICIES_FILE") {
+            Some(path) => {
+                let toml = std::fs::read_to_string(&path)
+                    .map_err(|e| settings.invalid("RATE_LIMIT_POLICIES_FILE", e))?;
+                RateLimitPolicies::parse(&toml)
+                    .map_err(|e| settings.invalid("RATE_LIMIT_POLICIES_FILE", e))?
+            }
+            None => defaults.policies,
+        };
+
+        Ok(Self {
+            backend,
+            algorithm,
+            redis_url,
+            cleanup_interval: settings
+                .secs("RATE_LIMIT_CLEANUP_INTERVAL_SECS")?
+                .unwrap_or(defaults.cleanup_interval),
+            policies,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pattern(pattern: &str) -> RoutePattern {
+        pattern.parse().unwrap()
+    }
+
+    #[test]
+    fn test_route_patterns_match_method_and_segments() {
+        let exact = pattern("POST /api/v1/auth/login");
+        assert!(exact.matches(&Method::POST, "/api/v1/auth/login"));
+        assert!(exact.matches(&Method::POST, "/api/v1/auth/login/"));
+        assert!(!exact.matches(&Method::GET, "/api/v1/auth/login"));
+        assert!(!exact.matches(&Method::POST, "/api/v1/auth/login/2fa"));
+
+        let any_method = pattern("/api/v1/users/{id}/tokens/*");
+        assert!(any_method.matches(&Method::DELETE, "/api/v1/users/me/tokens/7"));
+        assert!(!any_method.matches(&Method::DELETE, "/api/v1/users/me/tokens"));
+
+        let rest = pattern("get /api/v1/health/**");
+        assert!(rest.matches(&Method::GET, "/api/v1/health"));
+        assert!(rest.matches(&Method::GET, "/api/v1/health/live"));
+        assert!(!rest.matches(&Method::GET, "/api/v1/healthy"));
+        assert!(pattern("/**").matches(&Method::PATCH, "/"));
+
+        assert!("api/v1".parse::<RoutePattern>().is_err());
+        assert!("/api/**/users".parse::<RoutePattern>().is_err());
+        assert!("P(ST /api".parse::<RoutePattern>().is_err());
+    }
+
+    #[test]
+    fn test_policies_are_checked() {
+        let defaults = RateLimitPolicies::default();
+        assert_eq!(defaults.policies.len(), 2);
+        assert!(
+            defaults
+                .exempt
+                .iter()
+                .any(|route| route.matches(&Method::GET, "/api/v1/health/ready"))
+        );
+
+        let policies = RateLimitPolicies::parse(
+            r#"
+            [[policies]]
+            name = "tokens"
+            routes = ["/api/v1/subscriptions/**"]
+            key = "api_token"
+            algorithm = "token_bucket"
+            limit = 100
+            window_secs = 3600
+            "#,
+        )
+        .unwrap();
+        assert!(policies.exempt.is_empty());
+        assert_eq!(policies.policies[0].key, RateLimitKey::ApiToken);
+        assert_eq!(
+            policies.policies[0].algorithm,
+            Some(RateLimitAlgorithm::TokenBucket)
+        );
+        assert_eq!(policies.policies[0].block_secs, None);
+
+        let duplicate = r#"
+            [[policies]]
+            name = "a"
+            routes = ["/**"]
+            limit = 1
+            window_secs = 1
+            [[policies]]
+            name = "a"
+            routes = ["/**"]
+            limit = 1
+            window_secs = 1
+        "#;
+        assert!(RateLimitPolicies::parse(duplicate).is_err());
+        let zero_limit = r#"
+            [[policies]]
+            name = "a"
+            routes = ["/**"]
+            limit = 0
+            window_secs = 1
+        "#;
+        assert!(RateLimitPolicies::parse(zero_limit).is_err());
+        let bad_route = r#"
+            [[policies]]
+            name = "a"
+            routes = ["users"]
+            limit = 1
+            window_secs = 1
+        "#;
+        assert!(RateLimitPolicies::parse(bad_route).is_err());
+    }
+}
@@ -0,0 +1,46 @@
+use std::time::Duration;
+
+use super::Settings;
+
+/// Settings of the background renewal scheduler
+#[derive(Debug, Clone)]
+pub struct RenewalSchedulerConfig {
+    /// Whether the scheduler runs in this instance
+    pub enabled: bool,
+    /// Time between two runs
+    pub interval: Duration,
+    /// Whether a projected payment record is created for every rolled cycle
+    pub create_payments: bool,
+}
+
+impl Default for RenewalSchedulerConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            interval: Duration::from_secs(24 * 60 * 60), // daily
+            create_payments: false,
+        }
+    }
+}
+
+impl RenewalSchedulerConfig {
+    /// Load the scheduler settings
+    ///
+    /// - `RENEWAL_SCHEDULER_ENABLED`: `true`/`false`, defaults to `true`
+    /// - `RENEWAL_SCHEDULER_INTERVAL_SECS`: seconds between runs, defaults to a day
+    /// - `RENEWAL_CREATE_PAYMENTS`: `true`/`false`, defaults to `false`
+    pub fn from_settings(settings: &Settings) -> Result<Self, String> {
+        let defaults = Self::default();
+        Ok(Self {
+            enabled: settings
+                .flag("RENEWAL_SCHEDULER_ENABLED")?
+                .unwrap_or(defaults.enabled),
+            interval: settings
+                .secs("RENEWAL_SCHEDULER_INTERVAL_SECS")?
+                .unwrap_or(defaults.interval),
+            create_payments: settings
+                .flag("RENEWAL_CREATE_PAYMENTS")?
+                .unwrap_or(defaults.create_payments),
+        })
+    }
+}
@@ -0,0 +1,66 @@
+use std::net::{IpAddr, Ipv4Addr};
+use tracing_subscriber::EnvFilter;
+
+use super::Settings;
+
+/// Settings of the HTTP server
+#[derive(Debug, Clone)]
+pub struct ServerConfig {
+    /// Address to listen on
+    pub host: IpAddr,
+    pub port: u16,
+    /// Log filter in `RUST_LOG` syntax
+    pub log_level: String,
+    /// Whether this is a production deployment (`APP_ENV=production`)
+    pub production: bool,
+    /// Base URL of the web app, used for links in emails and redirects
+    pub app_url: String,
+}
+
+impl Default for ServerConfig {
+    fn default() -> Self {
+        Self {
+            host: IpAddr::V4(Ipv4Addr::UNSPECIFIED),
+            port: 3000,
+            log_level: "info".to_string(),
+            production: false,
+            app_url: "http://localhost:5173".to_string(),
+        }
+    }
+}
+
+impl ServerConfig {
+    /// Load the server settings
+    ///
+    /// - `HOST`: IPv4 or IPv6 address to listen on, defaults to `0.0.0.0`
+    /// - `PORT`: defaults to 3000
+    /// - `RUST_LOG`: log filter, defaults to `info`
+    /// - `APP_ENV`: `production` for production deployments
+    /// - `APP_URL`: base URL of the web app, defaults to `http://localhost:5173`
+    pub fn from_settings(settings: &Settings) -> Result<Self, String> {
+        let defaults = Self::default();
+
+        let log_level = settings.text("RUST_LOG").unwrap_or(defaults.log_level);
+        if let Err(e) = EnvFilter::try_new(&log_level) {
+            return Err(settings.invalid("RUST_LOG", e));
+        }
+
+        let app_url = settings
+            .text("APP_URL")
+            .map(|url| url.trim_end_matches('/').to_string())
+            .unwrap_or(defaults.app_url);
+        if !(app_url.starts_with("http://") || app_url.starts_with("https://")) {
+            return Err(settings.invalid("APP_URL", "expected an http:// or https:// URL"));
+        }
+
+        Ok(Self {
+            host: settings.parse("HOST")?.unwrap_or(defaults.host),
+            port: settings.parse("PORT")?.unwrap_or(defaults.port),
+            log_level,
+            production: settings
+                .text("APP_ENV")
+                .is_some_and(|app_env| app_env.eq_ignore_ascii_case("production")),
+            app_url,
+        })
+    }
+}
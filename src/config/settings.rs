@@ -0,0 +1,588 @@
+use clap::Parser;
+use std::collections::HashMap;
+use std::fmt::Display;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::time::Duration;
+
+/// A setting, by its key in the configuration file and its environment variable
+struct SettingSpec {
+    /// `section.name` in the configuration file and in `--set`
+    key: &'static str,
+    env: &'static str,
+    /// Whether the value may be read from a file named by `{env}_FILE` or
+    /// `{name}_file`, and is kept out of error messages
+    secret: bool,
+}
+
+const fn setting(key: &'static str, env: &'static str) -> SettingSpec {
+    SettingSpec {
+        key,
+        env,
+        secret: false,
+    }
+}
+
+const fn secret(key: &'static str, env: &'static str) -> SettingSpec {
+    SettingSpec {
+        key,
+        env,
+        secret: true,
+    }
+}
+
+/// Every setting of the server; `config/sub-pal.example.toml` describes them
+const SETTINGS: &[SettingSpec] = &[
+    setting("server.host", "HOST"),
+    setting("server.port", "PORT"),
+    setting("server.log_level", "RUST_LOG"),
+    setting("server.environment", "APP_ENV"),
+    setting("server.app_url", "APP_URL"),
+    secret("database.url", "DATABASE_URL"),
+    setting("database.max_connections", "DATABASE_MAX_CONNECTIONS"),
+    setting("database.min_connections", "DATABASE_MIN_CONNECTIONS"),
+    setting(
+        "database.acquire_timeout_secs",
+        "DATABASE_ACQUIRE_TIMEOUT_SECS",
+    ),
+    setting("database.idle_timeout_secs", "DATABASE_IDLE_TIMEOUT_SECS"),
+    setting("cors.allowed_origins", "ALLOWED_ORIGINS"),
+    setting("auth.jwt_keys", "JWT_KEYS"),
+    secret("auth.jwt_secret", "JWT_SECRET"),
+    setting("lockout.max_attempts", "LOGIN_LOCKOUT_MAX_ATTEMPTS"),
+    setting("lockout.base_secs", "LOGIN_LOCKOUT_BASE_SECS"),
+    setting("lockout.max_secs", "LOGIN_LOCKOUT_MAX_SECS"),
+    setting("lockout.reset_secs", "LOGIN_LOCKOUT_RESET_SECS"),
+    setting("account.deletion_grace_days", "ACCOUNT_DELETION_GRACE_DAYS"),
+    setting("account.purge_interval_secs", "ACCOUNT_PURGE_INTERVAL_SECS"),
+    setting("account.new_device_emails", "ACCOUNT_NEW_DEVICE_EMAILS"),
+    setting("mail.transport", "MAIL_TRANSPORT"),
+    setting("mail.smtp_host", "SMTP_HOST"),
+    setting("mail.smtp_port", "SMTP_PORT"),
+    setting("mail.smtp_security", "SMTP_SECURITY"),
+    setting("mail.smtp_username", "SMTP_USERNAME"),
+    secret("mail.smtp_password", "SMTP_PASSWORD"),
+    setting("mail.from", "MAIL_FROM"),
+    setting("mail.delivery_interval_secs", "MAIL_DELIVERY_INTERVAL_SECS"),
+    setting("oidc.issuer_url", "OIDC_ISSUER_URL"),
+    setting("oidc.client_id", "OIDC_CLIENT_ID"),
+    secret("oidc.client_secret", "OIDC_CLIENT_SECRET"),
+    setting("oidc.redirect_url", "OIDC_REDIRECT_URL"),
+    setting("oidc.scopes", "OIDC_SCOPES"),
+    setting("oidc.provider_name", "OIDC_PROVIDER_NAME"),
+    setting("oidc.allow_signup", "OIDC_ALLOW_SIGNUP"),
+    setting("webhook.worker_enabled", "WEBHOOK_WORKER_ENABLED"),
+    setting(
+        "webhook.delivery_interval_secs",
+        "WEBHOOK_DELIVERY_INTERVAL_SECS",
+    ),
+    setting("webhook.timeout_secs", "WEBHOOK_TIMEOUT_SECS"),
+    setting("webhook.allowed_networks", "WEBHOOK_ALLOWED_NETWORKS"),
+    setting("scheduler.enabled", "RENEWAL_SCHEDULER_ENABLED"),
+    setting("scheduler.interval_secs", "RENEWAL_SCHEDULER_INTERVAL_SECS"),
+    setting("scheduler.create_payments", "RENEWAL_CREATE_PAYMENTS"),
+    setting("rate_limit.store", "RATE_LIMIT_STORE"),
+    setting("rate_limit.algorithm", "RATE_LIMIT_ALGORITHM"),
+    secret("rate_limit.redis_url", "REDIS_URL"),
+    setting(
+        "rate_limit.cleanup_interval_secs",
+        "RATE_LIMIT_CLEANUP_INTERVAL_SECS",
+    ),
+    setting("rate_limit.policies_file", "RATE_LIMIT_POLICIES_FILE"),
+    setting("proxy.trusted_proxies", "TRUSTED_PROXIES"),
+    setting("proxy.client_ip_header", "CLIENT_IP_HEADER"),
+];
+
+/// Sub-Pal API server
+///
+/// Settings are read from the configuration file, then from environment
+/// variables, then from these flags, each overriding the one before.
+#[derive(Debug, Default, Parser)]
+#[command(name = "sub-pal", version)]
+pub struct Cli {
+    /// TOML configuration file
+    #[arg(short, long, value_name = "FILE", env = "CONFIG_FILE")]
+    pub config: Option<PathBuf>,
+    /// Address to listen on [HOST]
+    #[arg(long)]
+    pub host: Option<String>,
+    /// Port to listen on [PORT]
+    #[arg(short, long)]
+    pub port: Option<String>,
+    /// Log filter, e.g. `info` or `sub_pal=debug` [RUST_LOG]
+    #[arg(long)]
+    pub log_level: Option<String>,
+    /// Override any setting, e.g. `--set rate_limit.store=redis`
+    #[arg(long = "set", value_name = "KEY=VALUE")]
+    pub overrides: Vec<String>,
+    /// Check the configuration and exit
+    #[arg(long)]
+    pub check_config: bool,
+}
+
+/// A configured value
+#[derive(Debug, Clone, PartialEq)]
+enum Value {
+    Text(String),
+    /// An array of the configuration file
+    List(Vec<String>),
+}
+
+#[derive(Debug, Clone)]
+struct Entry {
+    value: Value,
+    /// Where the value comes from, for error messages
+    origin: String,
+    secret: bool,
+}
+
+/// Configured values by environment variable name, merged from the
+/// configuration file, the environment and the command line, each
+/// overriding the one before
+///
+/// Empty values count as unset, so `REDIS_URL=` does not hide a value from
+/// the configuration file. Settings that are not configured anywhere take
+/// the default of the config struct reading them.
+#[derive(Debug, Clone, Default)]
+pub struct Settings {
+    entries: HashMap<&'static str, Entry>,
+}
+
+impl Settings {
+    /// Read the settings of the configuration file named on the command
+    /// line, the process environment and the command line
+    pub fn load(cli: &Cli) -> Result<Self, Vec<String>> {
+        let file = match &cli.config {
+            Some(path) => match fs::read_to_string(path) {
+                Ok(toml) => Some((path.as_path(), toml)),
+                Err(e) => {
+                    return Err(vec![format!(
+                        "Could not read the configuration file {}: {e}",
+                        path.display()
+                    )]);
+                }
+            },
+            None => None,
+        };
+        let env: HashMap<String, String> = std::env::vars().collect();
+
+        Self::from_sources(
+            file.as_ref().map(|(path, toml)| (*path, toml.as_str())),
+            &env,
+            cli,
+        )
+    }
+
+    /// Merge settings from a configuration file, environment variables and
+    /// a command line
+    pub fn from_sources(
+        file: Option<(&Path, &str)>,
+        env: &HashMap<String, String>,
+        cli: &Cli,
+    ) -> Result<Self, Vec<String>> {
+        let mut settings = Self::default();
+        let mut errors = Vec::new();
+
+        if let Some((path, toml)) = file {
+            settings.merge_file(path, toml, &mut errors);
+        }
+        settings.merge_env(env, &mut errors);
+        settings.merge_cli(cli, &mut errors);
+
+        if errors.is_empty() {
+            Ok(settings)
+        } else {
+            Err(errors)
+        }
+    }
+
+    fn merge_file(&mut self, path: &Path, toml: &str, errors: &mut Vec<String>) {
+        let table: toml::Table = match toml.parse() {
+            Ok(table) => table,
+            Err(e) => {
+                errors.push(format!(
+                    "Invalid configuration file {}: {e}",
+                    path.display()
+                ));
+                return;
+            }
+        };
+
+        for (section, names) in table {
+            let toml::Value::Table(names) = names else {
+                errors.push(format!(
+                    "Unknown setting {section} in {}, settings belong in sections such as [server]",
+                    path.display()
+                ));
+                continue;
+            };
+            for (name, value) in names {
+                let key = format!("{section}.{name}");
+                let origin = format!("{key} in {}", path.display());
+                let value = match value {
+                    toml::Value::String(text) => Value::Text(text),
+                    toml::Value::Array(items) => {
+                        match items.into_iter().map(scalar).collect::<Option<Vec<_>>>() {
+                            Some(items) => Value::List(items),
+                            None => {
+                                errors.push(format!("Invalid {origin}: expected a list of values"));
+                                continue;
+                            }
+                        }
+                    }
+                    value => match scalar(value) {
+                        Some(text) => Value::Text(text),
+                        None => {
+                            errors.push(format!("Invalid {origin}: expected a value"));
+                            continue;
+                        }
+                    },
+                };
+
+                if let Some(spec) = find(|spec| spec.key == key) {
+                    self.set(spec, value, origin);
+                } else if let Some(spec) = key
+                    .strip_suffix("_file")
+                    .and_then(|key| find(|spec| spec.secret && spec.key == key))
+                {
+                    match value {
+                        Value::Text(secret_path) => {
+                            self.set_from_file(spec, &secret_path, origin, errors)
+                        }
+                        Value::List(_) => errors.push(format!("Invalid {origin}: expected a path")),
+                    }
+                } else {
+                    errors.push(format!("Unknown setting {key} in {}", path.display()));
+                }
+            }
+        }
+    }
+
+    fn merge_env(&mut self, env: &HashMap<String, String>, errors: &mut Vec<String>) {
+        let set = |name: &str| env.get(name).filter(|value| !value.trim().is_empty());
+
+        for spec in SETTINGS {
+            let value = set(spec.env);
+            let file_var = format!("{}_FILE", spec.env);
+            let file = spec.secret.then(|| set(&file_var)).flatten();
+            match (value, file) {
+                (Some(_), Some(_)) => {
+                    errors.push(format!("Set either {} or {file_var}, not both", spec.env))
+                }
+                (Some(value), None) => self.set(
+                    spec,
+                    Value::Text(value.clone()),
+                    format!("environment variable {}", spec.env),
+                ),
+                (None, Some(path)) => self.set_from_file(
+                    spec,
+                    path,
+                    format!("environment variable {file_var}"),
+                    errors,
+                ),
+                (None, None) => {}
+            }
+        }
+    }
+
+    fn merge_cli(&mut self, cli: &Cli, errors: &mut Vec<String>) {
+        for (flag, env, value) in [
+            ("--host", "HOST", &cli.host),
+            ("--port", "PORT", &cli.port),
+            ("--log-level", "RUST_LOG", &cli.log_level),
+        ] {
+            if let (Some(value), Some(spec)) = (value, find(|spec| spec.env == env)) {
+                self.set(spec, Value::Text(value.clone()), flag.to_string());
+            }
+        }
+
+        for assignment in &cli.overrides {
+            let Some((key, value)) = assignment.split_once('=') else {
+                errors.push(format!(
+                    "Invalid --set {assignment}, expected KEY=VALUE such as server.port=8080"
+                ));
+                continue;
+            };
+            let key = key.trim();
+            let origin = format!("--set {key}");
+            if let Some(spec) = find(|spec| spec.key == key) {
+                self.set(spec, Value::Text(value.to_string()), origin);
+            } else if let Some(spec) = key
+                .strip_suffix("_file")
+                .and_then(|key| find(|spec| spec.secret && spec.key == key))
+            {
+                self.set_from_file(spec, value, origin, errors);
+            } else {
+                errors.push(format!("Unknown setting {key} in --set"));
+            }
+        }
+    }
+
+    fn set(&mut self, spec: &SettingSpec, value: Value, origin: String) {
+        let value = match value {
+            Value::Text(text) => Value::Text(text.trim().to_string()),
+            Value::List(items) => Value::List(
+                items
+                    .into_iter()
+                    .map(|item| item.trim().to_string())
+                    .filter(|item| !item.is_empty())
+                    .collect(),
+            ),
+        };
+        if value == Value::Text(String::new()) {
+            return;
+        }
+        self.entries.insert(
+            spec.env,
+            Entry {
+                value,
+                origin,
+                secret: spec.secret,
+            },
+        );
+    }
+
+    /// Set a secret to the contents of a file, e.g. a Docker secret
+    fn set_from_file(
+        &mut self,
+        spec: &SettingSpec,
+        path: &str,
+        origin: String,
+        errors: &mut Vec<String>,
+    ) {
+        let path = path.trim();
+        match fs::read_to_string(path) {
+            Ok(secret) => self.set(
+                spec,
+                Value::Text(secret),
+                format!("file {path} of {origin}"),
+            ),
+            Err(e) => errors.push(format!("Could not read {path} of {origin}: {e}")),
+        }
+    }
+
+    /// The value of a setting, list items joined with commas
+    pub fn text(&self, name: &str) -> Option<String> {
+        self.entries.get(name).map(|entry| match &entry.value {
+            Value::Text(text) => text.clone(),
+            Value::List(items) => items.join(","),
+        })
+    }
+
+    /// The items of a list setting, given as an array or comma separated
+    pub fn list(&self, name: &str) -> Vec<String> {
+        match self.entries.get(name).map(|entry| &entry.value) {
+            Some(Value::Text(text)) => text
+                .split(',')
+                .map(str::trim)
+                .filter(|item| !item.is_empty())
+                .map(str::to_string)
+                .collect(),
+            Some(Value::List(items)) => items.clone(),
+            None => Vec::new(),
+        }
+    }
+
+    /// Parse the value of a setting
+    pub fn parse<T>(&self, name: &str) -> Result<Option<T>, String>
+    where
+        T: FromStr,
+        T::Err: Display,
+    {
+        self.text(name)
+            .map(|text| text.parse().map_err(|e| self.invalid(name, e)))
+            .transpose()
+    }
+
+    /// Parse a boolean setting: `true`/`false`, `yes`/`no`, `on`/`off` or `1`/`0`
+    pub fn flag(&self, name: &str) -> Result<Option<bool>, String> {
+        self.text(name)
+            .map(|text| match text.to_lowercase().as_str() {
+                "1" | "true" | "yes" | "on" => Ok(true),
+                "0" | "false" | "no" | "off" => Ok(false),
+                _ => Err(self.invalid(name, "expected true or false")),
+            })
+            .transpose()
+    }
+
+    /// Parse a positive number of seconds
+    pub fn secs(&self, name: &str) -> Result<Option<Duration>, String> {
+        match self.parse::<u64>(name)? {
+            Some(0) => Err(self.invalid(name, "expected a positive number of seconds")),
+            secs => Ok(secs.map(Duration::from_secs)),
+        }
+    }
+
+    /// An error about the value of a setting, naming where it was configured
+    pub fn invalid(&self, name: &str, reason: impl Display) -> String {
+        match self.entries.get(name) {
+            Some(entry) if entry.secret => format!("Invalid {}: {reason}", entry.origin),
+            Some(entry) => format!(
+                "Invalid {} {:?}: {reason}",
+                entry.origin,
+                self.text(name).unwrap_or_default()
+            ),
+            None => format!("Invalid {name}: {reason}"),
+        }
+    }
+
+    /// Where a setting is configured, for errors about settings that belong together
+    pub fn origin(&self, name: &str) -> String {
+        self.entries
+            .get(name)
+            .map(|entry| entry.origin.clone())
+            .unwrap_or_else(|| name.to_string())
+    }
+}
+
+fn find(predicate: impl Fn(&SettingSpec) -> bool) -> Option<&'static SettingSpec> {
+    SETTINGS.iter().find(|spec| predicate(spec))
+}
+
+/// The text of a single value of the configuration file
+fn scalar(value: toml::Value) -> Option<String> {
+    match value {
+        toml::Value::String(text) => Some(text),
+        toml::Value::Integer(number) => Some(number.to_string()),
+        toml::Value::Float(number) => Some(number.to_string()),
+        toml::Value::Boolean(flag) => Some(flag.to_string()),
+        toml::Value::Datetime(datetime) => Some(datetime.to_string()),
+        toml::Value::Array(_) | toml::Value::Table(_) => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn env(vars: &[(&str, &str)]) -> HashMap<String, String> {
+        vars.iter()
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn test_later_sources_override_earlier_ones() {
+        let file = r#"
+            [server]
+            host = "127.0.0.1"
+            port = 8080
+            log_level = "debug"
+
+            [cors]
+            allowed_origins = ["https://a.example", " https://b.example "]
+
+            [rate_limit]
+            redis_url = "redis://file"
+        "#;
+        let cli = Cli::parse_from(["sub-pal", "--port", "9090", "--set", "server.host=::1"]);
+        let settings = Settings::from_sources(
+            Some((Path::new("sub-pal.toml"), file)),
+            &env(&[("PORT", "8081"), ("RUST_LOG", "warn"), ("REDIS_URL", " ")]),
+            &cli,
+        )
+        .unwrap();
+
+        assert_eq!(settings.text("HOST").as_deref(), Some("::1"));
+        assert_eq!(settings.parse::<u16>("PORT"), Ok(Some(9090)));
+        assert_eq!(settings.text("RUST_LOG").as_deref(), Some("warn"));
+        // An empty variable does not hide the value of the file
+        assert_eq!(settings.text("REDIS_URL").as_deref(), Some("redis://file"));
+        assert_eq!(
+            settings.list("ALLOWED_ORIGINS"),
+            ["https://a.example", "https://b.example"]
+        );
+        assert_eq!(settings.text("APP_URL"), None);
+    }
+
+    #[test]
+    fn test_secrets_can_be_read_from_files() {
+        let path = std::env::temp_dir().join(format!("sub-pal-secret-{}", std::process::id()));
+        fs::write(&path, "s3cret\n").unwrap();
+        let path = path.to_str().unwrap();
+
+        let settings =
+            Settings::from_sources(None, &env(&[("JWT_SECRET_FILE", path)]), &Cli::default())
+                .unwrap();
+        assert_eq!(settings.text("JWT_SECRET").as_deref(), Some("s3cret"));
+
+        let file = format!("[database]\nurl_file = {path:?}\n");
+        let settings = Settings::from_sources(
+            Some((Path::new("a.toml"), &file)),
+            &env(&[]),
+            &Cli::default(),
+        )
+        .unwrap();
+        assert_eq!(settings.text("DATABASE_URL").as_deref(), Some("s3cret"));
+
+        let errors = Settings::from_sources(
+            None,
+            &env(&[("JWT_SECRET", "x"), ("JWT_SECRET_FILE", path)]),
+            &Cli::default(),
+        )
+        .unwrap_err();
+        assert_eq!(
+            errors,
+            ["Set either JWT_SECRET or JWT_SECRET_FILE, not both"]
+        );
+        // Only secrets are read from files
+        assert!(
+            Settings::from_sources(None, &env(&[("PORT_FILE", path)]), &Cli::default())
+                .unwrap()
+                .text("PORT")
+                .is_none()
+        );
+        fs::remove_file(path).unwrap();
+
+        let errors = Settings::from_sources(
+            None,
+            &env(&[("SMTP_PASSWORD_FILE", "/nonexistent/secret")]),
+            &Cli::default(),
+        )
+        .unwrap_err();
+        assert!(errors[0].contains("SMTP_PASSWORD_FILE"), "{errors:?}");
+    }
+
+    #[test]
+    fn test_unknown_settings_are_errors() {
+        let file = "port = 1\n[server]\nprot = 1\n[mail]\nfrom = { name = \"x\" }\n";
+        let cli = Cli::parse_from(["sub-pal", "--set", "server.hots=x", "--set", "nonsense"]);
+        let errors =
+            Settings::from_sources(Some((Path::new("a.toml"), file)), &env(&[]), &cli).unwrap_err();
+        assert_eq!(errors.len(), 5, "{errors:?}");
+        for expected in [
+            "setting port",
+            "server.prot",
+            "mail.from",
+            "server.hots",
+            "nonsense",
+        ] {
+            assert!(errors.iter().any(|e| e.contains(expected)), "{errors:?}");
+        }
+    }
+
+    #[test]
+    fn test_errors_name_the_source_of_the_value() {
+        let settings = Settings::from_sources(
+            None,
+            &env(&[
+                ("PORT", "eighty"),
+                ("WEBHOOK_TIMEOUT_SECS", "0"),
+                ("ACCOUNT_NEW_DEVICE_EMAILS", "maybe"),
+                ("REDIS_URL", "redis://:hunter2@localhost"),
+            ]),
+            &Cli::default(),
+        )
+        .unwrap();
+
+        let error = settings.parse::<u16>("PORT").unwrap_err();
+        assert!(
+            error.starts_with("Invalid environment variable PORT \"eighty\""),
+            "{error}"
+        );
+        assert!(settings.secs("WEBHOOK_TIMEOUT_SECS").is_err());
+        assert!(settings.flag("ACCOUNT_NEW_DEVICE_EMAILS").is_err());
+        assert!(!settings.invalid("REDIS_URL", "bad").contains("hunter2"));
+    }
+}
@@ -0,0 +1,62 @@
+use ipnet::IpNet;
+use std::time::Duration;
+
+use super::Settings;
+use super::proxy::parse_network;
+
+/// Settings of the webhook delivery worker
+#[derive(Debug, Clone)]
+pub struct WebhookConfig {
+    /// Whether the worker runs in this instance
+    pub enabled: bool,
+    /// Time between two delivery runs
+    pub interval: Duration,
+    /// How long to wait for an endpoint to respond
+    pub timeout: Duration,
+    /// Internal networks webhooks may be sent to, e.g. a home automation
+    /// server on the local network; all other internal addresses are refused
+    pub allowed_networks: Vec<IpNet>,
+}
+
+impl Default for WebhookConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            interval: Duration::from_secs(10),
+            timeout: Duration::from_secs(10),
+            allowed_networks: Vec::new(),
+        }
+    }
+}
+
+impl WebhookConfig {
+    /// Load the webhook settings
+    ///
+    /// - `WEBHOOK_WORKER_ENABLED`: `true`/`false`, defaults to `true`
+    /// - `WEBHOOK_DELIVERY_INTERVAL_SECS`: seconds between runs, defaults to 10
+    /// - `WEBHOOK_TIMEOUT_SECS`: request timeout in seconds, defaults to 10
+    /// - `WEBHOOK_ALLOWED_NETWORKS`: comma separated internal networks in CIDR
+    ///   notation or single addresses webhooks may be sent to; empty by default
+    pub fn from_settings(settings: &Settings) -> Result<Self, String> {
+        let defaults = Self::default();
+        let allowed_networks = settings
+            .list("WEBHOOK_ALLOWED_NETWORKS")
+            .iter()
+            .map(|network| parse_network(network))
+            .collect::<Result<_, _>>()
+            .map_err(|e| settings.invalid("WEBHOOK_ALLOWED_NETWORKS", e))?;
+
+        Ok(Self {
+            enabled: settings
+                .flag("WEBHOOK_WORKER_ENABLED")?
+                .unwrap_or(defaults.enabled),
+            interval: settings
+                .secs("WEBHOOK_DELIVERY_INTERVAL_SECS")?
+                .unwrap_or(defaults.interval),
+            timeout: settings
+                .secs("WEBHOOK_TIMEOUT_SECS")?
+                .unwrap_or(defaults.timeout),
+            allowed_networks,
+        })
+    }
+}
@@ -1,7 +1,8 @@
 use axum::Router;
-use axum::http::Method;
-use std::env;
+use axum::http::{HeaderName, Method};
+use clap::Parser;
 use std::net::SocketAddr;
+use std::sync::Arc;
 use tower_http::cors::{AllowOrigin, CorsLayer};
 use tower_http::trace::TraceLayer;
 use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
@@ -11,47 +12,37 @@ mod middleware;
 mod models;
 mod routes;
 mod services;
+mod state;
 mod utils;
 
-use config::create_pool;
-use middleware::{rate_limit_middleware, request_logger, security_headers};
-use routes::api_routes;
-
-// Configuration loaded from environment variables (provided by Docker)
-struct Config {
-    log_level: String,
-    port: u16,
-    host: String,
-}
-
-impl Config {
-    fn from_env() -> Self {
-        Self {
-            log_level: env::var("RUST_LOG").unwrap_or_else(|_| "info".to_string()),
-            port: env::var("PORT")
-                .unwrap_or_else(|_| "3000".to_string())
-                .parse()
-                .unwrap_or(3000),
-            host: env::var("HOST").unwrap_or_else(|_| "0.0.0.0".to_string()),
-        }
-    }
-}
+use config::{AppConfig, Cli, create_pool};
+use middleware::{
+    RATE_LIMIT_HEADERS, RateLimiter, rate_limit_middleware, request_logger, resolve_client_ip,
+    security_headers,
+};
+use routes::{api_routes, well_known_routes};
+use services::{
+    OidcClient, WebhookAddressPolicy, create_mailer, create_rate_limit_store,
+    spawn_account_purge_worker, spawn_delivery_worker, spawn_rate_limit_cleanup,
+    spawn_renewal_scheduler, spawn_webhook_worker,
+};
+use state::AppState;
+use utils::client::ClientIpResolver;
+use utils::jwt::{JwtKeys, init_jwt_keys};
 
 #[tokio::main]
 async fn main() {
-    // Load configuration from environment variables (provided by Docker)
-    let config = Config::from_env();
-
-    tracing::info!(
-        "Starting Sub-Pal with config - Host: {}, Port: {}, Log Level: {}",
-        config.host,
-        config.port,
-        config.log_level
-    );
+    // Merge the configuration file, environment variables and command line
+    let cli = Cli::parse();
+    let config = AppConfig::load(&cli);
 
     // Initialize tracing with detailed formatting
+    let log_level = config
+        .as_ref()
+        .map(|config| config.server.log_level.as_str())
+        .unwrap_or("info");
     tracing_subscriber::registry()
-        .with(tracing_subscriber::EnvFilter::new(&config.log_level))
+        .with(tracing_subscriber::EnvFilter::new(log_level))
         .with(
             tracing_subscriber::fmt::layer()
                 .with_target(true)
@@ -61,8 +52,42 @@ async fn main() {
         )
         .init();
 
+    let config = match config {
+        Ok(config) => config,
+        Err(errors) => {
+            for e in errors {
+                tracing::error!("Invalid configuration: {}", e);
+            }
+            std::process::exit(1);
+        }
+    };
+    tracing::info!(
+        "Starting Sub-Pal with config - Host: {}, Port: {}, Log Level: {}",
+        config.server.host,
+        config.server.port,
+        config.server.log_level
+    );
+
+    // Load the keys access tokens are signed with
+    match JwtKeys::from_config(&config.jwt) {
+        Ok(keys) => {
+            let (kid, algorithm) = keys.signing_key();
+            tracing::info!("Signing access tokens with key {} ({:?})", kid, algorithm);
+            init_jwt_keys(keys);
+        }
+        Err(e) => {
+            tracing::error!("Failed to load the JWT keys: {}", e);
+            std::process::exit(1);
+        }
+    }
+
+    if cli.check_config {
+        println!("Configuration is valid");
+        return;
+    }
+
     // Create database connection pool
-    let pool = match create_pool().await {
+    let pool = match create_pool(&config.database).await {
         Ok(pool) => {
             tracing::info!("Database connection pool created successfully");
             pool
@@ -73,31 +98,99 @@ async fn main() {
         }
     };
 
-    // Configure CORS with proper error handling
-    let allowed_origins = {
-        let origins_str = env::var("ALLOWED_ORIGINS").unwrap_or_else(|_| {
-            "http://localhost:5173,http://192.168.31.123:5173,http://wty92911.top:5173".to_string()
-        });
+    // Client addresses are read from forwarding headers set by trusted proxies
+    let proxy_config = &config.proxy;
+    if proxy_config.trusted_proxies.is_empty() {
+        tracing::info!("No trusted proxies, using connection addresses as client addresses");
+    } else {
+        tracing::info!(
+            "Trusting {:?} headers from proxies in {:?}",
+            proxy_config.client_ip_header,
+            proxy_config.trusted_proxies
+        );
+    }
+    let client_ip_resolver = Arc::new(ClientIpResolver::new(proxy_config));
 
-        let origins: Vec<&str> = origins_str.split(',').map(|s| s.trim()).collect();
+    // Set up the store rate limits are counted in and purge it periodically
+    let rate_limit_config = &config.rate_limit;
+    let rate_limiter = match create_rate_limit_store(rate_limit_config, pool.clone()).await {
+        Ok(store) => {
+            tracing::info!(
+                "Counting {} rate limit policies in the {:?} store",
+                rate_limit_config.policies.policies.len(),
+                rate_limit_config.backend
+            );
+            spawn_rate_limit_cleanup(store.clone(), rate_limit_config.cleanup_interval);
+            Arc::new(RateLimiter::new(store, rate_limit_config))
+        }
+        Err(e) => {
+            tracing::error!("Failed to set up the rate limit store: {}", e);
+            std::process::exit(1);
+        }
+    };
 
-        let parsed_origins: Result<Vec<_>, _> =
-            origins.iter().map(|origin| origin.parse()).collect();
+    // Start the background renewal scheduler
+    spawn_renewal_scheduler(pool.clone(), config.scheduler.clone());
 
-        match parsed_origins {
-            Ok(origins) => AllowOrigin::list(origins),
-            Err(e) => {
-                tracing::error!("Failed to parse CORS origins: {}", e);
-                std::process::exit(1);
-            }
+    // Start the webhook delivery worker
+    spawn_webhook_worker(pool.clone(), config.webhook.clone());
+
+    // Start the worker purging accounts whose deletion grace period ended
+    spawn_account_purge_worker(pool.clone(), config.account.clone());
+
+    // Start the email delivery worker when a mail transport is configured
+    let mailer = match create_mailer(&config.mail) {
+        Ok(Some(mailer)) => {
+            spawn_delivery_worker(pool.clone(), mailer.clone(), config.mail.clone());
+            Some(mailer)
+        }
+        Ok(None) => {
+            tracing::info!("Email delivery is disabled");
+            None
+        }
+        Err(e) => {
+            tracing::error!("Failed to configure email delivery: {}", e);
+            std::process::exit(1);
+        }
+    };
+
+    // Set up single sign-on when a provider is configured
+    let oidc = match config
+        .oidc
+        .clone()
+        .map(|config| {
+            tracing::info!("Single sign-on enabled with issuer {}", config.issuer_url);
+            OidcClient::new(config).map(Arc::new)
+        })
+        .transpose()
+    {
+        Ok(oidc) => oidc,
+        Err(e) => {
+            tracing::error!("Failed to configure single sign-on: {}", e);
+            std::process::exit(1);
         }
     };
 
+    let state = AppState {
+        pool,
+        mailer,
+        app_url: config.server.app_url.clone(),
+        account: config.account.clone(),
+        lockout: config.lockout.clone(),
+        oidc,
+        rate_limiter,
+        webhook_addresses: WebhookAddressPolicy::new(&config.webhook),
+    };
+
+    // Configure CORS for the origins of the web app
+    let allowed_origins = AllowOrigin::list(config.cors.allowed_origins.clone());
+
     // Define allowed methods
     let allowed_methods = vec![
         Method::GET,
         Method::POST,
         Method::PUT,
+        Method::PATCH,
         Method::DELETE,
         Method::OPTIONS,
     ];
@@ -109,42 +202,46 @@ async fn main() {
         axum::http::header::ACCEPT,
     ];
 
+    // Let the web app read the rate limit state of a response
+    let exposed_headers = RATE_LIMIT_HEADERS.map(HeaderName::from_static);
+
     let cors = CorsLayer::new()
         .allow_origin(allowed_origins)
         .allow_methods(allowed_methods)
         .allow_headers(allowed_headers)
+        .expose_headers(exposed_headers)
         .allow_credentials(true);
 
-    // Build application with routes and middleware
+    // Build application with routes and middleware; the last layer added is
+    // the outermost. CORS and security headers wrap the rate limiter so that
+    // `429` responses carry them too, or browsers would hide them from the app.
     tracing::info!("Setting up application middleware stack:");
-    tracing::info!("  - CORS layer");
+    tracing::info!("  - Rate limit middleware");
     tracing::info!("  - Trace layer");
     tracing::info!("  - Security headers middleware");
-    tracing::info!("  - Rate limit middleware (with ConnectInfo)");
+    tracing::info!("  - CORS layer");
     tracing::info!("  - Request logger middleware");
+    tracing::info!("  - Client IP resolver");
 
     let app = Router::new()
         .nest("/api/v1", api_routes())
-        .with_state(pool)
-        .layer(cors)
+        .merge(well_known_routes())
+        .with_state(state.clone())
+        .layer(axum::middleware::from_fn_with_state(
+            state,
+            rate_limit_middleware,
+        ))
         .layer(TraceLayer::new_for_http())
         .layer(axum::middleware::from_fn(security_headers))
-        .layer(axum::middleware::from_fn(rate_limit_middleware))
-        .layer(axum::middleware::from_fn(request_logger));
+        .layer(cors)
+        .layer(axum::middleware::from_fn(request_logger))
+        .layer(axum::middleware::from_fn_with_state(
+            client_ip_resolver,
+            resolve_client_ip,
+        ));
 
     // Start server with proper error handling
-    let host_parts: Vec<u8> = config
-        .host
-        .split('.')
-        .map(|s| s.parse().unwrap_or(0))
-        .collect();
-    let host_addr = if host_parts.len() == 4 {
-        [host_parts[0], host_parts[1], host_parts[2], host_parts[3]]
-    } else {
-        [0, 0, 0, 0] // Default to 0.0.0.0 if parsing fails
-    };
-
-    let addr = SocketAddr::from((host_addr, config.port));
+    let addr = SocketAddr::from((config.server.host, config.server.port));
     tracing::info!("Starting server on {}", addr);
 
     let listener = match tokio::net::TcpListener::bind(addr).await {
@@ -0,0 +1,22 @@
+use axum::extract::{ConnectInfo, Request, State};
+use axum::middleware::Next;
+use axum::response::Response;
+use std::net::SocketAddr;
+use std::sync::Arc;
+
+use crate::utils::client::{ClientIp, ClientIpResolver};
+
+/// Middleware resolving the address of the client behind trusted proxies
+///
+/// Stores it as [`ClientIp`] in the request extensions for the middleware
+/// and handlers after it.
+pub async fn resolve_client_ip(
+    State(resolver): State<Arc<ClientIpResolver>>,
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+    mut request: Request,
+    next: Next,
+) -> Response {
+    let ip = resolver.resolve(addr.ip(), request.headers());
+    request.extensions_mut().insert(ClientIp(ip));
+    next.run(request).await
+}
@@ -2,26 +2,31 @@ use axum::extract::Request;
 use axum::middleware::Next;
 use axum::response::Response;
 
+use crate::utils::client::ClientIp;
+
 /// Middleware to log every request before it's handled
 pub async fn request_logger(request: Request, next: Next) -> Response {
     let method = request.method().clone();
     let uri = request.uri().clone();
     let headers = request.headers().clone();
+    let client = ClientIp::of(request.extensions())
+        .map(|ip| ip.to_string())
+        .unwrap_or_else(|| "unknown".to_string());
 
     // Log request details
-    tracing::info!("Incoming request: {} {}", method, uri);
+    tracing::info!("Incoming request: {} {} from {}", method, uri, client);
 
     // Log headers (excluding sensitive ones)
-    if let Some(user_agent) = headers.get("user-agent") {
-        if let Ok(ua) = user_agent.to_str() {
-            tracing::debug!("User-Agent: {}", ua);
-        }
+    if let Some(user_agent) = headers.get("user-agent")
+        && let Ok(ua) = user_agent.to_str()
+    {
+        tracing::debug!("User-Agent: {}", ua);
     }
 
-    if let Some(origin) = headers.get("origin") {
-        if let Ok(origin_str) = origin.to_str() {
-            tracing::debug!("Origin: {}", origin_str);
-        }
+    if let Some(origin) = headers.get("origin")
+        && let Ok(origin_str) = origin.to_str()
+    {
+        tracing::debug!("Origin: {}", origin_str);
     }
 
     // Log authorization header presence (but not the actual token)
@@ -30,17 +35,17 @@ pub async fn request_logger(request: Request, next: Next) -> Response {
     }
 
     // Log content type if present
-    if let Some(content_type) = headers.get("content-type") {
-        if let Ok(ct) = content_type.to_str() {
-            tracing::debug!("Content-Type: {}", ct);
-        }
+    if let Some(content_type) = headers.get("content-type")
+        && let Ok(ct) = content_type.to_str()
+    {
+        tracing::debug!("Content-Type: {}", ct);
     }
 
     // Log request body size if available
-    if let Some(content_length) = headers.get("content-length") {
-        if let Ok(cl) = content_length.to_str() {
-            tracing::debug!("Content-Length: {}", cl);
-        }
+    if let Some(content_length) = headers.get("content-length")
+        && let Ok(cl) = content_length.to_str()
+    {
+        tracing::debug!("Content-Length: {}", cl);
     }
 
     // Process the request
@@ -1,7 +1,9 @@
+pub mod client_ip;
 pub mod logging;
 pub mod rate_limit;
 pub mod security;
 
+pub use client_ip::resolve_client_ip;
 pub use logging::request_logger;
-pub use rate_limit::{auth_rate_limit_middleware, rate_limit_middleware};
+pub use rate_limit::{RATE_LIMIT_HEADERS, RateLimiter, rate_limit_middleware};
 pub use security::security_headers;
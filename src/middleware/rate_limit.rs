@@ -1,270 +1,308 @@
-use axum::extract::{ConnectInfo, Request};
-use axum::http::{HeaderValue, StatusCode};
+use axum::extract::{Request, State};
+use axum::http::{HeaderMap, HeaderValue, Method, header::AUTHORIZATION};
 use axum::middleware::Next;
 use axum::response::{IntoResponse, Response};
-use std::collections::HashMap;
-use std::net::SocketAddr;
-use std::sync::{Arc, Mutex};
-use std::time::{Duration, Instant};
-
-#[derive(Clone)]
-pub struct RateLimitConfig {
-    pub max_requests: usize,
-    pub window_duration: Duration,
-    pub block_duration: Duration,
+use sqlx::PgPool;
+use std::sync::Arc;
+use std::time::Duration;
+use uuid::Uuid;
+
+use crate::config::RateLimitConfig;
+use crate::config::rate_limit::{RateLimitKey, RateLimitPolicy, RoutePattern};
+use crate::services::api_token_service::api_token_user;
+use crate::services::rate_limit_store::{RateLimitDecision, RateLimitQuota, RateLimitStore};
+use crate::state::AppState;
+use crate::utils::auth::{API_TOKEN_PREFIX, extract_session_from_token, hash_token};
+use crate::utils::client::ClientIp;
+use crate::utils::response::AppError;
+
+/// Counts requests against the configured rate limit policies
+pub struct RateLimiter {
+    store: Arc<dyn RateLimitStore>,
+    exempt: Vec<RoutePattern>,
+    policies: Vec<(RateLimitPolicy, RateLimitQuota)>,
 }
 
-impl Default for RateLimitConfig {
-    fn default() -> Self {
+impl RateLimiter {
+    /// Create a limiter applying the configured policies with counters in the given store
+    pub fn new(store: Arc<dyn RateLimitStore>, config: &RateLimitConfig) -> Self {
+        let policies = config
+            .policies
+            .policies
+            .iter()
+            .map(|policy| {
+                let quota = RateLimitQuota {
+                    algorithm: policy.algorithm.unwrap_or(config.algorithm),
+                    limit: policy.limit,
+                    window: Duration::from_secs(policy.window_secs),
+                    block: policy.block_secs.map(Duration::from_secs),
+                };
+                (policy.clone(), quota)
+            })
+            .collect();
+
         Self {
-            max_requests: 100,                        // 100 requests
-            window_duration: Duration::from_secs(60), // per minute
-            block_duration: Duration::from_secs(300), // block for 5 minutes
+            store,
+            exempt: config.policies.exempt.clone(),
+            policies,
         }
     }
-}
 
-#[derive(Debug)]
-struct ClientInfo {
-    request_count: usize,
-    window_start: Instant,
-    blocked_until: Option<Instant>,
-}
-
-impl Default for ClientInfo {
-    fn default() -> Self {
-        Self {
-            request_count: 1,
-            window_start: Instant::now(),
-            blocked_until: None,
+    /// The policies a request counts against, none on exempt routes
+    ///
+    /// CORS preflights are never counted: browsers send one before many
+    /// requests, and a refused preflight hides the real response from the app.
+    fn policies_for(&self, method: &Method, path: &str) -> Vec<&(RateLimitPolicy, RateLimitQuota)> {
+        if *method == Method::OPTIONS || self.exempt.iter().any(|route| route.matches(method, path))
+        {
+            return Vec::new();
         }
+        self.policies
+            .iter()
+            .filter(|(policy, _)| {
+                policy
+                    .routes
+                    .iter()
+                    .any(|route| route.matches(method, path))
+            })
+            .collect()
     }
 }
 
-#[derive(Clone)]
-pub struct RateLimiter {
-    clients: Arc<Mutex<HashMap<String, ClientInfo>>>,
-    config: RateLimitConfig,
+/// Who sent a request, as far as rate limits tell clients apart
+struct Requester {
+    ip: String,
+    user_id: Option<Uuid>,
+    /// Hash of a valid API token the request was sent with
+    api_token: Option<String>,
 }
 
-impl RateLimiter {
-    pub fn new(config: RateLimitConfig) -> Self {
-        Self {
-            clients: Arc::new(Mutex::new(HashMap::new())),
-            config,
-        }
-    }
-
-    #[allow(dead_code)] // Utility function for future use
-    pub fn with_default_config() -> Self {
-        Self::new(RateLimitConfig::default())
-    }
-
-    pub fn check_rate_limit(&self, client_ip: &str) -> Result<(), StatusCode> {
-        let mut clients = self.clients.lock().unwrap();
-        let now = Instant::now();
-
-        let client_info = clients.entry(client_ip.to_string()).or_default();
-
-        // Check if client is currently blocked
-        if let Some(blocked_until) = client_info.blocked_until {
-            if now < blocked_until {
-                tracing::warn!(
-                    "Rate limit: Client {} is blocked until {:?}",
-                    client_ip,
-                    blocked_until
-                );
-                return Err(StatusCode::TOO_MANY_REQUESTS);
-            } else {
-                // Unblock the client
-                client_info.blocked_until = None;
-                client_info.request_count = 1;
-                client_info.window_start = now;
-                return Ok(());
+impl Requester {
+    /// Identify the sender of a request, looking at its bearer token only
+    /// when a policy counts by user or API token
+    async fn identify(headers: &HeaderMap, ip: String, by_token: bool, pool: &PgPool) -> Self {
+        let mut requester = Self {
+            ip,
+            user_id: None,
+            api_token: None,
+        };
+        let Some(token) = by_token.then(|| bearer_token(headers)).flatten() else {
+            return requester;
+        };
+
+        if token.starts_with(API_TOKEN_PREFIX) {
+            match api_token_user(pool, token).await {
+                Ok(Some(user_id)) => {
+                    requester.user_id = Some(user_id);
+                    requester.api_token = Some(hash_token(token));
+                }
+                Ok(None) => {}
+                Err(e) => tracing::error!("API token lookup for rate limiting failed: {}", e),
             }
+        } else if let Ok((user_id, _)) = extract_session_from_token(token) {
+            requester.user_id = Some(user_id);
         }
-
-        // Check if we need to reset the window
-        if now.duration_since(client_info.window_start) >= self.config.window_duration {
-            client_info.request_count = 1;
-            client_info.window_start = now;
-            return Ok(());
-        }
-
-        // Increment request count
-        client_info.request_count += 1;
-
-        // Check if limit is exceeded
-        if client_info.request_count > self.config.max_requests {
-            client_info.blocked_until = Some(now + self.config.block_duration);
-            tracing::warn!(
-                "Rate limit exceeded for client {}: {} requests in {:?} window. Blocking for {:?}",
-                client_ip,
-                client_info.request_count,
-                self.config.window_duration,
-                self.config.block_duration
-            );
-            return Err(StatusCode::TOO_MANY_REQUESTS);
-        }
-
-        Ok(())
+        requester
     }
 
-    /// Clean up expired entries to prevent memory leaks
-    #[allow(dead_code)] // Utility function for memory management
-    pub fn cleanup_expired(&self) {
-        let mut clients = self.clients.lock().unwrap();
-        let now = Instant::now();
-
-        clients.retain(|_ip, info| {
-            // Remove clients whose block has expired and haven't made requests recently
-            if let Some(blocked_until) = info.blocked_until {
-                now < blocked_until
-            } else {
-                // Keep clients that have made requests in the last window + some buffer
-                now.duration_since(info.window_start)
-                    < self.config.window_duration + Duration::from_secs(300)
-            }
-        });
-
-        tracing::debug!("Rate limiter cleanup: {} active clients", clients.len());
-    }
-
-    /// Get current status for a client (for debugging/monitoring)
-    pub fn get_client_status(&self, client_ip: &str) -> Option<(usize, Duration, bool)> {
-        let clients = self.clients.lock().unwrap();
-        if let Some(info) = clients.get(client_ip) {
-            let now = Instant::now();
-            let is_blocked = info.blocked_until.is_some_and(|until| now < until);
-            let window_remaining = self
-                .config
-                .window_duration
-                .saturating_sub(now.duration_since(info.window_start));
-
-            Some((info.request_count, window_remaining, is_blocked))
-        } else {
-            None
+    /// What the requests counted together under a key share; requests
+    /// without a user or API token are counted by address
+    fn subject(&self, key: RateLimitKey) -> String {
+        match (key, &self.user_id, &self.api_token) {
+            (RateLimitKey::User, Some(user_id), _) => format!("user:{user_id}"),
+            (RateLimitKey::ApiToken, _, Some(token_hash)) => format!("api_token:{token_hash}"),
+            _ => format!("ip:{}", self.ip),
         }
     }
 }
 
+/// The token of a `Bearer` authorization header
+fn bearer_token(headers: &HeaderMap) -> Option<&str> {
+    headers
+        .get(AUTHORIZATION)?
+        .to_str()
+        .ok()?
+        .strip_prefix("Bearer ")
+}
+
 /// Rate limiting middleware
+///
+/// Counts the request against every policy matching its route and refuses
+/// it with `429` as soon as one is exceeded. Responses carry the
+/// `RateLimit-*` headers of the policy closest to its limit.
 pub async fn rate_limit_middleware(
-    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+    State(state): State<AppState>,
     request: Request,
     next: Next,
 ) -> Response {
+    let limiter = state.rate_limiter.clone();
+    let policies = limiter.policies_for(request.method(), request.uri().path());
+    if policies.is_empty() {
+        return next.run(request).await;
+    }
+    let ip = ClientIp::of(request.extensions())
+        .map(|ip| ip.to_string())
+        .unwrap_or_else(|| "unknown".to_string());
     tracing::debug!(
         "Rate limit middleware - Client IP: {}, Method: {}, URI: {}",
-        addr.ip(),
+        ip,
         request.method(),
         request.uri()
     );
-    // Get the rate limiter from the request extensions or create a default one
-    static RATE_LIMITER: std::sync::OnceLock<RateLimiter> = std::sync::OnceLock::new();
-    let rate_limiter = RATE_LIMITER.get_or_init(|| {
-        RateLimiter::new(RateLimitConfig {
-            max_requests: 60, // 60 requests per minute for general endpoints
-            window_duration: Duration::from_secs(60),
-            block_duration: Duration::from_secs(300), // 5 minute block
-        })
-    });
-
-    let client_ip = addr.ip().to_string();
-
-    // Check rate limit
-    match rate_limiter.check_rate_limit(&client_ip) {
-        Ok(()) => {
-            let mut response = next.run(request).await;
 
-            // Add rate limit headers to response
-            if let Some((count, remaining, _)) = rate_limiter.get_client_status(&client_ip) {
-                let headers = response.headers_mut();
-
-                if let Ok(count_header) = HeaderValue::from_str(&count.to_string()) {
-                    headers.insert("X-RateLimit-Requests", count_header);
-                }
-
-                if let Ok(limit_header) =
-                    HeaderValue::from_str(&rate_limiter.config.max_requests.to_string())
-                {
-                    headers.insert("X-RateLimit-Limit", limit_header);
-                }
-
-                if let Ok(remaining_header) =
-                    HeaderValue::from_str(&remaining.as_secs().to_string())
-                {
-                    headers.insert("X-RateLimit-Reset", remaining_header);
-                }
+    let by_token = policies
+        .iter()
+        .any(|(policy, _)| policy.key != RateLimitKey::Ip);
+    let requester = Requester::identify(request.headers(), ip, by_token, &state.pool).await;
+
+    let mut tightest: Option<RateLimitDecision> = None;
+    for (policy, quota) in policies {
+        let subject = requester.subject(policy.key);
+        let key = format!("{}:{}", policy.name, subject);
+        // Requests are let through while the store is unavailable rather
+        // than taking the whole API down with it
+        let decision = match limiter.store.check(&key, quota).await {
+            Ok(decision) => decision,
+            Err(e) => {
+                tracing::error!("Rate limit store failed, request let through: {}", e);
+                continue;
             }
-
-            response
+        };
+
+        if !decision.allowed {
+            tracing::warn!("Rate limit {} exceeded by {}", policy.name, subject);
+            let retry_after = decision.retry_after.unwrap_or(decision.reset_after);
+            let mut response = AppError::too_many_requests(
+                &policy.name,
+                format!("Rate limit {} exceeded", policy.name),
+                format!(
+                    "Too many requests. Please try again in {}.",
+                    describe_wait(retry_after)
+                ),
+                retry_after,
+            )
+            .into_response();
+            insert_rate_limit_headers(response.headers_mut(), &decision);
+            return response;
         }
-        Err(status) => {
-            tracing::warn!("Rate limit exceeded for IP: {}", client_ip);
-
-            let mut response = status.into_response();
-            let headers = response.headers_mut();
-
-            // Add rate limit headers
-            if let Ok(retry_after) =
-                HeaderValue::from_str(&rate_limiter.config.block_duration.as_secs().to_string())
-            {
-                headers.insert("Retry-After", retry_after);
-            }
+        if tightest
+            .as_ref()
+            .is_none_or(|tightest| decision.remaining < tightest.remaining)
+        {
+            tightest = Some(decision);
+        }
+    }
 
-            if let Ok(limit_header) =
-                HeaderValue::from_str(&rate_limiter.config.max_requests.to_string())
-            {
-                headers.insert("X-RateLimit-Limit", limit_header);
-            }
+    let mut response = next.run(request).await;
+    if let Some(decision) = tightest {
+        insert_rate_limit_headers(response.headers_mut(), &decision);
+    }
+    response
+}
 
-            response
-        }
+/// Headers of limited responses, which browsers only show to the web app
+/// when CORS exposes them
+pub const RATE_LIMIT_HEADERS: [&str; 4] = [
+    "ratelimit-limit",
+    "ratelimit-remaining",
+    "ratelimit-reset",
+    "retry-after",
+];
+
+/// Add the `RateLimit-Limit`, `RateLimit-Remaining` and `RateLimit-Reset`
+/// headers of a decision
+fn insert_rate_limit_headers(headers: &mut HeaderMap, decision: &RateLimitDecision) {
+    for (name, value) in [
+        ("RateLimit-Limit", u64::from(decision.limit)),
+        ("RateLimit-Remaining", u64::from(decision.remaining)),
+        ("RateLimit-Reset", secs(decision.reset_after)),
+    ] {
+        headers.insert(name, HeaderValue::from(value));
     }
 }
 
-/// Stricter rate limiting for authentication endpoints
-pub async fn auth_rate_limit_middleware(
-    ConnectInfo(addr): ConnectInfo<SocketAddr>,
-    request: Request,
-    next: Next,
-) -> Response {
-    tracing::info!(
-        "Auth rate limit middleware - Client IP: {}, Method: {}, URI: {}",
-        addr.ip(),
-        request.method(),
-        request.uri()
-    );
-    static AUTH_RATE_LIMITER: std::sync::OnceLock<RateLimiter> = std::sync::OnceLock::new();
-    let rate_limiter = AUTH_RATE_LIMITER.get_or_init(|| {
-        RateLimiter::new(RateLimitConfig {
-            max_requests: 5, // Only 5 auth attempts per minute
-            window_duration: Duration::from_secs(60),
-            block_duration: Duration::from_secs(900), // 15 minute block for auth abuse
-        })
-    });
+/// Whole seconds of a duration, rounded up
+fn secs(duration: Duration) -> u64 {
+    duration.as_millis().div_ceil(1000) as u64
+}
 
-    let client_ip = addr.ip().to_string();
+/// A wait for users, e.g. `30 seconds` or `5 minutes`
+fn describe_wait(wait: Duration) -> String {
+    match secs(wait).max(1) {
+        1 => "a second".to_string(),
+        secs @ 2..60 => format!("{secs} seconds"),
+        60 => "a minute".to_string(),
+        secs => format!("{} minutes", secs.div_ceil(60)),
+    }
+}
 
-    match rate_limiter.check_rate_limit(&client_ip) {
-        Ok(()) => next.run(request).await,
-        Err(status) => {
-            tracing::warn!("Auth rate limit exceeded for IP: {}", client_ip);
-            status.into_response()
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_requests_are_counted_by_the_key_of_the_policy() {
+        let requester = Requester {
+            ip: "203.0.113.7".to_string(),
+            user_id: None,
+            api_token: None,
+        };
+        for key in [RateLimitKey::Ip, RateLimitKey::User, RateLimitKey::ApiToken] {
+            assert_eq!(requester.subject(key), "ip:203.0.113.7");
         }
+
+        let user_id = Uuid::new_v4();
+        let requester = Requester {
+            user_id: Some(user_id),
+            api_token: Some("abc".to_string()),
+            ..requester
+        };
+        assert_eq!(requester.subject(RateLimitKey::Ip), "ip:203.0.113.7");
+        assert_eq!(
+            requester.subject(RateLimitKey::User),
+            format!("user:{user_id}")
+        );
+        assert_eq!(requester.subject(RateLimitKey::ApiToken), "api_token:abc");
     }
-}
 
-// Background task to periodically clean up expired entries
-#[allow(dead_code)] // Utility function for background cleanup
-pub fn start_cleanup_task(rate_limiter: RateLimiter) {
-    tokio::spawn(async move {
-        let mut interval = tokio::time::interval(Duration::from_secs(300)); // Every 5 minutes
+    #[test]
+    fn test_policies_match_routes_outside_exemptions() {
+        let limiter = RateLimiter::new(
+            Arc::new(crate::services::rate_limit_store::MemoryRateLimitStore::new()),
+            &RateLimitConfig::default(),
+        );
+        let names = |method: Method, path: &str| -> Vec<String> {
+            limiter
+                .policies_for(&method, path)
+                .into_iter()
+                .map(|(policy, _)| policy.name.clone())
+                .collect()
+        };
+
+        assert!(names(Method::GET, "/api/v1/health/live").is_empty());
+        assert!(names(Method::OPTIONS, "/api/v1/auth/login").is_empty());
+        assert_eq!(names(Method::GET, "/api/v1/subscriptions"), ["general"]);
+        assert_eq!(
+            names(Method::POST, "/api/v1/auth/login"),
+            ["general", "auth"]
+        );
+        assert_eq!(names(Method::POST, "/api/v1/auth/refresh"), ["general"]);
+        assert_eq!(
+            names(Method::POST, "/api/v1/auth/verify-email/resend"),
+            ["general", "auth"]
+        );
+        assert_eq!(names(Method::PATCH, "/api/v1/users/me"), ["general"]);
+        assert_eq!(
+            names(Method::DELETE, "/api/v1/users/me"),
+            ["general", "auth"]
+        );
+    }
 
-        loop {
-            interval.tick().await;
-            rate_limiter.cleanup_expired();
-        }
-    });
+    #[test]
+    fn test_waits_are_described_in_seconds_or_minutes() {
+        assert_eq!(describe_wait(Duration::from_millis(200)), "a second");
+        assert_eq!(describe_wait(Duration::from_secs(30)), "30 seconds");
+        assert_eq!(describe_wait(Duration::from_secs(60)), "a minute");
+        assert_eq!(describe_wait(Duration::from_secs(290)), "5 minutes");
+    }
 }
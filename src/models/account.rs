@@ -0,0 +1,50 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::models::{
+    ApiToken, Notification, PaymentRecord, Session, Subscription, SubscriptionEvent, User,
+    UserIdentity, UserProfile, WebhookEndpoint,
+};
+
+/// Name of the account export format, stored in every archive
+pub const ACCOUNT_EXPORT_FORMAT: &str = "sub-pal-account-export";
+/// Version of the account export format; bump it when the layout changes
+pub const ACCOUNT_EXPORT_VERSION: u32 = 2;
+
+/// Archive of all personal data of an account
+#[derive(Debug, Serialize)]
+pub struct AccountExport {
+    pub format: &'static str,
+    pub version: u32,
+    pub exported_at: DateTime<Utc>,
+    pub user: User,
+    pub profile: Option<UserProfile>,
+    pub subscriptions: Vec<Subscription>,
+    pub payments: Vec<PaymentRecord>,
+    pub notifications: Vec<Notification>,
+    pub events: Vec<SubscriptionEvent>,
+    pub webhook_endpoints: Vec<WebhookEndpoint>,
+    /// Every recorded login with its device details, including ended ones
+    pub sessions: Vec<Session>,
+    /// API tokens without the tokens themselves
+    pub api_tokens: Vec<ApiToken>,
+    /// Single sign-on identities linked to the account
+    pub identities: Vec<UserIdentity>,
+}
+
+/// Account deletion request DTO; the password confirms the deletion
+///
+/// Accounts without a password confirm by signing in again instead.
+#[derive(Debug, Deserialize)]
+pub struct DeleteAccountRequest {
+    pub password: Option<String>,
+}
+
+/// Outcome of an account deletion
+#[derive(Debug, Serialize)]
+pub struct AccountDeletion {
+    /// Whether the account is already gone
+    pub deleted: bool,
+    /// When the account will be purged, if deletion has a grace period
+    pub deletion_scheduled_at: Option<DateTime<Utc>>,
+}
@@ -0,0 +1,68 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+/// Maximum number of active API tokens a user can have
+pub const MAX_API_TOKENS: i64 = 20;
+/// Longest expiry that can be chosen for an API token
+pub const MAX_API_TOKEN_EXPIRY_DAYS: i64 = 365;
+
+/// What an API token may do
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ApiTokenScope {
+    /// Only requests that change nothing, such as `GET`
+    Read,
+    /// Reading and changing subscriptions, payments, notifications and webhooks
+    ReadWrite,
+}
+
+impl ApiTokenScope {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ApiTokenScope::Read => "read",
+            ApiTokenScope::ReadWrite => "read_write",
+        }
+    }
+}
+
+impl From<String> for ApiTokenScope {
+    fn from(s: String) -> Self {
+        match s.as_str() {
+            "read_write" => ApiTokenScope::ReadWrite,
+            _ => ApiTokenScope::Read,
+        }
+    }
+}
+
+/// A personal API token for scripts and automation
+#[derive(Debug, Serialize)]
+pub struct ApiToken {
+    pub id: Uuid,
+    pub name: String,
+    pub scope: ApiTokenScope,
+    /// Start of the token, to recognize it
+    pub token_prefix: String,
+    pub expires_at: Option<DateTime<Utc>>,
+    pub last_used_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// An API token together with the token itself
+///
+/// The token is only returned when it is created.
+#[derive(Debug, Serialize)]
+pub struct ApiTokenWithSecret {
+    #[serde(flatten)]
+    pub api_token: ApiToken,
+    pub token: String,
+}
+
+/// API token creation request DTO
+#[derive(Debug, Deserialize)]
+pub struct CreateApiTokenRequest {
+    pub name: String,
+    pub scope: ApiTokenScope,
+    /// Days until the token expires; the token does not expire when omitted
+    pub expires_in_days: Option<i64>,
+}
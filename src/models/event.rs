@@ -0,0 +1,338 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::str::FromStr;
+use uuid::Uuid;
+
+use super::subscription::{Subscription, SubscriptionStatus};
+
+/// Fields of a subscription tracked in event snapshots
+const TRACKED_FIELDS: [&str; 15] = [
+    "name",
+    "description",
+    "amount",
+    "currency",
+    "billing_cycle_unit",
+    "billing_cycle_interval",
+    "start_date",
+    "next_billing_date",
+    "billing_anchor_date",
+    "status",
+    "paused_at",
+    "end_date",
+    "trial_end_date",
+    "category",
+    "color",
+];
+
+/// Subscription event model representing one entry of a subscription's history
+#[derive(Debug, Serialize)]
+pub struct SubscriptionEvent {
+    pub id: Uuid,
+    /// `None` once the subscription was deleted
+    pub subscription_id: Option<Uuid>,
+    pub user_id: Uuid,
+    pub event_type: SubscriptionEventType,
+    // only the fields affected by the event
+    pub before: Option<Value>,
+    pub after: Option<Value>,
+    pub note: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// An event about to be recorded
+#[derive(Debug, PartialEq)]
+pub struct NewSubscriptionEvent {
+    pub event_type: SubscriptionEventType,
+    pub before: Option<Value>,
+    pub after: Option<Value>,
+    pub note: Option<String>,
+}
+
+/// Represents the kind of a subscription event
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+pub enum SubscriptionEventType {
+    Created,
+    Updated,
+    Repriced,
+    Paused,
+    Resumed,
+    Cancelled,
+    Renewed,
+    Deleted,
+}
+
+impl SubscriptionEventType {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SubscriptionEventType::Created => "created",
+            SubscriptionEventType::Updated => "updated",
+            SubscriptionEventType::Repriced => "repriced",
+            SubscriptionEventType::Paused => "paused",
+            SubscriptionEventType::Resumed => "resumed",
+            SubscriptionEventType::Cancelled => "cancelled",
+            SubscriptionEventType::Renewed => "renewed",
+            SubscriptionEventType::Deleted => "deleted",
+        }
+    }
+}
+
+impl FromStr for SubscriptionEventType {
+    type Err = String;
+
+    fn from_str(event_type: &str) -> Result<Self, Self::Err> {
+        match event_type.to_lowercase().as_str() {
+            "created" => Ok(SubscriptionEventType::Created),
+            "updated" => Ok(SubscriptionEventType::Updated),
+            "repriced" => Ok(SubscriptionEventType::Repriced),
+            "paused" => Ok(SubscriptionEventType::Paused),
+            "resumed" => Ok(SubscriptionEventType::Resumed),
+            "cancelled" => Ok(SubscriptionEventType::Cancelled),
+            "renewed" => Ok(SubscriptionEventType::Renewed),
+            "deleted" => Ok(SubscriptionEventType::Deleted),
+            _ => Err(format!("Invalid subscription event type: {event_type}")),
+        }
+    }
+}
+
+impl From<String> for SubscriptionEventType {
+    fn from(value: String) -> Self {
+        // Unknown types can only come from newer rows; show them as plain updates
+        SubscriptionEventType::from_str(&value).unwrap_or(SubscriptionEventType::Updated)
+    }
+}
+
+impl NewSubscriptionEvent {
+    /// Event for a newly created subscription, with a full snapshot as `after`
+    pub fn created(subscription: &Subscription) -> Self {
+        Self {
+            event_type: SubscriptionEventType::Created,
+            before: None,
+            after: Some(snapshot(subscription, &TRACKED_FIELDS)),
+            note: None,
+        }
+    }
+
+    /// Event for a deleted subscription, with a full snapshot and the id as
+    /// `before`, since the history no longer references the subscription
+    pub fn deleted(subscription: &Subscription) -> Self {
+        let fields: Vec<&str> = TRACKED_FIELDS.iter().copied().chain(["id"]).collect();
+        Self {
+            event_type: SubscriptionEventType::Deleted,
+            before: Some(snapshot(subscription, &fields)),
+            after: None,
+            note: None,
+        }
+    }
+
+    /// Events describing the changes between two versions of a subscription
+    ///
+    /// A status change becomes a Paused/Resumed/Cancelled event that also
+    /// carries the billing dates moved by the transition, a change of amount
+    /// or currency becomes a Repriced event, and any other change is reported
+    /// as a single Updated event.
+    pub fn changes(before: &Subscription, after: &Subscription) -> Vec<Self> {
+        let mut events = Vec::new();
+        let status_changed = before.status != after.status;
+
+        if status_changed {
+            let event_type = match after.status {
+                SubscriptionStatus::Active => SubscriptionEventType::Resumed,
+                SubscriptionStatus::Paused => SubscriptionEventType::Paused,
+                SubscriptionStatus::Cancelled => SubscriptionEventType::Cancelled,
+            };
+            let mut fields = vec!["status"];
+            if before.paused_at != after.paused_at {
+                fields.push("paused_at");
+            }
+            if before.end_date != after.end_date {
+                fields.push("end_date");
+            }
+            if before.next_billing_date != after.next_billing_date {
+                fields.push("next_billing_date");
+            }
+            if before.billing_anchor_date != after.billing_anchor_date {
+                fields.push("billing_anchor_date");
+            }
+            events.push(Self::diff(event_type, before, after, &fields));
+        }
+
+        if before.amount != after.amount || before.currency != after.currency {
+            events.push(Self::diff(
+                SubscriptionEventType::Repriced,
+                before,
+                after,
+                &["amount", "currency"],
+            ));
+        }
+
+        let mut updated = Vec::new();
+        if before.name != after.name {
+            updated.push("name");
+        }
+        if before.description != after.description {
+            updated.push("description");
+        }
+        if before.billing_cycle_unit != after.billing_cycle_unit {
+            updated.push("billing_cycle_unit");
+        }
+        if before.billing_cycle_interval != after.billing_cycle_interval {
+            updated.push("billing_cycle_interval");
+        }
+        if before.start_date != after.start_date {
+            updated.push("start_date");
+        }
+        if !status_changed && before.next_billing_date != after.next_billing_date {
+            updated.push("next_billing_date");
+        }
+        if !status_changed && before.billing_anchor_date != after.billing_anchor_date {
+            updated.push("billing_anchor_date");
+        }
+        if before.trial_end_date != after.trial_end_date {
+            updated.push("trial_end_date");
+        }
+        if before.category != after.category {
+            updated.push("category");
+        }
+        if before.color != after.color {
+            updated.push("color");
+        }
+        if !updated.is_empty() {
+            events.push(Self::diff(
+                SubscriptionEventType::Updated,
+                before,
+                after,
+                &updated,
+            ));
+        }
+
+        events
+    }
+
+    fn diff(
+        event_type: SubscriptionEventType,
+        before: &Subscription,
+        after: &Subscription,
+        fields: &[&str],
+    ) -> Self {
+        Self {
+            event_type,
+            before: Some(snapshot(before, fields)),
+            after: Some(snapshot(after, fields)),
+            note: None,
+        }
+    }
+}
+
+/// Serialize the given fields of a subscription into a JSON object
+fn snapshot(subscription: &Subscription, fields: &[&str]) -> Value {
+    let mut values = match serde_json::to_value(subscription) {
+        Ok(Value::Object(values)) => values,
+        _ => Map::new(),
+    };
+    values.retain(|key, _| fields.contains(&key.as_str()));
+    Value::Object(values)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::subscription::{BillingCycleUnit, Currency};
+    use bigdecimal::BigDecimal;
+    use chrono::NaiveDate;
+    use serde_json::json;
+
+    fn create_test_subscription() -> Subscription {
+        Subscription {
+            id: Uuid::new_v4(),
+            user_id: Uuid::new_v4(),
+            name: "Test Subscription".to_string(),
+            description: None,
+            amount: BigDecimal::from(10),
+            currency: Currency::Usd,
+            billing_cycle_unit: BillingCycleUnit::Month,
+            billing_cycle_interval: 1,
+            start_date: NaiveDate::from_ymd_opt(2024, 1, 1).unwrap(),
+            next_billing_date: NaiveDate::from_ymd_opt(2024, 2, 1).unwrap(),
+            billing_anchor_date: NaiveDate::from_ymd_opt(2024, 1, 1).unwrap(),
+            status: SubscriptionStatus::Active,
+            paused_at: None,
+            end_date: None,
+            trial_end_date: None,
+            category: None,
+            color: None,
+            created_at: None,
+            updated_at: None,
+        }
+    }
+
+    #[test]
+    fn test_created_event_has_full_snapshot() {
+        let event = NewSubscriptionEvent::created(&create_test_subscription());
+
+        assert_eq!(event.event_type, SubscriptionEventType::Created);
+        assert!(event.before.is_none());
+        let after = event.after.unwrap();
+        assert_eq!(after["name"], json!("Test Subscription"));
+        assert!(after.get("id").is_none());
+        assert!(after.get("user_id").is_none());
+    }
+
+    #[test]
+    fn test_deleted_event_names_the_subscription() {
+        let subscription = create_test_subscription();
+        let event = NewSubscriptionEvent::deleted(&subscription);
+
+        assert_eq!(event.event_type, SubscriptionEventType::Deleted);
+        assert!(event.after.is_none());
+        let before = event.before.unwrap();
+        assert_eq!(before["id"], json!(subscription.id));
+        assert_eq!(before["name"], json!("Test Subscription"));
+        assert!(before.get("user_id").is_none());
+    }
+
+    #[test]
+    fn test_no_changes_no_events() {
+        let before = create_test_subscription();
+        let mut after = create_test_subscription();
+        // Same value with a different scale is not a price change
+        after.amount = "10.00".parse().unwrap();
+
+        assert!(NewSubscriptionEvent::changes(&before, &after).is_empty());
+    }
+
+    #[test]
+    fn test_changes_are_classified() {
+        let before = create_test_subscription();
+        let mut after = create_test_subscription();
+        after.status = SubscriptionStatus::Paused;
+        after.paused_at = NaiveDate::from_ymd_opt(2024, 1, 20);
+        after.amount = BigDecimal::from(12);
+        after.name = "Renamed".to_string();
+
+        let events = NewSubscriptionEvent::changes(&before, &after);
+        let types: Vec<_> = events.iter().map(|e| e.event_type).collect();
+        assert_eq!(
+            types,
+            vec![
+                SubscriptionEventType::Paused,
+                SubscriptionEventType::Repriced,
+                SubscriptionEventType::Updated,
+            ]
+        );
+
+        assert_eq!(
+            events[0].before,
+            Some(json!({"status": "Active", "paused_at": null}))
+        );
+        assert_eq!(
+            events[0].after,
+            Some(json!({"status": "Paused", "paused_at": "2024-01-20"}))
+        );
+        assert_eq!(
+            events[1].after,
+            Some(json!({"amount": "12", "currency": "Usd"}))
+        );
+        assert_eq!(events[2].after, Some(json!({"name": "Renamed"})));
+    }
+}
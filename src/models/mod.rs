@@ -1,8 +1,63 @@
+pub mod account;
+pub mod api_token;
+pub mod event;
+pub mod notification;
+pub mod oidc;
+pub mod pagination;
+pub mod payment;
+pub mod session;
+pub mod statistics;
 pub mod subscription;
+pub mod two_factor;
 pub mod user;
+pub mod webhook;
+
+pub use self::account::{
+    ACCOUNT_EXPORT_FORMAT, ACCOUNT_EXPORT_VERSION, AccountDeletion, AccountExport,
+    DeleteAccountRequest,
+};
+
+pub use self::api_token::{
+    ApiToken, ApiTokenScope, ApiTokenWithSecret, CreateApiTokenRequest, MAX_API_TOKEN_EXPIRY_DAYS,
+    MAX_API_TOKENS,
+};
 
 pub use self::user::{
-    AuthResponse, LoginRequest, RegisterRequest, User, UserProfile, UserResponse,
+    AuthResponse, ChangeEmailRequest, ChangePasswordRequest, DEFAULT_REMINDER_LEAD_DAYS,
+    LoginRequest, LoginResponse, MAX_REMINDER_LEAD_DAYS, PasswordResetConfirm,
+    PasswordResetRequest, RefreshTokenRequest, RegisterRequest, TokenResponse,
+    UnlockAccountRequest, UpdateProfileRequest, User, UserProfile, UserResponse,
+    VerifyEmailRequest,
 };
 
-pub use self::subscription::{Subscription, SubscriptionListResponse};
+pub use self::session::Session;
+
+pub use self::oidc::{OidcAuthorization, OidcCallbackRequest, OidcProviderInfo, UserIdentity};
+
+pub use self::two_factor::{
+    DisableTwoFactorRequest, RecoveryCodes, TwoFactorChallenge, TwoFactorCodeRequest,
+    TwoFactorEnrollment, TwoFactorLoginRequest, TwoFactorStatus,
+};
+
+pub use self::subscription::{CancelSubscriptionRequest, StatusTransition, Subscription};
+
+pub use self::statistics::{
+    CategoryBreakdown, CurrencyTotals, StatisticsQuery, StatusBreakdown, SubscriptionStatistics,
+    TopSubscription, UpcomingRenewal,
+};
+
+pub use self::pagination::{PaginatedResponse, Pagination, PaginationQuery};
+
+pub use self::payment::{CreatePaymentRequest, MonthlyPayments, PaymentRecord};
+
+pub use self::event::{NewSubscriptionEvent, SubscriptionEvent, SubscriptionEventType};
+
+pub use self::notification::{
+    NewNotification, Notification, NotificationFilter, NotificationsReadResponse,
+};
+
+pub use self::webhook::{
+    CreateWebhookRequest, MAX_WEBHOOK_ENDPOINTS, UpdateWebhookRequest, WebhookDelivery,
+    WebhookDeliveryAttempt, WebhookDeliveryDetail, WebhookDeliveryFilter, WebhookEndpoint,
+    WebhookEndpointWithSecret, WebhookEvent, WebhookEventType,
+};
@@ -0,0 +1,217 @@
+use bigdecimal::BigDecimal;
+use chrono::{DateTime, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+use uuid::Uuid;
+
+use super::subscription::{Currency, Subscription};
+
+/// Notification model representing an entry of a user's notification center
+#[derive(Debug, Serialize)]
+pub struct Notification {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    pub subscription_id: Option<Uuid>,
+    pub notification_type: NotificationType,
+    pub title: String,
+    pub message: String,
+    // the date the notification is about, e.g. the renewal date
+    pub due_date: Option<NaiveDate>,
+    pub read: bool,
+    pub read_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// A notification about to be recorded
+#[derive(Debug, PartialEq)]
+pub struct NewNotification {
+    pub subscription_id: Option<Uuid>,
+    pub notification_type: NotificationType,
+    pub title: String,
+    pub message: String,
+    pub due_date: Option<NaiveDate>,
+}
+
+/// Query parameters filtering the notification list
+#[derive(Debug, Default, Deserialize)]
+pub struct NotificationFilter {
+    /// Only read (`true`) or unread (`false`) notifications
+    pub read: Option<bool>,
+}
+
+/// Result of marking all notifications as read
+#[derive(Debug, Serialize)]
+pub struct NotificationsReadResponse {
+    pub count: u64,
+}
+
+/// Represents the kind of a notification
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Default)]
+pub enum NotificationType {
+    #[default]
+    RenewalUpcoming,
+    TrialEnding,
+    PriceChanged,
+}
+
+impl NotificationType {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            NotificationType::RenewalUpcoming => "renewal_upcoming",
+            NotificationType::TrialEnding => "trial_ending",
+            NotificationType::PriceChanged => "price_changed",
+        }
+    }
+}
+
+impl FromStr for NotificationType {
+    type Err = String;
+
+    fn from_str(notification_type: &str) -> Result<Self, Self::Err> {
+        match notification_type.to_lowercase().as_str() {
+            "renewal_upcoming" => Ok(NotificationType::RenewalUpcoming),
+            "trial_ending" => Ok(NotificationType::TrialEnding),
+            "price_changed" => Ok(NotificationType::PriceChanged),
+            _ => Err(format!("Invalid notification type: {notification_type}")),
+        }
+    }
+}
+
+impl From<String> for NotificationType {
+    fn from(value: String) -> Self {
+        NotificationType::from_str(&value).unwrap_or_default()
+    }
+}
+
+impl NewNotification {
+    /// Reminder that a subscription renews on its next billing date
+    pub fn renewal_upcoming(
+        subscription_id: Uuid,
+        name: &str,
+        amount: &BigDecimal,
+        currency: &Currency,
+        next_billing_date: NaiveDate,
+        today: NaiveDate,
+    ) -> Self {
+        Self {
+            subscription_id: Some(subscription_id),
+            notification_type: NotificationType::RenewalUpcoming,
+            title: format!("{name} renews soon"),
+            message: format!(
+                "Your {name} subscription renews {} ({next_billing_date}) for {amount} {}.",
+                relative_day(next_billing_date, today),
+                currency.as_str()
+            ),
+            due_date: Some(next_billing_date),
+        }
+    }
+
+    /// Reminder that a subscription's free trial is about to end
+    pub fn trial_ending(
+        subscription_id: Uuid,
+        name: &str,
+        trial_end_date: NaiveDate,
+        today: NaiveDate,
+    ) -> Self {
+        Self {
+            subscription_id: Some(subscription_id),
+            notification_type: NotificationType::TrialEnding,
+            title: format!("{name} trial ends soon"),
+            message: format!(
+                "Your free trial of {name} ends {} ({trial_end_date}).",
+                relative_day(trial_end_date, today)
+            ),
+            due_date: Some(trial_end_date),
+        }
+    }
+
+    /// Notice of a price change between two versions of a subscription, if any
+    pub fn price_changed(before: &Subscription, after: &Subscription) -> Option<Self> {
+        if before.amount == after.amount && before.currency == after.currency {
+            return None;
+        }
+
+        Some(Self {
+            subscription_id: Some(after.id),
+            notification_type: NotificationType::PriceChanged,
+            title: format!("{} price changed", after.name),
+            message: format!(
+                "{} now costs {} {} instead of {} {}, starting with the renewal on {}.",
+                after.name,
+                after.amount,
+                after.currency.as_str(),
+                before.amount,
+                before.currency.as_str(),
+                after.next_billing_date
+            ),
+            due_date: Some(after.next_billing_date),
+        })
+    }
+}
+
+/// Describe a date relative to today, e.g. "tomorrow" or "in 3 days"
+fn relative_day(date: NaiveDate, today: NaiveDate) -> String {
+    match (date - today).num_days() {
+        0 => "today".to_string(),
+        1 => "tomorrow".to_string(),
+        days => format!("in {days} days"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2024, 3, day).unwrap()
+    }
+
+    #[test]
+    fn test_renewal_upcoming_message() {
+        let id = Uuid::new_v4();
+        let amount: BigDecimal = "15.99".parse().unwrap();
+
+        let notification = NewNotification::renewal_upcoming(
+            id,
+            "Netflix",
+            &amount,
+            &Currency::Usd,
+            date(4),
+            date(1),
+        );
+        assert_eq!(
+            notification.notification_type,
+            NotificationType::RenewalUpcoming
+        );
+        assert_eq!(notification.subscription_id, Some(id));
+        assert_eq!(notification.due_date, Some(date(4)));
+        assert_eq!(
+            notification.message,
+            "Your Netflix subscription renews in 3 days (2024-03-04) for 15.99 USD."
+        );
+
+        let notification = NewNotification::renewal_upcoming(
+            id,
+            "Netflix",
+            &amount,
+            &Currency::Usd,
+            date(2),
+            date(1),
+        );
+        assert!(notification.message.contains("renews tomorrow"));
+    }
+
+    #[test]
+    fn test_trial_ending_message() {
+        let notification =
+            NewNotification::trial_ending(Uuid::new_v4(), "Spotify", date(1), date(1));
+        assert_eq!(
+            notification.notification_type,
+            NotificationType::TrialEnding
+        );
+        assert_eq!(
+            notification.message,
+            "Your free trial of Spotify ends today (2024-03-01)."
+        );
+    }
+}
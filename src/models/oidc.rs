@@ -0,0 +1,40 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+/// Single sign-on settings the login page needs
+#[derive(Debug, Serialize)]
+pub struct OidcProviderInfo {
+    pub enabled: bool,
+    /// Name to show on the sign-in button, `None` when single sign-on is disabled
+    pub provider_name: Option<String>,
+}
+
+/// Provider login page a user is sent to
+#[derive(Debug, Serialize)]
+pub struct OidcAuthorization {
+    pub authorization_url: String,
+    /// State the provider passes back, for the app to check the callback
+    /// belongs to a login it started
+    pub state: String,
+}
+
+/// Parameters the provider appended to the redirect back to the app
+#[derive(Debug, Deserialize)]
+pub struct OidcCallbackRequest {
+    pub code: String,
+    pub state: String,
+}
+
+/// A single sign-on identity linked to an account
+#[derive(Debug, Serialize)]
+pub struct UserIdentity {
+    pub id: Uuid,
+    pub issuer: String,
+    /// The provider's id of the user, its `sub` claim
+    pub subject: String,
+    /// Address the provider reported when the identity was linked
+    pub email: Option<String>,
+    pub last_login_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+}
@@ -0,0 +1,95 @@
+use serde::{Deserialize, Serialize};
+
+/// Default number of items per page
+const DEFAULT_PAGE_LIMIT: i64 = 20;
+/// Maximum number of items per page
+const MAX_PAGE_LIMIT: i64 = 100;
+
+/// Pagination query parameters shared by list endpoints
+#[derive(Debug, Default, Deserialize)]
+pub struct PaginationQuery {
+    pub page: Option<i64>,
+    pub limit: Option<i64>,
+}
+
+impl PaginationQuery {
+    /// Requested page, starting at 1
+    pub fn page(&self) -> i64 {
+        self.page.unwrap_or(1).max(1)
+    }
+
+    /// Requested page size, clamped to `1..=MAX_PAGE_LIMIT`
+    pub fn limit(&self) -> i64 {
+        self.limit
+            .unwrap_or(DEFAULT_PAGE_LIMIT)
+            .clamp(1, MAX_PAGE_LIMIT)
+    }
+
+    /// Number of rows to skip for the requested page
+    pub fn offset(&self) -> i64 {
+        (self.page() - 1).saturating_mul(self.limit())
+    }
+}
+
+/// Pagination metadata returned with paginated lists
+#[derive(Debug, Serialize)]
+pub struct Pagination {
+    pub total: i64,
+    pub page: i64,
+    pub limit: i64,
+    pub pages: i64,
+}
+
+impl Pagination {
+    pub fn new(total: i64, query: &PaginationQuery) -> Self {
+        let limit = query.limit();
+        Self {
+            total,
+            page: query.page(),
+            limit,
+            pages: (total + limit - 1) / limit,
+        }
+    }
+}
+
+/// Paginated list response DTO
+#[derive(Debug, Serialize)]
+pub struct PaginatedResponse<T>
+where
+    T: Serialize,
+{
+    pub items: Vec<T>,
+    pub pagination: Pagination,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pagination_defaults() {
+        let query = PaginationQuery::default();
+
+        assert_eq!(query.page(), 1);
+        assert_eq!(query.limit(), DEFAULT_PAGE_LIMIT);
+        assert_eq!(query.offset(), 0);
+    }
+
+    #[test]
+    fn test_pagination_clamps_and_counts_pages() {
+        let query = PaginationQuery {
+            page: Some(3),
+            limit: Some(1000),
+        };
+        assert_eq!(query.limit(), MAX_PAGE_LIMIT);
+        assert_eq!(query.offset(), 2 * MAX_PAGE_LIMIT);
+
+        let query = PaginationQuery {
+            page: Some(0),
+            limit: Some(20),
+        };
+        assert_eq!(query.page(), 1);
+        assert_eq!(Pagination::new(41, &query).pages, 3);
+        assert_eq!(Pagination::new(0, &query).pages, 0);
+    }
+}
@@ -0,0 +1,48 @@
+use bigdecimal::BigDecimal;
+use chrono::{DateTime, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use super::subscription::Currency;
+
+/// Payment record representing an actual charge against a subscription
+#[derive(Debug, Serialize)]
+pub struct PaymentRecord {
+    pub id: Uuid,
+    pub subscription_id: Uuid,
+    pub user_id: Uuid,
+    pub amount: BigDecimal,
+    pub currency: Currency,
+    pub paid_date: NaiveDate,
+    // the billing cycle this payment covers: [cycle_start, cycle_end)
+    pub cycle_start: NaiveDate,
+    pub cycle_end: NaiveDate,
+    pub note: Option<String>,
+    // created by the renewal scheduler rather than recorded by the user
+    pub projected: bool,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Create payment record request DTO
+///
+/// Everything is optional: by default the payment covers the subscription's
+/// current cycle (its `next_billing_date`), for the subscription's amount and
+/// currency, paid today.
+#[derive(Debug, Default, Deserialize)]
+pub struct CreatePaymentRequest {
+    pub amount: Option<BigDecimal>,
+    pub currency: Option<Currency>,
+    pub paid_date: Option<NaiveDate>,
+    pub cycle_start: Option<NaiveDate>,
+    pub note: Option<String>,
+}
+
+/// Actual payments per month and currency
+#[derive(Debug, Serialize)]
+pub struct MonthlyPayments {
+    pub currency: Currency,
+    // first day of the month
+    pub month: NaiveDate,
+    pub count: i64,
+    pub total: BigDecimal,
+}
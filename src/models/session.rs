@@ -0,0 +1,18 @@
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use uuid::Uuid;
+
+/// A device the user is signed in on
+///
+/// A session starts at login and lasts as long as its refresh tokens.
+#[derive(Debug, Serialize)]
+pub struct Session {
+    pub id: Uuid,
+    pub user_agent: Option<String>,
+    pub ip_address: Option<String>,
+    pub created_at: DateTime<Utc>,
+    /// When a refresh token of the session was last exchanged
+    pub last_seen_at: DateTime<Utc>,
+    /// Whether the request listing the sessions was made by this session
+    pub current: bool,
+}
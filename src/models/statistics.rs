@@ -0,0 +1,83 @@
+use bigdecimal::BigDecimal;
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use super::payment::MonthlyPayments;
+use super::subscription::{Currency, SubscriptionStatus};
+
+/// Query parameters for the subscription statistics endpoint
+#[derive(Debug, Deserialize)]
+pub struct StatisticsQuery {
+    /// Number of most expensive subscriptions to return per currency
+    pub top: Option<i64>,
+    /// How many days ahead to look for upcoming renewals
+    pub upcoming_days: Option<i32>,
+    /// How many months of actual payments to include, counting the current one
+    pub history_months: Option<i32>,
+}
+
+/// Subscription statistics response DTO
+///
+/// All amounts are grouped by currency, since amounts in different
+/// currencies cannot be summed without an exchange rate.
+#[derive(Debug, Serialize)]
+pub struct SubscriptionStatistics {
+    pub totals: Vec<CurrencyTotals>,
+    pub by_category: Vec<CategoryBreakdown>,
+    pub by_status: Vec<StatusBreakdown>,
+    pub top_subscriptions: Vec<TopSubscription>,
+    pub upcoming_renewals: Vec<UpcomingRenewal>,
+    /// Historical spend from payments the user recorded, without projected
+    /// payments or amounts
+    pub payment_history: Vec<MonthlyPayments>,
+}
+
+/// Normalized spend of active subscriptions in one currency
+#[derive(Debug, Serialize)]
+pub struct CurrencyTotals {
+    pub currency: Currency,
+    pub active_count: i64,
+    pub monthly_spend: BigDecimal,
+    pub yearly_spend: BigDecimal,
+}
+
+/// Normalized spend of active subscriptions per category and currency
+#[derive(Debug, Serialize)]
+pub struct CategoryBreakdown {
+    pub currency: Currency,
+    pub category: String,
+    pub count: i64,
+    pub monthly_spend: BigDecimal,
+}
+
+/// Subscription count and normalized spend per status and currency
+#[derive(Debug, Serialize)]
+pub struct StatusBreakdown {
+    pub currency: Currency,
+    pub status: SubscriptionStatus,
+    pub count: i64,
+    pub monthly_spend: BigDecimal,
+}
+
+/// One of the most expensive active subscriptions in a currency
+#[derive(Debug, Serialize)]
+pub struct TopSubscription {
+    pub id: Uuid,
+    pub name: String,
+    pub category: Option<String>,
+    pub currency: Currency,
+    pub amount: BigDecimal,
+    pub monthly_amount: BigDecimal,
+}
+
+/// An active subscription that renews within the requested window
+#[derive(Debug, Serialize)]
+pub struct UpcomingRenewal {
+    pub id: Uuid,
+    pub name: String,
+    pub currency: Currency,
+    pub amount: BigDecimal,
+    pub next_billing_date: NaiveDate,
+    pub days_until: i32,
+}
@@ -1,11 +1,12 @@
 use bigdecimal::BigDecimal;
-use chrono::{DateTime, NaiveDate, Utc};
+use chrono::{DateTime, Days, Months, NaiveDate, Utc};
 use serde::{Deserialize, Serialize};
 use sqlx::FromRow;
+use std::fmt;
 use std::str::FromStr;
 use uuid::Uuid;
 /// Subscription model representing a subscription in the system
-#[derive(Debug, Serialize, Deserialize, FromRow)]
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
 pub struct Subscription {
     #[serde(skip_deserializing)]
     pub id: Uuid,
@@ -19,15 +20,31 @@ pub struct Subscription {
     pub amount: BigDecimal,
     #[sqlx(try_from = "String")]
     pub currency: Currency,
-    pub billing_cycle_days: i32,
+    #[sqlx(try_from = "String")]
+    pub billing_cycle_unit: BillingCycleUnit,
+    pub billing_cycle_interval: i32,
     pub start_date: NaiveDate,
 
-    // calculate from start_date and billing_cycle_days
+    // calculate from start_date and the billing cycle
     #[serde(skip_deserializing)]
     pub next_billing_date: NaiveDate,
 
+    // cycles are counted from this date; it starts as start_date and moves
+    // when a paused subscription is resumed
+    #[serde(skip_deserializing)]
+    pub billing_anchor_date: NaiveDate,
+
     #[sqlx(try_from = "String")]
     pub status: SubscriptionStatus,
+
+    // managed by the status transitions
+    #[serde(skip_deserializing)]
+    pub paused_at: Option<NaiveDate>,
+    #[serde(skip_deserializing)]
+    pub end_date: Option<NaiveDate>,
+    // last day of a free trial, if the subscription started with one
+    pub trial_end_date: Option<NaiveDate>,
+
     pub category: Option<String>,
     pub color: Option<String>,
 
@@ -37,15 +54,17 @@ pub struct Subscription {
     pub updated_at: Option<DateTime<Utc>>,
 }
 
-/// Subscription list response DTO with additional metrics
-#[derive(Debug, Serialize)]
-pub struct SubscriptionListResponse {
-    pub subscriptions: Vec<Subscription>,
+/// Request body of the cancel action
+#[derive(Debug, Default, Deserialize)]
+pub struct CancelSubscriptionRequest {
+    /// Last day the subscription is in effect
+    pub effective_date: Option<NaiveDate>,
 }
 
 /// Represents the status of a subscription
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Default)]
 pub enum SubscriptionStatus {
+    #[default]
     Active,
     Paused,
     Cancelled,
@@ -61,12 +80,6 @@ impl SubscriptionStatus {
     }
 }
 
-impl Default for SubscriptionStatus {
-    fn default() -> Self {
-        Self::Active
-    }
-}
-
 impl FromStr for SubscriptionStatus {
     type Err = String;
 
@@ -86,19 +99,53 @@ impl From<String> for SubscriptionStatus {
     }
 }
 
+/// A change of a subscription's status
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum StatusTransition {
+    Pause,
+    Resume,
+    /// Cancel, optionally at an explicit effective end date
+    Cancel {
+        effective_date: Option<NaiveDate>,
+    },
+}
+
+impl StatusTransition {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            StatusTransition::Pause => "pause",
+            StatusTransition::Resume => "resume",
+            StatusTransition::Cancel { .. } => "cancel",
+        }
+    }
+}
+
+/// A status transition that is not allowed from the current status
+#[derive(Debug, PartialEq)]
+pub struct InvalidTransition {
+    pub from: SubscriptionStatus,
+    pub transition: StatusTransition,
+}
+
+impl fmt::Display for InvalidTransition {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Cannot {} a subscription that is {}",
+            self.transition.as_str(),
+            self.from.as_str().to_lowercase()
+        )
+    }
+}
+
 /// Represents the currency of a subscription
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Default)]
 pub enum Currency {
     Usd,
+    #[default]
     Cny,
 }
 
-impl Default for Currency {
-    fn default() -> Self {
-        Self::Cny
-    }
-}
-
 impl From<Currency> for &'static str {
     fn from(value: Currency) -> Self {
         value.as_str()
@@ -134,7 +181,136 @@ impl From<String> for Currency {
     }
 }
 
+/// Represents the calendar unit of a subscription's billing cycle
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Default)]
+pub enum BillingCycleUnit {
+    Day,
+    Week,
+    #[default]
+    Month,
+    Year,
+}
+
+impl BillingCycleUnit {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            BillingCycleUnit::Day => "day",
+            BillingCycleUnit::Week => "week",
+            BillingCycleUnit::Month => "month",
+            BillingCycleUnit::Year => "year",
+        }
+    }
+
+    /// Add `count` units to a date.
+    ///
+    /// Month and year units clamp to the last day of the target month, so
+    /// Jan 31 + 1 month is Feb 28 (or Feb 29 in a leap year).
+    pub fn add_to(&self, date: NaiveDate, count: u32) -> Option<NaiveDate> {
+        match self {
+            BillingCycleUnit::Day => date.checked_add_days(Days::new(u64::from(count))),
+            BillingCycleUnit::Week => date.checked_add_days(Days::new(u64::from(count) * 7)),
+            BillingCycleUnit::Month => date.checked_add_months(Months::new(count)),
+            BillingCycleUnit::Year => date.checked_add_months(Months::new(count.checked_mul(12)?)),
+        }
+    }
+}
+
+impl FromStr for BillingCycleUnit {
+    type Err = String;
+
+    fn from_str(unit: &str) -> Result<Self, Self::Err> {
+        match unit.to_lowercase().as_str() {
+            "day" => Ok(BillingCycleUnit::Day),
+            "week" => Ok(BillingCycleUnit::Week),
+            "month" => Ok(BillingCycleUnit::Month),
+            "year" => Ok(BillingCycleUnit::Year),
+            _ => Err(format!("Invalid billing cycle unit: {unit}")),
+        }
+    }
+}
+
+impl From<String> for BillingCycleUnit {
+    fn from(value: String) -> Self {
+        BillingCycleUnit::from_str(&value).unwrap_or_default()
+    }
+}
+
 impl Subscription {
+    /// Get the billing date of the given cycle, counted from the start date.
+    ///
+    /// Cycles are always anchored on the start date rather than on the
+    /// previous billing date, so a plan starting on Jan 31 bills on Feb 29,
+    /// Mar 31, Apr 30, ... instead of drifting to the 28th/29th forever.
+    pub fn billing_date_for_cycle(&self, start_date: NaiveDate, cycle: u32) -> Option<NaiveDate> {
+        let interval = u32::try_from(self.billing_cycle_interval).ok()?;
+        self.billing_cycle_unit
+            .add_to(start_date, cycle.checked_mul(interval)?)
+    }
+
+    /// Check whether two subscriptions bill on the same dates
+    pub fn has_same_schedule(&self, other: &Subscription) -> bool {
+        self.start_date == other.start_date
+            && self.billing_cycle_unit == other.billing_cycle_unit
+            && self.billing_cycle_interval == other.billing_cycle_interval
+    }
+
+    /// Carry the billing position of the stored version of this subscription
+    /// over to an update of it
+    ///
+    /// An unchanged schedule keeps its anchor and the cycles already paid
+    /// for, or the frozen cycle of a paused subscription. A changed schedule
+    /// starts over from the start date; while paused it is placed as of the
+    /// day of the pause, so resuming shifts it by the days spent paused once.
+    pub fn reschedule_from(&mut self, existing: &Subscription, today: NaiveDate) {
+        if self.has_same_schedule(existing) {
+            self.billing_anchor_date = existing.billing_anchor_date;
+            self.next_billing_date = if existing.status == SubscriptionStatus::Active {
+                self.calculate_next_billing_date(self.billing_anchor_date, today)
+                    .max(existing.next_billing_date)
+            } else {
+                existing.next_billing_date
+            };
+            return;
+        }
+
+        let as_of = match existing.status {
+            SubscriptionStatus::Paused => existing.paused_at.unwrap_or(today),
+            _ => today,
+        };
+        self.billing_anchor_date = self.start_date;
+        self.next_billing_date = self.calculate_next_billing_date(self.start_date, as_of);
+    }
+
+    /// Get the first billing date on or after the given date
+    ///
+    /// Returns `None` when the billing cycle is invalid or the date overflows.
+    pub fn billing_date_on_or_after(
+        &self,
+        start_date: NaiveDate,
+        date: NaiveDate,
+    ) -> Option<NaiveDate> {
+        if self.billing_cycle_interval <= 0 {
+            return None;
+        }
+        if date <= start_date {
+            return Some(start_date);
+        }
+
+        let mut cycle = 1;
+        loop {
+            let billing_date = self.billing_date_for_cycle(start_date, cycle)?;
+            if billing_date >= date {
+                return Some(billing_date);
+            }
+            cycle += 1;
+        }
+    }
+
+    /// Check whether a date is one of the subscription's billing dates
+    pub fn is_billing_date(&self, start_date: NaiveDate, date: NaiveDate) -> bool {
+        date >= start_date && self.billing_date_on_or_after(start_date, date) == Some(date)
+    }
+
     /// Calculate the next billing date based on the current date and billing cycle
     /// Ensures the next billing date is after the start date
     pub fn calculate_next_billing_date(
@@ -142,21 +318,113 @@ impl Subscription {
         start_date: NaiveDate,
         current_date: NaiveDate,
     ) -> NaiveDate {
-        let mut next_billing_date = start_date;
-
         // Ensure the first billing date is at least at the start date
         // If start date is in the future, the first billing will be the start date
         if start_date >= current_date {
             return start_date;
         }
 
-        // For past start dates, calculate the next billing date after current date
-        while next_billing_date <= current_date {
-            next_billing_date = next_billing_date
-                .checked_add_signed(chrono::Duration::days(self.billing_cycle_days as i64))
-                .unwrap_or(start_date);
+        // For past start dates, find the first cycle that falls after current date
+        current_date
+            .succ_opt()
+            .and_then(|date| self.billing_date_on_or_after(start_date, date))
+            .unwrap_or(start_date)
+    }
+
+    /// Billing dates that have passed as of `today`, and the billing date following them
+    ///
+    /// Starts from the current `next_billing_date` and walks the schedule
+    /// until it reaches a date on or after `today`. Returns `None` when the
+    /// next billing date has not passed yet or the schedule is invalid.
+    pub fn roll_forward(&self, today: NaiveDate) -> Option<(Vec<NaiveDate>, NaiveDate)> {
+        let mut passed = Vec::new();
+        let mut date = self.next_billing_date;
+        while date < today {
+            passed.push(date);
+            date = date
+                .succ_opt()
+                .and_then(|date| self.billing_date_on_or_after(self.billing_anchor_date, date))?;
         }
-        next_billing_date
+
+        if passed.is_empty() {
+            None
+        } else {
+            Some((passed, date))
+        }
+    }
+
+    /// Apply a status transition, enforcing the subscription state machine
+    ///
+    /// Only Active -> Paused, Paused -> Active and Active/Paused -> Cancelled
+    /// are allowed. Pausing freezes the billing cycle; resuming shifts the
+    /// next billing date by the number of days spent paused and counts the
+    /// following cycles from there. Cancelling records the effective end
+    /// date, by default the end of the current cycle for an active
+    /// subscription and today for a paused one.
+    pub fn apply_transition(
+        &mut self,
+        transition: StatusTransition,
+        today: NaiveDate,
+    ) -> Result<(), InvalidTransition> {
+        match (&self.status, transition) {
+            (SubscriptionStatus::Active, StatusTransition::Pause) => {
+                self.status = SubscriptionStatus::Paused;
+                self.paused_at = Some(today);
+            }
+            (SubscriptionStatus::Paused, StatusTransition::Resume) => {
+                let paused_days = self
+                    .paused_at
+                    .map_or(0, |paused_at| (today - paused_at).num_days().max(0));
+                if let Some(date) = self
+                    .next_billing_date
+                    .checked_add_days(Days::new(paused_days.unsigned_abs()))
+                {
+                    self.next_billing_date = date;
+                }
+                self.billing_anchor_date = self.next_billing_date;
+                self.status = SubscriptionStatus::Active;
+                self.paused_at = None;
+            }
+            (
+                SubscriptionStatus::Active | SubscriptionStatus::Paused,
+                StatusTransition::Cancel { effective_date },
+            ) => {
+                let default_end = if self.status == SubscriptionStatus::Active {
+                    self.next_billing_date.max(today)
+                } else {
+                    today
+                };
+                self.status = SubscriptionStatus::Cancelled;
+                self.paused_at = None;
+                self.end_date = Some(effective_date.unwrap_or(default_end));
+            }
+            (from, transition) => {
+                return Err(InvalidTransition {
+                    from: from.clone(),
+                    transition,
+                });
+            }
+        }
+        Ok(())
+    }
+
+    /// Move to the given status through the matching transition
+    ///
+    /// Staying in the current status is a no-op.
+    pub fn transition_to(
+        &mut self,
+        status: SubscriptionStatus,
+        today: NaiveDate,
+    ) -> Result<(), InvalidTransition> {
+        let transition = match status {
+            _ if status == self.status => return Ok(()),
+            SubscriptionStatus::Active => StatusTransition::Resume,
+            SubscriptionStatus::Paused => StatusTransition::Pause,
+            SubscriptionStatus::Cancelled => StatusTransition::Cancel {
+                effective_date: None,
+            },
+        };
+        self.apply_transition(transition, today)
     }
 }
 
@@ -175,10 +443,15 @@ mod tests {
             description: Some("Test Description".to_string()),
             amount: BigDecimal::from(10),
             currency: Currency::Usd,
-            billing_cycle_days: 30,
+            billing_cycle_unit: BillingCycleUnit::Month,
+            billing_cycle_interval: 1,
             start_date: NaiveDate::from_ymd_opt(2024, 1, 1).unwrap(),
             next_billing_date: NaiveDate::from_ymd_opt(2024, 1, 1).unwrap(),
+            billing_anchor_date: NaiveDate::from_ymd_opt(2024, 1, 1).unwrap(),
             status: SubscriptionStatus::Active,
+            paused_at: None,
+            end_date: None,
+            trial_end_date: None,
             category: Some("Test".to_string()),
             color: Some("#FF0000".to_string()),
             created_at: None,
@@ -222,4 +495,249 @@ mod tests {
         // For start date today, next billing should be today
         assert_eq!(next_billing, today);
     }
+
+    #[test]
+    fn test_calculate_next_billing_date_clamps_to_month_end() {
+        let subscription = create_test_subscription();
+        let start = NaiveDate::from_ymd_opt(2024, 1, 31).unwrap();
+
+        // 2024 is a leap year, so February ends on the 29th
+        let next_billing = subscription
+            .calculate_next_billing_date(start, NaiveDate::from_ymd_opt(2024, 2, 1).unwrap());
+        assert_eq!(next_billing, NaiveDate::from_ymd_opt(2024, 2, 29).unwrap());
+
+        // The cycle stays anchored on the 31st instead of drifting to the 29th
+        let next_billing = subscription
+            .calculate_next_billing_date(start, NaiveDate::from_ymd_opt(2024, 3, 1).unwrap());
+        assert_eq!(next_billing, NaiveDate::from_ymd_opt(2024, 3, 31).unwrap());
+
+        let next_billing = subscription
+            .calculate_next_billing_date(start, NaiveDate::from_ymd_opt(2024, 4, 1).unwrap());
+        assert_eq!(next_billing, NaiveDate::from_ymd_opt(2024, 4, 30).unwrap());
+    }
+
+    #[test]
+    fn test_calculate_next_billing_date_yearly_leap_day() {
+        let mut subscription = create_test_subscription();
+        subscription.billing_cycle_unit = BillingCycleUnit::Year;
+        let start = NaiveDate::from_ymd_opt(2024, 2, 29).unwrap();
+
+        let next_billing = subscription
+            .calculate_next_billing_date(start, NaiveDate::from_ymd_opt(2024, 3, 1).unwrap());
+        assert_eq!(next_billing, NaiveDate::from_ymd_opt(2025, 2, 28).unwrap());
+
+        let next_billing = subscription
+            .calculate_next_billing_date(start, NaiveDate::from_ymd_opt(2027, 3, 1).unwrap());
+        assert_eq!(next_billing, NaiveDate::from_ymd_opt(2028, 2, 29).unwrap());
+    }
+
+    #[test]
+    fn test_calculate_next_billing_date_with_interval() {
+        let mut subscription = create_test_subscription();
+        subscription.billing_cycle_unit = BillingCycleUnit::Week;
+        subscription.billing_cycle_interval = 2;
+        let start = NaiveDate::from_ymd_opt(2024, 1, 1).unwrap();
+
+        let next_billing = subscription
+            .calculate_next_billing_date(start, NaiveDate::from_ymd_opt(2024, 1, 15).unwrap());
+        assert_eq!(next_billing, NaiveDate::from_ymd_opt(2024, 1, 29).unwrap());
+
+        subscription.billing_cycle_unit = BillingCycleUnit::Month;
+        subscription.billing_cycle_interval = 3;
+        let next_billing = subscription
+            .calculate_next_billing_date(start, NaiveDate::from_ymd_opt(2024, 2, 15).unwrap());
+        assert_eq!(next_billing, NaiveDate::from_ymd_opt(2024, 4, 1).unwrap());
+    }
+
+    #[test]
+    fn test_is_billing_date() {
+        let subscription = create_test_subscription();
+        let start = NaiveDate::from_ymd_opt(2024, 1, 31).unwrap();
+
+        assert!(subscription.is_billing_date(start, start));
+        assert!(subscription.is_billing_date(start, NaiveDate::from_ymd_opt(2024, 2, 29).unwrap()));
+        assert!(subscription.is_billing_date(start, NaiveDate::from_ymd_opt(2024, 4, 30).unwrap()));
+        assert!(
+            !subscription.is_billing_date(start, NaiveDate::from_ymd_opt(2024, 2, 28).unwrap())
+        );
+        assert!(
+            !subscription.is_billing_date(start, NaiveDate::from_ymd_opt(2023, 12, 31).unwrap())
+        );
+    }
+
+    #[test]
+    fn test_pause_and_resume_shifts_cycle() {
+        let mut subscription = create_test_subscription();
+        subscription.next_billing_date = NaiveDate::from_ymd_opt(2024, 2, 1).unwrap();
+
+        let paused_on = NaiveDate::from_ymd_opt(2024, 1, 20).unwrap();
+        subscription
+            .apply_transition(StatusTransition::Pause, paused_on)
+            .unwrap();
+        assert_eq!(subscription.status, SubscriptionStatus::Paused);
+        assert_eq!(subscription.paused_at, Some(paused_on));
+        // The cycle is frozen while paused
+        assert_eq!(
+            subscription.next_billing_date,
+            NaiveDate::from_ymd_opt(2024, 2, 1).unwrap()
+        );
+
+        subscription
+            .apply_transition(
+                StatusTransition::Resume,
+                NaiveDate::from_ymd_opt(2024, 3, 1).unwrap(),
+            )
+            .unwrap();
+        assert_eq!(subscription.status, SubscriptionStatus::Active);
+        assert_eq!(subscription.paused_at, None);
+        // Paused for 41 days, so the remaining 12 days of the cycle are kept
+        let resumed_next = NaiveDate::from_ymd_opt(2024, 3, 13).unwrap();
+        assert_eq!(subscription.next_billing_date, resumed_next);
+        assert_eq!(subscription.billing_anchor_date, resumed_next);
+        assert_eq!(
+            subscription.calculate_next_billing_date(
+                subscription.billing_anchor_date,
+                NaiveDate::from_ymd_opt(2024, 3, 20).unwrap()
+            ),
+            NaiveDate::from_ymd_opt(2024, 4, 13).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_schedule_changes_while_paused_start_from_the_pause() {
+        let mut existing = create_test_subscription();
+        existing.start_date = NaiveDate::from_ymd_opt(2024, 1, 1).unwrap();
+        existing.billing_anchor_date = existing.start_date;
+        existing.next_billing_date = NaiveDate::from_ymd_opt(2024, 2, 1).unwrap();
+        let paused_on = NaiveDate::from_ymd_opt(2024, 1, 20).unwrap();
+        existing
+            .apply_transition(StatusTransition::Pause, paused_on)
+            .unwrap();
+        let today = NaiveDate::from_ymd_opt(2024, 3, 10).unwrap();
+
+        // An unchanged schedule stays frozen
+        let mut update = existing.clone();
+        update.reschedule_from(&existing, today);
+        assert_eq!(
+            update.next_billing_date,
+            NaiveDate::from_ymd_opt(2024, 2, 1).unwrap()
+        );
+
+        // Billing every other month is placed as of the pause, not as of today
+        let mut update = existing.clone();
+        update.billing_cycle_interval = 2;
+        update.reschedule_from(&existing, today);
+        assert_eq!(
+            update.next_billing_date,
+            NaiveDate::from_ymd_opt(2024, 3, 1).unwrap()
+        );
+        assert_eq!(update.billing_anchor_date, update.start_date);
+
+        // Resuming shifts it by the 50 days spent paused
+        update
+            .apply_transition(StatusTransition::Resume, today)
+            .unwrap();
+        assert_eq!(
+            update.next_billing_date,
+            NaiveDate::from_ymd_opt(2024, 4, 20).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_cancel_records_end_date() {
+        let today = NaiveDate::from_ymd_opt(2024, 1, 20).unwrap();
+
+        let mut active = create_test_subscription();
+        active.next_billing_date = NaiveDate::from_ymd_opt(2024, 2, 1).unwrap();
+        active
+            .apply_transition(
+                StatusTransition::Cancel {
+                    effective_date: None,
+                },
+                today,
+            )
+            .unwrap();
+        assert_eq!(active.status, SubscriptionStatus::Cancelled);
+        assert_eq!(active.end_date, NaiveDate::from_ymd_opt(2024, 2, 1));
+
+        let mut paused = create_test_subscription();
+        paused
+            .apply_transition(StatusTransition::Pause, today)
+            .unwrap();
+        let effective_date = NaiveDate::from_ymd_opt(2024, 1, 25).unwrap();
+        paused
+            .apply_transition(
+                StatusTransition::Cancel {
+                    effective_date: Some(effective_date),
+                },
+                today,
+            )
+            .unwrap();
+        assert_eq!(paused.end_date, Some(effective_date));
+        assert_eq!(paused.paused_at, None);
+    }
+
+    #[test]
+    fn test_illegal_transitions_are_rejected() {
+        let today = NaiveDate::from_ymd_opt(2024, 1, 20).unwrap();
+        let mut subscription = create_test_subscription();
+
+        let err = subscription
+            .apply_transition(StatusTransition::Resume, today)
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Cannot resume a subscription that is active"
+        );
+
+        subscription
+            .transition_to(SubscriptionStatus::Cancelled, today)
+            .unwrap();
+        for transition in [
+            StatusTransition::Pause,
+            StatusTransition::Resume,
+            StatusTransition::Cancel {
+                effective_date: None,
+            },
+        ] {
+            assert_eq!(
+                subscription.apply_transition(transition, today),
+                Err(InvalidTransition {
+                    from: SubscriptionStatus::Cancelled,
+                    transition,
+                })
+            );
+        }
+        // Keeping the current status is not a transition
+        assert!(
+            subscription
+                .transition_to(SubscriptionStatus::Cancelled, today)
+                .is_ok()
+        );
+    }
+
+    #[test]
+    fn test_roll_forward() {
+        let mut subscription = create_test_subscription();
+        subscription.billing_anchor_date = NaiveDate::from_ymd_opt(2024, 1, 31).unwrap();
+        subscription.next_billing_date = NaiveDate::from_ymd_opt(2024, 2, 29).unwrap();
+
+        // Not due yet on the billing date itself
+        assert_eq!(
+            subscription.roll_forward(NaiveDate::from_ymd_opt(2024, 2, 29).unwrap()),
+            None
+        );
+
+        let (passed, next) = subscription
+            .roll_forward(NaiveDate::from_ymd_opt(2024, 4, 15).unwrap())
+            .unwrap();
+        assert_eq!(
+            passed,
+            vec![
+                NaiveDate::from_ymd_opt(2024, 2, 29).unwrap(),
+                NaiveDate::from_ymd_opt(2024, 3, 31).unwrap(),
+            ]
+        );
+        assert_eq!(next, NaiveDate::from_ymd_opt(2024, 4, 30).unwrap());
+    }
 }
@@ -0,0 +1,60 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// Secret of a started enrollment, to be added to an authenticator app
+#[derive(Debug, Serialize)]
+pub struct TwoFactorEnrollment {
+    /// Base32 secret for manual entry
+    pub secret: String,
+    /// `otpauth://` URI, usually shown as a QR code
+    pub otpauth_uri: String,
+}
+
+/// Two-factor authentication state of the current user
+#[derive(Debug, Serialize)]
+pub struct TwoFactorStatus {
+    pub enabled: bool,
+    pub enabled_at: Option<DateTime<Utc>>,
+    /// Recovery codes that were not used yet
+    pub recovery_codes_remaining: i64,
+}
+
+/// Recovery codes, shown once when they are generated
+#[derive(Debug, Serialize)]
+pub struct RecoveryCodes {
+    pub recovery_codes: Vec<String>,
+}
+
+/// Request carrying a code from the authenticator app or a recovery code
+#[derive(Debug, Deserialize)]
+pub struct TwoFactorCodeRequest {
+    pub code: String,
+}
+
+/// Two-factor disable DTO; both the password and a code are required
+///
+/// Accounts without a password sign in again instead of sending one.
+#[derive(Debug, Deserialize)]
+pub struct DisableTwoFactorRequest {
+    pub password: Option<String>,
+    pub code: String,
+}
+
+/// Second login step DTO
+#[derive(Debug, Deserialize)]
+pub struct TwoFactorLoginRequest {
+    pub challenge_token: String,
+    /// Code from the authenticator app, or a recovery code
+    pub code: String,
+}
+
+/// Returned by the password step of login when the account uses two-factor
+/// authentication
+#[derive(Debug, Serialize)]
+pub struct TwoFactorChallenge {
+    /// Always `true`, so clients can tell this response from a completed login
+    pub two_factor_required: bool,
+    pub challenge_token: String,
+    /// Seconds until the challenge token expires
+    pub expires_in: i64,
+}
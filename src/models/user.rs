@@ -1,8 +1,11 @@
 use chrono::{DateTime, Utc};
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Deserializer, Serialize};
 use sqlx::FromRow;
 use uuid::Uuid;
 
+use crate::models::subscription::Currency;
+use crate::models::two_factor::TwoFactorChallenge;
+
 /// User model representing a user in the system
 #[derive(Debug, Serialize, Deserialize, FromRow)]
 pub struct User {
@@ -11,10 +14,20 @@ pub struct User {
     #[serde(skip_serializing)]
     #[allow(dead_code)] // Used by UserService for authentication
     pub password_hash: String,
+    /// When the user confirmed the email address, `None` until then
+    pub email_verified_at: Option<DateTime<Utc>>,
+    /// When two-factor authentication was enabled, `None` while it is off
+    pub two_factor_enabled_at: Option<DateTime<Utc>>,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
 }
 
+/// Days before a renewal or trial end that reminders are sent, unless the
+/// user set `reminder_lead_days` in their preferences
+pub const DEFAULT_REMINDER_LEAD_DAYS: i32 = 3;
+/// Upper bound of the `reminder_lead_days` preference
+pub const MAX_REMINDER_LEAD_DAYS: i32 = 365;
+
 /// User profile model representing additional user information
 #[derive(Debug, Serialize, Deserialize, FromRow)]
 pub struct UserProfile {
@@ -46,6 +59,8 @@ pub struct LoginRequest {
 pub struct UserResponse {
     pub id: Uuid,
     pub email: String,
+    pub email_verified_at: Option<DateTime<Utc>>,
+    pub two_factor_enabled: bool,
     pub name: Option<String>,
     pub preferences: Option<serde_json::Value>,
 }
@@ -58,12 +73,141 @@ pub struct AuthResponse {
     pub user: UserResponse,
 }
 
+/// Response of the password step of login
+///
+/// Accounts with two-factor authentication get a challenge that is completed
+/// with a code; all others are signed in right away.
+#[derive(Debug, Serialize)]
+#[serde(untagged)]
+pub enum LoginResponse {
+    Authenticated(AuthResponse),
+    TwoFactorRequired(TwoFactorChallenge),
+}
+
+/// Refresh token exchange and logout request DTO
+#[derive(Debug, Deserialize)]
+pub struct RefreshTokenRequest {
+    pub refresh_token: String,
+}
+
+/// Token pair issued when a refresh token is exchanged
+#[derive(Debug, Serialize)]
+pub struct TokenResponse {
+    pub token: String,
+    pub refresh_token: String,
+}
+
+/// Password reset request DTO
+#[derive(Debug, Deserialize)]
+pub struct PasswordResetRequest {
+    pub email: String,
+}
+
+/// Email verification DTO, carrying the token from the verification email
+#[derive(Debug, Deserialize)]
+pub struct VerifyEmailRequest {
+    pub token: String,
+}
+
+/// Account unlock DTO, carrying the token from the unlock email
+#[derive(Debug, Deserialize)]
+pub struct UnlockAccountRequest {
+    pub token: String,
+}
+
+/// Password reset confirmation DTO, carrying the token from the reset email
+#[derive(Debug, Deserialize)]
+pub struct PasswordResetConfirm {
+    pub token: String,
+    pub password: String,
+}
+
+/// Profile update DTO; omitted fields stay unchanged
+#[derive(Debug, Deserialize)]
+pub struct UpdateProfileRequest {
+    /// New display name; an empty name removes it
+    pub name: Option<String>,
+    pub preferences: Option<UserPreferences>,
+}
+
+/// Typed schema of the profile `preferences`, used as a patch
+///
+/// Omitted fields stay unchanged and `null` resets a preference to its
+/// default. Unknown fields are rejected.
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct UserPreferences {
+    /// Currency preselected for new subscriptions
+    #[serde(
+        default,
+        deserialize_with = "nullable",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub default_currency: Option<Option<Currency>>,
+    /// IANA time zone name, e.g. `Europe/Berlin`
+    #[serde(
+        default,
+        deserialize_with = "nullable",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub timezone: Option<Option<String>>,
+    /// Days before a renewal or trial end that reminders are sent
+    #[serde(
+        default,
+        deserialize_with = "nullable",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub reminder_lead_days: Option<Option<i32>>,
+    /// BCP 47 language tag, e.g. `en-US`
+    #[serde(
+        default,
+        deserialize_with = "nullable",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub locale: Option<Option<String>>,
+    /// Whether reminders are also delivered by email
+    #[serde(
+        default,
+        deserialize_with = "nullable",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub email_reminders: Option<Option<bool>>,
+}
+
+/// Deserialize a present field as `Some`, keeping an explicit `null` apart
+/// from an omitted field
+fn nullable<'de, T, D>(deserializer: D) -> Result<Option<Option<T>>, D::Error>
+where
+    T: Deserialize<'de>,
+    D: Deserializer<'de>,
+{
+    Option::<T>::deserialize(deserializer).map(Some)
+}
+
+/// Password change DTO
+#[derive(Debug, Deserialize)]
+pub struct ChangePasswordRequest {
+    pub current_password: String,
+    pub new_password: String,
+}
+
+/// Email change DTO; the password confirms the change
+///
+/// Accounts without a password confirm by signing in again instead.
+#[derive(Debug, Deserialize)]
+pub struct ChangeEmailRequest {
+    pub new_email: String,
+    pub password: Option<String>,
+}
+
 impl User {
     /// Creates a new user response from a user and profile
     pub fn to_response(&self, profile: Option<&UserProfile>) -> UserResponse {
         UserResponse {
             id: self.id,
             email: self.email.clone(),
+            email_verified_at: self.email_verified_at,
+            two_factor_enabled: self.two_factor_enabled_at.is_some(),
             name: profile.and_then(|p| p.name.clone()),
             preferences: profile.map(|p| p.preferences.clone()),
         }
@@ -0,0 +1,355 @@
+use chrono::{DateTime, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
+use std::str::FromStr;
+use uuid::Uuid;
+
+use super::event::{NewSubscriptionEvent, SubscriptionEventType};
+use super::subscription::Subscription;
+
+/// Maximum number of webhook endpoints a user can register
+pub const MAX_WEBHOOK_ENDPOINTS: i64 = 10;
+
+/// A URL registered by a user to receive webhook events
+#[derive(Debug, Serialize)]
+pub struct WebhookEndpoint {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    pub url: String,
+    pub description: Option<String>,
+    // events sent to the endpoint; empty for all events
+    pub event_types: Vec<WebhookEventType>,
+    pub active: bool,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// A webhook endpoint together with its signing secret
+///
+/// The secret is only returned when the endpoint is created or its secret
+/// is rotated.
+#[derive(Debug, Serialize)]
+pub struct WebhookEndpointWithSecret {
+    #[serde(flatten)]
+    pub endpoint: WebhookEndpoint,
+    pub secret: String,
+}
+
+/// Webhook endpoint creation request DTO
+#[derive(Debug, Deserialize)]
+pub struct CreateWebhookRequest {
+    pub url: String,
+    pub description: Option<String>,
+    #[serde(default)]
+    pub event_types: Vec<WebhookEventType>,
+}
+
+/// Webhook endpoint update request DTO; missing fields are left unchanged
+#[derive(Debug, Deserialize)]
+pub struct UpdateWebhookRequest {
+    pub url: Option<String>,
+    pub description: Option<String>,
+    pub event_types: Option<Vec<WebhookEventType>>,
+    pub active: Option<bool>,
+}
+
+/// Represents the kind of event sent to webhook endpoints
+///
+/// Serialized with the dotted names sent to the endpoints, e.g.
+/// `subscription.created`.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[allow(clippy::enum_variant_names)] // named after the `<resource>.<event>` names
+pub enum WebhookEventType {
+    #[serde(rename = "subscription.created")]
+    SubscriptionCreated,
+    #[serde(rename = "subscription.updated")]
+    SubscriptionUpdated,
+    #[serde(rename = "subscription.paused")]
+    SubscriptionPaused,
+    #[serde(rename = "subscription.resumed")]
+    SubscriptionResumed,
+    #[serde(rename = "subscription.cancelled")]
+    SubscriptionCancelled,
+    #[serde(rename = "subscription.renewed")]
+    SubscriptionRenewed,
+    #[serde(rename = "subscription.deleted")]
+    SubscriptionDeleted,
+    #[serde(rename = "subscription.renewal_upcoming")]
+    SubscriptionRenewalUpcoming,
+    #[serde(rename = "subscription.trial_ending")]
+    SubscriptionTrialEnding,
+}
+
+impl WebhookEventType {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            WebhookEventType::SubscriptionCreated => "subscription.created",
+            WebhookEventType::SubscriptionUpdated => "subscription.updated",
+            WebhookEventType::SubscriptionPaused => "subscription.paused",
+            WebhookEventType::SubscriptionResumed => "subscription.resumed",
+            WebhookEventType::SubscriptionCancelled => "subscription.cancelled",
+            WebhookEventType::SubscriptionRenewed => "subscription.renewed",
+            WebhookEventType::SubscriptionDeleted => "subscription.deleted",
+            WebhookEventType::SubscriptionRenewalUpcoming => "subscription.renewal_upcoming",
+            WebhookEventType::SubscriptionTrialEnding => "subscription.trial_ending",
+        }
+    }
+
+    /// Webhook event announcing a recorded subscription event
+    pub fn from_subscription_event(event_type: SubscriptionEventType) -> Self {
+        match event_type {
+            SubscriptionEventType::Created => WebhookEventType::SubscriptionCreated,
+            SubscriptionEventType::Updated | SubscriptionEventType::Repriced => {
+                WebhookEventType::SubscriptionUpdated
+            }
+            SubscriptionEventType::Paused => WebhookEventType::SubscriptionPaused,
+            SubscriptionEventType::Resumed => WebhookEventType::SubscriptionResumed,
+            SubscriptionEventType::Cancelled => WebhookEventType::SubscriptionCancelled,
+            SubscriptionEventType::Renewed => WebhookEventType::SubscriptionRenewed,
+            SubscriptionEventType::Deleted => WebhookEventType::SubscriptionDeleted,
+        }
+    }
+}
+
+impl FromStr for WebhookEventType {
+    type Err = String;
+
+    fn from_str(event_type: &str) -> Result<Self, Self::Err> {
+        match event_type.to_lowercase().as_str() {
+            "subscription.created" => Ok(WebhookEventType::SubscriptionCreated),
+            "subscription.updated" => Ok(WebhookEventType::SubscriptionUpdated),
+            "subscription.paused" => Ok(WebhookEventType::SubscriptionPaused),
+            "subscription.resumed" => Ok(WebhookEventType::SubscriptionResumed),
+            "subscription.cancelled" => Ok(WebhookEventType::SubscriptionCancelled),
+            "subscription.renewed" => Ok(WebhookEventType::SubscriptionRenewed),
+            "subscription.deleted" => Ok(WebhookEventType::SubscriptionDeleted),
+            "subscription.renewal_upcoming" => Ok(WebhookEventType::SubscriptionRenewalUpcoming),
+            "subscription.trial_ending" => Ok(WebhookEventType::SubscriptionTrialEnding),
+            _ => Err(format!("Invalid webhook event type: {event_type}")),
+        }
+    }
+}
+
+impl From<String> for WebhookEventType {
+    fn from(value: String) -> Self {
+        // Unknown types can only come from newer rows; show them as plain updates
+        WebhookEventType::from_str(&value).unwrap_or(WebhookEventType::SubscriptionUpdated)
+    }
+}
+
+/// An event about to be sent to the webhook endpoints of a user
+#[derive(Debug)]
+pub struct WebhookEvent {
+    pub id: Uuid,
+    pub event_type: WebhookEventType,
+    pub created_at: DateTime<Utc>,
+    pub data: Value,
+}
+
+impl WebhookEvent {
+    pub fn new(event_type: WebhookEventType, data: Value) -> Self {
+        Self {
+            id: Uuid::new_v4(),
+            event_type,
+            created_at: Utc::now(),
+            data,
+        }
+    }
+
+    /// Event about a subscription, carrying the subscription as its data
+    pub fn subscription(event_type: WebhookEventType, subscription: &Subscription) -> Self {
+        Self::new(event_type, json!({ "subscription": subscription }))
+    }
+
+    /// Reminder event about a subscription's upcoming renewal or trial end
+    pub fn reminder(
+        event_type: WebhookEventType,
+        subscription: &Subscription,
+        due_date: NaiveDate,
+    ) -> Self {
+        Self::new(
+            event_type,
+            json!({ "subscription": subscription, "due_date": due_date }),
+        )
+    }
+
+    /// Events announcing recorded subscription events, at most one per type
+    pub fn for_changes(subscription: &Subscription, events: &[NewSubscriptionEvent]) -> Vec<Self> {
+        let mut event_types: Vec<WebhookEventType> = Vec::new();
+        for event in events {
+            let event_type = WebhookEventType::from_subscription_event(event.event_type);
+            if !event_types.contains(&event_type) {
+                event_types.push(event_type);
+            }
+        }
+
+        event_types
+            .into_iter()
+            .map(|event_type| Self::subscription(event_type, subscription))
+            .collect()
+    }
+
+    /// JSON body sent to the endpoints
+    pub fn payload(&self) -> Value {
+        json!({
+            "id": self.id,
+            "type": self.event_type.as_str(),
+            "created_at": self.created_at,
+            "data": self.data,
+        })
+    }
+}
+
+/// One event queued for delivery to one webhook endpoint
+#[derive(Debug, Serialize)]
+pub struct WebhookDelivery {
+    pub id: Uuid,
+    pub endpoint_id: Uuid,
+    pub event_id: Uuid,
+    pub event_type: WebhookEventType,
+    pub status: WebhookDeliveryStatus,
+    pub attempts: i32,
+    pub next_attempt_at: Option<DateTime<Utc>>,
+    pub last_attempt_at: Option<DateTime<Utc>>,
+    pub delivered_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// A webhook delivery with its payload and the log of its attempts
+#[derive(Debug, Serialize)]
+pub struct WebhookDeliveryDetail {
+    #[serde(flatten)]
+    pub delivery: WebhookDelivery,
+    pub payload: Value,
+    pub attempt_log: Vec<WebhookDeliveryAttempt>,
+}
+
+/// One attempt of sending a webhook delivery
+#[derive(Debug, Serialize)]
+pub struct WebhookDeliveryAttempt {
+    pub id: Uuid,
+    pub attempt: i32,
+    // HTTP status of the response, if one was received
+    pub response_status: Option<i32>,
+    pub response_body: Option<String>,
+    pub error: Option<String>,
+    pub duration_ms: i32,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Query parameters filtering the delivery log
+#[derive(Debug, Default, Deserialize)]
+pub struct WebhookDeliveryFilter {
+    pub status: Option<WebhookDeliveryStatus>,
+}
+
+/// Represents the state of a webhook delivery
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Default)]
+pub enum WebhookDeliveryStatus {
+    #[default]
+    Pending,
+    Delivered,
+    Failed,
+}
+
+impl WebhookDeliveryStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            WebhookDeliveryStatus::Pending => "pending",
+            WebhookDeliveryStatus::Delivered => "delivered",
+            WebhookDeliveryStatus::Failed => "failed",
+        }
+    }
+}
+
+impl FromStr for WebhookDeliveryStatus {
+    type Err = String;
+
+    fn from_str(status: &str) -> Result<Self, Self::Err> {
+        match status.to_lowercase().as_str() {
+            "pending" => Ok(WebhookDeliveryStatus::Pending),
+            "delivered" => Ok(WebhookDeliveryStatus::Delivered),
+            "failed" => Ok(WebhookDeliveryStatus::Failed),
+            _ => Err(format!("Invalid webhook delivery status: {status}")),
+        }
+    }
+}
+
+impl From<String> for WebhookDeliveryStatus {
+    fn from(value: String) -> Self {
+        WebhookDeliveryStatus::from_str(&value).unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::subscription::{BillingCycleUnit, Currency, SubscriptionStatus};
+    use bigdecimal::BigDecimal;
+
+    fn create_test_subscription() -> Subscription {
+        Subscription {
+            id: Uuid::new_v4(),
+            user_id: Uuid::new_v4(),
+            name: "Test Subscription".to_string(),
+            description: None,
+            amount: BigDecimal::from(10),
+            currency: Currency::Usd,
+            billing_cycle_unit: BillingCycleUnit::Month,
+            billing_cycle_interval: 1,
+            start_date: NaiveDate::from_ymd_opt(2024, 1, 1).unwrap(),
+            next_billing_date: NaiveDate::from_ymd_opt(2024, 2, 1).unwrap(),
+            billing_anchor_date: NaiveDate::from_ymd_opt(2024, 1, 1).unwrap(),
+            status: SubscriptionStatus::Active,
+            paused_at: None,
+            end_date: None,
+            trial_end_date: None,
+            category: None,
+            color: None,
+            created_at: None,
+            updated_at: None,
+        }
+    }
+
+    #[test]
+    fn test_event_type_names() {
+        let event_type: WebhookEventType =
+            serde_json::from_value(json!("subscription.renewal_upcoming")).unwrap();
+        assert_eq!(event_type, WebhookEventType::SubscriptionRenewalUpcoming);
+        assert_eq!(
+            serde_json::to_value(WebhookEventType::SubscriptionCancelled).unwrap(),
+            json!(WebhookEventType::SubscriptionCancelled.as_str())
+        );
+        assert_eq!(
+            "Subscription.Created".parse::<WebhookEventType>(),
+            Ok(WebhookEventType::SubscriptionCreated)
+        );
+    }
+
+    #[test]
+    fn test_events_for_changes_are_deduplicated() {
+        let before = create_test_subscription();
+        let mut after = create_test_subscription();
+        after.status = SubscriptionStatus::Paused;
+        after.paused_at = NaiveDate::from_ymd_opt(2024, 1, 20);
+        after.amount = BigDecimal::from(12);
+        after.name = "Renamed".to_string();
+
+        let changes = NewSubscriptionEvent::changes(&before, &after);
+        let events = WebhookEvent::for_changes(&after, &changes);
+        let types: Vec<_> = events.iter().map(|e| e.event_type).collect();
+        // Repriced and Updated are both reported as one update
+        assert_eq!(
+            types,
+            vec![
+                WebhookEventType::SubscriptionPaused,
+                WebhookEventType::SubscriptionUpdated,
+            ]
+        );
+
+        let payload = events[0].payload();
+        assert_eq!(payload["type"], json!("subscription.paused"));
+        assert_eq!(payload["data"]["subscription"]["name"], json!("Renamed"));
+        assert_eq!(payload["id"], json!(events[0].id));
+    }
+}
@@ -1,25 +1,63 @@
-use axum::{Json, Router, extract::State, routing::post};
+use axum::{
+    Json, Router,
+    extract::{State, rejection::JsonRejection},
+    http::{
+        HeaderMap, HeaderName,
+        header::{COOKIE, SET_COOKIE},
+    },
+    routing::{get, post},
+};
 use sqlx::PgPool;
+use std::sync::Arc;
 use tracing;
 
-use crate::middleware::auth_rate_limit_middleware;
-use crate::models::{AuthResponse, LoginRequest, RegisterRequest, UserResponse};
-use crate::services::UserService;
+use crate::models::{
+    AuthResponse, LoginRequest, LoginResponse, OidcAuthorization, OidcCallbackRequest,
+    OidcProviderInfo, PasswordResetConfirm, PasswordResetRequest, RefreshTokenRequest,
+    RegisterRequest, TokenResponse, TwoFactorLoginRequest, UnlockAccountRequest, UserResponse,
+    VerifyEmailRequest,
+};
+use crate::services::oidc_service::OIDC_LOGIN_TTL_MINUTES;
+use crate::services::{
+    ChallengeOutcome, EmailVerificationService, LoginLockoutService, OidcClient, OidcService,
+    PasswordResetService, RefreshTokenService, SessionService, TwoFactorService, UserService,
+};
+use crate::state::AppState;
+use crate::utils::auth::SessionAuth;
+use crate::utils::client::ClientInfo;
 use crate::utils::response::{ApiResponse, AppError, success};
+use crate::utils::user_validation::is_valid_email;
 
 /// Create authentication routes
-pub fn auth_routes() -> Router<PgPool> {
+///
+/// Routes checking credentials fall under the strict `auth` rate limit
+/// policy. Refresh tokens cannot be guessed, so refresh and logout stay out
+/// of it; clients refreshing regularly would run into it.
+pub fn auth_routes() -> Router<AppState> {
     Router::new()
         .route("/register", post(register))
         .route("/login", post(login))
-        .layer(axum::middleware::from_fn(auth_rate_limit_middleware))
+        .route("/login/2fa", post(login_two_factor))
+        .route("/password-reset/request", post(request_password_reset))
+        .route("/password-reset/confirm", post(confirm_password_reset))
+        .route("/verify-email", post(verify_email))
+        .route("/verify-email/resend", post(resend_verification_email))
+        .route("/unlock", post(unlock_account))
+        .route("/oidc/authorize", post(start_oidc_login))
+        .route("/oidc/callback", post(complete_oidc_login))
+        .route("/refresh", post(refresh))
+        .route("/logout", post(logout))
+        .route("/oidc", get(oidc_provider_info))
 }
 
 /// Register a new user
 async fn register(
-    State(pool): State<PgPool>,
-    Json(request): Json<RegisterRequest>,
+    State(state): State<AppState>,
+    payload: Result<Json<RegisterRequest>, JsonRejection>,
 ) -> Result<Json<ApiResponse<UserResponse>>, AppError> {
+    let Json(mut request) = payload?;
+    request.email = request.email.trim().to_string();
+
     // Log the request
     tracing::info!("Register request received for email: {}", request.email);
 
@@ -31,6 +69,13 @@ async fn register(
             "Please enter a valid email address to create your account.",
         ));
     }
+    if !is_valid_email(&request.email) {
+        tracing::warn!("Register request failed: Email is malformed");
+        return Err(AppError::validation_error(
+            "Email address is malformed",
+            "Please enter a valid email address to create your account.",
+        ));
+    }
     if request.password.is_empty() {
         tracing::warn!("Register request failed: Password is empty");
         return Err(AppError::validation_error(
@@ -47,7 +92,7 @@ async fn register(
     }
 
     // Create user service
-    let user_service = UserService::new(pool);
+    let user_service = UserService::new(state.pool.clone());
 
     // Capture email for logging before moving request
     let email = request.email.clone();
@@ -69,15 +114,28 @@ async fn register(
         }
     };
 
+    // Send the verification link; registration succeeds even if the email fails
+    EmailVerificationService::new(state.pool).send_verification(
+        user.id,
+        state.mailer,
+        state.app_url,
+    );
+
     // Return success response
     Ok(success(user))
 }
 
 /// Login a user
+///
+/// Accounts with two-factor authentication get a challenge token instead of
+/// tokens, to be completed at `/login/2fa`. Besides the limit per client
+/// address, failed logins are counted per account and lock it for a while.
 async fn login(
-    State(pool): State<PgPool>,
-    Json(request): Json<LoginRequest>,
-) -> Result<Json<ApiResponse<AuthResponse>>, AppError> {
+    State(state): State<AppState>,
+    client_info: ClientInfo,
+    payload: Result<Json<LoginRequest>, JsonRejection>,
+) -> Result<Json<ApiResponse<LoginResponse>>, AppError> {
+    let Json(request) = payload?;
     // Log the request with more detail
     tracing::info!(
         "=== LOGIN REQUEST START === Email: {}, Password length: {}",
@@ -101,18 +159,19 @@ async fn login(
         ));
     }
 
+    // A locked account does not get its password checked
+    let lockout_service = LoginLockoutService::new(state.pool.clone(), state.lockout.clone());
+    let attempt = lockout_service.begin_attempt(&request.email).await?;
+
     // Create user service
-    let user_service = UserService::new(pool);
+    let user_service = UserService::new(state.pool.clone());
 
     // Capture email for logging before moving request
     let email = request.email.clone();
 
     // Login user with detailed error logging
-    let auth = match user_service.login(request).await {
-        Ok(auth_response) => {
-            tracing::info!("User logged in successfully: {}", auth_response.user.email);
-            auth_response
-        }
+    let login = match user_service.login(request, &client_info).await {
+        Ok(login_response) => login_response,
         Err(e) => {
             tracing::error!(
                 "Login failed for email '{}': Error type: {:?}, Message: {}",
@@ -120,11 +179,359 @@ async fn login(
                 std::mem::discriminant(&e),
                 e
             );
+            if matches!(e, AppError::Unauthorized { .. })
+                && let Some(lock) = lockout_service.record_wrong_password(
+                    &attempt,
+                    state.mailer.clone(),
+                    state.app_url.clone(),
+                )
+            {
+                return Err(AppError::account_locked(lock));
+            }
             return Err(e);
         }
     };
 
     // Return success response
-    tracing::info!("=== LOGIN REQUEST SUCCESS === Email: {}", auth.user.email);
+    match &login {
+        LoginResponse::Authenticated(auth) => {
+            // Failures are only forgotten once the login is complete, or a
+            // second factor could be guessed without ever locking the account
+            lockout_service.record_success(&email).await?;
+            tracing::info!("=== LOGIN REQUEST SUCCESS === Email: {}", auth.user.email);
+            notify_new_device(&state, auth);
+        }
+        LoginResponse::TwoFactorRequired(_) => {
+            lockout_service.withdraw_attempt(&email).await?;
+            tracing::info!("=== LOGIN REQUEST NEEDS 2FA === Email: {}", email)
+        }
+    }
+    Ok(success(login))
+}
+
+/// Complete a login with the challenge token and a two-factor code
+async fn login_two_factor(
+    State(state): State<AppState>,
+    client_info: ClientInfo,
+    payload: Result<Json<TwoFactorLoginRequest>, JsonRejection>,
+) -> Result<Json<ApiResponse<AuthResponse>>, AppError> {
+    let Json(request) = payload?;
+    tracing::info!("Two-factor login request received");
+
+    if request.code.trim().is_empty() {
+        return Err(AppError::field_validation_error(vec![(
+            "code",
+            "Please enter the code from your authenticator app".to_string(),
+        )]));
+    }
+
+    let two_factor_service = TwoFactorService::new(state.pool.clone());
+    let outcome = two_factor_service
+        .complete_challenge(&request.challenge_token, &request.code)
+        .await?;
+
+    // Wrong codes count towards the lock of the account like wrong passwords
+    let user_service = UserService::new(state.pool.clone());
+    let lockout_service = LoginLockoutService::new(state.pool.clone(), state.lockout.clone());
+    let user_id = match outcome {
+        ChallengeOutcome::Passed(user_id) => user_id,
+        ChallengeOutcome::WrongCode(user_id) => {
+            let user = user_service.get_user_by_id(user_id).await?;
+            if let Some(lock) = lockout_service
+                .record_failure(&user.email, state.mailer.clone(), state.app_url.clone())
+                .await?
+            {
+                return Err(AppError::account_locked(lock));
+            }
+            return Err(AppError::unauthorized_with_message(
+                "Two-factor code mismatch",
+                "The code is incorrect. Please try again.",
+            ));
+        }
+    };
+
+    let user = user_service.get_user_by_id(user_id).await?;
+    lockout_service.record_success(&user.email).await?;
+    let auth = user_service.start_session(user, &client_info).await?;
+    notify_new_device(&state, &auth);
+
+    tracing::info!("Two-factor login successful for user: {}", user_id);
     Ok(success(auth))
 }
+
+/// Tell the login page whether single sign-on is available
+async fn oidc_provider_info(State(state): State<AppState>) -> Json<ApiResponse<OidcProviderInfo>> {
+    success(OidcProviderInfo {
+        enabled: state.oidc.is_some(),
+        provider_name: state
+            .oidc
+            .map(|client| client.config().provider_name.clone()),
+    })
+}
+
+/// Cookie binding a single sign-on login to the browser that started it
+const OIDC_STATE_COOKIE: &str = "sub_pal_oidc_state";
+
+/// Response header setting or removing the single sign-on state cookie
+type StateCookie = [(HeaderName, String); 1];
+
+/// Start a single sign-on login, returning the provider page to redirect to
+///
+/// The state is also set as an HttpOnly cookie, so that the callback is only
+/// accepted from the browser that started the login. Otherwise an attacker
+/// could send a victim the callback link of their own login and sign the
+/// victim into the attacker's account.
+async fn start_oidc_login(
+    State(state): State<AppState>,
+) -> Result<(StateCookie, Json<ApiResponse<OidcAuthorization>>), AppError> {
+    let client = oidc_client(&state)?;
+    tracing::info!("OIDC login started");
+
+    let oidc_service = OidcService::new(state.pool.clone());
+    let authorization = oidc_service.start_login(&client).await?;
+    let cookie = oidc_state_cookie(
+        &authorization.state,
+        OIDC_LOGIN_TTL_MINUTES * 60,
+        &state.app_url,
+    );
+
+    Ok(([(SET_COOKIE, cookie)], success(authorization)))
+}
+
+/// Complete a single sign-on login with the code the provider returned
+///
+/// Like a password login, accounts with two-factor authentication get a
+/// challenge token to be completed at `/login/2fa`.
+async fn complete_oidc_login(
+    State(state): State<AppState>,
+    client_info: ClientInfo,
+    headers: HeaderMap,
+    payload: Result<Json<OidcCallbackRequest>, JsonRejection>,
+) -> Result<(StateCookie, Json<ApiResponse<LoginResponse>>), AppError> {
+    let Json(request) = payload?;
+    let client = oidc_client(&state)?;
+    tracing::info!("OIDC login callback received");
+
+    if request.code.is_empty() || request.state.is_empty() {
+        return Err(AppError::validation_error(
+            "OIDC code and state are required",
+            "This sign-in link is invalid. Please sign in again.",
+        ));
+    }
+    if cookie_value(&headers, OIDC_STATE_COOKIE) != Some(request.state.as_str()) {
+        tracing::warn!("OIDC login callback from a browser that did not start the login");
+        return Err(AppError::unauthorized_with_message(
+            "OIDC login state does not match the state cookie",
+            "This sign-in was not started in this browser. Please sign in again.",
+        ));
+    }
+
+    let oidc_service = OidcService::new(state.pool.clone());
+    let user_id = oidc_service.complete_login(&client, request).await?;
+
+    let user_service = UserService::new(state.pool.clone());
+    let user = user_service.get_user_by_id(user_id).await?;
+    let login = user_service.sign_in(user, &client_info).await?;
+    if let LoginResponse::Authenticated(auth) = &login {
+        notify_new_device(&state, auth);
+    }
+
+    tracing::info!("OIDC login successful for user: {}", user_id);
+    let cookie = oidc_state_cookie("", 0, &state.app_url);
+    Ok(([(SET_COOKIE, cookie)], success(login)))
+}
+
+/// `Set-Cookie` value of the single sign-on state cookie; an empty value
+/// and no lifetime remove it
+fn oidc_state_cookie(value: &str, max_age_secs: i64, app_url: &str) -> String {
+    let secure = if app_url.starts_with("https://") {
+        "; Secure"
+    } else {
+        ""
+    };
+    format!(
+        "{OIDC_STATE_COOKIE}={value}; Max-Age={max_age_secs}; Path=/; HttpOnly; SameSite=Lax{secure}"
+    )
+}
+
+/// Value of a cookie sent with a request
+fn cookie_value<'a>(headers: &'a HeaderMap, name: &str) -> Option<&'a str> {
+    headers
+        .get_all(COOKIE)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(';'))
+        .filter_map(|pair| pair.trim().split_once('='))
+        .find(|(key, _)| *key == name)
+        .map(|(_, value)| value)
+}
+
+/// Email the user about a login from a new device, when enabled
+fn notify_new_device(state: &AppState, auth: &AuthResponse) {
+    if state.account.new_device_emails {
+        SessionService::new(state.pool.clone()).notify_new_device(
+            auth,
+            state.mailer.clone(),
+            state.app_url.clone(),
+        );
+    }
+}
+
+fn oidc_client(state: &AppState) -> Result<Arc<OidcClient>, AppError> {
+    state
+        .oidc
+        .clone()
+        .ok_or_else(|| AppError::not_found("OIDC provider", "Single sign-on is not configured"))
+}
+
+/// Exchange a refresh token for a new access token and refresh token
+async fn refresh(
+    State(pool): State<PgPool>,
+    client_info: ClientInfo,
+    payload: Result<Json<RefreshTokenRequest>, JsonRejection>,
+) -> Result<Json<ApiResponse<TokenResponse>>, AppError> {
+    let Json(request) = payload?;
+    tracing::info!("Refresh token request received");
+
+    if request.refresh_token.is_empty() {
+        return Err(AppError::validation_error(
+            "Refresh token is required",
+            "Your session has expired. Please sign in again.",
+        ));
+    }
+
+    let refresh_token_service = RefreshTokenService::new(pool);
+    let tokens = refresh_token_service
+        .refresh(&request.refresh_token, &client_info)
+        .await?;
+
+    Ok(success(tokens))
+}
+
+/// Logout a user by revoking their refresh token
+async fn logout(
+    State(pool): State<PgPool>,
+    payload: Result<Json<RefreshTokenRequest>, JsonRejection>,
+) -> Result<Json<ApiResponse<()>>, AppError> {
+    let Json(request) = payload?;
+    tracing::info!("Logout request received");
+
+    let refresh_token_service = RefreshTokenService::new(pool);
+    refresh_token_service.revoke(&request.refresh_token).await?;
+
+    Ok(success(()))
+}
+
+/// Request a password reset email
+///
+/// Always succeeds, so the response does not reveal whether the account exists.
+async fn request_password_reset(
+    State(state): State<AppState>,
+    payload: Result<Json<PasswordResetRequest>, JsonRejection>,
+) -> Result<Json<ApiResponse<()>>, AppError> {
+    let Json(request) = payload?;
+    tracing::info!("Password reset request received");
+
+    if request.email.trim().is_empty() {
+        return Err(AppError::validation_error(
+            "Email is required for password reset",
+            "Please enter the email address of your account.",
+        ));
+    }
+
+    let password_reset_service = PasswordResetService::new(state.pool);
+    password_reset_service.request_reset(request.email, state.mailer, state.app_url);
+
+    Ok(success(()))
+}
+
+/// Set a new password with the token from a password reset email
+async fn confirm_password_reset(
+    State(pool): State<PgPool>,
+    payload: Result<Json<PasswordResetConfirm>, JsonRejection>,
+) -> Result<Json<ApiResponse<()>>, AppError> {
+    let Json(request) = payload?;
+    tracing::info!("Password reset confirmation received");
+
+    if request.token.is_empty() {
+        return Err(AppError::validation_error(
+            "Password reset token is required",
+            "This password reset link is invalid. Please request a new one.",
+        ));
+    }
+    if request.password.len() < 8 {
+        return Err(AppError::validation_error(
+            "Password must be at least 8 characters",
+            "Your password must be at least 8 characters long for security.",
+        ));
+    }
+
+    let password_reset_service = PasswordResetService::new(pool);
+    password_reset_service
+        .reset_password(&request.token, &request.password)
+        .await?;
+
+    Ok(success(()))
+}
+
+/// Verify the user's email address with the token from a verification email
+async fn verify_email(
+    State(pool): State<PgPool>,
+    payload: Result<Json<VerifyEmailRequest>, JsonRejection>,
+) -> Result<Json<ApiResponse<()>>, AppError> {
+    let Json(request) = payload?;
+    tracing::info!("Email verification request received");
+
+    if request.token.is_empty() {
+        return Err(AppError::validation_error(
+            "Email verification token is required",
+            "This verification link is invalid. Please request a new one.",
+        ));
+    }
+
+    let email_verification_service = EmailVerificationService::new(pool);
+    email_verification_service
+        .verify_email(&request.token)
+        .await?;
+
+    Ok(success(()))
+}
+
+/// Lift the lock of an account with the token from an unlock email
+async fn unlock_account(
+    State(state): State<AppState>,
+    payload: Result<Json<UnlockAccountRequest>, JsonRejection>,
+) -> Result<Json<ApiResponse<()>>, AppError> {
+    let Json(request) = payload?;
+    tracing::info!("Account unlock request received");
+
+    if request.token.is_empty() {
+        return Err(AppError::validation_error(
+            "Account unlock token is required",
+            "This unlock link is invalid. Please reset your password instead.",
+        ));
+    }
+
+    let lockout_service = LoginLockoutService::new(state.pool, state.lockout);
+    lockout_service.unlock(&request.token).await?;
+
+    Ok(success(()))
+}
+
+/// Send a new verification email to the authenticated user
+async fn resend_verification_email(
+    auth: SessionAuth,
+    State(state): State<AppState>,
+) -> Result<Json<ApiResponse<()>>, AppError> {
+    tracing::info!(
+        "Resend verification email request for user: {}",
+        auth.user_id
+    );
+
+    let email_verification_service = EmailVerificationService::new(state.pool);
+    email_verification_service
+        .resend_verification(auth.user_id, state.mailer, &state.app_url)
+        .await?;
+
+    Ok(success(()))
+}
@@ -0,0 +1,38 @@
+use axum::{
+    Json, Router,
+    extract::{Path, Query, State},
+    routing::get,
+};
+use sqlx::PgPool;
+use tracing;
+use uuid::Uuid;
+
+use crate::models::{PaginatedResponse, PaginationQuery, SubscriptionEvent};
+use crate::services::EventService;
+use crate::state::AppState;
+use crate::utils::auth::Auth;
+use crate::utils::response::{ApiResponse, AppError, success};
+
+/// Create subscription event routes, nested under a subscription
+pub fn event_routes() -> Router<AppState> {
+    Router::new().route("/{id}/events", get(list_events))
+}
+
+/// List the event history of a subscription
+async fn list_events(
+    auth: Auth,
+    State(pool): State<PgPool>,
+    Path(id): Path<Uuid>,
+    Query(query): Query<PaginationQuery>,
+) -> Result<Json<ApiResponse<PaginatedResponse<SubscriptionEvent>>>, AppError> {
+    tracing::info!(
+        "List events request for subscription: {} user: {}",
+        id,
+        auth.user_id
+    );
+
+    let event_service = EventService::new(pool);
+    let events = event_service.list_events(auth.user_id, id, &query).await?;
+
+    Ok(success(events))
+}
@@ -2,6 +2,8 @@ use axum::{Router, extract::State, http::StatusCode, response::Json, routing::ge
 use serde_json::{Value, json};
 use sqlx::PgPool;
 
+use crate::state::AppState;
+
 /// Health check endpoint that verifies database connectivity
 async fn health_check(State(pool): State<PgPool>) -> Result<Json<Value>, StatusCode> {
     // Check database connection
@@ -45,7 +47,7 @@ async fn readiness_check(State(pool): State<PgPool>) -> Result<Json<Value>, Stat
 }
 
 /// Create health check routes
-pub fn health_routes() -> Router<PgPool> {
+pub fn health_routes() -> Router<AppState> {
     Router::new()
         .route("/health", get(health_check))
         .route("/health/live", get(liveness_check))
@@ -1,21 +1,32 @@
 pub mod auth;
+pub mod events;
 pub mod health;
+pub mod notifications;
+pub mod payments;
 pub mod subscriptions;
 pub mod users;
+pub mod webhooks;
+pub mod well_known;
 
 use axum::Router;
-use sqlx::PgPool;
+
+use crate::state::AppState;
 
 pub use self::auth::auth_routes;
 pub use self::health::health_routes;
+pub use self::notifications::notification_routes;
 pub use self::subscriptions::subscription_routes;
 pub use self::users::user_routes;
+pub use self::webhooks::webhook_routes;
+pub use self::well_known::well_known_routes;
 
 /// Create all API routes
-pub fn api_routes() -> Router<PgPool> {
+pub fn api_routes() -> Router<AppState> {
     Router::new()
         .merge(health_routes())
         .nest("/auth", auth_routes())
         .nest("/users", user_routes())
         .nest("/subscriptions", subscription_routes())
+        .nest("/notifications", notification_routes())
+        .nest("/webhooks", webhook_routes())
 }
@@ -0,0 +1,76 @@
+use axum::{
+    Json, Router,
+    extract::{Path, Query, State},
+    routing::{get, post},
+};
+use sqlx::PgPool;
+use tracing;
+use uuid::Uuid;
+
+use crate::models::{
+    Notification, NotificationFilter, NotificationsReadResponse, PaginatedResponse, PaginationQuery,
+};
+use crate::services::NotificationService;
+use crate::state::AppState;
+use crate::utils::auth::Auth;
+use crate::utils::response::{ApiResponse, AppError, success};
+
+/// Create notification center routes
+pub fn notification_routes() -> Router<AppState> {
+    Router::new()
+        .route("/", get(list_notifications))
+        .route("/read-all", post(mark_all_read))
+        .route("/{id}/read", post(mark_read))
+}
+
+/// List notifications of the authenticated user
+async fn list_notifications(
+    auth: Auth,
+    State(pool): State<PgPool>,
+    Query(filter): Query<NotificationFilter>,
+    Query(query): Query<PaginationQuery>,
+) -> Result<Json<ApiResponse<PaginatedResponse<Notification>>>, AppError> {
+    tracing::info!("List notifications request for user: {}", auth.user_id);
+
+    // Reminders are generated by the renewal scheduler; listing only reads
+    let notification_service = NotificationService::new(pool);
+    let notifications = notification_service
+        .list_notifications(auth.user_id, &filter, &query)
+        .await?;
+
+    Ok(success(notifications))
+}
+
+/// Mark a notification as read
+async fn mark_read(
+    auth: Auth,
+    State(pool): State<PgPool>,
+    Path(id): Path<Uuid>,
+) -> Result<Json<ApiResponse<Notification>>, AppError> {
+    tracing::info!(
+        "Mark notification read request for: {} user: {}",
+        id,
+        auth.user_id
+    );
+
+    let notification_service = NotificationService::new(pool);
+    let notification = notification_service.mark_read(auth.user_id, id).await?;
+
+    Ok(success(notification))
+}
+
+/// Mark all notifications of the authenticated user as read
+async fn mark_all_read(
+    auth: Auth,
+    State(pool): State<PgPool>,
+) -> Result<Json<ApiResponse<NotificationsReadResponse>>, AppError> {
+    tracing::info!(
+        "Mark all notifications read request for user: {}",
+        auth.user_id
+    );
+
+    let notification_service = NotificationService::new(pool);
+    let count = notification_service.mark_all_read(auth.user_id).await?;
+
+    Ok(success(NotificationsReadResponse { count }))
+}
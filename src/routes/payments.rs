@@ -0,0 +1,69 @@
+use axum::{
+    Json, Router,
+    extract::{Path, Query, State, rejection::JsonRejection},
+    routing::get,
+};
+use chrono::Utc;
+use sqlx::PgPool;
+use tracing;
+use uuid::Uuid;
+
+use crate::models::{CreatePaymentRequest, PaginatedResponse, PaginationQuery, PaymentRecord};
+use crate::services::PaymentService;
+use crate::state::AppState;
+use crate::utils::auth::Auth;
+use crate::utils::response::{ApiResponse, AppError, success};
+
+/// Create payment record routes, nested under a subscription
+pub fn payment_routes() -> Router<AppState> {
+    Router::new().route("/{id}/payments", get(list_payments).post(record_payment))
+}
+
+/// List payment records of a subscription
+async fn list_payments(
+    auth: Auth,
+    State(pool): State<PgPool>,
+    Path(id): Path<Uuid>,
+    Query(query): Query<PaginationQuery>,
+) -> Result<Json<ApiResponse<PaginatedResponse<PaymentRecord>>>, AppError> {
+    tracing::info!(
+        "List payments request for subscription: {} user: {}",
+        id,
+        auth.user_id
+    );
+
+    let payment_service = PaymentService::new(pool);
+    let payments = payment_service
+        .list_payments(auth.user_id, id, &query)
+        .await?;
+
+    Ok(success(payments))
+}
+
+/// Record a payment against a subscription
+async fn record_payment(
+    auth: Auth,
+    State(pool): State<PgPool>,
+    Path(id): Path<Uuid>,
+    payload: Result<Json<CreatePaymentRequest>, JsonRejection>,
+) -> Result<Json<ApiResponse<PaymentRecord>>, AppError> {
+    let Json(req) = payload?;
+    tracing::info!(
+        "Record payment request for subscription: {} user: {}",
+        id,
+        auth.user_id
+    );
+
+    let payment_service = PaymentService::new(pool);
+    let payment = payment_service
+        .record_payment(auth.user_id, id, req, Utc::now().naive_utc().date())
+        .await?;
+
+    tracing::info!(
+        "Payment recorded successfully: {} for subscription: {}",
+        payment.id,
+        id
+    );
+
+    Ok(success(payment))
+}
@@ -1,321 +1,318 @@
 use axum::{
     Json, Router,
-    extract::{Path, State},
-    http::{HeaderMap, StatusCode},
-    routing::get,
+    extract::{Path, Query, State, rejection::JsonRejection},
+    routing::{get, post},
 };
 use chrono::Utc;
-use serde_json::json;
 use sqlx::PgPool;
 use tracing;
 use uuid::Uuid;
 
-use crate::models::Subscription;
-use crate::services::SubscriptionService;
-use crate::utils::auth::extract_auth;
+use crate::models::{
+    CancelSubscriptionRequest, StatisticsQuery, StatusTransition, Subscription,
+    SubscriptionStatistics,
+};
+use crate::routes::events::event_routes;
+use crate::routes::payments::payment_routes;
+use crate::services::{StatisticsService, SubscriptionService};
+use crate::state::AppState;
+use crate::utils::auth::Auth;
+use crate::utils::response::{ApiResponse, AppError, success};
 use crate::utils::validate_subscription_request;
 
+/// Default number of top subscriptions returned by the stats endpoint
+const DEFAULT_TOP_SUBSCRIPTIONS: i64 = 5;
+/// Default look-ahead window for upcoming renewals in days
+const DEFAULT_UPCOMING_DAYS: i32 = 30;
+/// Default number of months of payment history, counting the current one
+const DEFAULT_HISTORY_MONTHS: i32 = 12;
+
 /// Create subscription routes
-pub fn subscription_routes() -> Router<PgPool> {
+pub fn subscription_routes() -> Router<AppState> {
     Router::new()
         .route("/", get(get_subscriptions).post(create_subscription))
+        .route("/stats", get(get_subscription_stats))
+        .merge(payment_routes())
+        .merge(event_routes())
         .route(
             "/{id}",
             get(get_subscription)
                 .put(update_subscription)
                 .delete(delete_subscription),
         )
+        .route("/{id}/pause", post(pause_subscription))
+        .route("/{id}/resume", post(resume_subscription))
+        .route("/{id}/cancel", post(cancel_subscription))
 }
 
 /// Get all subscriptions for the authenticated user
 async fn get_subscriptions(
-    headers: HeaderMap,
+    auth: Auth,
     State(pool): State<PgPool>,
-) -> Result<Json<serde_json::Value>, (StatusCode, Json<serde_json::Value>)> {
-    // Log the request
-    tracing::info!("Get subscriptions request received");
-
-    // Extract auth from headers
-    let auth = match extract_auth(&headers) {
-        Ok(auth) => auth,
-        Err(_) => {
-            tracing::warn!("Get subscriptions request failed: Unauthorized");
-            return Err((
-                StatusCode::UNAUTHORIZED,
-                Json(json!({"error": "Unauthorized"})),
-            ));
-        }
-    };
+) -> Result<Json<ApiResponse<Vec<Subscription>>>, AppError> {
+    tracing::info!("Get subscriptions request for user: {}", auth.user_id);
 
     let subscription_service = SubscriptionService::new(pool);
+    let subscriptions = subscription_service.get_subscriptions(auth.user_id).await?;
 
-    match subscription_service.get_subscriptions(auth.user_id).await {
-        Ok(response) => {
-            tracing::info!(
-                "Subscriptions retrieved successfully for user: {}",
-                auth.user_id
-            );
-            Ok(Json(json!(response)))
-        }
-        Err(_) => {
-            tracing::error!(
-                "Failed to retrieve subscriptions for user: {}",
-                auth.user_id
-            );
-            Err((
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(json!({"error": "Failed to retrieve subscriptions"})),
-            ))
-        }
-    }
+    Ok(success(subscriptions))
 }
 
 /// Create a new subscription for the authenticated user
 async fn create_subscription(
-    headers: HeaderMap,
+    auth: Auth,
     State(pool): State<PgPool>,
-    Json(mut req): Json<Subscription>,
-) -> Result<Json<serde_json::Value>, (StatusCode, Json<serde_json::Value>)> {
-    // Log the request
-    tracing::info!("Create subscription request received for: {}", req.name);
-
-    // Extract auth from headers
-    let auth = match extract_auth(&headers) {
-        Ok(auth) => auth,
-        Err(_) => {
-            tracing::warn!("Create subscription request failed: Unauthorized");
-            return Err((
-                StatusCode::UNAUTHORIZED,
-                Json(json!({"error": "Unauthorized"})),
-            ));
-        }
-    };
+    payload: Result<Json<Subscription>, JsonRejection>,
+) -> Result<Json<ApiResponse<Subscription>>, AppError> {
+    let Json(mut req) = payload?;
+
+    tracing::info!(
+        "Create subscription request for: {} user: {}",
+        req.name,
+        auth.user_id
+    );
 
+    let today = Utc::now().naive_utc().date();
     req.user_id = auth.user_id;
-    req.next_billing_date =
-        req.calculate_next_billing_date(req.start_date, Utc::now().naive_utc().date());
+    req.billing_anchor_date = req.start_date;
+    req.next_billing_date = req.calculate_next_billing_date(req.start_date, today);
     // Validate the request
     validate_subscription_request(&req)?;
 
+    // New subscriptions start out active and reach the requested status
+    // through the regular transitions
+    let status = std::mem::take(&mut req.status);
+    req.transition_to(status, today)
+        .map_err(|e| AppError::conflict("Invalid status transition", e.to_string()))?;
+
     let subscription_service = SubscriptionService::new(pool);
+    let subscription = subscription_service.create_subscription(req).await?;
 
-    match subscription_service.create_subscription(req).await {
-        Ok(subscription) => {
-            tracing::info!(
-                "Subscription created successfully: {} for user: {}",
-                subscription.name,
-                auth.user_id
-            );
-            Ok(Json(json!(subscription)))
-        }
-        Err(_) => {
-            tracing::error!("Failed to create subscription for user: {}", auth.user_id);
-            Err((
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(json!({"error": "Failed to create subscription"})),
-            ))
-        }
-    }
+    tracing::info!(
+        "Subscription created successfully: {} for user: {}",
+        subscription.name,
+        auth.user_id
+    );
+
+    Ok(success(subscription))
 }
 
 /// Get a specific subscription by ID
 async fn get_subscription(
-    headers: HeaderMap,
+    auth: Auth,
     State(pool): State<PgPool>,
     Path(id): Path<Uuid>,
-) -> Result<Json<serde_json::Value>, (StatusCode, Json<serde_json::Value>)> {
-    // Log the request
-    tracing::info!("Get subscription request received for ID: {}", id);
-
-    // Extract auth from headers
-    let auth = match extract_auth(&headers) {
-        Ok(auth) => auth,
-        Err(_) => {
-            tracing::warn!("Get subscription request failed: Unauthorized");
-            return Err((
-                StatusCode::UNAUTHORIZED,
-                Json(json!({"error": "Unauthorized"})),
-            ));
-        }
-    };
+) -> Result<Json<ApiResponse<Subscription>>, AppError> {
+    tracing::info!(
+        "Get subscription request for: {} user: {}",
+        id,
+        auth.user_id
+    );
 
     let subscription_service = SubscriptionService::new(pool);
-
-    match subscription_service
+    let subscription = subscription_service
         .get_subscription(auth.user_id, id)
-        .await
-    {
-        Ok(subscription) => {
-            tracing::info!(
-                "Subscription retrieved successfully: {} for user: {}",
-                subscription.name,
-                auth.user_id
-            );
-            Ok(Json(json!(subscription)))
-        }
-        Err(sqlx::Error::RowNotFound) => {
-            tracing::warn!("Subscription not found: {} for user: {}", id, auth.user_id);
-            Err((
-                StatusCode::NOT_FOUND,
-                Json(json!({"error": "Subscription not found"})),
-            ))
-        }
-        Err(_) => {
-            tracing::error!(
-                "Failed to retrieve subscription: {} for user: {}",
-                id,
-                auth.user_id
-            );
-            Err((
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(json!({"error": "Failed to retrieve subscription"})),
-            ))
-        }
-    }
+        .await?;
+
+    Ok(success(subscription))
 }
 
 /// Update a subscription
 async fn update_subscription(
-    headers: HeaderMap,
+    auth: Auth,
     State(pool): State<PgPool>,
     Path(id): Path<Uuid>,
-    Json(mut req): Json<Subscription>,
-) -> Result<Json<serde_json::Value>, (StatusCode, Json<serde_json::Value>)> {
-    // Log the request
-    tracing::info!("Update subscription request received for ID: {}", id);
-
-    // Extract auth from headers
-    let auth = match extract_auth(&headers) {
-        Ok(auth) => auth,
-        Err(_) => {
-            tracing::warn!("Update subscription request failed: Unauthorized");
-            return Err((
-                StatusCode::UNAUTHORIZED,
-                Json(json!({"error": "Unauthorized"})),
-            ));
-        }
-    };
+    payload: Result<Json<Subscription>, JsonRejection>,
+) -> Result<Json<ApiResponse<Subscription>>, AppError> {
+    let Json(mut req) = payload?;
+
+    tracing::info!(
+        "Update subscription request for: {} user: {}",
+        id,
+        auth.user_id
+    );
 
+    let today = Utc::now().naive_utc().date();
     req.user_id = auth.user_id;
-    req.next_billing_date =
-        req.calculate_next_billing_date(req.start_date, Utc::now().naive_utc().date());
+    req.billing_anchor_date = req.start_date;
+    req.next_billing_date = req.calculate_next_billing_date(req.start_date, today);
     // Validate the request
     validate_subscription_request(&req)?;
 
     let subscription_service = SubscriptionService::new(pool);
 
     // First check if the subscription belongs to the user
-    match subscription_service
+    let existing = subscription_service
         .get_subscription(auth.user_id, id)
-        .await
-    {
-        Ok(_) => {
-            // Subscription belongs to user, proceed with update
-            match subscription_service.update_subscription(id, req).await {
-                Ok(subscription) => {
-                    tracing::info!(
-                        "Subscription updated successfully: {} for user: {}",
-                        subscription.name,
-                        auth.user_id
-                    );
-                    Ok(Json(json!(subscription)))
-                }
-                Err(_) => {
-                    tracing::error!(
-                        "Failed to update subscription: {} for user: {}",
-                        id,
-                        auth.user_id
-                    );
-                    Err((
-                        StatusCode::INTERNAL_SERVER_ERROR,
-                        Json(json!({"error": "Failed to update subscription"})),
-                    ))
-                }
-            }
-        }
-        Err(sqlx::Error::RowNotFound) => {
-            tracing::warn!(
-                "Subscription not found for update: {} for user: {}",
-                id,
-                auth.user_id
-            );
-            Err((
-                StatusCode::NOT_FOUND,
-                Json(json!({"error": "Subscription not found"})),
-            ))
-        }
-        Err(_) => {
-            tracing::error!(
-                "Failed to verify subscription ownership: {} for user: {}",
-                id,
-                auth.user_id
-            );
-            Err((
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(json!({"error": "Failed to verify subscription ownership"})),
-            ))
-        }
-    }
+        .await?;
+
+    req.reschedule_from(&existing, today);
+
+    // A status change goes through the same transitions as the
+    // pause/resume/cancel actions
+    let status = std::mem::replace(&mut req.status, existing.status.clone());
+    req.paused_at = existing.paused_at;
+    req.end_date = existing.end_date;
+    req.transition_to(status, today)
+        .map_err(|e| AppError::conflict("Invalid status transition", e.to_string()))?;
+
+    // Subscription belongs to user, proceed with update
+    let subscription = subscription_service.update_subscription(id, req).await?;
+
+    tracing::info!(
+        "Subscription updated successfully: {} for user: {}",
+        subscription.name,
+        auth.user_id
+    );
+
+    Ok(success(subscription))
 }
 
 /// Delete a subscription
 async fn delete_subscription(
-    headers: HeaderMap,
+    auth: Auth,
     State(pool): State<PgPool>,
     Path(id): Path<Uuid>,
-) -> Result<Json<serde_json::Value>, (StatusCode, Json<serde_json::Value>)> {
-    // Log the request
-    tracing::info!("Delete subscription request received for ID: {}", id);
-
-    // Extract auth from headers
-    let auth = match extract_auth(&headers) {
-        Ok(auth) => auth,
-        Err(_) => {
-            tracing::warn!("Delete subscription request failed: Unauthorized");
-            return Err((
-                StatusCode::UNAUTHORIZED,
-                Json(json!({"error": "Unauthorized"})),
-            ));
-        }
-    };
+) -> Result<Json<ApiResponse<()>>, AppError> {
+    tracing::info!(
+        "Delete subscription request for: {} user: {}",
+        id,
+        auth.user_id
+    );
 
     let subscription_service = SubscriptionService::new(pool);
-
-    match subscription_service
+    subscription_service
         .delete_subscription(auth.user_id, id)
-        .await
-    {
-        Ok(_) => {
-            tracing::info!(
-                "Subscription deleted successfully: {} for user: {}",
-                id,
-                auth.user_id
-            );
-            Ok(Json(
-                json!({"success": true, "message": "Subscription deleted"}),
-            ))
-        }
-        Err(sqlx::Error::RowNotFound) => {
-            tracing::warn!(
-                "Subscription not found for deletion: {} for user: {}",
-                id,
-                auth.user_id
-            );
-            Err((
-                StatusCode::NOT_FOUND,
-                Json(json!({"error": "Subscription not found"})),
-            ))
-        }
-        Err(_) => {
-            tracing::error!(
-                "Failed to delete subscription: {} for user: {}",
-                id,
-                auth.user_id
-            );
-            Err((
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(json!({"error": "Failed to delete subscription"})),
-            ))
-        }
+        .await?;
+
+    tracing::info!(
+        "Subscription deleted successfully: {} for user: {}",
+        id,
+        auth.user_id
+    );
+
+    Ok(success(()))
+}
+
+/// Get spending statistics for the authenticated user
+async fn get_subscription_stats(
+    auth: Auth,
+    State(pool): State<PgPool>,
+    Query(query): Query<StatisticsQuery>,
+) -> Result<Json<ApiResponse<SubscriptionStatistics>>, AppError> {
+    tracing::info!("Get subscription stats request for user: {}", auth.user_id);
+
+    let top = query.top.unwrap_or(DEFAULT_TOP_SUBSCRIPTIONS);
+    if !(1..=50).contains(&top) {
+        return Err(AppError::validation_error(
+            format!("Invalid top value: {top}"),
+            "The number of top subscriptions must be between 1 and 50.",
+        ));
     }
+
+    let upcoming_days = query.upcoming_days.unwrap_or(DEFAULT_UPCOMING_DAYS);
+    if !(0..=365).contains(&upcoming_days) {
+        return Err(AppError::validation_error(
+            format!("Invalid upcoming_days value: {upcoming_days}"),
+            "The upcoming renewal window must be between 0 and 365 days.",
+        ));
+    }
+
+    let history_months = query.history_months.unwrap_or(DEFAULT_HISTORY_MONTHS);
+    if !(1..=60).contains(&history_months) {
+        return Err(AppError::validation_error(
+            format!("Invalid history_months value: {history_months}"),
+            "The payment history must cover between 1 and 60 months.",
+        ));
+    }
+
+    let statistics_service = StatisticsService::new(pool);
+    let stats = statistics_service
+        .get_statistics(
+            auth.user_id,
+            top,
+            Utc::now().naive_utc().date(),
+            upcoming_days,
+            history_months,
+        )
+        .await?;
+
+    tracing::info!(
+        "Subscription stats retrieved successfully for user: {}",
+        auth.user_id
+    );
+
+    Ok(success(stats))
+}
+
+/// Pause an active subscription, freezing its billing cycle
+async fn pause_subscription(
+    auth: Auth,
+    State(pool): State<PgPool>,
+    Path(id): Path<Uuid>,
+) -> Result<Json<ApiResponse<Subscription>>, AppError> {
+    tracing::info!(
+        "Pause subscription request for: {} user: {}",
+        id,
+        auth.user_id
+    );
+
+    transition_subscription(pool, auth.user_id, id, StatusTransition::Pause).await
+}
+
+/// Resume a paused subscription, shifting its next billing date by the paused duration
+async fn resume_subscription(
+    auth: Auth,
+    State(pool): State<PgPool>,
+    Path(id): Path<Uuid>,
+) -> Result<Json<ApiResponse<Subscription>>, AppError> {
+    tracing::info!(
+        "Resume subscription request for: {} user: {}",
+        id,
+        auth.user_id
+    );
+
+    transition_subscription(pool, auth.user_id, id, StatusTransition::Resume).await
+}
+
+/// Cancel a subscription, recording its effective end date
+async fn cancel_subscription(
+    auth: Auth,
+    State(pool): State<PgPool>,
+    Path(id): Path<Uuid>,
+    req: Option<Json<CancelSubscriptionRequest>>,
+) -> Result<Json<ApiResponse<Subscription>>, AppError> {
+    tracing::info!(
+        "Cancel subscription request for: {} user: {}",
+        id,
+        auth.user_id
+    );
+
+    let Json(req) = req.unwrap_or_default();
+    let transition = StatusTransition::Cancel {
+        effective_date: req.effective_date,
+    };
+    transition_subscription(pool, auth.user_id, id, transition).await
+}
+
+/// Apply a status transition for the pause/resume/cancel actions
+async fn transition_subscription(
+    pool: PgPool,
+    user_id: Uuid,
+    id: Uuid,
+    transition: StatusTransition,
+) -> Result<Json<ApiResponse<Subscription>>, AppError> {
+    let subscription_service = SubscriptionService::new(pool);
+    let subscription = subscription_service
+        .transition_subscription(user_id, id, transition, Utc::now().naive_utc().date())
+        .await?;
+
+    tracing::info!(
+        "Subscription {} transitioned to {} for user: {}",
+        id,
+        subscription.status.as_str(),
+        user_id
+    );
+
+    Ok(success(subscription))
 }
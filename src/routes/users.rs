@@ -1,27 +1,66 @@
-use axum::{Json, Router, extract::State, http::HeaderMap, routing::get};
+use axum::{
+    Json, Router,
+    extract::{Path, State, rejection::JsonRejection},
+    http::header,
+    response::IntoResponse,
+    routing::{delete, get, post, put},
+};
 use sqlx::PgPool;
 use tracing;
+use uuid::Uuid;
 
-use crate::models::UserResponse;
-use crate::services::UserService;
-use crate::utils::auth::extract_auth;
+use crate::models::{
+    AccountDeletion, ApiToken, ApiTokenWithSecret, ChangeEmailRequest, ChangePasswordRequest,
+    CreateApiTokenRequest, DeleteAccountRequest, DisableTwoFactorRequest,
+    MAX_API_TOKEN_EXPIRY_DAYS, RecoveryCodes, Session, TokenResponse, TwoFactorCodeRequest,
+    TwoFactorEnrollment, TwoFactorStatus, UpdateProfileRequest, UserResponse,
+};
+use crate::services::{
+    AccountService, ApiTokenService, EmailVerificationService, SessionService, TwoFactorService,
+    UserService,
+};
+use crate::state::AppState;
+use crate::utils::auth::{Auth, SessionAuth};
+use crate::utils::client::ClientInfo;
 use crate::utils::response::{ApiResponse, AppError, success};
+use crate::utils::user_validation::{is_valid_email, validate_profile_update};
 
 /// Create user routes
-pub fn user_routes() -> Router<PgPool> {
-    Router::new().route("/me", get(get_current_user))
+///
+/// Except for reading the current user, these routes manage the account and
+/// need an interactive login; API tokens are rejected.
+pub fn user_routes() -> Router<AppState> {
+    Router::new()
+        .route("/me/export", get(export_current_user))
+        // Routes checking the password or two-factor codes fall under the
+        // strict `auth` rate limit policy
+        .route(
+            "/me",
+            get(get_current_user)
+                .patch(update_current_user)
+                .delete(delete_current_user),
+        )
+        .route("/me/password", put(change_password))
+        .route("/me/email", post(change_email))
+        .route("/me/2fa", get(get_two_factor_status))
+        .route("/me/2fa/enroll", post(enroll_two_factor))
+        .route("/me/2fa/confirm", post(confirm_two_factor))
+        .route("/me/2fa/disable", post(disable_two_factor))
+        .route("/me/2fa/recovery-codes", post(regenerate_recovery_codes))
+        .route("/me/tokens", get(list_api_tokens).post(create_api_token))
+        .route("/me/tokens/{id}", delete(revoke_api_token))
+        .route(
+            "/me/sessions",
+            get(list_sessions).delete(revoke_all_sessions),
+        )
+        .route("/me/sessions/{id}", delete(revoke_session))
 }
 
 /// Get current user
 async fn get_current_user(
-    headers: HeaderMap,
+    auth: Auth,
     State(pool): State<PgPool>,
 ) -> Result<Json<ApiResponse<UserResponse>>, AppError> {
-    // Extract auth from headers
-    let auth = extract_auth(&headers).map_err(|_| {
-        AppError::unauthorized("Authentication required to access user information")
-    })?;
-
     // Log the request
     tracing::info!("Get current user request for user ID: {}", auth.user_id);
 
@@ -36,3 +75,322 @@ async fn get_current_user(
     // Return success response
     Ok(success(user))
 }
+
+/// Update the name and preferences of the current user
+async fn update_current_user(
+    auth: SessionAuth,
+    State(pool): State<PgPool>,
+    payload: Result<Json<UpdateProfileRequest>, JsonRejection>,
+) -> Result<Json<ApiResponse<UserResponse>>, AppError> {
+    let Json(request) = payload?;
+    tracing::info!("Update profile request for user ID: {}", auth.user_id);
+
+    validate_profile_update(&request)?;
+
+    // Reminder emails are only sent to verified addresses
+    if let Some(preferences) = &request.preferences
+        && preferences.email_reminders == Some(Some(true))
+    {
+        EmailVerificationService::new(pool.clone())
+            .require_verified(auth.user_id, "enable email reminders")
+            .await?;
+    }
+
+    let user_service = UserService::new(pool);
+    let user = user_service.update_profile(auth.user_id, request).await?;
+
+    Ok(success(user))
+}
+
+/// Change the password of the current user
+///
+/// Other sessions are signed out; the response carries new tokens for this one.
+async fn change_password(
+    auth: SessionAuth,
+    State(pool): State<PgPool>,
+    client_info: ClientInfo,
+    payload: Result<Json<ChangePasswordRequest>, JsonRejection>,
+) -> Result<Json<ApiResponse<TokenResponse>>, AppError> {
+    let Json(request) = payload?;
+    tracing::info!("Change password request for user ID: {}", auth.user_id);
+
+    if request.new_password.len() < 8 {
+        return Err(AppError::field_validation_error(vec![(
+            "new_password",
+            "Password must be at least 8 characters".to_string(),
+        )]));
+    }
+
+    let user_service = UserService::new(pool);
+    let tokens = user_service
+        .change_password(auth.user_id, request, &client_info)
+        .await?;
+
+    Ok(success(tokens))
+}
+
+/// Start changing the email address of the current user
+///
+/// The new address takes effect once the link sent to it is followed.
+async fn change_email(
+    auth: SessionAuth,
+    State(state): State<AppState>,
+    payload: Result<Json<ChangeEmailRequest>, JsonRejection>,
+) -> Result<Json<ApiResponse<()>>, AppError> {
+    let Json(request) = payload?;
+    tracing::info!("Change email request for user ID: {}", auth.user_id);
+
+    let new_email = request.new_email.trim();
+    if !is_valid_email(new_email) {
+        return Err(AppError::field_validation_error(vec![(
+            "new_email",
+            "Please enter a valid email address".to_string(),
+        )]));
+    }
+
+    UserService::new(state.pool.clone())
+        .confirm_sensitive_change(auth.user_id, auth.session_id, request.password.as_deref())
+        .await?;
+
+    let email_verification_service = EmailVerificationService::new(state.pool);
+    email_verification_service
+        .request_email_change(auth.user_id, new_email, state.mailer, &state.app_url)
+        .await?;
+
+    Ok(success(()))
+}
+
+/// Download all personal data of the current user as a JSON archive
+async fn export_current_user(
+    auth: SessionAuth,
+    State(pool): State<PgPool>,
+) -> Result<impl IntoResponse, AppError> {
+    tracing::info!("Account export request for user ID: {}", auth.user_id);
+
+    let account_service = AccountService::new(pool);
+    let export = account_service.export(auth.user_id).await?;
+
+    let disposition = format!(
+        "attachment; filename=\"sub-pal-export-{}.json\"",
+        export.exported_at.format("%Y-%m-%d")
+    );
+    Ok(([(header::CONTENT_DISPOSITION, disposition)], Json(export)))
+}
+
+/// Delete the account of the current user after confirming it is them
+async fn delete_current_user(
+    auth: SessionAuth,
+    State(state): State<AppState>,
+    payload: Result<Json<DeleteAccountRequest>, JsonRejection>,
+) -> Result<Json<ApiResponse<AccountDeletion>>, AppError> {
+    let Json(request) = payload?;
+    tracing::info!("Account deletion request for user ID: {}", auth.user_id);
+
+    UserService::new(state.pool.clone())
+        .confirm_sensitive_change(auth.user_id, auth.session_id, request.password.as_deref())
+        .await?;
+
+    let account_service = AccountService::new(state.pool);
+    let deletion = account_service
+        .delete_account(auth.user_id, state.account.deletion_grace_days)
+        .await?;
+
+    Ok(success(deletion))
+}
+
+/// Get the two-factor authentication state of the current user
+async fn get_two_factor_status(
+    auth: SessionAuth,
+    State(pool): State<PgPool>,
+) -> Result<Json<ApiResponse<TwoFactorStatus>>, AppError> {
+    let two_factor_service = TwoFactorService::new(pool);
+    let status = two_factor_service.status(auth.user_id).await?;
+
+    Ok(success(status))
+}
+
+/// Start enabling two-factor authentication for the current user
+///
+/// Two-factor authentication is enabled once a code for the returned secret
+/// is confirmed.
+async fn enroll_two_factor(
+    auth: SessionAuth,
+    State(pool): State<PgPool>,
+) -> Result<Json<ApiResponse<TwoFactorEnrollment>>, AppError> {
+    tracing::info!(
+        "Two-factor enrollment request for user ID: {}",
+        auth.user_id
+    );
+
+    let two_factor_service = TwoFactorService::new(pool);
+    let enrollment = two_factor_service.begin_enrollment(auth.user_id).await?;
+
+    Ok(success(enrollment))
+}
+
+/// Enable two-factor authentication with a code from the authenticator app
+async fn confirm_two_factor(
+    auth: SessionAuth,
+    State(pool): State<PgPool>,
+    payload: Result<Json<TwoFactorCodeRequest>, JsonRejection>,
+) -> Result<Json<ApiResponse<RecoveryCodes>>, AppError> {
+    let Json(request) = payload?;
+    tracing::info!(
+        "Two-factor confirmation request for user ID: {}",
+        auth.user_id
+    );
+
+    let two_factor_service = TwoFactorService::new(pool);
+    let recovery_codes = two_factor_service
+        .confirm_enrollment(auth.user_id, &request.code)
+        .await?;
+
+    Ok(success(recovery_codes))
+}
+
+/// Disable two-factor authentication after confirming the user and a code
+async fn disable_two_factor(
+    auth: SessionAuth,
+    State(pool): State<PgPool>,
+    payload: Result<Json<DisableTwoFactorRequest>, JsonRejection>,
+) -> Result<Json<ApiResponse<()>>, AppError> {
+    let Json(request) = payload?;
+    tracing::info!("Two-factor disable request for user ID: {}", auth.user_id);
+
+    UserService::new(pool.clone())
+        .confirm_sensitive_change(auth.user_id, auth.session_id, request.password.as_deref())
+        .await?;
+
+    let two_factor_service = TwoFactorService::new(pool);
+    two_factor_service
+        .disable(auth.user_id, &request.code)
+        .await?;
+
+    Ok(success(()))
+}
+
+/// Replace the recovery codes of the current user after checking a code
+async fn regenerate_recovery_codes(
+    auth: SessionAuth,
+    State(pool): State<PgPool>,
+    payload: Result<Json<TwoFactorCodeRequest>, JsonRejection>,
+) -> Result<Json<ApiResponse<RecoveryCodes>>, AppError> {
+    let Json(request) = payload?;
+    tracing::info!(
+        "Recovery code regeneration request for user ID: {}",
+        auth.user_id
+    );
+
+    let two_factor_service = TwoFactorService::new(pool);
+    let recovery_codes = two_factor_service
+        .regenerate_recovery_codes(auth.user_id, &request.code)
+        .await?;
+
+    Ok(success(recovery_codes))
+}
+
+/// List the API tokens of the current user
+async fn list_api_tokens(
+    auth: SessionAuth,
+    State(pool): State<PgPool>,
+) -> Result<Json<ApiResponse<Vec<ApiToken>>>, AppError> {
+    let api_token_service = ApiTokenService::new(pool);
+    let tokens = api_token_service.list_tokens(auth.user_id).await?;
+
+    Ok(success(tokens))
+}
+
+/// Create an API token; the response carries the token, which is not shown again
+async fn create_api_token(
+    auth: SessionAuth,
+    State(pool): State<PgPool>,
+    payload: Result<Json<CreateApiTokenRequest>, JsonRejection>,
+) -> Result<Json<ApiResponse<ApiTokenWithSecret>>, AppError> {
+    let Json(request) = payload?;
+    tracing::info!("Create API token request for user ID: {}", auth.user_id);
+
+    let mut field_errors = Vec::new();
+    let name_length = request.name.trim().chars().count();
+    if name_length == 0 || name_length > 100 {
+        field_errors.push((
+            "name",
+            "Name must be between 1 and 100 characters".to_string(),
+        ));
+    }
+    if let Some(days) = request.expires_in_days
+        && !(1..=MAX_API_TOKEN_EXPIRY_DAYS).contains(&days)
+    {
+        field_errors.push((
+            "expires_in_days",
+            format!("Expiry must be between 1 and {MAX_API_TOKEN_EXPIRY_DAYS} days"),
+        ));
+    }
+    if !field_errors.is_empty() {
+        return Err(AppError::field_validation_error(field_errors));
+    }
+
+    let api_token_service = ApiTokenService::new(pool);
+    let token = api_token_service
+        .create_token(auth.user_id, request)
+        .await?;
+
+    Ok(success(token))
+}
+
+/// Revoke an API token of the current user
+async fn revoke_api_token(
+    auth: SessionAuth,
+    State(pool): State<PgPool>,
+    Path(id): Path<Uuid>,
+) -> Result<Json<ApiResponse<()>>, AppError> {
+    tracing::info!(
+        "Revoke API token request for: {} user: {}",
+        id,
+        auth.user_id
+    );
+
+    let api_token_service = ApiTokenService::new(pool);
+    api_token_service.revoke_token(auth.user_id, id).await?;
+
+    Ok(success(()))
+}
+
+/// List the devices the current user is signed in on
+async fn list_sessions(
+    auth: SessionAuth,
+    State(pool): State<PgPool>,
+) -> Result<Json<ApiResponse<Vec<Session>>>, AppError> {
+    let session_service = SessionService::new(pool);
+    let sessions = session_service
+        .list_sessions(auth.user_id, auth.session_id)
+        .await?;
+
+    Ok(success(sessions))
+}
+
+/// Sign out one device of the current user
+async fn revoke_session(
+    auth: SessionAuth,
+    State(pool): State<PgPool>,
+    Path(id): Path<Uuid>,
+) -> Result<Json<ApiResponse<()>>, AppError> {
+    tracing::info!("Revoke session request for: {} user: {}", id, auth.user_id);
+
+    let session_service = SessionService::new(pool);
+    session_service.revoke_session(auth.user_id, id).await?;
+
+    Ok(success(()))
+}
+
+/// Sign out every device of the current user, this one included
+async fn revoke_all_sessions(
+    auth: SessionAuth,
+    State(pool): State<PgPool>,
+) -> Result<Json<ApiResponse<()>>, AppError> {
+    tracing::info!("Sign out everywhere request for user: {}", auth.user_id);
+
+    let session_service = SessionService::new(pool);
+    session_service.revoke_all_sessions(auth.user_id).await?;
+
+    Ok(success(()))
+}
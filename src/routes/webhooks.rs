@@ -0,0 +1,174 @@
+use axum::{
+    Json, Router,
+    extract::{Path, Query, State, rejection::JsonRejection},
+    routing::{get, post},
+};
+use sqlx::PgPool;
+use tracing;
+use uuid::Uuid;
+
+use crate::models::{
+    CreateWebhookRequest, PaginatedResponse, PaginationQuery, UpdateWebhookRequest,
+    WebhookDelivery, WebhookDeliveryDetail, WebhookDeliveryFilter, WebhookEndpoint,
+    WebhookEndpointWithSecret,
+};
+use crate::services::{EmailVerificationService, WebhookService};
+use crate::state::AppState;
+use crate::utils::auth::Auth;
+use crate::utils::response::{ApiResponse, AppError, success};
+
+/// Create webhook endpoint routes
+pub fn webhook_routes() -> Router<AppState> {
+    Router::new()
+        .route("/", get(list_webhooks).post(create_webhook))
+        .route(
+            "/{id}",
+            get(get_webhook)
+                .patch(update_webhook)
+                .delete(delete_webhook),
+        )
+        .route("/{id}/rotate-secret", post(rotate_secret))
+        .route("/{id}/deliveries", get(list_deliveries))
+        .route("/{id}/deliveries/{delivery_id}", get(get_delivery))
+}
+
+/// List the webhook endpoints of the authenticated user
+async fn list_webhooks(
+    auth: Auth,
+    State(pool): State<PgPool>,
+) -> Result<Json<ApiResponse<Vec<WebhookEndpoint>>>, AppError> {
+    tracing::info!("List webhooks request for user: {}", auth.user_id);
+
+    let webhook_service = WebhookService::new(pool);
+    let endpoints = webhook_service.list_endpoints(auth.user_id).await?;
+
+    Ok(success(endpoints))
+}
+
+/// Register a webhook endpoint; the response carries its signing secret
+async fn create_webhook(
+    auth: Auth,
+    State(state): State<AppState>,
+    payload: Result<Json<CreateWebhookRequest>, JsonRejection>,
+) -> Result<Json<ApiResponse<WebhookEndpointWithSecret>>, AppError> {
+    let Json(req) = payload?;
+    tracing::info!("Create webhook request for user: {}", auth.user_id);
+
+    // Webhooks send account data to arbitrary URLs, so the account must be verified
+    EmailVerificationService::new(state.pool.clone())
+        .require_verified(auth.user_id, "create webhooks")
+        .await?;
+
+    let webhook_service =
+        WebhookService::new(state.pool).with_address_policy(state.webhook_addresses);
+    let endpoint = webhook_service.create_endpoint(auth.user_id, req).await?;
+
+    Ok(success(endpoint))
+}
+
+/// Get a webhook endpoint
+async fn get_webhook(
+    auth: Auth,
+    State(pool): State<PgPool>,
+    Path(id): Path<Uuid>,
+) -> Result<Json<ApiResponse<WebhookEndpoint>>, AppError> {
+    tracing::info!("Get webhook request for: {} user: {}", id, auth.user_id);
+
+    let webhook_service = WebhookService::new(pool);
+    let endpoint = webhook_service.get_endpoint(auth.user_id, id).await?;
+
+    Ok(success(endpoint))
+}
+
+/// Update the URL, description, events or active state of a webhook endpoint
+async fn update_webhook(
+    auth: Auth,
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+    payload: Result<Json<UpdateWebhookRequest>, JsonRejection>,
+) -> Result<Json<ApiResponse<WebhookEndpoint>>, AppError> {
+    let Json(req) = payload?;
+    tracing::info!("Update webhook request for: {} user: {}", id, auth.user_id);
+
+    let webhook_service =
+        WebhookService::new(state.pool).with_address_policy(state.webhook_addresses);
+    let endpoint = webhook_service
+        .update_endpoint(auth.user_id, id, req)
+        .await?;
+
+    Ok(success(endpoint))
+}
+
+/// Delete a webhook endpoint and its delivery log
+async fn delete_webhook(
+    auth: Auth,
+    State(pool): State<PgPool>,
+    Path(id): Path<Uuid>,
+) -> Result<Json<ApiResponse<()>>, AppError> {
+    tracing::info!("Delete webhook request for: {} user: {}", id, auth.user_id);
+
+    let webhook_service = WebhookService::new(pool);
+    webhook_service.delete_endpoint(auth.user_id, id).await?;
+
+    Ok(success(()))
+}
+
+/// Replace the signing secret of a webhook endpoint
+async fn rotate_secret(
+    auth: Auth,
+    State(pool): State<PgPool>,
+    Path(id): Path<Uuid>,
+) -> Result<Json<ApiResponse<WebhookEndpointWithSecret>>, AppError> {
+    tracing::info!(
+        "Rotate webhook secret request for: {} user: {}",
+        id,
+        auth.user_id
+    );
+
+    let webhook_service = WebhookService::new(pool);
+    let endpoint = webhook_service.rotate_secret(auth.user_id, id).await?;
+
+    Ok(success(endpoint))
+}
+
+/// List the deliveries of a webhook endpoint, newest first
+async fn list_deliveries(
+    auth: Auth,
+    State(pool): State<PgPool>,
+    Path(id): Path<Uuid>,
+    Query(filter): Query<WebhookDeliveryFilter>,
+    Query(query): Query<PaginationQuery>,
+) -> Result<Json<ApiResponse<PaginatedResponse<WebhookDelivery>>>, AppError> {
+    tracing::info!(
+        "List webhook deliveries request for: {} user: {}",
+        id,
+        auth.user_id
+    );
+
+    let webhook_service = WebhookService::new(pool);
+    let deliveries = webhook_service
+        .list_deliveries(auth.user_id, id, &filter, &query)
+        .await?;
+
+    Ok(success(deliveries))
+}
+
+/// Get a delivery with its payload and the log of its attempts
+async fn get_delivery(
+    auth: Auth,
+    State(pool): State<PgPool>,
+    Path((id, delivery_id)): Path<(Uuid, Uuid)>,
+) -> Result<Json<ApiResponse<WebhookDeliveryDetail>>, AppError> {
+    tracing::info!(
+        "Get webhook delivery request for: {} user: {}",
+        delivery_id,
+        auth.user_id
+    );
+
+    let webhook_service = WebhookService::new(pool);
+    let delivery = webhook_service
+        .get_delivery(auth.user_id, id, delivery_id)
+        .await?;
+
+    Ok(success(delivery))
+}
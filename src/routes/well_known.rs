@@ -0,0 +1,29 @@
+use axum::{
+    Json, Router,
+    http::header::{CACHE_CONTROL, HeaderValue},
+    response::IntoResponse,
+    routing::get,
+};
+
+use crate::state::AppState;
+use crate::utils::jwt::jwt_keys;
+
+/// Create the routes under `/.well-known`, served outside the API prefix
+pub fn well_known_routes() -> Router<AppState> {
+    Router::new().route("/.well-known/jwks.json", get(jwks))
+}
+
+/// Public keys access tokens are signed with (RFC 7517), so other services
+/// can verify them
+///
+/// HMAC keys are secret and never listed; the set is empty when only those
+/// are configured.
+async fn jwks() -> impl IntoResponse {
+    (
+        [(
+            CACHE_CONTROL,
+            HeaderValue::from_static("public, max-age=300"),
+        )],
+        Json(jwt_keys().jwks()),
+    )
+}
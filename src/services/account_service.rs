@@ -0,0 +1,473 @@
+use chrono::{DateTime, Utc};
+use sqlx::{PgPool, Row};
+use tokio::task::JoinHandle;
+use tokio::time::MissedTickBehavior;
+use uuid::Uuid;
+
+use crate::config::AccountConfig;
+use crate::models::{
+    ACCOUNT_EXPORT_FORMAT, ACCOUNT_EXPORT_VERSION, AccountDeletion, AccountExport, Session, User,
+    UserIdentity, UserProfile,
+};
+use crate::services::api_token_service::api_token_from_row;
+use crate::services::event_service::event_from_row;
+use crate::services::notification_service::notification_from_row;
+use crate::services::payment_service::payment_from_row;
+use crate::services::refresh_token_service::revoke_user_refresh_tokens;
+use crate::services::subscription_service::subscription_from_row;
+use crate::services::webhook_service::endpoint_from_row;
+use crate::utils::response::AppError;
+
+/// Service exporting and deleting whole accounts
+///
+/// Deleting an account deletes the user row; everything else of the account
+/// goes with it through the `ON DELETE CASCADE` foreign keys.
+pub struct AccountService {
+    pool: PgPool,
+}
+
+impl AccountService {
+    /// Create a new AccountService with the given database pool
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    /// Collect all personal data of a user into a versioned archive
+    pub async fn export(&self, user_id: Uuid) -> Result<AccountExport, AppError> {
+        let user_row = sqlx::query(
+            r#"
+            SELECT id, email, password_hash, email_verified_at, two_factor_enabled_at,
+                   created_at, updated_at
+            FROM users
+            WHERE id = $1
+            "#,
+        )
+        .bind(user_id)
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| AppError::database_error("user lookup", format!("Database error: {e}")))?
+        .ok_or_else(|| AppError::not_found("User", format!("User {user_id} not found")))?;
+        let user = User {
+            id: user_row.get("id"),
+            email: user_row.get("email"),
+            password_hash: user_row.get("password_hash"),
+            email_verified_at: user_row.get("email_verified_at"),
+            two_factor_enabled_at: user_row.get("two_factor_enabled_at"),
+            created_at: user_row.get("created_at"),
+            updated_at: user_row.get("updated_at"),
+        };
+
+        let profile = sqlx::query(
+            r#"
+            SELECT id, user_id, name, preferences, created_at, updated_at
+            FROM user_profiles
+            WHERE user_id = $1
+            "#,
+        )
+        .bind(user_id)
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| AppError::database_error("profile lookup", format!("Database error: {e}")))?
+        .map(|row| UserProfile {
+            id: row.get("id"),
+            user_id: row.get("user_id"),
+            name: row.get("name"),
+            preferences: row.get("preferences"),
+            created_at: row.get("created_at"),
+            updated_at: row.get("updated_at"),
+        });
+
+        let subscriptions = sqlx::query(
+            r#"
+            SELECT id, user_id, name, description, amount,
+                   currency, billing_cycle_unit, billing_cycle_interval,
+                   start_date, next_billing_date, billing_anchor_date,
+                   status, paused_at, end_date, trial_end_date, category, color, created_at, updated_at
+            FROM subscriptions
+            WHERE user_id = $1
+            ORDER BY created_at
+            "#,
+        )
+        .bind(user_id)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| {
+            AppError::database_error("subscription export", format!("Database error: {e}"))
+        })?;
+
+        let payments = sqlx::query(
+            r#"
+            SELECT id, subscription_id, user_id, amount, currency, paid_date,
+                   cycle_start, cycle_end, note, projected, created_at
+            FROM payment_records
+            WHERE user_id = $1
+            ORDER BY cycle_start, created_at
+            "#,
+        )
+        .bind(user_id)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| AppError::database_error("payment export", format!("Database error: {e}")))?;
+
+        let notifications = sqlx::query(
+            r#"
+            SELECT id, user_id, subscription_id, notification_type, title, message,
+                   due_date, read_at, created_at
+            FROM notifications
+            WHERE user_id = $1
+            ORDER BY created_at, id
+            "#,
+        )
+        .bind(user_id)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| {
+            AppError::database_error("notification export", format!("Database error: {e}"))
+        })?;
+
+        let events = sqlx::query(
+            r#"
+            SELECT id, subscription_id, user_id, event_type, before, after, note, created_at
+            FROM subscription_events
+            WHERE user_id = $1
+            ORDER BY created_at, id
+            "#,
+        )
+        .bind(user_id)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| AppError::database_error("event export", format!("Database error: {e}")))?;
+
+        let webhook_endpoints = sqlx::query(
+            r#"
+            SELECT id, user_id, url, description, event_types, active, created_at, updated_at
+            FROM webhook_endpoints
+            WHERE user_id = $1
+            ORDER BY created_at
+            "#,
+        )
+        .bind(user_id)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| {
+            AppError::database_error("webhook endpoint export", format!("Database error: {e}"))
+        })?;
+
+        let sessions = sqlx::query(
+            r#"
+            SELECT id, user_agent, ip_address, created_at, last_seen_at
+            FROM user_sessions
+            WHERE user_id = $1
+            ORDER BY created_at, id
+            "#,
+        )
+        .bind(user_id)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| AppError::database_error("session export", format!("Database error: {e}")))?;
+
+        let api_tokens = sqlx::query(
+            r#"
+            SELECT id, name, scope, token_prefix, expires_at, last_used_at, created_at
+            FROM api_tokens
+            WHERE user_id = $1
+            ORDER BY created_at, id
+            "#,
+        )
+        .bind(user_id)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| {
+            AppError::database_error("API token export", format!("Database error: {e}"))
+        })?;
+
+        let identities = sqlx::query(
+            r#"
+            SELECT id, issuer, subject, email, last_login_at, created_at
+            FROM user_identities
+            WHERE user_id = $1
+            ORDER BY created_at, id
+            "#,
+        )
+        .bind(user_id)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| AppError::database_error("identity export", format!("Database error: {e}")))?;
+
+        Ok(AccountExport {
+            format: ACCOUNT_EXPORT_FORMAT,
+            version: ACCOUNT_EXPORT_VERSION,
+            exported_at: Utc::now(),
+            user,
+            profile,
+            subscriptions: subscriptions.iter().map(subscription_from_row).collect(),
+            payments: payments.iter().map(payment_from_row).collect(),
+            notifications: notifications.iter().map(notification_from_row).collect(),
+            events: events.iter().map(event_from_row).collect(),
+            webhook_endpoints: webhook_endpoints.iter().map(endpoint_from_row).collect(),
+            sessions: sessions
+                .iter()
+                .map(|row| Session {
+                    id: row.get("id"),
+                    user_agent: row.get("user_agent"),
+                    ip_address: row.get("ip_address"),
+                    created_at: row.get("created_at"),
+                    last_seen_at: row.get("last_seen_at"),
+                    current: false,
+                })
+                .collect(),
+            api_tokens: api_tokens.iter().map(api_token_from_row).collect(),
+            identities: identities
+                .iter()
+                .map(|row| UserIdentity {
+                    id: row.get("id"),
+                    issuer: row.get("issuer"),
+                    subject: row.get("subject"),
+                    email: row.get("email"),
+                    last_login_at: row.get("last_login_at"),
+                    created_at: row.get("created_at"),
+                })
+                .collect(),
+        })
+    }
+
+    /// Delete a user's account, or schedule its deletion after a grace period
+    ///
+    /// Either way every refresh token and pending email link of the account
+    /// stops working. Signing in during the grace period cancels the deletion.
+    pub async fn delete_account(
+        &self,
+        user_id: Uuid,
+        grace_days: u32,
+    ) -> Result<AccountDeletion, AppError> {
+        let mut tx = self.pool.begin().await.map_err(|e| {
+            AppError::database_error("transaction start", format!("Transaction error: {e}"))
+        })?;
+
+        if grace_days == 0 {
+            sqlx::query("DELETE FROM users WHERE id = $1")
+                .bind(user_id)
+                .execute(&mut *tx)
+                .await
+                .map_err(|e| {
+                    AppError::database_error("account deletion", format!("Database error: {e}"))
+                })?;
+            tx.commit().await.map_err(|e| {
+                AppError::database_error("transaction commit", format!("Transaction error: {e}"))
+            })?;
+
+            tracing::info!("Account of user {} deleted", user_id);
+
+            return Ok(AccountDeletion {
+                deleted: true,
+                deletion_scheduled_at: None,
+            });
+        }
+
+        let deletion_scheduled_at: DateTime<Utc> = sqlx::query(
+            r#"
+            UPDATE users
+            SET deletion_scheduled_at = CURRENT_TIMESTAMP + make_interval(days => $2)
+            WHERE id = $1
+            RETURNING deletion_scheduled_at
+            "#,
+        )
+        .bind(user_id)
+        .bind(grace_days as i32)
+        .fetch_optional(&mut *tx)
+        .await
+        .map_err(|e| {
+            AppError::database_error(
+                "account deletion scheduling",
+                format!("Database error: {e}"),
+            )
+        })?
+        .ok_or_else(|| AppError::not_found("User", format!("User {user_id} not found")))?
+        .get("deletion_scheduled_at");
+
+        revoke_user_refresh_tokens(&mut tx, user_id)
+            .await
+            .map_err(|e| {
+                AppError::database_error("refresh token revocation", format!("Database error: {e}"))
+            })?;
+        for table in [
+            "password_reset_tokens",
+            "email_verification_tokens",
+            "two_factor_challenges",
+        ] {
+            sqlx::query(&format!(
+                "UPDATE {table} SET used_at = CURRENT_TIMESTAMP WHERE user_id = $1 AND used_at IS NULL"
+            ))
+            .bind(user_id)
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| {
+                AppError::database_error("account token update", format!("Database error: {e}"))
+            })?;
+        }
+        sqlx::query(
+            "UPDATE api_tokens SET revoked_at = CURRENT_TIMESTAMP WHERE user_id = $1 AND revoked_at IS NULL",
+        )
+        .bind(user_id)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| {
+            AppError::database_error("API token revocation", format!("Database error: {e}"))
+        })?;
+
+        tx.commit().await.map_err(|e| {
+            AppError::database_error("transaction commit", format!("Transaction error: {e}"))
+        })?;
+
+        tracing::info!(
+            "Account of user {} scheduled for deletion at {}",
+            user_id,
+            deletion_scheduled_at
+        );
+
+        Ok(AccountDeletion {
+            deleted: false,
+            deletion_scheduled_at: Some(deletion_scheduled_at),
+        })
+    }
+
+    /// Delete the accounts whose deletion grace period has ended
+    ///
+    /// Returns the number of deleted accounts.
+    pub async fn purge_scheduled_deletions(&self) -> Result<u64, AppError> {
+        let result =
+            sqlx::query("DELETE FROM users WHERE deletion_scheduled_at <= CURRENT_TIMESTAMP")
+                .execute(&self.pool)
+                .await
+                .map_err(|e| {
+                    AppError::database_error("account purge", format!("Database error: {e}"))
+                })?;
+
+        Ok(result.rows_affected())
+    }
+}
+
+/// Start the background worker purging accounts whose deletion grace period ended
+///
+/// The worker also runs without a grace period, so accounts scheduled before
+/// the grace period was turned off are still purged.
+pub fn spawn_account_purge_worker(pool: PgPool, config: AccountConfig) -> JoinHandle<()> {
+    tracing::info!(
+        "Starting account purge worker - interval: {:?}, deletion grace period: {} day(s)",
+        config.purge_interval,
+        config.deletion_grace_days
+    );
+
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(config.purge_interval);
+        ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+        loop {
+            ticker.tick().await;
+
+            let account_service = AccountService::new(pool.clone());
+            match account_service.purge_scheduled_deletions().await {
+                Ok(0) => {}
+                Ok(purged) => tracing::info!("Account purge run deleted {} account(s)", purged),
+                Err(e) => tracing::error!("Account purge run failed: {}", e),
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::UserService;
+    use crate::services::session_service::create_session;
+    use crate::services::test_support::{connect, create_user};
+    use crate::utils::client::ClientInfo;
+    use std::env;
+
+    #[tokio::test]
+    async fn test_accounts_without_password_confirm_deletion_by_signing_in() {
+        // This test requires a valid DATABASE_URL environment variable
+        if let Ok(url) = env::var("DATABASE_URL") {
+            let pool = connect(url).await;
+            let (user_id, _) = create_user(&pool).await;
+            sqlx::query("UPDATE users SET password_set = FALSE WHERE id = $1")
+                .bind(user_id)
+                .execute(&pool)
+                .await
+                .unwrap();
+            let mut conn = pool.acquire().await.unwrap();
+            let session_id = create_session(&mut conn, user_id, &ClientInfo::default())
+                .await
+                .unwrap();
+            drop(conn);
+            let user_service = UserService::new(pool.clone());
+
+            // A session of an earlier sign-in does not confirm the deletion
+            sqlx::query(
+                "UPDATE user_sessions SET created_at = created_at - INTERVAL '1 hour' WHERE id = $1",
+            )
+            .bind(session_id)
+            .execute(&pool)
+            .await
+            .unwrap();
+            assert!(matches!(
+                user_service
+                    .confirm_sensitive_change(user_id, Some(session_id), Some("guess"))
+                    .await,
+                Err(AppError::Forbidden { .. })
+            ));
+            assert!(matches!(
+                user_service
+                    .confirm_sensitive_change(user_id, None, None)
+                    .await,
+                Err(AppError::Forbidden { .. })
+            ));
+
+            // Signing in again with the provider starts a fresh session
+            sqlx::query("UPDATE user_sessions SET created_at = CURRENT_TIMESTAMP WHERE id = $1")
+                .bind(session_id)
+                .execute(&pool)
+                .await
+                .unwrap();
+            user_service
+                .confirm_sensitive_change(user_id, Some(session_id), None)
+                .await
+                .unwrap();
+
+            let deletion = AccountService::new(pool.clone())
+                .delete_account(user_id, 0)
+                .await
+                .unwrap();
+            assert!(deletion.deleted);
+            let remaining: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM users WHERE id = $1")
+                .bind(user_id)
+                .fetch_one(&pool)
+                .await
+                .unwrap();
+            assert_eq!(remaining, 0);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_accounts_with_password_confirm_deletion_with_it() {
+        // This test requires a valid DATABASE_URL environment variable
+        if let Ok(url) = env::var("DATABASE_URL") {
+            let pool = connect(url).await;
+            let (user_id, _) = create_user(&pool).await;
+            let mut conn = pool.acquire().await.unwrap();
+            let session_id = create_session(&mut conn, user_id, &ClientInfo::default())
+                .await
+                .unwrap();
+            drop(conn);
+            let user_service = UserService::new(pool);
+
+            // A fresh session is not enough while the account has a password
+            assert!(matches!(
+                user_service
+                    .confirm_sensitive_change(user_id, Some(session_id), None)
+                    .await,
+                Err(AppError::ValidationError { .. })
+            ));
+        }
+    }
+}
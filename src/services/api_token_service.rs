@@ -0,0 +1,259 @@
+use sqlx::{PgPool, Row, postgres::PgRow};
+use uuid::Uuid;
+
+use crate::models::{
+    ApiToken, ApiTokenScope, ApiTokenWithSecret, CreateApiTokenRequest, MAX_API_TOKENS,
+};
+use crate::utils::auth::{generate_api_token, hash_token};
+use crate::utils::response::AppError;
+
+/// Characters of a token kept in `token_prefix`: the `sp_pat_` marker and a
+/// few random ones
+const TOKEN_PREFIX_LEN: usize = 12;
+
+/// Service managing personal API tokens
+///
+/// API tokens are an alternative to access tokens for scripts. Only their
+/// hash is stored; a revoked or expired token stops working at once.
+pub struct ApiTokenService {
+    pool: PgPool,
+}
+
+impl ApiTokenService {
+    /// Create a new ApiTokenService with the given database pool
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    /// List the API tokens of a user that were not revoked, newest first
+    pub async fn list_tokens(&self, user_id: Uuid) -> Result<Vec<ApiToken>, AppError> {
+        let rows = sqlx::query(
+            r#"
+            SELECT id, name, scope, token_prefix, expires_at, last_used_at, created_at
+            FROM api_tokens
+            WHERE user_id = $1 AND revoked_at IS NULL
+            ORDER BY created_at DESC
+            "#,
+        )
+        .bind(user_id)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| {
+            AppError::database_error("API token listing", format!("Database error: {e}"))
+        })?;
+
+        Ok(rows.iter().map(api_token_from_row).collect())
+    }
+
+    /// Create an API token; the response carries the token itself
+    pub async fn create_token(
+        &self,
+        user_id: Uuid,
+        request: CreateApiTokenRequest,
+    ) -> Result<ApiTokenWithSecret, AppError> {
+        let mut tx = self.pool.begin().await.map_err(|e| {
+            AppError::database_error("transaction start", format!("Transaction error: {e}"))
+        })?;
+
+        // Lock the user so that concurrent requests cannot pass the limit together
+        sqlx::query("SELECT id FROM users WHERE id = $1 FOR UPDATE")
+            .bind(user_id)
+            .fetch_optional(&mut *tx)
+            .await
+            .map_err(|e| AppError::database_error("user lookup", format!("Database error: {e}")))?
+            .ok_or_else(|| AppError::not_found("User", format!("User {user_id} not found")))?;
+
+        let count: i64 = sqlx::query(
+            r#"
+            SELECT COUNT(*) AS count
+            FROM api_tokens
+            WHERE user_id = $1 AND revoked_at IS NULL
+              AND (expires_at IS NULL OR expires_at > CURRENT_TIMESTAMP)
+            "#,
+        )
+        .bind(user_id)
+        .fetch_one(&mut *tx)
+        .await
+        .map_err(|e| AppError::database_error("API token count", format!("Database error: {e}")))?
+        .get("count");
+        if count >= MAX_API_TOKENS {
+            return Err(AppError::conflict(
+                "API token limit reached",
+                format!("A user can have at most {MAX_API_TOKENS} active API tokens"),
+            ));
+        }
+
+        let token = generate_api_token();
+        let row = sqlx::query(
+            r#"
+            INSERT INTO api_tokens (user_id, name, scope, token_hash, token_prefix, expires_at)
+            VALUES ($1, $2, $3, $4, $5, CURRENT_TIMESTAMP + make_interval(days => $6))
+            RETURNING id, name, scope, token_prefix, expires_at, last_used_at, created_at
+            "#,
+        )
+        .bind(user_id)
+        .bind(request.name.trim())
+        .bind(request.scope.as_str())
+        .bind(hash_token(&token))
+        .bind(&token[..TOKEN_PREFIX_LEN])
+        .bind(request.expires_in_days.map(|days| days as i32))
+        .fetch_one(&mut *tx)
+        .await
+        .map_err(|e| {
+            AppError::database_error("API token creation", format!("Database error: {e}"))
+        })?;
+
+        tx.commit().await.map_err(|e| {
+            AppError::database_error("transaction commit", format!("Transaction error: {e}"))
+        })?;
+
+        tracing::info!(
+            "API token {} created for user {}",
+            row.get::<Uuid, _>("id"),
+            user_id
+        );
+
+        Ok(ApiTokenWithSecret {
+            api_token: api_token_from_row(&row),
+            token,
+        })
+    }
+
+    /// Revoke an API token of a user
+    pub async fn revoke_token(&self, user_id: Uuid, token_id: Uuid) -> Result<(), AppError> {
+        let result = sqlx::query(
+            r#"
+            UPDATE api_tokens
+            SET revoked_at = CURRENT_TIMESTAMP
+            WHERE id = $1 AND user_id = $2 AND revoked_at IS NULL
+            "#,
+        )
+        .bind(token_id)
+        .bind(user_id)
+        .execute(&self.pool)
+        .await
+        .map_err(|e| {
+            AppError::database_error("API token revocation", format!("Database error: {e}"))
+        })?;
+
+        if result.rows_affected() == 0 {
+            return Err(AppError::not_found(
+                "API token",
+                format!("API token {token_id} not found"),
+            ));
+        }
+
+        tracing::info!("API token {} revoked by user {}", token_id, user_id);
+        Ok(())
+    }
+}
+
+/// Look up the user and scope of a valid API token, recording its use
+///
+/// Returns `None` for unknown, revoked and expired tokens.
+pub(crate) async fn authenticate_api_token(
+    pool: &PgPool,
+    token: &str,
+) -> Result<Option<(Uuid, ApiTokenScope)>, sqlx::Error> {
+    let row = sqlx::query(
+        r#"
+        UPDATE api_tokens
+        SET last_used_at = CURRENT_TIMESTAMP
+        WHERE token_hash = $1 AND revoked_at IS NULL
+          AND (expires_at IS NULL OR expires_at > CURRENT_TIMESTAMP)
+        RETURNING user_id, scope
+        "#,
+    )
+    .bind(hash_token(token))
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(row.map(|row| {
+        (
+            row.get("user_id"),
+            ApiTokenScope::from(row.get::<String, _>("scope")),
+        )
+    }))
+}
+
+/// Look up the user of a valid API token without recording its use
+pub(crate) async fn api_token_user(
+    pool: &PgPool,
+    token: &str,
+) -> Result<Option<Uuid>, sqlx::Error> {
+    let row = sqlx::query(
+        r#"
+        SELECT user_id FROM api_tokens
+        WHERE token_hash = $1 AND revoked_at IS NULL
+          AND (expires_at IS NULL OR expires_at > CURRENT_TIMESTAMP)
+        "#,
+    )
+    .bind(hash_token(token))
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(row.map(|row| row.get("user_id")))
+}
+
+pub(crate) fn api_token_from_row(row: &PgRow) -> ApiToken {
+    ApiToken {
+        id: row.get("id"),
+        name: row.get("name"),
+        scope: ApiTokenScope::from(row.get::<String, _>("scope")),
+        token_prefix: row.get("token_prefix"),
+        expires_at: row.get("expires_at"),
+        last_used_at: row.get("last_used_at"),
+        created_at: row.get("created_at"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::test_support::{connect, create_user};
+    use std::env;
+
+    #[tokio::test]
+    async fn test_concurrent_creations_respect_the_token_limit() {
+        // This test requires a valid DATABASE_URL environment variable
+        if let Ok(url) = env::var("DATABASE_URL") {
+            let pool = connect(url).await;
+            let (user_id, _) = create_user(&pool).await;
+
+            let tasks: Vec<_> = (0..MAX_API_TOKENS + 5)
+                .map(|i| {
+                    let service = ApiTokenService::new(pool.clone());
+                    tokio::spawn(async move {
+                        service
+                            .create_token(
+                                user_id,
+                                CreateApiTokenRequest {
+                                    name: format!("Script {i}"),
+                                    scope: ApiTokenScope::Read,
+                                    expires_in_days: None,
+                                },
+                            )
+                            .await
+                    })
+                })
+                .collect();
+            let mut created = 0;
+            for task in tasks {
+                match task.await.unwrap() {
+                    Ok(_) => created += 1,
+                    Err(e) => assert!(matches!(e, AppError::Conflict { .. }), "{e}"),
+                }
+            }
+
+            assert_eq!(created, MAX_API_TOKENS);
+            assert_eq!(
+                ApiTokenService::new(pool)
+                    .list_tokens(user_id)
+                    .await
+                    .unwrap()
+                    .len() as i64,
+                MAX_API_TOKENS
+            );
+        }
+    }
+}
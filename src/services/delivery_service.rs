@@ -0,0 +1,361 @@
+use chrono::{NaiveDate, Utc};
+use sqlx::{PgPool, Row, postgres::PgRow};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::task::JoinHandle;
+use tokio::time::MissedTickBehavior;
+use uuid::Uuid;
+
+use crate::config::MailConfig;
+use crate::services::mailer::{EmailMessage, MailSender};
+use crate::utils::backoff::exponential_backoff;
+use crate::utils::email_template::REMINDER_TEMPLATE;
+use crate::utils::response::AppError;
+
+/// Attempts after which a delivery is given up and marked as failed
+const MAX_DELIVERY_ATTEMPTS: i32 = 5;
+/// Deliveries sent by one run
+const DELIVERY_BATCH_SIZE: usize = 50;
+/// How long a claimed delivery is hidden from other runs while it is sent;
+/// deliveries are claimed one at a time, so this only has to outlast the
+/// mailer's timeout for a single email
+const DELIVERY_LEASE_SECS: i64 = 300;
+/// Delay before the first retry, doubled with every further attempt
+const RETRY_BASE_DELAY: Duration = Duration::from_secs(60);
+/// Longest delay between two attempts
+const RETRY_MAX_DELAY: Duration = Duration::from_secs(6 * 60 * 60);
+
+/// Outcome of a delivery run
+#[derive(Debug, Default)]
+pub struct DeliverySummary {
+    pub sent: usize,
+    pub retried: usize,
+    pub failed: usize,
+}
+
+/// Service delivering notifications by email
+pub struct DeliveryService {
+    pool: PgPool,
+}
+
+impl DeliveryService {
+    /// Create a new DeliveryService with the given database pool
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    /// Queue email deliveries of renewal and trial ending reminders
+    ///
+    /// Only reminders of users who verified their email address and enabled
+    /// `email_reminders` in their preferences, and whose date has not passed,
+    /// are queued; each reminder is queued once. Accounts scheduled for
+    /// deletion get no emails. Returns the number of new deliveries.
+    pub async fn queue_email_reminders(&self, today: NaiveDate) -> Result<u64, AppError> {
+        let result = sqlx::query(
+            r#"
+            INSERT INTO notification_deliveries (notification_id, user_id, channel, recipient)
+            SELECT n.id, n.user_id, 'email', u.email
+            FROM notifications n
+            JOIN users u ON u.id = n.user_id
+            JOIN user_profiles p ON p.user_id = n.user_id
+            WHERE n.notification_type IN ('renewal_upcoming', 'trial_ending')
+              AND n.due_date >= $1
+              AND u.email_verified_at IS NOT NULL
+              AND u.deletion_scheduled_at IS NULL
+              AND p.preferences->>'email_reminders' = 'true'
+            ON CONFLICT (notification_id, channel) DO NOTHING
+            "#,
+        )
+        .bind(today)
+        .execute(&self.pool)
+        .await
+        .map_err(|e| {
+            AppError::database_error("delivery queueing", format!("Database error: {e}"))
+        })?;
+
+        Ok(result.rows_affected())
+    }
+
+    /// Send pending email deliveries that are due
+    ///
+    /// Deliveries are claimed one at a time with `SKIP LOCKED`, so several
+    /// instances can run this at the same time without sending an email twice. A failed delivery is retried with exponential
+    /// backoff until it has been attempted `MAX_DELIVERY_ATTEMPTS` times.
+    pub async fn deliver_pending(
+        &self,
+        mailer: &dyn MailSender,
+        app_url: &str,
+    ) -> Result<DeliverySummary, AppError> {
+        let mut summary = DeliverySummary::default();
+        for _ in 0..DELIVERY_BATCH_SIZE {
+            let Some(row) = self.claim_delivery().await? else {
+                break;
+            };
+            let delivery_id: Uuid = row.get("id");
+            let title: String = row.get("title");
+            let message: String = row.get("message");
+            let name = row
+                .get::<Option<String>, _>("name")
+                .filter(|name| !name.trim().is_empty())
+                .unwrap_or_else(|| "there".to_string());
+
+            let (text_body, html_body) = REMINDER_TEMPLATE.render(&[
+                ("name", &name),
+                ("title", &title),
+                ("message", &message),
+                ("app_url", app_url),
+            ]);
+            let email = EmailMessage {
+                to: row.get("recipient"),
+                subject: title,
+                text_body,
+                html_body,
+            };
+
+            match mailer.send(&email).await {
+                Ok(()) => {
+                    self.mark_sent(delivery_id).await?;
+                    summary.sent += 1;
+                }
+                Err(error) => {
+                    let attempts = row.get::<i32, _>("attempts") + 1;
+                    tracing::warn!(
+                        "Delivery {} failed (attempt {}): {}",
+                        delivery_id,
+                        attempts,
+                        error
+                    );
+                    if attempts >= MAX_DELIVERY_ATTEMPTS {
+                        summary.failed += 1;
+                    } else {
+                        summary.retried += 1;
+                    }
+                    self.mark_failed_attempt(delivery_id, attempts, &error)
+                        .await?;
+                }
+            }
+        }
+
+        Ok(summary)
+    }
+
+    /// Claim the next due delivery, hiding it from other runs while it is sent
+    async fn claim_delivery(&self) -> Result<Option<PgRow>, AppError> {
+        sqlx::query(
+            r#"
+            UPDATE notification_deliveries d
+            SET next_attempt_at = CURRENT_TIMESTAMP + make_interval(secs => $1)
+            FROM notifications n, user_profiles p
+            WHERE d.id = (
+                SELECT due.id FROM notification_deliveries due
+                JOIN notifications dn ON dn.id = due.notification_id
+                JOIN user_profiles dp ON dp.user_id = due.user_id
+                WHERE due.status = 'pending' AND due.next_attempt_at <= CURRENT_TIMESTAMP
+                ORDER BY due.next_attempt_at
+                LIMIT 1
+                FOR UPDATE OF due SKIP LOCKED
+            )
+              AND n.id = d.notification_id
+              AND p.user_id = d.user_id
+            RETURNING d.id, d.recipient, d.attempts, n.title, n.message, p.name
+            "#,
+        )
+        .bind(DELIVERY_LEASE_SECS as f64)
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| AppError::database_error("delivery claim", format!("Database error: {e}")))
+    }
+
+    async fn mark_sent(&self, delivery_id: Uuid) -> Result<(), AppError> {
+        sqlx::query(
+            r#"
+            UPDATE notification_deliveries
+            SET status = 'sent', attempts = attempts + 1, last_error = NULL,
+                sent_at = CURRENT_TIMESTAMP
+            WHERE id = $1
+            "#,
+        )
+        .bind(delivery_id)
+        .execute(&self.pool)
+        .await
+        .map_err(|e| AppError::database_error("delivery update", format!("Database error: {e}")))?;
+
+        Ok(())
+    }
+
+    async fn mark_failed_attempt(
+        &self,
+        delivery_id: Uuid,
+        attempts: i32,
+        error: &str,
+    ) -> Result<(), AppError> {
+        let status = if attempts >= MAX_DELIVERY_ATTEMPTS {
+            "failed"
+        } else {
+            "pending"
+        };
+
+        sqlx::query(
+            r#"
+            UPDATE notification_deliveries
+            SET status = $2, attempts = $3, last_error = $4,
+                next_attempt_at = CURRENT_TIMESTAMP + make_interval(secs => $5)
+            WHERE id = $1
+            "#,
+        )
+        .bind(delivery_id)
+        .bind(status)
+        .bind(attempts)
+        .bind(error)
+        .bind(exponential_backoff(RETRY_BASE_DELAY, attempts, RETRY_MAX_DELAY).as_secs_f64())
+        .execute(&self.pool)
+        .await
+        .map_err(|e| AppError::database_error("delivery update", format!("Database error: {e}")))?;
+
+        Ok(())
+    }
+}
+
+/// Start the background email delivery worker
+///
+/// Every run queues new email reminders and sends the pending deliveries.
+pub fn spawn_delivery_worker(
+    pool: PgPool,
+    mailer: Arc<dyn MailSender>,
+    config: MailConfig,
+) -> JoinHandle<()> {
+    tracing::info!(
+        "Starting email delivery worker - transport: {:?}, interval: {:?}",
+        config.transport,
+        config.delivery_interval
+    );
+
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(config.delivery_interval);
+        ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+        loop {
+            ticker.tick().await;
+
+            let delivery_service = DeliveryService::new(pool.clone());
+            let queued = match delivery_service
+                .queue_email_reminders(Utc::now().naive_utc().date())
+                .await
+            {
+                Ok(queued) => queued,
+                Err(e) => {
+                    tracing::error!("Email reminder queueing failed: {}", e);
+                    0
+                }
+            };
+
+            match delivery_service
+                .deliver_pending(mailer.as_ref(), &config.app_url)
+                .await
+            {
+                Ok(summary)
+                    if queued > 0 || summary.sent + summary.retried + summary.failed > 0 =>
+                {
+                    tracing::info!(
+                        "Delivery run completed: {} queued, {} sent, {} to retry, {} failed",
+                        queued,
+                        summary.sent,
+                        summary.retried,
+                        summary.failed
+                    )
+                }
+                Ok(_) => {}
+                Err(e) => tracing::error!("Delivery run failed: {}", e),
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::test_support::{connect, create_user, subscription};
+    use crate::services::{NotificationService, SubscriptionService};
+    use async_trait::async_trait;
+    use chrono::Days;
+    use std::env;
+    use std::sync::Mutex;
+
+    /// Records how many deliveries of a recipient were claimed while each of
+    /// its emails was being sent
+    struct LeaseCheckingMailer {
+        pool: PgPool,
+        recipient: String,
+        claimed: Mutex<Vec<i64>>,
+    }
+
+    #[async_trait]
+    impl MailSender for LeaseCheckingMailer {
+        async fn send(&self, message: &EmailMessage) -> Result<(), String> {
+            if message.to == self.recipient {
+                let claimed: i64 = sqlx::query_scalar(
+                    r#"
+                    SELECT COUNT(*) FROM notification_deliveries
+                    WHERE recipient = $1 AND status = 'pending'
+                      AND next_attempt_at > CURRENT_TIMESTAMP
+                    "#,
+                )
+                .bind(&self.recipient)
+                .fetch_one(&self.pool)
+                .await
+                .unwrap();
+                self.claimed.lock().unwrap().push(claimed);
+            }
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_deliveries_are_claimed_one_at_a_time() {
+        // This test requires a valid DATABASE_URL environment variable
+        if let Ok(url) = env::var("DATABASE_URL") {
+            let pool = connect(url).await;
+            let (user_id, email) = create_user(&pool).await;
+            sqlx::query("UPDATE users SET email_verified_at = CURRENT_TIMESTAMP WHERE id = $1")
+                .bind(user_id)
+                .execute(&pool)
+                .await
+                .unwrap();
+            sqlx::query(
+                r#"INSERT INTO user_profiles (user_id, preferences) VALUES ($1, '{"email_reminders": true}')"#,
+            )
+            .bind(user_id)
+            .execute(&pool)
+            .await
+            .unwrap();
+
+            let today = Utc::now().naive_utc().date();
+            let subscriptions = SubscriptionService::new(pool.clone());
+            for days in [1, 2] {
+                subscriptions
+                    .create_subscription(subscription(user_id, today + Days::new(days)))
+                    .await
+                    .unwrap();
+            }
+            NotificationService::new(pool.clone())
+                .generate_reminders(today, Some(user_id))
+                .await
+                .unwrap();
+            let service = DeliveryService::new(pool.clone());
+            service.queue_email_reminders(today).await.unwrap();
+
+            let mailer = LeaseCheckingMailer {
+                pool,
+                recipient: email,
+                claimed: Mutex::new(Vec::new()),
+            };
+            service
+                .deliver_pending(&mailer, "http://localhost")
+                .await
+                .unwrap();
+
+            // Only the email being sent is claimed, not the rest of the run
+            assert_eq!(*mailer.claimed.lock().unwrap(), [1, 1]);
+        }
+    }
+}
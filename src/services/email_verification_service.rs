@@ -0,0 +1,427 @@
+use sqlx::{PgPool, Row, postgres::PgRow};
+use std::sync::Arc;
+use uuid::Uuid;
+
+use crate::services::mailer::{EmailMessage, MailSender};
+use crate::utils::auth::{generate_opaque_token, hash_token};
+use crate::utils::email_template::VERIFY_EMAIL_TEMPLATE;
+use crate::utils::response::AppError;
+
+/// Hours an email verification link stays valid
+pub const EMAIL_VERIFICATION_TOKEN_TTL_HOURS: i64 = 24;
+
+/// Service verifying that users own their email address
+///
+/// A verification token verifies the address it was sent to. Email changes
+/// use the same tokens: following a link sent to a new address switches the
+/// account to it. Only the latest token of a user works.
+pub struct EmailVerificationService {
+    pool: PgPool,
+}
+
+impl EmailVerificationService {
+    /// Create a new EmailVerificationService with the given database pool
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    /// Email a verification link to a newly registered user in the background
+    ///
+    /// Registration does not fail when the email cannot be sent; the user can
+    /// ask for a new link later.
+    pub fn send_verification(
+        self,
+        user_id: Uuid,
+        mailer: Option<Arc<dyn MailSender>>,
+        app_url: String,
+    ) {
+        tokio::spawn(async move {
+            let Some(mailer) = mailer else {
+                tracing::warn!("Email delivery is disabled, verification email not sent");
+                return;
+            };
+
+            match self.create_verification_email(user_id, &app_url).await {
+                Ok(message) => {
+                    if let Err(e) = mailer.send(&message).await {
+                        tracing::error!("Failed to send verification email: {}", e);
+                    }
+                }
+                Err(e) => tracing::error!("Failed to create verification token: {}", e),
+            }
+        });
+    }
+
+    /// Email a new verification link to a user who has not verified yet
+    pub async fn resend_verification(
+        &self,
+        user_id: Uuid,
+        mailer: Option<Arc<dyn MailSender>>,
+        app_url: &str,
+    ) -> Result<(), AppError> {
+        let Some(mailer) = mailer else {
+            return Err(AppError::internal_error(
+                "Email delivery is disabled, verification email not sent",
+            ));
+        };
+
+        let message = self.create_verification_email(user_id, app_url).await?;
+        mailer.send(&message).await.map_err(|e| {
+            AppError::internal_error(format!("Failed to send verification email: {e}"))
+        })?;
+
+        Ok(())
+    }
+
+    /// Start changing a user's email address
+    ///
+    /// A verification link is sent to the new address; the account keeps its
+    /// current address until the link is followed.
+    pub async fn request_email_change(
+        &self,
+        user_id: Uuid,
+        new_email: &str,
+        mailer: Option<Arc<dyn MailSender>>,
+        app_url: &str,
+    ) -> Result<(), AppError> {
+        let Some(mailer) = mailer else {
+            return Err(AppError::internal_error(
+                "Email delivery is disabled, email change not possible",
+            ));
+        };
+
+        let user = self.find_user(user_id).await?;
+        if user.get::<String, _>("email") == new_email {
+            return Err(AppError::field_validation_error(vec![(
+                "new_email",
+                "This is already the email address of your account".to_string(),
+            )]));
+        }
+        self.ensure_email_available(new_email).await?;
+
+        let message = self
+            .create_token_email(user_id, new_email, user.get("name"), app_url)
+            .await?;
+        mailer.send(&message).await.map_err(|e| {
+            AppError::internal_error(format!("Failed to send verification email: {e}"))
+        })?;
+
+        tracing::info!("Email change requested for user {}", user_id);
+
+        Ok(())
+    }
+
+    /// Create a verification token for the user's current address and render its email
+    ///
+    /// Fails with a conflict when the address is already verified.
+    pub async fn create_verification_email(
+        &self,
+        user_id: Uuid,
+        app_url: &str,
+    ) -> Result<EmailMessage, AppError> {
+        let user = self.find_user(user_id).await?;
+        if user.get::<bool, _>("verified") {
+            return Err(AppError::conflict(
+                "Email already verified",
+                format!("Email address of user {user_id} is already verified"),
+            ));
+        }
+
+        self.create_token_email(user_id, user.get("email"), user.get("name"), app_url)
+            .await
+    }
+
+    async fn find_user(&self, user_id: Uuid) -> Result<PgRow, AppError> {
+        sqlx::query(
+            r#"
+            SELECT u.email, u.email_verified_at IS NOT NULL AS verified, p.name
+            FROM users u
+            LEFT JOIN user_profiles p ON p.user_id = u.id
+            WHERE u.id = $1
+            "#,
+        )
+        .bind(user_id)
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| AppError::database_error("user lookup", format!("Database error: {e}")))?
+        .ok_or_else(|| AppError::not_found("User", format!("User {user_id} not found")))
+    }
+
+    async fn ensure_email_available(&self, email: &str) -> Result<(), AppError> {
+        let existing = sqlx::query("SELECT id FROM users WHERE email = $1")
+            .bind(email)
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(|e| AppError::database_error("user lookup", format!("Database error: {e}")))?;
+
+        match existing {
+            Some(_) => Err(email_taken(email)),
+            None => Ok(()),
+        }
+    }
+
+    /// Create a token verifying `email` for the user and render its email
+    ///
+    /// Earlier tokens of the user stop working.
+    async fn create_token_email(
+        &self,
+        user_id: Uuid,
+        email: &str,
+        name: Option<String>,
+        app_url: &str,
+    ) -> Result<EmailMessage, AppError> {
+        let mut tx = self.pool.begin().await.map_err(|e| {
+            AppError::database_error("transaction start", format!("Transaction error: {e}"))
+        })?;
+
+        sqlx::query(
+            r#"
+            UPDATE email_verification_tokens
+            SET used_at = CURRENT_TIMESTAMP
+            WHERE user_id = $1 AND used_at IS NULL
+            "#,
+        )
+        .bind(user_id)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| {
+            AppError::database_error(
+                "email verification token update",
+                format!("Database error: {e}"),
+            )
+        })?;
+
+        let token = generate_opaque_token();
+        sqlx::query(
+            r#"
+            INSERT INTO email_verification_tokens (user_id, email, token_hash, expires_at)
+            VALUES ($1, $2, $3, CURRENT_TIMESTAMP + make_interval(hours => $4))
+            "#,
+        )
+        .bind(user_id)
+        .bind(email)
+        .bind(hash_token(&token))
+        .bind(EMAIL_VERIFICATION_TOKEN_TTL_HOURS as i32)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| {
+            AppError::database_error(
+                "email verification token creation",
+                format!("Database error: {e}"),
+            )
+        })?;
+
+        tx.commit().await.map_err(|e| {
+            AppError::database_error("transaction commit", format!("Transaction error: {e}"))
+        })?;
+
+        let name = name
+            .filter(|name| !name.trim().is_empty())
+            .unwrap_or_else(|| "there".to_string());
+        let verify_url = format!("{app_url}/verify-email?token={token}");
+        let (text_body, html_body) = VERIFY_EMAIL_TEMPLATE.render(&[
+            ("name", &name),
+            ("email", email),
+            ("verify_url", &verify_url),
+            (
+                "expires_hours",
+                &EMAIL_VERIFICATION_TOKEN_TTL_HOURS.to_string(),
+            ),
+        ]);
+
+        Ok(EmailMessage {
+            to: email.to_string(),
+            subject: "Confirm your Sub-Pal email address".to_string(),
+            text_body,
+            html_body,
+        })
+    }
+
+    /// Mark the address of a verification email as the user's verified address
+    pub async fn verify_email(&self, token: &str) -> Result<(), AppError> {
+        let mut tx = self.pool.begin().await.map_err(|e| {
+            AppError::database_error("transaction start", format!("Transaction error: {e}"))
+        })?;
+
+        let row = sqlx::query(
+            r#"
+            SELECT user_id, email
+            FROM email_verification_tokens
+            WHERE token_hash = $1 AND used_at IS NULL AND expires_at > CURRENT_TIMESTAMP
+            FOR UPDATE
+            "#,
+        )
+        .bind(hash_token(token))
+        .fetch_optional(&mut *tx)
+        .await
+        .map_err(|e| {
+            AppError::database_error(
+                "email verification token lookup",
+                format!("Database error: {e}"),
+            )
+        })?
+        .ok_or_else(invalid_verification_token)?;
+        let user_id: Uuid = row.get("user_id");
+        let email: String = row.get("email");
+
+        // For an email change this switches the account to the new address
+        sqlx::query(
+            r#"
+            UPDATE users
+            SET email = $2, email_verified_at = CURRENT_TIMESTAMP
+            WHERE id = $1
+            "#,
+        )
+        .bind(user_id)
+        .bind(&email)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| match e {
+            sqlx::Error::Database(db) if db.is_unique_violation() => email_taken(&email),
+            e => AppError::database_error("email verification", format!("Database error: {e}")),
+        })?;
+
+        sqlx::query(
+            r#"
+            UPDATE email_verification_tokens
+            SET used_at = CURRENT_TIMESTAMP
+            WHERE user_id = $1 AND used_at IS NULL
+            "#,
+        )
+        .bind(user_id)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| {
+            AppError::database_error(
+                "email verification token update",
+                format!("Database error: {e}"),
+            )
+        })?;
+
+        tx.commit().await.map_err(|e| {
+            AppError::database_error("transaction commit", format!("Transaction error: {e}"))
+        })?;
+
+        tracing::info!("Email address verified for user {}", user_id);
+
+        Ok(())
+    }
+
+    /// Fail with a forbidden error unless the user's email address is verified
+    ///
+    /// `action` completes the sentence "verify your email address to ...".
+    pub async fn require_verified(&self, user_id: Uuid, action: &str) -> Result<(), AppError> {
+        let verified: bool = sqlx::query(
+            "SELECT email_verified_at IS NOT NULL AS verified FROM users WHERE id = $1",
+        )
+        .bind(user_id)
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| AppError::database_error("user lookup", format!("Database error: {e}")))?
+        .ok_or_else(|| AppError::not_found("User", format!("User {user_id} not found")))?
+        .get("verified");
+
+        if verified {
+            Ok(())
+        } else {
+            Err(AppError::email_not_verified(action))
+        }
+    }
+}
+
+fn email_taken(email: &str) -> AppError {
+    AppError::conflict(
+        "Email already exists",
+        format!("An account with the email '{email}' already exists."),
+    )
+}
+
+fn invalid_verification_token() -> AppError {
+    AppError::unauthorized_with_message(
+        "Invalid or expired email verification token",
+        "This verification link is invalid or has expired. Please request a new one.",
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::test_support::{connect, create_user, link_token};
+    use std::env;
+
+    #[tokio::test]
+    async fn test_verification_tokens_work_once() {
+        // This test requires a valid DATABASE_URL environment variable
+        if let Ok(url) = env::var("DATABASE_URL") {
+            let pool = connect(url).await;
+            let (user_id, _) = create_user(&pool).await;
+            let service = EmailVerificationService::new(pool);
+
+            assert!(matches!(
+                service.require_verified(user_id, "do this").await,
+                Err(AppError::Forbidden { .. })
+            ));
+
+            // Sending another email disables the earlier link
+            let first = link_token(
+                &service
+                    .create_verification_email(user_id, "http://localhost")
+                    .await
+                    .unwrap(),
+            );
+            let second = link_token(
+                &service
+                    .create_verification_email(user_id, "http://localhost")
+                    .await
+                    .unwrap(),
+            );
+            assert!(matches!(
+                service.verify_email(&first).await,
+                Err(AppError::Unauthorized { .. })
+            ));
+
+            service.verify_email(&second).await.unwrap();
+            service.require_verified(user_id, "do this").await.unwrap();
+            assert!(matches!(
+                service.verify_email(&second).await,
+                Err(AppError::Unauthorized { .. })
+            ));
+            assert!(matches!(
+                service
+                    .create_verification_email(user_id, "http://localhost")
+                    .await,
+                Err(AppError::Conflict { .. })
+            ));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_expired_verification_tokens_are_rejected() {
+        // This test requires a valid DATABASE_URL environment variable
+        if let Ok(url) = env::var("DATABASE_URL") {
+            let pool = connect(url).await;
+            let (user_id, _) = create_user(&pool).await;
+            let service = EmailVerificationService::new(pool.clone());
+
+            let token = link_token(
+                &service
+                    .create_verification_email(user_id, "http://localhost")
+                    .await
+                    .unwrap(),
+            );
+            sqlx::query(
+                "UPDATE email_verification_tokens SET expires_at = CURRENT_TIMESTAMP - INTERVAL '1 minute' WHERE user_id = $1",
+            )
+            .bind(user_id)
+            .execute(&pool)
+            .await
+            .unwrap();
+
+            assert!(matches!(
+                service.verify_email(&token).await,
+                Err(AppError::Unauthorized { .. })
+            ));
+            assert!(service.require_verified(user_id, "do this").await.is_err());
+        }
+    }
+}
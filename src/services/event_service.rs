@@ -0,0 +1,112 @@
+use sqlx::{PgConnection, PgPool, Row, postgres::PgRow};
+use uuid::Uuid;
+
+use crate::models::{
+    NewSubscriptionEvent, PaginatedResponse, Pagination, PaginationQuery, SubscriptionEvent,
+};
+use crate::utils::response::AppError;
+
+/// Service for reading a subscription's event history
+pub struct EventService {
+    pool: PgPool,
+}
+
+impl EventService {
+    /// Create a new EventService with the given database pool
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    /// List events of a subscription, most recent first
+    pub async fn list_events(
+        &self,
+        user_id: Uuid,
+        subscription_id: Uuid,
+        query: &PaginationQuery,
+    ) -> Result<PaginatedResponse<SubscriptionEvent>, AppError> {
+        let exists = sqlx::query("SELECT 1 FROM subscriptions WHERE id = $1 AND user_id = $2")
+            .bind(subscription_id)
+            .bind(user_id)
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(|e| {
+                AppError::database_error("subscription lookup", format!("Database error: {e}"))
+            })?;
+        if exists.is_none() {
+            return Err(AppError::not_found(
+                "Subscription",
+                format!("Subscription with ID {subscription_id} not found"),
+            ));
+        }
+
+        let total: i64 = sqlx::query_scalar(
+            "SELECT COUNT(*) FROM subscription_events WHERE subscription_id = $1",
+        )
+        .bind(subscription_id)
+        .fetch_one(&self.pool)
+        .await
+        .map_err(|e| AppError::database_error("event count", format!("Database error: {e}")))?;
+
+        let rows = sqlx::query(
+            r#"
+            SELECT id, subscription_id, user_id, event_type, before, after, note, created_at
+            FROM subscription_events
+            WHERE subscription_id = $1
+            ORDER BY created_at DESC, id
+            LIMIT $2 OFFSET $3
+            "#,
+        )
+        .bind(subscription_id)
+        .bind(query.limit())
+        .bind(query.offset())
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| AppError::database_error("event listing", format!("Database error: {e}")))?;
+
+        Ok(PaginatedResponse {
+            items: rows.iter().map(event_from_row).collect(),
+            pagination: Pagination::new(total, query),
+        })
+    }
+}
+
+/// Record subscription events as part of the caller's transaction
+pub(crate) async fn record_events(
+    conn: &mut PgConnection,
+    subscription_id: Uuid,
+    user_id: Uuid,
+    events: Vec<NewSubscriptionEvent>,
+) -> Result<(), sqlx::Error> {
+    for event in events {
+        sqlx::query(
+            r#"
+            INSERT INTO subscription_events
+            (subscription_id, user_id, event_type, before, after, note)
+            VALUES ($1, $2, $3, $4, $5, $6)
+            "#,
+        )
+        .bind(subscription_id)
+        .bind(user_id)
+        .bind(event.event_type.as_str())
+        .bind(event.before)
+        .bind(event.after)
+        .bind(event.note)
+        .execute(&mut *conn)
+        .await?;
+    }
+
+    Ok(())
+}
+
+pub(crate) fn event_from_row(row: &PgRow) -> SubscriptionEvent {
+    SubscriptionEvent {
+        id: row.get("id"),
+        subscription_id: row.get("subscription_id"),
+        user_id: row.get("user_id"),
+        event_type: row.get::<String, _>("event_type").into(),
+        before: row.get("before"),
+        after: row.get("after"),
+        note: row.get("note"),
+        created_at: row.get("created_at"),
+    }
+}
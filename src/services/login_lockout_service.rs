@@ -0,0 +1,642 @@
+use chrono::{DateTime, Utc};
+use sqlx::{PgConnection, PgPool, Postgres, Row, Transaction};
+use std::sync::Arc;
+use std::time::Duration;
+use uuid::Uuid;
+
+use crate::config::LoginLockoutConfig;
+use crate::services::mailer::{EmailMessage, MailSender};
+use crate::utils::auth::{generate_opaque_token, hash_token};
+use crate::utils::email_template::UNLOCK_ACCOUNT_TEMPLATE;
+use crate::utils::response::AppError;
+
+/// Hours an account unlock link stays valid
+pub const ACCOUNT_UNLOCK_TOKEN_TTL_HOURS: i64 = 24;
+
+/// A login attempt counted by [`LoginLockoutService::begin_attempt`]
+#[derive(Debug)]
+pub struct LoginAttempt {
+    /// The account of the email, `None` when there is none
+    user_id: Option<Uuid>,
+    /// Whether the attempt caused the first lock of a series
+    first_lock: bool,
+    /// How long the account is locked should the password be wrong
+    lock: Option<Duration>,
+}
+
+/// Service locking accounts after repeated failed logins
+///
+/// Failures are counted per account in the database, so an attack spread
+/// over many addresses still locks the account it targets, and users behind
+/// the same address as an attacker are not locked out of their own accounts.
+/// The first lock of a series emails the owner a link to lift it.
+pub struct LoginLockoutService {
+    pool: PgPool,
+    config: LoginLockoutConfig,
+}
+
+impl LoginLockoutService {
+    /// Create a new LoginLockoutService with the given database pool and settings
+    pub fn new(pool: PgPool, config: LoginLockoutConfig) -> Self {
+        Self { pool, config }
+    }
+
+    /// Count a login attempt to the given email before its password is
+    /// checked
+    ///
+    /// Attempts to a locked account are refused. Every attempt counts as a
+    /// failure until `record_success` or `withdraw_attempt` says otherwise,
+    /// and the count is checked and raised in one statement under the row
+    /// lock, so parallel guesses cannot get more passwords checked than the
+    /// limit allows. Emails without an account are counted and locked the
+    /// same way, so a lock does not tell whether an account exists.
+    pub async fn begin_attempt(&self, email: &str) -> Result<LoginAttempt, AppError> {
+        let mut tx = self.pool.begin().await.map_err(|e| {
+            AppError::database_error("transaction start", format!("Transaction error: {e}"))
+        })?;
+
+        let row = sqlx::query(
+            r#"
+            UPDATE users
+            SET failed_login_attempts = CASE
+                    WHEN last_failed_login_at IS NULL
+                      OR last_failed_login_at < CURRENT_TIMESTAMP - make_interval(secs => $2)
+                    THEN 1
+                    ELSE failed_login_attempts + 1
+                END,
+                last_failed_login_at = CURRENT_TIMESTAMP
+            WHERE email = $1 AND (locked_until IS NULL OR locked_until <= CURRENT_TIMESTAMP)
+            RETURNING id, failed_login_attempts
+            "#,
+        )
+        .bind(email)
+        .bind(self.config.reset_after.as_secs_f64())
+        .fetch_optional(&mut *tx)
+        .await
+        .map_err(|e| {
+            AppError::database_error("login attempt update", format!("Database error: {e}"))
+        })?;
+
+        let Some(row) = row else {
+            let locked_until: Option<Option<DateTime<Utc>>> =
+                sqlx::query_scalar("SELECT locked_until FROM users WHERE email = $1")
+                    .bind(email)
+                    .fetch_optional(&mut *tx)
+                    .await
+                    .map_err(|e| {
+                        AppError::database_error(
+                            "account lock lookup",
+                            format!("Database error: {e}"),
+                        )
+                    })?;
+            return match locked_until {
+                Some(locked_until) => Err(AppError::account_locked(remaining(
+                    locked_until.unwrap_or_else(Utc::now),
+                ))),
+                None => self.begin_unknown_attempt(tx, email).await,
+            };
+        };
+        let user_id: Uuid = row.get("id");
+        let failures = row.get::<i32, _>("failed_login_attempts").max(0) as u32;
+
+        let lock = self.lock(&mut tx, user_id, failures).await?;
+        tx.commit().await.map_err(|e| {
+            AppError::database_error("transaction commit", format!("Transaction error: {e}"))
+        })?;
+
+        Ok(LoginAttempt {
+            user_id: Some(user_id),
+            first_lock: lock.is_some() && failures == self.config.max_attempts,
+            lock,
+        })
+    }
+
+    /// Count an attempt to an email without an account, see `begin_attempt`
+    async fn begin_unknown_attempt(
+        &self,
+        mut tx: Transaction<'_, Postgres>,
+        email: &str,
+    ) -> Result<LoginAttempt, AppError> {
+        sqlx::query(
+            "DELETE FROM login_failures WHERE last_failed_login_at < CURRENT_TIMESTAMP - make_interval(secs => $1)",
+        )
+        .bind(self.config.reset_after.as_secs_f64())
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| {
+            AppError::database_error("login failure cleanup", format!("Database error: {e}"))
+        })?;
+
+        let failures: Option<i32> = sqlx::query_scalar(
+            r#"
+            INSERT INTO login_failures (email, failed_login_attempts)
+            VALUES ($1, 1)
+            ON CONFLICT (email) DO UPDATE
+            SET failed_login_attempts = login_failures.failed_login_attempts + 1,
+                last_failed_login_at = CURRENT_TIMESTAMP
+            WHERE login_failures.locked_until IS NULL
+               OR login_failures.locked_until <= CURRENT_TIMESTAMP
+            RETURNING failed_login_attempts
+            "#,
+        )
+        .bind(email)
+        .fetch_optional(&mut *tx)
+        .await
+        .map_err(|e| {
+            AppError::database_error("login attempt update", format!("Database error: {e}"))
+        })?;
+
+        let Some(failures) = failures else {
+            let locked_until: Option<DateTime<Utc>> =
+                sqlx::query_scalar("SELECT locked_until FROM login_failures WHERE email = $1")
+                    .bind(email)
+                    .fetch_one(&mut *tx)
+                    .await
+                    .map_err(|e| {
+                        AppError::database_error(
+                            "account lock lookup",
+                            format!("Database error: {e}"),
+                        )
+                    })?;
+            return Err(AppError::account_locked(remaining(
+                locked_until.unwrap_or_else(Utc::now),
+            )));
+        };
+
+        let lock = self.config.lock_duration(failures.max(0) as u32);
+        if let Some(lock) = lock {
+            sqlx::query(
+                "UPDATE login_failures SET locked_until = CURRENT_TIMESTAMP + make_interval(secs => $2) WHERE email = $1",
+            )
+            .bind(email)
+            .bind(lock.as_secs_f64())
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| {
+                AppError::database_error("account lock", format!("Database error: {e}"))
+            })?;
+        }
+        tx.commit().await.map_err(|e| {
+            AppError::database_error("transaction commit", format!("Transaction error: {e}"))
+        })?;
+
+        Ok(LoginAttempt {
+            user_id: None,
+            first_lock: false,
+            lock,
+        })
+    }
+
+    /// Finish an attempt whose password was wrong, returning the lock it caused
+    ///
+    /// The first lock of a series emails the owner an unlock link.
+    pub fn record_wrong_password(
+        &self,
+        attempt: &LoginAttempt,
+        mailer: Option<Arc<dyn MailSender>>,
+        app_url: String,
+    ) -> Option<Duration> {
+        if attempt.first_lock
+            && let Some(user_id) = attempt.user_id
+        {
+            self.send_unlock_email(user_id, mailer, app_url);
+        }
+        attempt.lock
+    }
+
+    /// Take back the attempt counted by `begin_attempt` when the password was
+    /// right but the login still needs a second factor
+    ///
+    /// Earlier failures are kept until the login is complete, and a lock the
+    /// attempt caused is lifted.
+    pub async fn withdraw_attempt(&self, email: &str) -> Result<(), AppError> {
+        sqlx::query(
+            r#"
+            UPDATE users
+            SET failed_login_attempts = GREATEST(failed_login_attempts - 1, 0),
+                locked_until = NULL
+            WHERE email = $1 AND failed_login_attempts > 0
+            "#,
+        )
+        .bind(email)
+        .execute(&self.pool)
+        .await
+        .map_err(|e| {
+            AppError::database_error("login attempt update", format!("Database error: {e}"))
+        })?;
+
+        Ok(())
+    }
+
+    /// Count a wrong second factor of the account of the given email,
+    /// returning the lock it caused
+    ///
+    /// Unknown emails are ignored.
+    pub async fn record_failure(
+        &self,
+        email: &str,
+        mailer: Option<Arc<dyn MailSender>>,
+        app_url: String,
+    ) -> Result<Option<Duration>, AppError> {
+        let mut tx = self.pool.begin().await.map_err(|e| {
+            AppError::database_error("transaction start", format!("Transaction error: {e}"))
+        })?;
+
+        let Some(row) = sqlx::query(
+            r#"
+            UPDATE users
+            SET failed_login_attempts = CASE
+                    WHEN last_failed_login_at IS NULL
+                      OR last_failed_login_at < CURRENT_TIMESTAMP - make_interval(secs => $2)
+                    THEN 1
+                    ELSE failed_login_attempts + 1
+                END,
+                last_failed_login_at = CURRENT_TIMESTAMP
+            WHERE email = $1
+            RETURNING id, failed_login_attempts
+            "#,
+        )
+        .bind(email)
+        .bind(self.config.reset_after.as_secs_f64())
+        .fetch_optional(&mut *tx)
+        .await
+        .map_err(|e| {
+            AppError::database_error("failed login update", format!("Database error: {e}"))
+        })?
+        else {
+            return Ok(None);
+        };
+        let user_id: Uuid = row.get("id");
+        let failures = row.get::<i32, _>("failed_login_attempts").max(0) as u32;
+
+        let lock = self.lock(&mut tx, user_id, failures).await?;
+        tx.commit().await.map_err(|e| {
+            AppError::database_error("transaction commit", format!("Transaction error: {e}"))
+        })?;
+
+        if lock.is_some() && failures == self.config.max_attempts {
+            self.send_unlock_email(user_id, mailer, app_url);
+        }
+        Ok(lock)
+    }
+
+    /// Lock an account for its `failures`-th failed login, if that calls for a lock
+    async fn lock(
+        &self,
+        conn: &mut PgConnection,
+        user_id: Uuid,
+        failures: u32,
+    ) -> Result<Option<Duration>, AppError> {
+        let Some(lock) = self.config.lock_duration(failures) else {
+            return Ok(None);
+        };
+        sqlx::query(
+            "UPDATE users SET locked_until = CURRENT_TIMESTAMP + make_interval(secs => $2) WHERE id = $1",
+        )
+        .bind(user_id)
+        .bind(lock.as_secs_f64())
+        .execute(conn)
+        .await
+        .map_err(|e| AppError::database_error("account lock", format!("Database error: {e}")))?;
+
+        tracing::warn!(
+            "Account {} locked for {}s after {} failed logins",
+            user_id,
+            lock.as_secs(),
+            failures
+        );
+        Ok(Some(lock))
+    }
+
+    /// Forget the failed logins of the account of the given email after its
+    /// password was entered correctly
+    pub async fn record_success(&self, email: &str) -> Result<(), AppError> {
+        sqlx::query(
+            r#"
+            UPDATE users
+            SET failed_login_attempts = 0, last_failed_login_at = NULL, locked_until = NULL
+            WHERE email = $1 AND failed_login_attempts > 0
+            "#,
+        )
+        .bind(email)
+        .execute(&self.pool)
+        .await
+        .map_err(|e| {
+            AppError::database_error("failed login reset", format!("Database error: {e}"))
+        })?;
+
+        Ok(())
+    }
+
+    /// Lift the lock of an account with the token from an unlock email
+    ///
+    /// The token can be used once.
+    pub async fn unlock(&self, token: &str) -> Result<(), AppError> {
+        let mut tx = self.pool.begin().await.map_err(|e| {
+            AppError::database_error("transaction start", format!("Transaction error: {e}"))
+        })?;
+
+        let user_id: Uuid = sqlx::query(
+            r#"
+            UPDATE account_unlock_tokens
+            SET used_at = CURRENT_TIMESTAMP
+            WHERE token_hash = $1 AND used_at IS NULL AND expires_at > CURRENT_TIMESTAMP
+            RETURNING user_id
+            "#,
+        )
+        .bind(hash_token(token))
+        .fetch_optional(&mut *tx)
+        .await
+        .map_err(|e| {
+            AppError::database_error("unlock token lookup", format!("Database error: {e}"))
+        })?
+        .ok_or_else(|| {
+            AppError::unauthorized_with_message(
+                "Invalid or expired account unlock token",
+                "This unlock link is invalid or has expired. Please reset your password instead.",
+            )
+        })?
+        .get("user_id");
+
+        sqlx::query(
+            r#"
+            UPDATE users
+            SET failed_login_attempts = 0, last_failed_login_at = NULL, locked_until = NULL
+            WHERE id = $1
+            "#,
+        )
+        .bind(user_id)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| AppError::database_error("account unlock", format!("Database error: {e}")))?;
+
+        tx.commit().await.map_err(|e| {
+            AppError::database_error("transaction commit", format!("Transaction error: {e}"))
+        })?;
+
+        tracing::info!("Account {} unlocked by email link", user_id);
+        Ok(())
+    }
+
+    /// Email an unlock link to the owner of a locked account in the background
+    fn send_unlock_email(
+        &self,
+        user_id: Uuid,
+        mailer: Option<Arc<dyn MailSender>>,
+        app_url: String,
+    ) {
+        let Some(mailer) = mailer else {
+            tracing::warn!("Email delivery is disabled, account unlock email not sent");
+            return;
+        };
+        let pool = self.pool.clone();
+        tokio::spawn(async move {
+            match create_unlock_email(&pool, user_id, &app_url).await {
+                Ok(message) => {
+                    if let Err(e) = mailer.send(&message).await {
+                        tracing::error!("Failed to send account unlock email: {}", e);
+                    }
+                }
+                Err(e) => tracing::error!("Failed to create account unlock token: {}", e),
+            }
+        });
+    }
+}
+
+/// Create an unlock token for a user and render its email
+///
+/// Earlier tokens of the user stop working.
+async fn create_unlock_email(
+    pool: &PgPool,
+    user_id: Uuid,
+    app_url: &str,
+) -> Result<EmailMessage, AppError> {
+    let user = sqlx::query(
+        r#"
+        SELECT u.email, p.name
+        FROM users u
+        LEFT JOIN user_profiles p ON p.user_id = u.id
+        WHERE u.id = $1
+        "#,
+    )
+    .bind(user_id)
+    .fetch_one(pool)
+    .await
+    .map_err(|e| AppError::database_error("user lookup", format!("Database error: {e}")))?;
+
+    let mut tx = pool.begin().await.map_err(|e| {
+        AppError::database_error("transaction start", format!("Transaction error: {e}"))
+    })?;
+
+    sqlx::query(
+        r#"
+        UPDATE account_unlock_tokens
+        SET used_at = CURRENT_TIMESTAMP
+        WHERE user_id = $1 AND used_at IS NULL
+        "#,
+    )
+    .bind(user_id)
+    .execute(&mut *tx)
+    .await
+    .map_err(|e| AppError::database_error("unlock token update", format!("Database error: {e}")))?;
+
+    let token = generate_opaque_token();
+    sqlx::query(
+        r#"
+        INSERT INTO account_unlock_tokens (user_id, token_hash, expires_at)
+        VALUES ($1, $2, CURRENT_TIMESTAMP + make_interval(hours => $3))
+        "#,
+    )
+    .bind(user_id)
+    .bind(hash_token(&token))
+    .bind(ACCOUNT_UNLOCK_TOKEN_TTL_HOURS as i32)
+    .execute(&mut *tx)
+    .await
+    .map_err(|e| {
+        AppError::database_error("unlock token creation", format!("Database error: {e}"))
+    })?;
+
+    tx.commit().await.map_err(|e| {
+        AppError::database_error("transaction commit", format!("Transaction error: {e}"))
+    })?;
+
+    let name = user
+        .get::<Option<String>, _>("name")
+        .filter(|name| !name.trim().is_empty())
+        .unwrap_or_else(|| "there".to_string());
+    let unlock_url = format!("{app_url}/unlock-account?token={token}");
+    let (text_body, html_body) = UNLOCK_ACCOUNT_TEMPLATE.render(&[
+        ("name", &name),
+        ("unlock_url", &unlock_url),
+        ("expires_hours", &ACCOUNT_UNLOCK_TOKEN_TTL_HOURS.to_string()),
+    ]);
+
+    Ok(EmailMessage {
+        to: user.get("email"),
+        subject: "Your Sub-Pal account was locked".to_string(),
+        text_body,
+        html_body,
+    })
+}
+
+/// Time left until a lock ends
+pub(crate) fn remaining(locked_until: DateTime<Utc>) -> Duration {
+    (locked_until - Utc::now()).to_std().unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::test_support::{connect, create_user, link_token};
+    use std::env;
+
+    fn lockout_config() -> LoginLockoutConfig {
+        LoginLockoutConfig {
+            max_attempts: 3,
+            ..LoginLockoutConfig::default()
+        }
+    }
+
+    /// Attempt a login with a wrong password, returning the lock it caused
+    async fn fail(service: &LoginLockoutService, email: &str) -> Option<Duration> {
+        let attempt = service.begin_attempt(email).await.unwrap();
+        service.record_wrong_password(&attempt, None, "http://localhost".to_string())
+    }
+
+    fn is_locked<T>(result: Result<T, AppError>) -> bool {
+        matches!(result, Err(AppError::TooManyRequests { .. }))
+    }
+
+    #[tokio::test]
+    async fn test_accounts_lock_and_unlock_by_email() {
+        // This test requires a valid DATABASE_URL environment variable
+        if let Ok(url) = env::var("DATABASE_URL") {
+            let pool = connect(url).await;
+            let (user_id, email) = create_user(&pool).await;
+            let service = LoginLockoutService::new(pool.clone(), lockout_config());
+
+            assert_eq!(fail(&service, &email).await, None);
+            assert_eq!(fail(&service, &email).await, None);
+            assert_eq!(fail(&service, &email).await, Some(Duration::from_secs(60)));
+            assert!(is_locked(service.begin_attempt(&email).await));
+
+            let token = link_token(
+                &create_unlock_email(&pool, user_id, "http://localhost")
+                    .await
+                    .unwrap(),
+            );
+            service.unlock(&token).await.unwrap();
+            assert!(matches!(
+                service.unlock(&token).await,
+                Err(AppError::Unauthorized { .. })
+            ));
+
+            // The failures before the unlock do not count towards the next lock
+            assert_eq!(fail(&service, &email).await, None);
+            assert_eq!(fail(&service, &email).await, None);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_expired_unlock_tokens_are_rejected() {
+        // This test requires a valid DATABASE_URL environment variable
+        if let Ok(url) = env::var("DATABASE_URL") {
+            let pool = connect(url).await;
+            let (user_id, email) = create_user(&pool).await;
+            let service = LoginLockoutService::new(pool.clone(), lockout_config());
+
+            for _ in 0..3 {
+                fail(&service, &email).await;
+            }
+            let token = link_token(
+                &create_unlock_email(&pool, user_id, "http://localhost")
+                    .await
+                    .unwrap(),
+            );
+            sqlx::query(
+                "UPDATE account_unlock_tokens SET expires_at = CURRENT_TIMESTAMP - INTERVAL '1 minute' WHERE user_id = $1",
+            )
+            .bind(user_id)
+            .execute(&pool)
+            .await
+            .unwrap();
+
+            assert!(matches!(
+                service.unlock(&token).await,
+                Err(AppError::Unauthorized { .. })
+            ));
+            assert!(is_locked(service.begin_attempt(&email).await));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_a_successful_login_forgets_failures() {
+        // This test requires a valid DATABASE_URL environment variable
+        if let Ok(url) = env::var("DATABASE_URL") {
+            let pool = connect(url).await;
+            let (_, email) = create_user(&pool).await;
+            let service = LoginLockoutService::new(pool, lockout_config());
+
+            fail(&service, &email).await;
+            fail(&service, &email).await;
+            service.begin_attempt(&email).await.unwrap();
+            service.record_success(&email).await.unwrap();
+            assert_eq!(fail(&service, &email).await, None);
+            assert_eq!(fail(&service, &email).await, None);
+
+            // A right password that still needs a second factor is not a
+            // failure, but the failures before it are kept
+            let attempt = service.begin_attempt(&email).await.unwrap();
+            assert!(attempt.lock.is_some());
+            service.withdraw_attempt(&email).await.unwrap();
+            let lock = service
+                .record_failure(&email, None, "http://localhost".to_string())
+                .await
+                .unwrap();
+            assert_eq!(lock, Some(Duration::from_secs(60)));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_unknown_emails_are_locked_like_accounts() {
+        // This test requires a valid DATABASE_URL environment variable
+        if let Ok(url) = env::var("DATABASE_URL") {
+            let pool = connect(url).await;
+            let service = LoginLockoutService::new(pool, lockout_config());
+            let email = format!("nobody-{}@example.com", Uuid::new_v4());
+
+            assert_eq!(fail(&service, &email).await, None);
+            assert_eq!(fail(&service, &email).await, None);
+            assert_eq!(fail(&service, &email).await, Some(Duration::from_secs(60)));
+            assert!(is_locked(service.begin_attempt(&email).await));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_parallel_attempts_cannot_pass_the_limit() {
+        // This test requires a valid DATABASE_URL environment variable
+        if let Ok(url) = env::var("DATABASE_URL") {
+            let pool = connect(url).await;
+            let (_, email) = create_user(&pool).await;
+
+            let tasks: Vec<_> = (0..10)
+                .map(|_| {
+                    let service = LoginLockoutService::new(pool.clone(), lockout_config());
+                    let email = email.clone();
+                    tokio::spawn(async move { service.begin_attempt(&email).await })
+                })
+                .collect();
+            let mut allowed = 0;
+            for task in tasks {
+                let result = task.await.unwrap();
+                if result.is_ok() {
+                    allowed += 1;
+                } else {
+                    assert!(is_locked(result));
+                }
+            }
+
+            assert_eq!(allowed, 3);
+        }
+    }
+}
@@ -0,0 +1,152 @@
+use async_trait::async_trait;
+use lettre::message::{Mailbox, MultiPart};
+use lettre::transport::smtp::authentication::Credentials;
+use lettre::{AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor};
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::config::MailConfig;
+use crate::config::mail::{MailTransport, SmtpSecurity};
+
+/// An email ready to be sent
+#[derive(Debug, Clone)]
+pub struct EmailMessage {
+    pub to: String,
+    pub subject: String,
+    pub text_body: String,
+    pub html_body: String,
+}
+
+/// A way of delivering emails
+#[async_trait]
+pub trait MailSender: Send + Sync {
+    /// Send one email, returning a description of the problem on failure
+    async fn send(&self, message: &EmailMessage) -> Result<(), String>;
+}
+
+/// Sends emails through an SMTP server
+pub struct SmtpMailer {
+    transport: AsyncSmtpTransport<Tokio1Executor>,
+    from: Mailbox,
+}
+
+impl SmtpMailer {
+    /// Create a mailer for the SMTP server of the given configuration
+    pub fn new(config: &MailConfig) -> Result<Self, String> {
+        let from: Mailbox = config
+            .from
+            .parse()
+            .map_err(|e| format!("Invalid MAIL_FROM address: {e}"))?;
+        if config.smtp_host.is_empty() {
+            return Err("SMTP_HOST is required for the smtp mail transport".to_string());
+        }
+
+        let builder = match config.smtp_security {
+            SmtpSecurity::None => {
+                AsyncSmtpTransport::<Tokio1Executor>::builder_dangerous(&config.smtp_host)
+            }
+            SmtpSecurity::StartTls => {
+                AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(&config.smtp_host)
+                    .map_err(|e| format!("Invalid SMTP configuration: {e}"))?
+            }
+            SmtpSecurity::Tls => AsyncSmtpTransport::<Tokio1Executor>::relay(&config.smtp_host)
+                .map_err(|e| format!("Invalid SMTP configuration: {e}"))?,
+        };
+        let mut builder = builder
+            .port(config.smtp_port)
+            .timeout(Some(Duration::from_secs(30)));
+        if let (Some(username), Some(password)) = (&config.smtp_username, &config.smtp_password) {
+            builder = builder.credentials(Credentials::new(username.clone(), password.clone()));
+        }
+
+        Ok(Self {
+            transport: builder.build(),
+            from,
+        })
+    }
+}
+
+#[async_trait]
+impl MailSender for SmtpMailer {
+    async fn send(&self, message: &EmailMessage) -> Result<(), String> {
+        let to: Mailbox = message
+            .to
+            .parse()
+            .map_err(|e| format!("Invalid recipient address: {e}"))?;
+        let email = Message::builder()
+            .from(self.from.clone())
+            .to(to)
+            .subject(&message.subject)
+            .multipart(MultiPart::alternative_plain_html(
+                message.text_body.clone(),
+                message.html_body.clone(),
+            ))
+            .map_err(|e| format!("Could not build email: {e}"))?;
+
+        self.transport
+            .send(email)
+            .await
+            .map(|_| ())
+            .map_err(|e| format!("SMTP error: {e}"))
+    }
+}
+
+/// Writes emails to the log instead of sending them, for development
+///
+/// Only the recipient and subject are logged: bodies carry password reset,
+/// verification and unlock links, which must not end up in log files.
+pub struct LogMailer;
+
+#[async_trait]
+impl MailSender for LogMailer {
+    async fn send(&self, message: &EmailMessage) -> Result<(), String> {
+        tracing::info!("Email to {} - subject: {}", message.to, message.subject);
+        Ok(())
+    }
+}
+
+/// Create the mail sender of the configured transport
+///
+/// Returns `None` when email is disabled.
+pub fn create_mailer(config: &MailConfig) -> Result<Option<Arc<dyn MailSender>>, String> {
+    match config.transport {
+        MailTransport::Disabled => Ok(None),
+        MailTransport::Log => Ok(Some(Arc::new(LogMailer))),
+        MailTransport::Smtp => Ok(Some(Arc::new(SmtpMailer::new(config)?))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+
+    #[tokio::test]
+    async fn test_smtp_mailer_sends_email() {
+        // This test requires a local SMTP stand-in such as MailHog, e.g.
+        // SMTP_TEST_HOST=localhost SMTP_TEST_PORT=1025
+        if let Ok(host) = env::var("SMTP_TEST_HOST") {
+            let config = MailConfig {
+                transport: MailTransport::Smtp,
+                smtp_host: host,
+                smtp_port: env::var("SMTP_TEST_PORT")
+                    .ok()
+                    .and_then(|port| port.parse().ok())
+                    .unwrap_or(1025),
+                smtp_security: SmtpSecurity::None,
+                ..MailConfig::default()
+            };
+
+            let mailer = SmtpMailer::new(&config).unwrap();
+            let result = mailer
+                .send(&EmailMessage {
+                    to: "test@example.com".to_string(),
+                    subject: "Sub-Pal test email".to_string(),
+                    text_body: "Plain-text body".to_string(),
+                    html_body: "<p>HTML body</p>".to_string(),
+                })
+                .await;
+            assert!(result.is_ok(), "{result:?}");
+        }
+    }
+}
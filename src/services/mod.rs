@@ -1,5 +1,45 @@
+pub mod account_service;
+pub mod api_token_service;
+pub mod delivery_service;
+pub mod email_verification_service;
+pub mod event_service;
+pub mod login_lockout_service;
+pub mod mailer;
+pub mod notification_service;
+pub mod oidc_client;
+pub mod oidc_service;
+pub mod password_reset_service;
+pub mod payment_service;
+pub mod rate_limit_store;
+pub mod refresh_token_service;
+pub mod renewal_service;
+pub mod session_service;
+pub mod statistics_service;
 pub mod subscription_service;
+#[cfg(test)]
+mod test_support;
+pub mod two_factor_service;
 pub mod user_service;
+pub mod webhook_service;
 
+pub use self::account_service::{AccountService, spawn_account_purge_worker};
+pub use self::api_token_service::ApiTokenService;
+pub use self::delivery_service::spawn_delivery_worker;
+pub use self::email_verification_service::EmailVerificationService;
+pub use self::event_service::EventService;
+pub use self::login_lockout_service::LoginLockoutService;
+pub use self::mailer::create_mailer;
+pub use self::notification_service::NotificationService;
+pub use self::oidc_client::OidcClient;
+pub use self::oidc_service::OidcService;
+pub use self::password_reset_service::PasswordResetService;
+pub use self::payment_service::PaymentService;
+pub use self::rate_limit_store::{create_rate_limit_store, spawn_rate_limit_cleanup};
+pub use self::refresh_token_service::RefreshTokenService;
+pub use self::renewal_service::spawn_renewal_scheduler;
+pub use self::session_service::SessionService;
+pub use self::statistics_service::StatisticsService;
 pub use self::subscription_service::SubscriptionService;
+pub use self::two_factor_service::{ChallengeOutcome, TwoFactorService};
 pub use self::user_service::UserService;
+pub use self::webhook_service::{WebhookAddressPolicy, WebhookService, spawn_webhook_worker};
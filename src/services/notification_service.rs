@@ -0,0 +1,357 @@
+use chrono::{DateTime, NaiveDate, Utc};
+use sqlx::{PgConnection, PgPool, Row, postgres::PgRow};
+use uuid::Uuid;
+
+use crate::models::{
+    DEFAULT_REMINDER_LEAD_DAYS, MAX_REMINDER_LEAD_DAYS, NewNotification, Notification,
+    NotificationFilter, PaginatedResponse, Pagination, PaginationQuery, WebhookEvent,
+    WebhookEventType,
+};
+use crate::services::subscription_service::subscription_from_row;
+use crate::services::webhook_service::queue_webhook_event;
+use crate::utils::response::AppError;
+
+/// Service for generating and reading a user's notifications
+pub struct NotificationService {
+    pool: PgPool,
+}
+
+impl NotificationService {
+    /// Create a new NotificationService with the given database pool
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    /// List notifications of a user, newest first
+    pub async fn list_notifications(
+        &self,
+        user_id: Uuid,
+        filter: &NotificationFilter,
+        query: &PaginationQuery,
+    ) -> Result<PaginatedResponse<Notification>, AppError> {
+        let total: i64 = sqlx::query_scalar(
+            r#"
+            SELECT COUNT(*) FROM notifications
+            WHERE user_id = $1 AND ($2::boolean IS NULL OR (read_at IS NOT NULL) = $2)
+            "#,
+        )
+        .bind(user_id)
+        .bind(filter.read)
+        .fetch_one(&self.pool)
+        .await
+        .map_err(|e| {
+            AppError::database_error("notification count", format!("Database error: {e}"))
+        })?;
+
+        let rows = sqlx::query(
+            r#"
+            SELECT id, user_id, subscription_id, notification_type, title, message,
+                   due_date, read_at, created_at
+            FROM notifications
+            WHERE user_id = $1 AND ($2::boolean IS NULL OR (read_at IS NOT NULL) = $2)
+            ORDER BY created_at DESC, id
+            LIMIT $3 OFFSET $4
+            "#,
+        )
+        .bind(user_id)
+        .bind(filter.read)
+        .bind(query.limit())
+        .bind(query.offset())
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| {
+            AppError::database_error("notification listing", format!("Database error: {e}"))
+        })?;
+
+        Ok(PaginatedResponse {
+            items: rows.iter().map(notification_from_row).collect(),
+            pagination: Pagination::new(total, query),
+        })
+    }
+
+    /// Mark one notification of a user as read
+    pub async fn mark_read(
+        &self,
+        user_id: Uuid,
+        notification_id: Uuid,
+    ) -> Result<Notification, AppError> {
+        let row = sqlx::query(
+            r#"
+            UPDATE notifications
+            SET read_at = COALESCE(read_at, CURRENT_TIMESTAMP)
+            WHERE id = $1 AND user_id = $2
+            RETURNING id, user_id, subscription_id, notification_type, title, message,
+                      due_date, read_at, created_at
+            "#,
+        )
+        .bind(notification_id)
+        .bind(user_id)
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| {
+            AppError::database_error("notification update", format!("Database error: {e}"))
+        })?
+        .ok_or_else(|| {
+            AppError::not_found(
+                "Notification",
+                format!("Notification with ID {notification_id} not found"),
+            )
+        })?;
+
+        Ok(notification_from_row(&row))
+    }
+
+    /// Mark all unread notifications of a user as read, returning how many changed
+    pub async fn mark_all_read(&self, user_id: Uuid) -> Result<u64, AppError> {
+        let result = sqlx::query(
+            r#"
+            UPDATE notifications
+            SET read_at = CURRENT_TIMESTAMP
+            WHERE user_id = $1 AND read_at IS NULL
+            "#,
+        )
+        .bind(user_id)
+        .execute(&self.pool)
+        .await
+        .map_err(|e| {
+            AppError::database_error("notification update", format!("Database error: {e}"))
+        })?;
+
+        Ok(result.rows_affected())
+    }
+
+    /// Create renewal and trial ending reminders that are due as of `today`
+    ///
+    /// A reminder is due once the renewal or trial end is within the user's
+    /// `reminder_lead_days` preference. Reminders already created are not
+    /// duplicated, so this can run any number of times a day. Limited to one
+    /// user when `user_id` is given; returns the number of new notifications.
+    /// Every new reminder is also sent to the user's webhook endpoints. Each
+    /// user's reminders are created in a transaction of their own, so a user
+    /// whose reminders fail is logged and skipped without undoing the others.
+    pub async fn generate_reminders(
+        &self,
+        today: NaiveDate,
+        user_id: Option<Uuid>,
+    ) -> Result<u64, AppError> {
+        let rows = sqlx::query(
+            r#"
+            SELECT s.id, s.user_id, s.name, s.description, s.amount,
+                   s.currency, s.billing_cycle_unit, s.billing_cycle_interval,
+                   s.start_date, s.next_billing_date, s.billing_anchor_date,
+                   s.status, s.paused_at, s.end_date, s.trial_end_date, s.category, s.color,
+                   s.created_at, s.updated_at,
+                   $1::date + lead.days AS remind_until
+            FROM subscriptions s
+            LEFT JOIN user_profiles p ON p.user_id = s.user_id
+            CROSS JOIN LATERAL (
+                SELECT CASE
+                    WHEN p.preferences->>'reminder_lead_days' ~ '^[0-9]{1,9}$'
+                    THEN LEAST((p.preferences->>'reminder_lead_days')::integer, $3)
+                    ELSE $2
+                END AS days
+            ) lead
+            WHERE LOWER(s.status) = 'active'
+              AND ($4::uuid IS NULL OR s.user_id = $4)
+              AND (s.next_billing_date BETWEEN $1::date AND $1::date + lead.days
+                   OR s.trial_end_date BETWEEN $1::date AND $1::date + lead.days)
+            "#,
+        )
+        .bind(today)
+        .bind(DEFAULT_REMINDER_LEAD_DAYS)
+        .bind(MAX_REMINDER_LEAD_DAYS)
+        .bind(user_id)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| AppError::database_error("reminder lookup", format!("Database error: {e}")))?;
+
+        // Rows come ordered by user, so each user's reminders are consecutive
+        let mut users: Vec<(Uuid, Vec<PgRow>)> = Vec::new();
+        for row in rows {
+            let row_user_id: Uuid = row.get("user_id");
+            match users.last_mut() {
+                Some((last_user_id, user_rows)) if *last_user_id == row_user_id => {
+                    user_rows.push(row)
+                }
+                _ => users.push((row_user_id, vec![row])),
+            }
+        }
+
+        let mut created = 0;
+        for (user_id, rows) in users {
+            match self.remind_user(today, &rows).await {
+                Ok(recorded) => created += recorded,
+                Err(e) => tracing::error!(
+                    "Reminders of user {} could not be created, skipped: {}",
+                    user_id,
+                    e
+                ),
+            }
+        }
+
+        Ok(created)
+    }
+
+    /// Record the due reminders of one user's subscriptions and their
+    /// webhook events in a transaction of their own
+    async fn remind_user(&self, today: NaiveDate, rows: &[PgRow]) -> Result<u64, AppError> {
+        let mut tx = self.pool.begin().await.map_err(|e| {
+            AppError::database_error("transaction start", format!("Transaction error: {e}"))
+        })?;
+
+        let mut created = 0;
+        for row in rows {
+            let subscription = subscription_from_row(row);
+            let remind_until: NaiveDate = row.get("remind_until");
+
+            let mut reminders = Vec::new();
+            if (today..=remind_until).contains(&subscription.next_billing_date) {
+                reminders.push((
+                    NewNotification::renewal_upcoming(
+                        subscription.id,
+                        &subscription.name,
+                        &subscription.amount,
+                        &subscription.currency,
+                        subscription.next_billing_date,
+                        today,
+                    ),
+                    WebhookEvent::reminder(
+                        WebhookEventType::SubscriptionRenewalUpcoming,
+                        &subscription,
+                        subscription.next_billing_date,
+                    ),
+                ));
+            }
+            if let Some(trial_end_date) = subscription.trial_end_date
+                && (today..=remind_until).contains(&trial_end_date)
+            {
+                reminders.push((
+                    NewNotification::trial_ending(
+                        subscription.id,
+                        &subscription.name,
+                        trial_end_date,
+                        today,
+                    ),
+                    WebhookEvent::reminder(
+                        WebhookEventType::SubscriptionTrialEnding,
+                        &subscription,
+                        trial_end_date,
+                    ),
+                ));
+            }
+
+            for (reminder, event) in reminders {
+                let recorded = record_notification(&mut tx, subscription.user_id, reminder)
+                    .await
+                    .map_err(|e| {
+                        AppError::database_error(
+                            "notification creation",
+                            format!("Database error: {e}"),
+                        )
+                    })?;
+                // Only new reminders are announced, like the notifications themselves
+                if recorded > 0 {
+                    queue_webhook_event(&mut tx, subscription.user_id, &event)
+                        .await
+                        .map_err(|e| {
+                            AppError::database_error(
+                                "webhook queueing",
+                                format!("Database error: {e}"),
+                            )
+                        })?;
+                }
+                created += recorded;
+            }
+        }
+
+        tx.commit().await.map_err(|e| {
+            AppError::database_error("transaction commit", format!("Transaction error: {e}"))
+        })?;
+
+        Ok(created)
+    }
+}
+
+/// Record a notification with the given connection, e.g. inside a transaction
+///
+/// Reminders that already exist for the same subscription and date are
+/// skipped; returns the number of notifications created.
+pub(crate) async fn record_notification(
+    conn: &mut PgConnection,
+    user_id: Uuid,
+    notification: NewNotification,
+) -> Result<u64, sqlx::Error> {
+    let result = sqlx::query(
+        r#"
+        INSERT INTO notifications
+        (user_id, subscription_id, notification_type, title, message, due_date)
+        VALUES ($1, $2, $3, $4, $5, $6)
+        ON CONFLICT DO NOTHING
+        "#,
+    )
+    .bind(user_id)
+    .bind(notification.subscription_id)
+    .bind(notification.notification_type.as_str())
+    .bind(notification.title)
+    .bind(notification.message)
+    .bind(notification.due_date)
+    .execute(conn)
+    .await?;
+
+    Ok(result.rows_affected())
+}
+
+pub(crate) fn notification_from_row(row: &PgRow) -> Notification {
+    let read_at: Option<DateTime<Utc>> = row.get("read_at");
+    Notification {
+        id: row.get("id"),
+        user_id: row.get("user_id"),
+        subscription_id: row.get("subscription_id"),
+        notification_type: row.get::<String, _>("notification_type").into(),
+        title: row.get("title"),
+        message: row.get("message"),
+        due_date: row.get("due_date"),
+        read: read_at.is_some(),
+        read_at,
+        created_at: row.get("created_at"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::SubscriptionService;
+    use crate::services::test_support::{connect, create_user, subscription};
+    use chrono::Days;
+    use std::env;
+
+    #[tokio::test]
+    async fn test_reminders_are_created_once() {
+        // This test requires a valid DATABASE_URL environment variable
+        if let Ok(url) = env::var("DATABASE_URL") {
+            let pool = connect(url).await;
+            let (user_id, _) = create_user(&pool).await;
+            let today = Utc::now().naive_utc().date();
+            SubscriptionService::new(pool.clone())
+                .create_subscription(subscription(user_id, today + Days::new(2)))
+                .await
+                .unwrap();
+            let service = NotificationService::new(pool);
+
+            assert_eq!(
+                service
+                    .generate_reminders(today, Some(user_id))
+                    .await
+                    .unwrap(),
+                1
+            );
+            assert_eq!(
+                service
+                    .generate_reminders(today, Some(user_id))
+                    .await
+                    .unwrap(),
+                0
+            );
+        }
+    }
+}
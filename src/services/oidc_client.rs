@@ -0,0 +1,529 @@
+use jsonwebtoken::jwk::{AlgorithmParameters, Jwk, JwkSet, PublicKeyUse};
+use jsonwebtoken::{Algorithm, DecodingKey, Validation, decode, decode_header};
+use reqwest::Url;
+use serde::Deserialize;
+use serde::de::DeserializeOwned;
+use std::time::{Duration, Instant};
+use tokio::sync::RwLock;
+
+use crate::config::OidcConfig;
+
+/// Time the provider metadata and signing keys are cached
+const METADATA_CACHE_TTL: Duration = Duration::from_secs(60 * 60);
+/// Time allowed for one request to the provider
+const PROVIDER_TIMEOUT: Duration = Duration::from_secs(10);
+/// Signature algorithms accepted for ID tokens; symmetric ones are never
+/// accepted, as the keys come from the provider's public key set
+const ID_TOKEN_ALGORITHMS: [Algorithm; 9] = [
+    Algorithm::RS256,
+    Algorithm::RS384,
+    Algorithm::RS512,
+    Algorithm::PS256,
+    Algorithm::PS384,
+    Algorithm::PS512,
+    Algorithm::ES256,
+    Algorithm::ES384,
+    Algorithm::EdDSA,
+];
+
+/// The parts of the provider metadata (OpenID Connect Discovery 1.0) used here
+#[derive(Debug, Clone, Deserialize)]
+pub struct ProviderMetadata {
+    pub issuer: String,
+    pub authorization_endpoint: String,
+    pub token_endpoint: String,
+    pub jwks_uri: String,
+}
+
+/// A user as identified by a validated ID token
+#[derive(Debug, Clone)]
+pub struct OidcIdentity {
+    pub issuer: String,
+    pub subject: String,
+    pub email: Option<String>,
+    pub email_verified: bool,
+    pub name: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct IdTokenClaims {
+    sub: String,
+    nonce: Option<String>,
+    email: Option<String>,
+    #[serde(default)]
+    email_verified: bool,
+    name: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct TokenResponse {
+    id_token: Option<String>,
+}
+
+struct Cached<T> {
+    value: T,
+    fetched_at: Instant,
+}
+
+impl<T: Clone> Cached<T> {
+    fn fresh(cached: &Option<Self>) -> Option<T> {
+        cached
+            .as_ref()
+            .filter(|cached| cached.fetched_at.elapsed() < METADATA_CACHE_TTL)
+            .map(|cached| cached.value.clone())
+    }
+}
+
+/// Client of an OpenID Connect provider using the authorization code flow
+///
+/// The provider metadata and its signing keys are discovered on first use and
+/// cached; the keys are fetched again when a token names an unknown key, so
+/// key rotation at the provider needs no restart.
+pub struct OidcClient {
+    config: OidcConfig,
+    http: reqwest::Client,
+    metadata: RwLock<Option<Cached<ProviderMetadata>>>,
+    jwks: RwLock<Option<Cached<JwkSet>>>,
+}
+
+impl OidcClient {
+    /// Create a client for the provider of the given configuration
+    pub fn new(config: OidcConfig) -> Result<Self, String> {
+        let http = reqwest::Client::builder()
+            .timeout(PROVIDER_TIMEOUT)
+            .redirect(reqwest::redirect::Policy::none())
+            .user_agent(concat!("Sub-Pal/", env!("CARGO_PKG_VERSION")))
+            .build()
+            .map_err(|e| format!("Failed to create the OIDC HTTP client: {e}"))?;
+
+        Ok(Self {
+            config,
+            http,
+            metadata: RwLock::new(None),
+            jwks: RwLock::new(None),
+        })
+    }
+
+    pub fn config(&self) -> &OidcConfig {
+        &self.config
+    }
+
+    /// Build the URL of the provider's login page
+    ///
+    /// `code_challenge` is the S256 PKCE challenge of the verifier later
+    /// passed to [`OidcClient::exchange_code`].
+    pub async fn authorization_url(
+        &self,
+        state: &str,
+        nonce: &str,
+        code_challenge: &str,
+    ) -> Result<String, String> {
+        let metadata = self.metadata().await?;
+        let url = Url::parse_with_params(
+            &metadata.authorization_endpoint,
+            &[
+                ("response_type", "code"),
+                ("client_id", self.config.client_id.as_str()),
+                ("redirect_uri", self.config.redirect_url.as_str()),
+                ("scope", self.config.scopes.join(" ").as_str()),
+                ("state", state),
+                ("nonce", nonce),
+                ("code_challenge", code_challenge),
+                ("code_challenge_method", "S256"),
+            ],
+        )
+        .map_err(|e| format!("Invalid authorization endpoint: {e}"))?;
+
+        Ok(url.to_string())
+    }
+
+    /// Exchange an authorization code for the user's validated identity
+    pub async fn exchange_code(
+        &self,
+        code: &str,
+        code_verifier: &str,
+        nonce: &str,
+    ) -> Result<OidcIdentity, String> {
+        let metadata = self.metadata().await?;
+
+        let mut form = vec![
+            ("grant_type", "authorization_code"),
+            ("code", code),
+            ("redirect_uri", self.config.redirect_url.as_str()),
+            ("client_id", self.config.client_id.as_str()),
+            ("code_verifier", code_verifier),
+        ];
+        if let Some(secret) = &self.config.client_secret {
+            form.push(("client_secret", secret.as_str()));
+        }
+
+        let response = self
+            .http
+            .post(&metadata.token_endpoint)
+            .form(&form)
+            .send()
+            .await
+            .map_err(|e| format!("Token request failed: {e}"))?;
+        let status = response.status();
+        let body = response
+            .text()
+            .await
+            .map_err(|e| format!("Failed to read the token response: {e}"))?;
+        if !status.is_success() {
+            let body: String = body.chars().take(200).collect();
+            return Err(format!("Token endpoint answered {status}: {body}"));
+        }
+
+        let tokens: TokenResponse =
+            serde_json::from_str(&body).map_err(|e| format!("Invalid token response: {e}"))?;
+        let id_token = tokens
+            .id_token
+            .ok_or("The token response contains no ID token")?;
+
+        self.validate_id_token(&id_token, nonce).await
+    }
+
+    /// Check the signature and claims of an ID token
+    pub async fn validate_id_token(
+        &self,
+        id_token: &str,
+        nonce: &str,
+    ) -> Result<OidcIdentity, String> {
+        let header = decode_header(id_token).map_err(|e| format!("Invalid ID token: {e}"))?;
+        if !ID_TOKEN_ALGORITHMS.contains(&header.alg) {
+            return Err(format!(
+                "ID token signed with unsupported algorithm {:?}",
+                header.alg
+            ));
+        }
+
+        let key = self.signing_key(header.kid.as_deref()).await?;
+        let key = DecodingKey::from_jwk(&key).map_err(|e| format!("Invalid signing key: {e}"))?;
+
+        let mut validation = Validation::new(header.alg);
+        validation.set_issuer(&[&self.config.issuer_url]);
+        validation.set_audience(&[&self.config.client_id]);
+        validation.set_required_spec_claims(&["exp", "iss", "aud", "sub"]);
+
+        let claims = decode::<IdTokenClaims>(id_token, &key, &validation)
+            .map_err(|e| format!("ID token rejected: {e}"))?
+            .claims;
+        if claims.nonce.as_deref() != Some(nonce) {
+            return Err("ID token nonce does not match the login".to_string());
+        }
+
+        Ok(OidcIdentity {
+            issuer: self.config.issuer_url.clone(),
+            subject: claims.sub,
+            email: claims.email.map(|email| email.trim().to_lowercase()),
+            email_verified: claims.email_verified,
+            name: claims.name,
+        })
+    }
+
+    async fn metadata(&self) -> Result<ProviderMetadata, String> {
+        if let Some(metadata) = Cached::fresh(&*self.metadata.read().await) {
+            return Ok(metadata);
+        }
+
+        let url = format!(
+            "{}/.well-known/openid-configuration",
+            self.config.issuer_url
+        );
+        let metadata: ProviderMetadata = self.get_json(&url).await?;
+        if metadata.issuer.trim_end_matches('/') != self.config.issuer_url {
+            return Err(format!(
+                "Discovered issuer {} does not match {}",
+                metadata.issuer, self.config.issuer_url
+            ));
+        }
+
+        *self.metadata.write().await = Some(Cached {
+            value: metadata.clone(),
+            fetched_at: Instant::now(),
+        });
+        Ok(metadata)
+    }
+
+    /// Find the key an ID token was signed with, refreshing the key set once
+    /// when the key is unknown
+    async fn signing_key(&self, kid: Option<&str>) -> Result<Jwk, String> {
+        if let Some(key) =
+            Cached::fresh(&*self.jwks.read().await).and_then(|jwks| find_key(&jwks, kid))
+        {
+            return Ok(key);
+        }
+
+        let metadata = self.metadata().await?;
+        let jwks: JwkSet = self.get_json(&metadata.jwks_uri).await?;
+        let key = find_key(&jwks, kid);
+        *self.jwks.write().await = Some(Cached {
+            value: jwks,
+            fetched_at: Instant::now(),
+        });
+
+        key.ok_or_else(|| format!("No signing key {} at the provider", kid.unwrap_or("")))
+    }
+
+    async fn get_json<T: DeserializeOwned>(&self, url: &str) -> Result<T, String> {
+        let response = self
+            .http
+            .get(url)
+            .send()
+            .await
+            .map_err(|e| format!("Request to {url} failed: {e}"))?;
+        if !response.status().is_success() {
+            return Err(format!("{url} answered {}", response.status()));
+        }
+        let body = response
+            .text()
+            .await
+            .map_err(|e| format!("Failed to read {url}: {e}"))?;
+        serde_json::from_str(&body).map_err(|e| format!("Invalid response from {url}: {e}"))
+    }
+}
+
+/// Pick the key with the given id, or the only signing key when the token
+/// names none; symmetric and encryption keys are ignored
+fn find_key(jwks: &JwkSet, kid: Option<&str>) -> Option<Jwk> {
+    let mut keys = jwks.keys.iter().filter(|key| {
+        !matches!(key.algorithm, AlgorithmParameters::OctetKey(_))
+            && key.common.public_key_use != Some(PublicKeyUse::Encryption)
+    });
+    match kid {
+        Some(kid) => keys.find(|key| key.common.key_id.as_deref() == Some(kid)),
+        None => match (keys.next(), keys.next()) {
+            (Some(key), None) => Some(key),
+            _ => None,
+        },
+    }
+    .cloned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::extract::{Form, State};
+    use axum::http::StatusCode;
+    use axum::routing::{get, post};
+    use axum::{Json, Router};
+    use jsonwebtoken::{EncodingKey, Header, encode};
+    use serde_json::{Value, json};
+    use std::collections::HashMap;
+
+    // Throwaway key of the mock provider; it signs nothing outside tests
+    const TEST_RSA_KEY: &str = include_str!("../../testdata/rsa_test_key.pem");
+    const TEST_RSA_MODULUS: &str = "8AFMM_esgGW76ui-xr4Ajcjo16r_BzJOur2GGEtfvMHTbJinpdRduwLwFxuCmicmQslzHa5s8g2C2DhRrzS_wUBmW-sXrj8GchozrkwTuR3vj-_mSDfP2Fj63uYFzxRfX_aZqRNn0KGVYV6kOP42aUnWzkc31K55inKhTththWJoIme_mst8NuMTHo9gPmzWWTa07nqFRrFCejxfhlckbNjqJQ8m5sR7OQGsyLNUXVSt63egwrUVEJ8kq-IPzp3vEahiqGoinYsidplWvzMLF76JuPcbU6mk7pU0VMCVhP7PqD1EMyfS2SPT8nuViRDb_Ekj5bJ1TcVZ4mu-ziGh_w";
+    const CLIENT_ID: &str = "sub-pal-test";
+
+    #[derive(Clone)]
+    struct MockProvider {
+        issuer: String,
+        id_token: String,
+    }
+
+    /// Serve discovery, keys and a token endpoint that answers the code
+    /// `good-code` with the verifier `verifier` by `id_token`
+    async fn start_provider(id_token: impl Fn(&str) -> String) -> OidcClient {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let issuer = format!("http://{}", listener.local_addr().unwrap());
+        let provider = MockProvider {
+            id_token: id_token(&issuer),
+            issuer: issuer.clone(),
+        };
+
+        let app = Router::new()
+            .route(
+                "/.well-known/openid-configuration",
+                get(|State(provider): State<MockProvider>| async move {
+                    Json(json!({
+                        "issuer": provider.issuer,
+                        "authorization_endpoint": format!("{}/authorize", provider.issuer),
+                        "token_endpoint": format!("{}/token", provider.issuer),
+                        "jwks_uri": format!("{}/jwks", provider.issuer),
+                    }))
+                }),
+            )
+            .route(
+                "/jwks",
+                get(|| async {
+                    Json(json!({ "keys": [{
+                        "kty": "RSA", "use": "sig", "alg": "RS256", "kid": "test-key",
+                        "n": TEST_RSA_MODULUS, "e": "AQAB",
+                    }]}))
+                }),
+            )
+            .route(
+                "/token",
+                post(
+                    |State(provider): State<MockProvider>,
+                     Form(form): Form<HashMap<String, String>>| async move {
+                        let field = |name: &str| form.get(name).map(String::as_str);
+                        if field("grant_type") == Some("authorization_code")
+                            && field("code") == Some("good-code")
+                            && field("code_verifier") == Some("verifier")
+                            && field("client_id") == Some(CLIENT_ID)
+                        {
+                            Ok(Json(json!({
+                                "access_token": "access",
+                                "token_type": "Bearer",
+                                "id_token": provider.id_token,
+                            })))
+                        } else {
+                            Err((
+                                StatusCode::BAD_REQUEST,
+                                Json(json!({ "error": "invalid_grant" })),
+                            ))
+                        }
+                    },
+                ),
+            )
+            .with_state(provider);
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+
+        OidcClient::new(OidcConfig {
+            issuer_url: issuer,
+            client_id: CLIENT_ID.to_string(),
+            client_secret: None,
+            redirect_url: "http://localhost:5173/oidc/callback".to_string(),
+            scopes: vec!["openid".to_string(), "email".to_string()],
+            provider_name: "Mock".to_string(),
+            allow_signup: true,
+        })
+        .unwrap()
+    }
+
+    fn claims(issuer: &str) -> Value {
+        let now = chrono::Utc::now().timestamp();
+        json!({
+            "iss": issuer,
+            "aud": CLIENT_ID,
+            "sub": "user-1",
+            "iat": now,
+            "exp": now + 300,
+            "nonce": "nonce-1",
+            "email": "Jane@Example.com",
+            "email_verified": true,
+            "name": "Jane",
+        })
+    }
+
+    fn sign(claims: &Value) -> String {
+        let mut header = Header::new(Algorithm::RS256);
+        header.kid = Some("test-key".to_string());
+        let key = EncodingKey::from_rsa_pem(TEST_RSA_KEY.as_bytes()).unwrap();
+        encode(&header, claims, &key).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_authorization_url_carries_pkce_challenge() {
+        let client = start_provider(|issuer| sign(&claims(issuer))).await;
+
+        let url = client
+            .authorization_url("state-1", "nonce-1", "challenge-1")
+            .await
+            .unwrap();
+        let url = Url::parse(&url).unwrap();
+        let query: HashMap<_, _> = url.query_pairs().into_owned().collect();
+
+        assert_eq!(url.path(), "/authorize");
+        assert_eq!(query["response_type"], "code");
+        assert_eq!(query["client_id"], CLIENT_ID);
+        assert_eq!(query["redirect_uri"], "http://localhost:5173/oidc/callback");
+        assert_eq!(query["scope"], "openid email");
+        assert_eq!(query["state"], "state-1");
+        assert_eq!(query["nonce"], "nonce-1");
+        assert_eq!(query["code_challenge"], "challenge-1");
+        assert_eq!(query["code_challenge_method"], "S256");
+    }
+
+    #[tokio::test]
+    async fn test_exchange_code_returns_validated_identity() {
+        let client = start_provider(|issuer| sign(&claims(issuer))).await;
+
+        let identity = client
+            .exchange_code("good-code", "verifier", "nonce-1")
+            .await
+            .unwrap();
+        assert_eq!(identity.issuer, client.config().issuer_url);
+        assert_eq!(identity.subject, "user-1");
+        assert_eq!(identity.email.as_deref(), Some("jane@example.com"));
+        assert!(identity.email_verified);
+        assert_eq!(identity.name.as_deref(), Some("Jane"));
+
+        // A wrong verifier is refused by the provider, a wrong nonce by the client
+        assert!(
+            client
+                .exchange_code("good-code", "other", "nonce-1")
+                .await
+                .is_err()
+        );
+        assert!(
+            client
+                .exchange_code("good-code", "verifier", "nonce-2")
+                .await
+                .is_err()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_invalid_id_tokens_are_rejected() {
+        let client = start_provider(|issuer| sign(&claims(issuer))).await;
+        let issuer = client.config().issuer_url.clone();
+        let with = |name: &str, value: Value| {
+            let mut claims = claims(&issuer);
+            claims[name] = value;
+            claims
+        };
+
+        let valid = sign(&claims(&issuer));
+        assert!(client.validate_id_token(&valid, "nonce-1").await.is_ok());
+
+        for claims in [
+            with("aud", json!("another-client")),
+            with("iss", json!("https://evil.example.com")),
+            with("exp", json!(chrono::Utc::now().timestamp() - 3600)),
+        ] {
+            assert!(
+                client
+                    .validate_id_token(&sign(&claims), "nonce-1")
+                    .await
+                    .is_err()
+            );
+        }
+
+        // Unknown key and a symmetric signature
+        let mut header = Header::new(Algorithm::RS256);
+        header.kid = Some("other-key".to_string());
+        let key = EncodingKey::from_rsa_pem(TEST_RSA_KEY.as_bytes()).unwrap();
+        let unknown_key = encode(&header, &claims(&issuer), &key).unwrap();
+        assert!(
+            client
+                .validate_id_token(&unknown_key, "nonce-1")
+                .await
+                .is_err()
+        );
+
+        let hs256 = encode(
+            &Header::new(Algorithm::HS256),
+            &claims(&issuer),
+            &EncodingKey::from_secret(TEST_RSA_MODULUS.as_bytes()),
+        )
+        .unwrap();
+        assert!(client.validate_id_token(&hs256, "nonce-1").await.is_err());
+
+        // Tampered payload
+        let mut parts: Vec<String> = valid.split('.').map(str::to_string).collect();
+        parts[1] = sign(&with("sub", json!("user-2")))
+            .split('.')
+            .nth(1)
+            .unwrap()
+            .to_string();
+        assert!(
+            client
+                .validate_id_token(&parts.join("."), "nonce-1")
+                .await
+                .is_err()
+        );
+    }
+}
@@ -0,0 +1,314 @@
+use data_encoding::BASE64URL_NOPAD;
+use sha2::{Digest, Sha256};
+use sqlx::{PgConnection, PgPool, Row};
+use uuid::Uuid;
+
+use crate::models::{OidcAuthorization, OidcCallbackRequest};
+use crate::services::oidc_client::{OidcClient, OidcIdentity};
+use crate::services::refresh_token_service::revoke_user_refresh_tokens;
+use crate::utils::auth::{generate_opaque_token, hash_password, hash_token};
+use crate::utils::response::AppError;
+use crate::utils::user_validation::is_valid_email;
+
+/// Minutes a user has to sign in at the provider
+pub const OIDC_LOGIN_TTL_MINUTES: i64 = 10;
+
+/// Service signing users in through an OpenID Connect provider
+///
+/// A provider identity is linked to the account with the same verified email
+/// address on first sign-in, or to a new account when there is none. Later
+/// sign-ins find the account by the identity, even if the email changed.
+pub struct OidcService {
+    pool: PgPool,
+}
+
+impl OidcService {
+    /// Create a new OidcService with the given database pool
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    /// Start a login, returning the provider page to send the user to
+    ///
+    /// The state, nonce and PKCE verifier are kept until the user comes back.
+    pub async fn start_login(&self, client: &OidcClient) -> Result<OidcAuthorization, AppError> {
+        let state = generate_opaque_token();
+        let nonce = generate_opaque_token();
+        let code_verifier = generate_opaque_token();
+        let code_challenge = BASE64URL_NOPAD.encode(&Sha256::digest(code_verifier.as_bytes()));
+
+        let authorization_url = client
+            .authorization_url(&state, &nonce, &code_challenge)
+            .await
+            .map_err(|e| {
+                tracing::error!("OIDC discovery failed: {}", e);
+                AppError::internal_error(format!("OIDC discovery failed: {e}"))
+            })?;
+
+        sqlx::query("DELETE FROM oidc_login_states WHERE expires_at < CURRENT_TIMESTAMP")
+            .execute(&self.pool)
+            .await
+            .map_err(|e| {
+                AppError::database_error("OIDC login state cleanup", format!("Database error: {e}"))
+            })?;
+        sqlx::query(
+            r#"
+            INSERT INTO oidc_login_states (state_hash, nonce, code_verifier, expires_at)
+            VALUES ($1, $2, $3, CURRENT_TIMESTAMP + make_interval(mins => $4))
+            "#,
+        )
+        .bind(hash_token(&state))
+        .bind(&nonce)
+        .bind(&code_verifier)
+        .bind(OIDC_LOGIN_TTL_MINUTES as i32)
+        .execute(&self.pool)
+        .await
+        .map_err(|e| {
+            AppError::database_error("OIDC login state creation", format!("Database error: {e}"))
+        })?;
+
+        Ok(OidcAuthorization {
+            authorization_url,
+            state,
+        })
+    }
+
+    /// Finish a login with the code the provider returned, returning the id
+    /// of the signed in user
+    pub async fn complete_login(
+        &self,
+        client: &OidcClient,
+        request: OidcCallbackRequest,
+    ) -> Result<Uuid, AppError> {
+        // Each state works once
+        let login = sqlx::query(
+            r#"
+            DELETE FROM oidc_login_states
+            WHERE state_hash = $1 AND expires_at > CURRENT_TIMESTAMP
+            RETURNING nonce, code_verifier
+            "#,
+        )
+        .bind(hash_token(&request.state))
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| {
+            AppError::database_error("OIDC login state lookup", format!("Database error: {e}"))
+        })?
+        .ok_or_else(|| {
+            AppError::unauthorized_with_message(
+                "Unknown or expired OIDC login state",
+                "This sign-in link has expired. Please sign in again.",
+            )
+        })?;
+        let nonce: String = login.get("nonce");
+        let code_verifier: String = login.get("code_verifier");
+
+        let identity = client
+            .exchange_code(&request.code, &code_verifier, &nonce)
+            .await
+            .map_err(|e| {
+                tracing::warn!("OIDC login failed: {}", e);
+                AppError::unauthorized_with_message(
+                    format!("OIDC login failed: {e}"),
+                    format!(
+                        "Signing in with {} failed. Please try again.",
+                        client.config().provider_name
+                    ),
+                )
+            })?;
+
+        let mut tx = self.pool.begin().await.map_err(|e| {
+            AppError::database_error("transaction start", format!("Transaction error: {e}"))
+        })?;
+        let user_id = self.link_identity(&mut tx, client, &identity).await?;
+        tx.commit().await.map_err(|e| {
+            AppError::database_error("transaction commit", format!("Transaction error: {e}"))
+        })?;
+
+        Ok(user_id)
+    }
+
+    /// Find or create the account of a provider identity
+    async fn link_identity(
+        &self,
+        conn: &mut PgConnection,
+        client: &OidcClient,
+        identity: &OidcIdentity,
+    ) -> Result<Uuid, AppError> {
+        let linked = sqlx::query(
+            r#"
+            UPDATE user_identities
+            SET last_login_at = CURRENT_TIMESTAMP, email = COALESCE($3, email)
+            WHERE issuer = $1 AND subject = $2
+            RETURNING user_id
+            "#,
+        )
+        .bind(&identity.issuer)
+        .bind(&identity.subject)
+        .bind(&identity.email)
+        .fetch_optional(&mut *conn)
+        .await
+        .map_err(|e| AppError::database_error("identity lookup", format!("Database error: {e}")))?;
+        if let Some(row) = linked {
+            return Ok(row.get("user_id"));
+        }
+
+        // Only an address the provider vouches for may claim an account
+        let provider = &client.config().provider_name;
+        let email = identity
+            .email
+            .as_deref()
+            .filter(|email| identity.email_verified && is_valid_email(email))
+            .ok_or_else(|| {
+                AppError::unauthorized_with_message(
+                    format!("OIDC identity {} has no verified email", identity.subject),
+                    format!("Your {provider} account has no verified email address."),
+                )
+            })?;
+
+        let existing = sqlx::query(
+            r#"
+            SELECT id, email_verified_at IS NOT NULL AS verified
+            FROM users
+            WHERE lower(email) = $1
+            FOR UPDATE
+            "#,
+        )
+        .bind(email)
+        .fetch_optional(&mut *conn)
+        .await
+        .map_err(|e| AppError::database_error("user lookup", format!("Database error: {e}")))?;
+
+        let user_id = match existing {
+            Some(row) => {
+                let user_id: Uuid = row.get("id");
+                if !row.get::<bool, _>("verified") {
+                    reset_unverified_account(conn, user_id).await?;
+                }
+                tracing::info!("OIDC identity linked to existing user {}", user_id);
+                user_id
+            }
+            None if client.config().allow_signup => {
+                let user_id = create_user(conn, email, identity.name.as_deref()).await?;
+                tracing::info!("User {} created on first OIDC sign-in", user_id);
+                user_id
+            }
+            None => {
+                return Err(AppError::unauthorized_with_message(
+                    format!("No account for OIDC identity {}", identity.subject),
+                    format!(
+                        "There is no account for the email address of your {provider} account."
+                    ),
+                ));
+            }
+        };
+
+        sqlx::query(
+            r#"
+            INSERT INTO user_identities (user_id, issuer, subject, email, last_login_at)
+            VALUES ($1, $2, $3, $4, CURRENT_TIMESTAMP)
+            "#,
+        )
+        .bind(user_id)
+        .bind(&identity.issuer)
+        .bind(&identity.subject)
+        .bind(email)
+        .execute(&mut *conn)
+        .await
+        .map_err(|e| {
+            AppError::database_error("identity creation", format!("Database error: {e}"))
+        })?;
+
+        Ok(user_id)
+    }
+}
+
+/// Take over an account whose email address was never verified
+///
+/// Whoever registered it could not prove they own the address, so their
+/// password, second factor, tokens and sessions stop working; the provider
+/// proved the address belongs to the user signing in.
+async fn reset_unverified_account(conn: &mut PgConnection, user_id: Uuid) -> Result<(), AppError> {
+    let password_hash = unusable_password_hash()?;
+    sqlx::query(
+        r#"
+        UPDATE users
+        SET email_verified_at = CURRENT_TIMESTAMP, password_hash = $2, password_set = FALSE,
+            totp_secret = NULL, two_factor_enabled_at = NULL, totp_last_used_step = NULL
+        WHERE id = $1
+        "#,
+    )
+    .bind(user_id)
+    .bind(&password_hash)
+    .execute(&mut *conn)
+    .await
+    .map_err(|e| AppError::database_error("account reset", format!("Database error: {e}")))?;
+
+    for statement in [
+        "DELETE FROM two_factor_recovery_codes WHERE user_id = $1",
+        "UPDATE api_tokens SET revoked_at = CURRENT_TIMESTAMP WHERE user_id = $1 AND revoked_at IS NULL",
+    ] {
+        sqlx::query(statement)
+            .bind(user_id)
+            .execute(&mut *conn)
+            .await
+            .map_err(|e| {
+                AppError::database_error("account reset", format!("Database error: {e}"))
+            })?;
+    }
+    revoke_user_refresh_tokens(conn, user_id)
+        .await
+        .map_err(|e| AppError::database_error("account reset", format!("Database error: {e}")))?;
+
+    tracing::warn!(
+        "Unverified account {} taken over by its verified OIDC identity",
+        user_id
+    );
+    Ok(())
+}
+
+/// Create an account for a provider identity; it has no usable password
+/// until the user sets one through a password reset
+async fn create_user(
+    conn: &mut PgConnection,
+    email: &str,
+    name: Option<&str>,
+) -> Result<Uuid, AppError> {
+    let password_hash = unusable_password_hash()?;
+    let user_id: Uuid = sqlx::query(
+        r#"
+        INSERT INTO users (email, password_hash, password_set, email_verified_at)
+        VALUES ($1, $2, FALSE, CURRENT_TIMESTAMP)
+        RETURNING id
+        "#,
+    )
+    .bind(email)
+    .bind(&password_hash)
+    .fetch_one(&mut *conn)
+    .await
+    .map_err(|e| AppError::database_error("user creation", format!("User creation error: {e}")))?
+    .get("id");
+
+    sqlx::query(
+        r#"
+        INSERT INTO user_profiles (user_id, name, preferences)
+        VALUES ($1, $2, $3)
+        "#,
+    )
+    .bind(user_id)
+    .bind(name.map(str::trim).filter(|name| !name.is_empty()))
+    .bind(serde_json::Value::Object(serde_json::Map::new()))
+    .execute(&mut *conn)
+    .await
+    .map_err(|e| {
+        AppError::database_error("profile creation", format!("Profile creation error: {e}"))
+    })?;
+
+    Ok(user_id)
+}
+
+/// Hash of a random password nobody knows
+fn unusable_password_hash() -> Result<String, AppError> {
+    hash_password(&generate_opaque_token())
+        .map_err(|e| AppError::internal_error(format!("Password hashing error: {e}")))
+}
@@ -0,0 +1,339 @@
+use sqlx::{PgPool, Row};
+use std::sync::Arc;
+use uuid::Uuid;
+
+use crate::services::mailer::{EmailMessage, MailSender};
+use crate::services::refresh_token_service::revoke_user_refresh_tokens;
+use crate::utils::auth::{generate_opaque_token, hash_token};
+use crate::utils::email_template::PASSWORD_RESET_TEMPLATE;
+use crate::utils::hash_password;
+use crate::utils::response::AppError;
+
+/// Minutes a password reset link stays valid
+pub const PASSWORD_RESET_TOKEN_TTL_MINUTES: i64 = 60;
+
+/// Service handling password resets by email
+pub struct PasswordResetService {
+    pool: PgPool,
+}
+
+impl PasswordResetService {
+    /// Create a new PasswordResetService with the given database pool
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    /// Email a password reset link to the account of the given address
+    ///
+    /// The work happens in the background, so neither the response nor its
+    /// timing tells the caller whether an account with the address exists.
+    pub fn request_reset(
+        self,
+        email: String,
+        mailer: Option<Arc<dyn MailSender>>,
+        app_url: String,
+    ) {
+        tokio::spawn(async move {
+            let Some(mailer) = mailer else {
+                tracing::warn!("Email delivery is disabled, password reset email not sent");
+                return;
+            };
+
+            match self.create_reset_email(&email, &app_url).await {
+                Ok(Some(message)) => {
+                    if let Err(e) = mailer.send(&message).await {
+                        tracing::error!("Failed to send password reset email: {}", e);
+                    }
+                }
+                // Addresses typed into the form are personal data; only
+                // debug logs name them
+                Ok(None) => {
+                    tracing::info!("Password reset requested for an unknown email");
+                    tracing::debug!("Unknown email of the password reset request: {}", email)
+                }
+                Err(e) => tracing::error!("Failed to create password reset token: {}", e),
+            }
+        });
+    }
+
+    /// Create a reset token for the account of the given email and render its email
+    ///
+    /// Earlier tokens of the account stop working. Returns `None` when there
+    /// is no such account.
+    pub async fn create_reset_email(
+        &self,
+        email: &str,
+        app_url: &str,
+    ) -> Result<Option<EmailMessage>, AppError> {
+        let Some(user) = sqlx::query(
+            r#"
+            SELECT u.id, u.email, p.name
+            FROM users u
+            LEFT JOIN user_profiles p ON p.user_id = u.id
+            WHERE u.email = $1
+            "#,
+        )
+        .bind(email.trim())
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| AppError::database_error("user lookup", format!("Database error: {e}")))?
+        else {
+            return Ok(None);
+        };
+        let user_id: Uuid = user.get("id");
+
+        let mut tx = self.pool.begin().await.map_err(|e| {
+            AppError::database_error("transaction start", format!("Transaction error: {e}"))
+        })?;
+
+        sqlx::query(
+            r#"
+            UPDATE password_reset_tokens
+            SET used_at = CURRENT_TIMESTAMP
+            WHERE user_id = $1 AND used_at IS NULL
+            "#,
+        )
+        .bind(user_id)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| {
+            AppError::database_error(
+                "password reset token update",
+                format!("Database error: {e}"),
+            )
+        })?;
+
+        let token = generate_opaque_token();
+        sqlx::query(
+            r#"
+            INSERT INTO password_reset_tokens (user_id, token_hash, expires_at)
+            VALUES ($1, $2, CURRENT_TIMESTAMP + make_interval(mins => $3))
+            "#,
+        )
+        .bind(user_id)
+        .bind(hash_token(&token))
+        .bind(PASSWORD_RESET_TOKEN_TTL_MINUTES as i32)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| {
+            AppError::database_error(
+                "password reset token creation",
+                format!("Database error: {e}"),
+            )
+        })?;
+
+        tx.commit().await.map_err(|e| {
+            AppError::database_error("transaction commit", format!("Transaction error: {e}"))
+        })?;
+
+        let name = user
+            .get::<Option<String>, _>("name")
+            .filter(|name| !name.trim().is_empty())
+            .unwrap_or_else(|| "there".to_string());
+        let reset_url = format!("{app_url}/reset-password?token={token}");
+        let (text_body, html_body) = PASSWORD_RESET_TEMPLATE.render(&[
+            ("name", &name),
+            ("reset_url", &reset_url),
+            (
+                "expires_minutes",
+                &PASSWORD_RESET_TOKEN_TTL_MINUTES.to_string(),
+            ),
+        ]);
+
+        Ok(Some(EmailMessage {
+            to: user.get("email"),
+            subject: "Reset your Sub-Pal password".to_string(),
+            text_body,
+            html_body,
+        }))
+    }
+
+    /// Set a new password with a token from a reset email
+    ///
+    /// The token can be used once. All refresh tokens of the account are
+    /// revoked, so every existing session has to sign in again, and a lock
+    /// after failed logins is lifted.
+    pub async fn reset_password(&self, token: &str, new_password: &str) -> Result<(), AppError> {
+        let mut tx = self.pool.begin().await.map_err(|e| {
+            AppError::database_error("transaction start", format!("Transaction error: {e}"))
+        })?;
+
+        let user_id: Uuid = sqlx::query(
+            r#"
+            SELECT user_id
+            FROM password_reset_tokens
+            WHERE token_hash = $1 AND used_at IS NULL AND expires_at > CURRENT_TIMESTAMP
+            FOR UPDATE
+            "#,
+        )
+        .bind(hash_token(token))
+        .fetch_optional(&mut *tx)
+        .await
+        .map_err(|e| {
+            AppError::database_error(
+                "password reset token lookup",
+                format!("Database error: {e}"),
+            )
+        })?
+        .ok_or_else(|| {
+            AppError::unauthorized_with_message(
+                "Invalid or expired password reset token",
+                "This password reset link is invalid or has expired. Please request a new one.",
+            )
+        })?
+        .get("user_id");
+
+        let password_hash = hash_password(new_password)
+            .map_err(|e| AppError::internal_error(format!("Password hashing error: {e}")))?;
+
+        sqlx::query(
+            r#"
+            UPDATE users
+            SET password_hash = $2, password_set = TRUE,
+                failed_login_attempts = 0, last_failed_login_at = NULL, locked_until = NULL
+            WHERE id = $1
+            "#,
+        )
+        .bind(user_id)
+        .bind(&password_hash)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| AppError::database_error("password update", format!("Database error: {e}")))?;
+
+        sqlx::query(
+            r#"
+            UPDATE password_reset_tokens
+            SET used_at = CURRENT_TIMESTAMP
+            WHERE user_id = $1 AND used_at IS NULL
+            "#,
+        )
+        .bind(user_id)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| {
+            AppError::database_error(
+                "password reset token update",
+                format!("Database error: {e}"),
+            )
+        })?;
+
+        let revoked = revoke_user_refresh_tokens(&mut tx, user_id)
+            .await
+            .map_err(|e| {
+                AppError::database_error("refresh token revocation", format!("Database error: {e}"))
+            })?;
+
+        tx.commit().await.map_err(|e| {
+            AppError::database_error("transaction commit", format!("Transaction error: {e}"))
+        })?;
+
+        tracing::info!(
+            "Password reset for user {}, {} refresh tokens revoked",
+            user_id,
+            revoked
+        );
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::test_support::{connect, create_user, link_token};
+    use crate::utils::verify_password;
+    use chrono::{DateTime, Utc};
+    use std::env;
+
+    #[tokio::test]
+    async fn test_reset_tokens_work_once() {
+        // This test requires a valid DATABASE_URL environment variable
+        if let Ok(url) = env::var("DATABASE_URL") {
+            let pool = connect(url).await;
+            let (user_id, email) = create_user(&pool).await;
+            let service = PasswordResetService::new(pool.clone());
+
+            let unknown = service
+                .create_reset_email("nobody@example.com", "http://localhost")
+                .await
+                .unwrap();
+            assert!(unknown.is_none());
+
+            // Requesting another link disables the earlier one
+            let first = link_token(
+                &service
+                    .create_reset_email(&email, "http://localhost")
+                    .await
+                    .unwrap()
+                    .unwrap(),
+            );
+            let second = link_token(
+                &service
+                    .create_reset_email(&email, "http://localhost")
+                    .await
+                    .unwrap()
+                    .unwrap(),
+            );
+            assert!(matches!(
+                service.reset_password(&first, "new password").await,
+                Err(AppError::Unauthorized { .. })
+            ));
+
+            sqlx::query("UPDATE users SET locked_until = CURRENT_TIMESTAMP + INTERVAL '1 hour' WHERE id = $1")
+                .bind(user_id)
+                .execute(&pool)
+                .await
+                .unwrap();
+            service
+                .reset_password(&second, "new password")
+                .await
+                .unwrap();
+            assert!(matches!(
+                service.reset_password(&second, "another password").await,
+                Err(AppError::Unauthorized { .. })
+            ));
+
+            let user = sqlx::query("SELECT password_hash, locked_until FROM users WHERE id = $1")
+                .bind(user_id)
+                .fetch_one(&pool)
+                .await
+                .unwrap();
+            let password_hash: String = user.get("password_hash");
+            assert!(verify_password("new password", &password_hash).unwrap());
+            assert!(
+                user.get::<Option<DateTime<Utc>>, _>("locked_until")
+                    .is_none()
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn test_expired_reset_tokens_are_rejected() {
+        // This test requires a valid DATABASE_URL environment variable
+        if let Ok(url) = env::var("DATABASE_URL") {
+            let pool = connect(url).await;
+            let (user_id, email) = create_user(&pool).await;
+            let service = PasswordResetService::new(pool.clone());
+
+            let token = link_token(
+                &service
+                    .create_reset_email(&email, "http://localhost")
+                    .await
+                    .unwrap()
+                    .unwrap(),
+            );
+            sqlx::query(
+                "UPDATE password_reset_tokens SET expires_at = CURRENT_TIMESTAMP - INTERVAL '1 minute' WHERE user_id = $1",
+            )
+            .bind(user_id)
+            .execute(&pool)
+            .await
+            .unwrap();
+
+            assert!(matches!(
+                service.reset_password(&token, "new password").await,
+                Err(AppError::Unauthorized { .. })
+            ));
+        }
+    }
+}
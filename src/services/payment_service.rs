@@ -0,0 +1,348 @@
+use bigdecimal::BigDecimal;
+use chrono::NaiveDate;
+use serde_json::json;
+use sqlx::{PgPool, Row, postgres::PgRow};
+use uuid::Uuid;
+
+use crate::models::{
+    CreatePaymentRequest, NewSubscriptionEvent, PaginatedResponse, Pagination, PaginationQuery,
+    PaymentRecord, Subscription, SubscriptionEventType,
+};
+use crate::services::event_service::record_events;
+use crate::services::subscription_service::subscription_from_row;
+use crate::services::webhook_service::queue_subscription_webhooks;
+use crate::utils::response::AppError;
+
+/// Service for recording and listing actual subscription payments
+pub struct PaymentService {
+    pool: PgPool,
+}
+
+impl PaymentService {
+    /// Create a new PaymentService with the given database pool
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    /// List payments of a subscription, most recent cycle first
+    pub async fn list_payments(
+        &self,
+        user_id: Uuid,
+        subscription_id: Uuid,
+        query: &PaginationQuery,
+    ) -> Result<PaginatedResponse<PaymentRecord>, AppError> {
+        let exists = sqlx::query("SELECT 1 FROM subscriptions WHERE id = $1 AND user_id = $2")
+            .bind(subscription_id)
+            .bind(user_id)
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(|e| {
+                AppError::database_error("subscription lookup", format!("Database error: {e}"))
+            })?;
+        if exists.is_none() {
+            return Err(subscription_not_found(subscription_id));
+        }
+
+        let total: i64 =
+            sqlx::query_scalar("SELECT COUNT(*) FROM payment_records WHERE subscription_id = $1")
+                .bind(subscription_id)
+                .fetch_one(&self.pool)
+                .await
+                .map_err(|e| {
+                    AppError::database_error("payment count", format!("Database error: {e}"))
+                })?;
+
+        let rows = sqlx::query(
+            r#"
+            SELECT id, subscription_id, user_id, amount, currency, paid_date,
+                   cycle_start, cycle_end, note, projected, created_at
+            FROM payment_records
+            WHERE subscription_id = $1
+            ORDER BY cycle_start DESC, created_at DESC
+            LIMIT $2 OFFSET $3
+            "#,
+        )
+        .bind(subscription_id)
+        .bind(query.limit())
+        .bind(query.offset())
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| AppError::database_error("payment listing", format!("Database error: {e}")))?;
+
+        Ok(PaginatedResponse {
+            items: rows.iter().map(payment_from_row).collect(),
+            pagination: Pagination::new(total, query),
+        })
+    }
+
+    /// Record a payment against a subscription and advance its next billing date
+    pub async fn record_payment(
+        &self,
+        user_id: Uuid,
+        subscription_id: Uuid,
+        req: CreatePaymentRequest,
+        today: NaiveDate,
+    ) -> Result<PaymentRecord, AppError> {
+        let mut tx = self.pool.begin().await.map_err(|e| {
+            AppError::database_error("transaction start", format!("Transaction error: {e}"))
+        })?;
+
+        // Lock the subscription so concurrent payments advance it consistently
+        let row = sqlx::query(
+            r#"
+            SELECT id, user_id, name, description, amount,
+                   currency, billing_cycle_unit, billing_cycle_interval,
+                   start_date, next_billing_date, billing_anchor_date,
+                   status, paused_at, end_date, trial_end_date, category, color, created_at, updated_at
+            FROM subscriptions
+            WHERE id = $1 AND user_id = $2
+            FOR UPDATE
+            "#,
+        )
+        .bind(subscription_id)
+        .bind(user_id)
+        .fetch_optional(&mut *tx)
+        .await
+        .map_err(|e| {
+            AppError::database_error("subscription lookup", format!("Database error: {e}"))
+        })?
+        .ok_or_else(|| subscription_not_found(subscription_id))?;
+        let subscription = subscription_from_row(&row);
+
+        let amount = req.amount.unwrap_or_else(|| subscription.amount.clone());
+        if amount <= BigDecimal::from(0) {
+            return Err(AppError::validation_error(
+                "Payment amount must be positive",
+                "Please enter a payment amount greater than zero.",
+            ));
+        }
+
+        let paid_date = req.paid_date.unwrap_or(today);
+        if paid_date > today {
+            return Err(AppError::validation_error(
+                "Payment date cannot be in the future",
+                "Please enter the date the payment was actually made.",
+            ));
+        }
+
+        let cycle_start = req.cycle_start.unwrap_or(subscription.next_billing_date);
+        if !subscription.is_billing_date(subscription.billing_anchor_date, cycle_start) {
+            return Err(AppError::validation_error(
+                format!("{cycle_start} is not a billing date of subscription {subscription_id}"),
+                "The selected cycle does not match the subscription's billing schedule.",
+            ));
+        }
+        let cycle_end = cycle_start
+            .succ_opt()
+            .and_then(|date| {
+                subscription.billing_date_on_or_after(subscription.billing_anchor_date, date)
+            })
+            .ok_or_else(|| {
+                AppError::internal_error(format!(
+                    "Could not compute the end of the cycle starting {cycle_start}"
+                ))
+            })?;
+
+        // An actual payment replaces the scheduler's projection of the same cycle
+        sqlx::query(
+            "DELETE FROM payment_records WHERE subscription_id = $1 AND cycle_start = $2 AND projected",
+        )
+        .bind(subscription_id)
+        .bind(cycle_start)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| {
+            AppError::database_error("projected payment removal", format!("Database error: {e}"))
+        })?;
+
+        let row = sqlx::query(
+            r#"
+            INSERT INTO payment_records
+            (subscription_id, user_id, amount, currency, paid_date, cycle_start, cycle_end, note)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+            RETURNING id, subscription_id, user_id, amount, currency, paid_date,
+                      cycle_start, cycle_end, note, projected, created_at
+            "#,
+        )
+        .bind(subscription_id)
+        .bind(user_id)
+        .bind(&amount)
+        .bind(
+            req.currency
+                .unwrap_or(subscription.currency.clone())
+                .as_str(),
+        )
+        .bind(paid_date)
+        .bind(cycle_start)
+        .bind(cycle_end)
+        .bind(req.note)
+        .fetch_one(&mut *tx)
+        .await
+        .map_err(|e| match e {
+            sqlx::Error::Database(db) if db.is_unique_violation() => AppError::conflict(
+                "Payment already recorded",
+                format!("A payment for the cycle starting {cycle_start} is already recorded"),
+            ),
+            e => AppError::database_error("payment creation", format!("Database error: {e}")),
+        })?;
+        let payment = payment_from_row(&row);
+
+        // Paying the cycle that is due moves the next billing date past it and
+        // past the later cycles that were paid in advance. Paying a later
+        // cycle leaves the date alone, so the cycles before it stay due.
+        if cycle_start <= subscription.next_billing_date
+            && subscription.next_billing_date < cycle_end
+        {
+            let paid_ahead = sqlx::query(
+                r#"
+                SELECT cycle_start, cycle_end
+                FROM payment_records
+                WHERE subscription_id = $1 AND cycle_start >= $2 AND NOT projected
+                ORDER BY cycle_start
+                "#,
+            )
+            .bind(subscription_id)
+            .bind(cycle_end)
+            .fetch_all(&mut *tx)
+            .await
+            .map_err(|e| {
+                AppError::database_error("payment lookup", format!("Database error: {e}"))
+            })?;
+            let mut next_billing_date = cycle_end;
+            for row in &paid_ahead {
+                if row.get::<NaiveDate, _>("cycle_start") != next_billing_date {
+                    break;
+                }
+                next_billing_date = row.get("cycle_end");
+            }
+
+            sqlx::query("UPDATE subscriptions SET next_billing_date = $2 WHERE id = $1")
+                .bind(subscription_id)
+                .bind(next_billing_date)
+                .execute(&mut *tx)
+                .await
+                .map_err(|e| {
+                    AppError::database_error(
+                        "next billing date update",
+                        format!("Database error: {e}"),
+                    )
+                })?;
+
+            let renewed = NewSubscriptionEvent {
+                event_type: SubscriptionEventType::Renewed,
+                before: Some(json!({ "next_billing_date": subscription.next_billing_date })),
+                after: Some(json!({ "next_billing_date": next_billing_date })),
+                note: Some(format!("Payment {} recorded", payment.id)),
+            };
+            let events = vec![renewed];
+            let renewed_subscription = Subscription {
+                next_billing_date,
+                ..subscription
+            };
+            queue_subscription_webhooks(&mut tx, &renewed_subscription, &events)
+                .await
+                .map_err(|e| {
+                    AppError::database_error("webhook queueing", format!("Database error: {e}"))
+                })?;
+            record_events(&mut tx, subscription_id, user_id, events)
+                .await
+                .map_err(|e| {
+                    AppError::database_error("event recording", format!("Database error: {e}"))
+                })?;
+        }
+
+        tx.commit().await.map_err(|e| {
+            AppError::database_error(
+                "transaction commit",
+                format!("Transaction commit error: {e}"),
+            )
+        })?;
+
+        Ok(payment)
+    }
+}
+
+fn subscription_not_found(subscription_id: Uuid) -> AppError {
+    AppError::not_found(
+        "Subscription",
+        format!("Subscription with ID {subscription_id} not found"),
+    )
+}
+
+pub(crate) fn payment_from_row(row: &PgRow) -> PaymentRecord {
+    PaymentRecord {
+        id: row.get("id"),
+        subscription_id: row.get("subscription_id"),
+        user_id: row.get("user_id"),
+        amount: row.get("amount"),
+        currency: row.get::<String, _>("currency").into(),
+        paid_date: row.get("paid_date"),
+        cycle_start: row.get("cycle_start"),
+        cycle_end: row.get("cycle_end"),
+        note: row.get("note"),
+        projected: row.get("projected"),
+        created_at: row.get("created_at"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::SubscriptionService;
+    use crate::services::test_support::{connect, create_user, subscription};
+    use std::env;
+
+    fn date(year: i32, month: u32, day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(year, month, day).unwrap()
+    }
+
+    fn cycle(cycle_start: NaiveDate) -> CreatePaymentRequest {
+        CreatePaymentRequest {
+            cycle_start: Some(cycle_start),
+            ..CreatePaymentRequest::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_paying_ahead_keeps_earlier_cycles_due() {
+        // This test requires a valid DATABASE_URL environment variable
+        if let Ok(url) = env::var("DATABASE_URL") {
+            let pool = connect(url).await;
+            let (user_id, _) = create_user(&pool).await;
+            let subscriptions = SubscriptionService::new(pool.clone());
+            let created = subscriptions
+                .create_subscription(subscription(user_id, date(2025, 1, 1)))
+                .await
+                .unwrap();
+            let service = PaymentService::new(pool);
+            let today = date(2025, 1, 5);
+            let next_billing_date = async || {
+                subscriptions
+                    .get_subscription(user_id, created.id)
+                    .await
+                    .unwrap()
+                    .next_billing_date
+            };
+
+            // Cycle N + 2 is paid while cycle N is still open
+            service
+                .record_payment(user_id, created.id, cycle(date(2025, 3, 1)), today)
+                .await
+                .unwrap();
+            assert_eq!(next_billing_date().await, date(2025, 1, 1));
+
+            service
+                .record_payment(user_id, created.id, cycle(date(2025, 1, 1)), today)
+                .await
+                .unwrap();
+            assert_eq!(next_billing_date().await, date(2025, 2, 1));
+
+            // Paying cycle N + 1 skips the cycle that was paid in advance
+            service
+                .record_payment(user_id, created.id, cycle(date(2025, 2, 1)), today)
+                .await
+                .unwrap();
+            assert_eq!(next_billing_date().await, date(2025, 4, 1));
+        }
+    }
+}
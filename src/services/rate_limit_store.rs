@@ -0,0 +1,642 @@
+use async_trait::async_trait;
+use redis::Script;
+use redis::aio::ConnectionManager;
+use sqlx::{PgPool, Row};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::config::RateLimitConfig;
+use crate::config::rate_limit::{RateLimitAlgorithm, RateLimitBackend};
+
+/// Prefix of the Redis keys of rate limit counters
+const REDIS_KEY_PREFIX: &str = "rate_limit:";
+
+/// A limit on the requests counted under one key
+#[derive(Debug, Clone, PartialEq)]
+pub struct RateLimitQuota {
+    pub algorithm: RateLimitAlgorithm,
+    /// Requests allowed per window
+    pub limit: u32,
+    pub window: Duration,
+    /// How long a key is refused after exceeding the limit, `None` to refuse
+    /// only the requests over the limit
+    pub block: Option<Duration>,
+}
+
+/// The outcome of counting one request against a quota
+#[derive(Debug, Clone, PartialEq)]
+pub struct RateLimitDecision {
+    pub allowed: bool,
+    pub limit: u32,
+    /// Requests still allowed right now
+    pub remaining: u32,
+    /// Time until the full limit is available again
+    pub reset_after: Duration,
+    /// Time until a request is allowed again, set when this one was refused
+    pub retry_after: Option<Duration>,
+}
+
+/// What a store keeps per key, with times in seconds since the Unix epoch
+///
+/// All backends run the same algorithms on this state; Redis runs them in
+/// a Lua port of [`evaluate`].
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct LimitState {
+    /// Sliding window: start of the current window; token bucket: time of the last refill
+    pub timestamp: f64,
+    /// Sliding window: requests in the current window; token bucket: tokens left
+    pub value: f64,
+    /// Sliding window: requests in the previous window; unused by the token bucket
+    pub previous: f64,
+    /// End of the block after the limit was exceeded, 0 when not blocked
+    pub blocked_until: f64,
+    /// Time from which the state no longer limits anything and can be dropped
+    pub expires_at: f64,
+}
+
+/// Count a request at `now` against a quota, returning the new state of
+/// its key and the decision
+///
+/// Refused requests are not counted. A state that expired is treated like
+/// a missing one.
+pub fn evaluate(
+    quota: &RateLimitQuota,
+    state: Option<LimitState>,
+    now: f64,
+) -> (LimitState, RateLimitDecision) {
+    let limit = f64::from(quota.limit.max(1));
+    let window = quota.window.as_secs_f64().max(0.001);
+    let mut state = state
+        .filter(|state| state.expires_at > now)
+        .unwrap_or(LimitState {
+            timestamp: now,
+            value: match quota.algorithm {
+                RateLimitAlgorithm::TokenBucket => limit,
+                RateLimitAlgorithm::SlidingWindow => 0.0,
+            },
+            ..LimitState::default()
+        });
+
+    if state.blocked_until > now {
+        let wait = Duration::from_secs_f64(state.blocked_until - now);
+        return (state, refused(quota, wait));
+    }
+
+    let (allowed, remaining, reset_after, retry_after) = match quota.algorithm {
+        RateLimitAlgorithm::TokenBucket => {
+            let refill = (now - state.timestamp).max(0.0) * limit / window;
+            let mut tokens = (state.value + refill).min(limit);
+            let allowed = tokens >= 1.0;
+            if allowed {
+                tokens -= 1.0;
+            }
+            state.timestamp = now;
+            state.value = tokens;
+            let reset_after = (limit - tokens) * window / limit;
+            state.expires_at = now + reset_after;
+            let retry_after = (!allowed).then(|| (1.0 - tokens) * window / limit);
+            (allowed, tokens.floor(), reset_after, retry_after)
+        }
+        RateLimitAlgorithm::SlidingWindow => {
+            let mut elapsed = (now - state.timestamp).max(0.0);
+            if elapsed >= window {
+                let windows = (elapsed / window).floor();
+                state.previous = if windows == 1.0 { state.value } else { 0.0 };
+                state.value = 0.0;
+                state.timestamp += windows * window;
+                elapsed -= windows * window;
+            }
+            let estimate = state.previous * (1.0 - elapsed / window) + state.value;
+            let allowed = estimate + 1.0 <= limit;
+            let retry_after = if allowed {
+                state.value += 1.0;
+                None
+            } else if state.value + 1.0 <= limit {
+                // The requests of the previous window have to age out
+                Some(
+                    window * (state.previous - (limit - 1.0 - state.value)) / state.previous
+                        - elapsed,
+                )
+            } else {
+                // This window is full; wait for it to end and its requests to age out
+                Some(window - elapsed + window * (state.value - (limit - 1.0)) / state.value)
+            };
+            let remaining = if allowed { limit - estimate - 1.0 } else { 0.0 };
+            let reset_after = if state.value > 0.0 {
+                2.0 * window - elapsed
+            } else {
+                window - elapsed
+            };
+            state.expires_at = state.timestamp + 2.0 * window;
+            (allowed, remaining.floor(), reset_after, retry_after)
+        }
+    };
+
+    if !allowed && let Some(block) = quota.block {
+        state.blocked_until = now + block.as_secs_f64();
+        state.expires_at = state.expires_at.max(state.blocked_until);
+        return (state, refused(quota, block));
+    }
+
+    (
+        state,
+        RateLimitDecision {
+            allowed,
+            limit: quota.limit,
+            remaining: remaining.max(0.0) as u32,
+            reset_after: Duration::from_secs_f64(reset_after.max(0.0)),
+            retry_after: retry_after.map(|secs| Duration::from_secs_f64(secs.max(0.0))),
+        },
+    )
+}
+
+/// Decision refusing a request for the given time
+fn refused(quota: &RateLimitQuota, wait: Duration) -> RateLimitDecision {
+    RateLimitDecision {
+        allowed: false,
+        limit: quota.limit,
+        remaining: 0,
+        reset_after: wait,
+        retry_after: Some(wait),
+    }
+}
+
+/// Where rate limit counters are kept
+#[async_trait]
+pub trait RateLimitStore: Send + Sync {
+    /// Count a request under a key against a quota
+    async fn check(&self, key: &str, quota: &RateLimitQuota) -> Result<RateLimitDecision, String>;
+
+    /// Drop the counters that no longer limit anything, returning how many
+    async fn cleanup(&self) -> Result<u64, String>;
+}
+
+/// Keeps counters in this process
+#[derive(Default)]
+pub struct MemoryRateLimitStore {
+    entries: Mutex<HashMap<String, LimitState>>,
+}
+
+impl MemoryRateLimitStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+#[async_trait]
+impl RateLimitStore for MemoryRateLimitStore {
+    async fn check(&self, key: &str, quota: &RateLimitQuota) -> Result<RateLimitDecision, String> {
+        let mut entries = self.entries.lock().unwrap();
+        let (state, decision) = evaluate(quota, entries.get(key).copied(), unix_now());
+        entries.insert(key.to_string(), state);
+        Ok(decision)
+    }
+
+    async fn cleanup(&self) -> Result<u64, String> {
+        let mut entries = self.entries.lock().unwrap();
+        let now = unix_now();
+        let before = entries.len();
+        entries.retain(|_, state| state.expires_at > now);
+        Ok((before - entries.len()) as u64)
+    }
+}
+
+/// Keeps counters in the `rate_limits` table, shared by all instances
+/// using the database
+///
+/// The row of a key is locked while a request is counted, and times come
+/// from the database clock, so instances agree even when their clocks drift.
+pub struct PostgresRateLimitStore {
+    pool: PgPool,
+}
+
+impl PostgresRateLimitStore {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait]
+impl RateLimitStore for PostgresRateLimitStore {
+    async fn check(&self, key: &str, quota: &RateLimitQuota) -> Result<RateLimitDecision, String> {
+        let mut tx = self
+            .pool
+            .begin()
+            .await
+            .map_err(|e| format!("Database error: {e}"))?;
+
+        // Creating or touching the row locks it until the transaction ends
+        let row = sqlx::query(
+            r#"
+            INSERT INTO rate_limits (key) VALUES ($1)
+            ON CONFLICT (key) DO UPDATE SET key = EXCLUDED.key
+            RETURNING window_timestamp, value, previous, blocked_until, expires_at,
+                      EXTRACT(EPOCH FROM clock_timestamp())::FLOAT8 AS now
+            "#,
+        )
+        .bind(key)
+        .fetch_one(&mut *tx)
+        .await
+        .map_err(|e| format!("Database error: {e}"))?;
+
+        let (state, decision) = evaluate(
+            quota,
+            Some(LimitState {
+                timestamp: row.get("window_timestamp"),
+                value: row.get("value"),
+                previous: row.get("previous"),
+                blocked_until: row.get("blocked_until"),
+                expires_at: row.get("expires_at"),
+            }),
+            row.get("now"),
+        );
+
+        sqlx::query(
+            r#"
+            UPDATE rate_limits
+            SET window_timestamp = $2, value = $3, previous = $4, blocked_until = $5, expires_at = $6
+            WHERE key = $1
+            "#,
+        )
+        .bind(key)
+        .bind(state.timestamp)
+        .bind(state.value)
+        .bind(state.previous)
+        .bind(state.blocked_until)
+        .bind(state.expires_at)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| format!("Database error: {e}"))?;
+
+        tx.commit()
+            .await
+            .map_err(|e| format!("Database error: {e}"))?;
+        Ok(decision)
+    }
+
+    async fn cleanup(&self) -> Result<u64, String> {
+        sqlx::query(
+            "DELETE FROM rate_limits WHERE expires_at <= EXTRACT(EPOCH FROM clock_timestamp())",
+        )
+        .execute(&self.pool)
+        .await
+        .map(|result| result.rows_affected())
+        .map_err(|e| format!("Database error: {e}"))
+    }
+}
+
+/// Lua port of [`evaluate`], run atomically by Redis on the hash of a key
+///
+/// Arguments: algorithm (1 token bucket, 2 sliding window), limit, window
+/// and block in milliseconds. Returns whether the request is allowed, the
+/// remaining requests and the reset and retry times in milliseconds, -1
+/// for no retry. Times come from the Redis clock.
+const REDIS_SCRIPT: &str = r#"
+if redis.replicate_commands then redis.replicate_commands() end
+local algorithm = tonumber(ARGV[1])
+local limit = math.max(tonumber(ARGV[2]), 1)
+local window = math.max(tonumber(ARGV[3]), 1) / 1000
+local block = tonumber(ARGV[4]) / 1000
+local time = redis.call('TIME')
+local now = tonumber(time[1]) + tonumber(time[2]) / 1000000
+
+local fields = redis.call('HMGET', KEYS[1], 't', 'v', 'p', 'b', 'e')
+local t, v, p, b = tonumber(fields[1]), tonumber(fields[2]), tonumber(fields[3]), tonumber(fields[4])
+local e = tonumber(fields[5])
+if e == nil or e <= now then
+  t, p, b = now, 0, 0
+  if algorithm == 1 then v = limit else v = 0 end
+end
+
+local function save(expires_at)
+  redis.call('HSET', KEYS[1], 't', tostring(t), 'v', tostring(v), 'p', tostring(p),
+    'b', tostring(b), 'e', tostring(expires_at))
+  redis.call('PEXPIRE', KEYS[1], math.max(math.ceil((expires_at - now) * 1000), 1))
+end
+
+if b > now then
+  local wait = math.ceil((b - now) * 1000)
+  return {0, 0, wait, wait}
+end
+
+local allowed, remaining, reset_after, retry_after, expires_at
+if algorithm == 1 then
+  local tokens = math.min(v + math.max(now - t, 0) * limit / window, limit)
+  allowed = tokens >= 1
+  if allowed then tokens = tokens - 1 end
+  t, v = now, tokens
+  reset_after = (limit - tokens) * window / limit
+  expires_at = now + reset_after
+  if not allowed then retry_after = (1 - tokens) * window / limit end
+  remaining = math.floor(tokens)
+else
+  local elapsed = math.max(now - t, 0)
+  if elapsed >= window then
+    local windows = math.floor(elapsed / window)
+    if windows == 1 then p = v else p = 0 end
+    v = 0
+    t = t + windows * window
+    elapsed = elapsed - windows * window
+  end
+  local estimate = p * (1 - elapsed / window) + v
+  allowed = estimate + 1 <= limit
+  if allowed then
+    v = v + 1
+    remaining = math.floor(limit - estimate - 1)
+  else
+    remaining = 0
+    if v + 1 <= limit then
+      retry_after = window * (p - (limit - 1 - v)) / p - elapsed
+    else
+      retry_after = window - elapsed + window * (v - (limit - 1)) / v
+    end
+  end
+  if v > 0 then reset_after = 2 * window - elapsed else reset_after = window - elapsed end
+  expires_at = t + 2 * window
+end
+
+if not allowed and block > 0 then
+  b = now + block
+  save(math.max(expires_at, b))
+  local wait = math.ceil(block * 1000)
+  return {0, 0, wait, wait}
+end
+
+save(expires_at)
+local retry = -1
+if retry_after then retry = math.ceil(math.max(retry_after, 0) * 1000) end
+return {allowed and 1 or 0, math.max(remaining, 0), math.ceil(math.max(reset_after, 0) * 1000), retry}
+"#;
+
+/// Keeps counters in Redis, shared by all instances using the server
+///
+/// Each key is a hash counted by a Lua script, so a request is counted
+/// atomically, and expires on its own once it no longer limits anything.
+pub struct RedisRateLimitStore {
+    connection: ConnectionManager,
+    script: Script,
+}
+
+impl RedisRateLimitStore {
+    /// Connect to the Redis server at the given URL
+    pub async fn connect(url: &str) -> Result<Self, String> {
+        let client = redis::Client::open(url).map_err(|e| format!("Invalid REDIS_URL: {e}"))?;
+        let connection = ConnectionManager::new(client)
+            .await
+            .map_err(|e| format!("Could not connect to Redis: {e}"))?;
+        Ok(Self {
+            connection,
+            script: Script::new(REDIS_SCRIPT),
+        })
+    }
+}
+
+#[async_trait]
+impl RateLimitStore for RedisRateLimitStore {
+    async fn check(&self, key: &str, quota: &RateLimitQuota) -> Result<RateLimitDecision, String> {
+        let algorithm = match quota.algorithm {
+            RateLimitAlgorithm::TokenBucket => 1,
+            RateLimitAlgorithm::SlidingWindow => 2,
+        };
+        let mut connection = self.connection.clone();
+        let (allowed, remaining, reset_after, retry_after): (i64, i64, i64, i64) = self
+            .script
+            .key(format!("{REDIS_KEY_PREFIX}{key}"))
+            .arg(algorithm)
+            .arg(quota.limit)
+            .arg(quota.window.as_millis() as u64)
+            .arg(quota.block.map_or(0, |block| block.as_millis() as u64))
+            .invoke_async(&mut connection)
+            .await
+            .map_err(|e| format!("Redis error: {e}"))?;
+
+        Ok(RateLimitDecision {
+            allowed: allowed == 1,
+            limit: quota.limit,
+            remaining: remaining.max(0) as u32,
+            reset_after: Duration::from_millis(reset_after.max(0) as u64),
+            retry_after: (retry_after >= 0).then(|| Duration::from_millis(retry_after as u64)),
+        })
+    }
+
+    async fn cleanup(&self) -> Result<u64, String> {
+        // Keys expire in Redis
+        Ok(0)
+    }
+}
+
+/// Create the rate limit store of the configured backend
+pub async fn create_rate_limit_store(
+    config: &RateLimitConfig,
+    pool: PgPool,
+) -> Result<Arc<dyn RateLimitStore>, String> {
+    match config.backend {
+        RateLimitBackend::Memory => Ok(Arc::new(MemoryRateLimitStore::new())),
+        RateLimitBackend::Postgres => Ok(Arc::new(PostgresRateLimitStore::new(pool))),
+        RateLimitBackend::Redis => {
+            let url = config
+                .redis_url
+                .as_deref()
+                .ok_or("REDIS_URL is required when RATE_LIMIT_STORE is redis")?;
+            Ok(Arc::new(RedisRateLimitStore::connect(url).await?))
+        }
+    }
+}
+
+/// Start the background task purging counters that no longer limit anything
+pub fn spawn_rate_limit_cleanup(store: Arc<dyn RateLimitStore>, interval: Duration) {
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(interval);
+        loop {
+            ticker.tick().await;
+            match store.cleanup().await {
+                Ok(removed) => tracing::debug!("Rate limit cleanup removed {} counters", removed),
+                Err(e) => tracing::error!("Rate limit cleanup failed: {}", e),
+            }
+        }
+    });
+}
+
+/// Current time in seconds since the Unix epoch
+fn unix_now() -> f64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs_f64()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{DatabaseConfig, create_pool};
+    use std::env;
+    use uuid::Uuid;
+
+    fn quota(algorithm: RateLimitAlgorithm, limit: u32, block: Option<u64>) -> RateLimitQuota {
+        RateLimitQuota {
+            algorithm,
+            limit,
+            window: Duration::from_secs(60),
+            block: block.map(Duration::from_secs),
+        }
+    }
+
+    /// Count `count` requests at `now`, returning the last decision
+    fn run(
+        quota: &RateLimitQuota,
+        state: &mut Option<LimitState>,
+        now: f64,
+        count: u32,
+    ) -> RateLimitDecision {
+        let mut decision = None;
+        for _ in 0..count {
+            let (next, outcome) = evaluate(quota, *state, now);
+            *state = Some(next);
+            decision = Some(outcome);
+        }
+        decision.unwrap()
+    }
+
+    #[test]
+    fn test_token_bucket_allows_bursts_and_refills_evenly() {
+        let quota = quota(RateLimitAlgorithm::TokenBucket, 6, None);
+        let mut state = None;
+
+        let decision = run(&quota, &mut state, 1000.0, 6);
+        assert!(decision.allowed);
+        assert_eq!(decision.remaining, 0);
+        assert_eq!(decision.reset_after, Duration::from_secs(60));
+
+        let decision = run(&quota, &mut state, 1000.0, 1);
+        assert!(!decision.allowed);
+        assert_eq!(decision.retry_after, Some(Duration::from_secs(10)));
+
+        // One token every 10 seconds
+        let decision = run(&quota, &mut state, 1010.0, 1);
+        assert!(decision.allowed);
+        assert_eq!(decision.remaining, 0);
+        assert!(!run(&quota, &mut state, 1015.0, 1).allowed);
+
+        // The bucket is full again after a minute and the state can be dropped
+        assert_eq!(state.unwrap().expires_at, 1070.0);
+        let decision = run(&quota, &mut state, 1070.0, 1);
+        assert!(decision.allowed);
+        assert_eq!(decision.remaining, 5);
+    }
+
+    #[test]
+    fn test_sliding_window_weighs_the_previous_window() {
+        let quota = quota(RateLimitAlgorithm::SlidingWindow, 10, None);
+        let mut state = None;
+
+        let decision = run(&quota, &mut state, 1000.0, 10);
+        assert!(decision.allowed);
+        assert_eq!(decision.remaining, 0);
+        let decision = run(&quota, &mut state, 1030.0, 1);
+        assert!(!decision.allowed);
+        // The window ends at 1060, then one of its ten requests has to age out
+        assert_eq!(decision.retry_after, Some(Duration::from_secs(36)));
+
+        // Halfway into the next window half of the previous requests count
+        let decision = run(&quota, &mut state, 1090.0, 5);
+        assert!(decision.allowed);
+        assert_eq!(decision.remaining, 0);
+        let decision = run(&quota, &mut state, 1090.0, 1);
+        assert!(!decision.allowed);
+        assert_eq!(decision.retry_after, Some(Duration::from_secs(6)));
+
+        // Two windows later nothing counts
+        let decision = run(&quota, &mut state, 1180.0, 1);
+        assert!(decision.allowed);
+        assert_eq!(decision.remaining, 9);
+    }
+
+    #[test]
+    fn test_exceeding_the_limit_blocks_the_key() {
+        for algorithm in [
+            RateLimitAlgorithm::TokenBucket,
+            RateLimitAlgorithm::SlidingWindow,
+        ] {
+            let quota = quota(algorithm, 2, Some(300));
+            let mut state = None;
+
+            assert!(run(&quota, &mut state, 1000.0, 2).allowed);
+            let decision = run(&quota, &mut state, 1000.0, 1);
+            assert!(!decision.allowed);
+            assert_eq!(decision.retry_after, Some(Duration::from_secs(300)));
+
+            let decision = run(&quota, &mut state, 1200.0, 1);
+            assert!(!decision.allowed);
+            assert_eq!(decision.retry_after, Some(Duration::from_secs(100)));
+            assert_eq!(state.unwrap().expires_at, 1300.0);
+
+            assert!(run(&quota, &mut state, 1300.0, 1).allowed);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_memory_store_counts_keys_apart_and_cleans_up() {
+        let store = MemoryRateLimitStore::new();
+        let quota = RateLimitQuota {
+            window: Duration::from_millis(50),
+            ..quota(RateLimitAlgorithm::SlidingWindow, 1, None)
+        };
+
+        assert!(store.check("a", &quota).await.unwrap().allowed);
+        assert!(!store.check("a", &quota).await.unwrap().allowed);
+        assert!(store.check("b", &quota).await.unwrap().allowed);
+        assert_eq!(store.cleanup().await.unwrap(), 0);
+
+        tokio::time::sleep(Duration::from_millis(120)).await;
+        assert_eq!(store.cleanup().await.unwrap(), 2);
+        assert!(store.check("a", &quota).await.unwrap().allowed);
+    }
+
+    /// Count requests under a fresh key against a store as the shared tests do
+    async fn check_shared_store(store: &dyn RateLimitStore) {
+        for algorithm in [
+            RateLimitAlgorithm::TokenBucket,
+            RateLimitAlgorithm::SlidingWindow,
+        ] {
+            let key = format!("test:{}", Uuid::new_v4());
+            let quota = quota(algorithm, 3, Some(60));
+            for remaining in [2, 1, 0] {
+                let decision = store.check(&key, &quota).await.unwrap();
+                assert!(decision.allowed, "{algorithm:?}");
+                assert_eq!(decision.remaining, remaining, "{algorithm:?}");
+            }
+            let decision = store.check(&key, &quota).await.unwrap();
+            assert!(!decision.allowed, "{algorithm:?}");
+            assert_eq!(decision.retry_after, Some(Duration::from_secs(60)));
+            let decision = store.check(&key, &quota).await.unwrap();
+            assert!(!decision.allowed, "{algorithm:?}");
+            assert!(decision.retry_after.unwrap() <= Duration::from_secs(60));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_postgres_store() {
+        // This test requires a valid DATABASE_URL environment variable
+        if let Ok(url) = env::var("DATABASE_URL") {
+            let config = DatabaseConfig {
+                url,
+                ..DatabaseConfig::default()
+            };
+            let pool = create_pool(&config).await.unwrap();
+            let store = PostgresRateLimitStore::new(pool);
+            check_shared_store(&store).await;
+            assert!(store.cleanup().await.is_ok());
+        }
+    }
+
+    #[tokio::test]
+    async fn test_redis_store() {
+        // This test requires a local Redis server, e.g.
+        // REDIS_URL=redis://localhost:6379
+        if let Ok(url) = env::var("REDIS_URL") {
+            let store = RedisRateLimitStore::connect(&url).await.unwrap();
+            check_shared_store(&store).await;
+        }
+    }
+}
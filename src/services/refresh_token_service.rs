@@ -0,0 +1,208 @@
+use chrono::{DateTime, Utc};
+use sqlx::{PgConnection, PgPool, Row};
+use uuid::Uuid;
+
+use crate::models::TokenResponse;
+use crate::services::session_service::touch_session;
+use crate::utils::auth::{REFRESH_TOKEN_TTL_DAYS, generate_refresh_token, hash_token};
+use crate::utils::client::ClientInfo;
+use crate::utils::generate_token;
+use crate::utils::response::AppError;
+
+/// Service exchanging and revoking refresh tokens
+///
+/// Every login starts a token family. Exchanging a refresh token marks it as
+/// used and issues a new one of the same family, so each token works once.
+/// Presenting a used token again means it was copied: the whole family is
+/// revoked and the user has to sign in again. A family is the session of a
+/// device, see `SessionService`.
+pub struct RefreshTokenService {
+    pool: PgPool,
+}
+
+impl RefreshTokenService {
+    /// Create a new RefreshTokenService with the given database pool
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    /// Exchange a refresh token for a new access token and refresh token,
+    /// recording the client as the last user of the session
+    pub async fn refresh(
+        &self,
+        refresh_token: &str,
+        client: &ClientInfo,
+    ) -> Result<TokenResponse, AppError> {
+        let mut tx = self.pool.begin().await.map_err(|e| {
+            AppError::database_error("transaction start", format!("Transaction error: {e}"))
+        })?;
+
+        // Lock the token so that concurrent exchanges cannot both rotate it
+        let row = sqlx::query(
+            r#"
+            SELECT id, user_id, family_id, expires_at, used_at, revoked_at
+            FROM refresh_tokens
+            WHERE token_hash = $1
+            FOR UPDATE
+            "#,
+        )
+        .bind(hash_token(refresh_token))
+        .fetch_optional(&mut *tx)
+        .await
+        .map_err(|e| {
+            AppError::database_error("refresh token lookup", format!("Database error: {e}"))
+        })?
+        .ok_or_else(|| invalid_refresh_token("Unknown refresh token"))?;
+
+        let token_id: Uuid = row.get("id");
+        let user_id: Uuid = row.get("user_id");
+        let family_id: Uuid = row.get("family_id");
+        let expires_at: DateTime<Utc> = row.get("expires_at");
+        let used_at: Option<DateTime<Utc>> = row.get("used_at");
+        let revoked_at: Option<DateTime<Utc>> = row.get("revoked_at");
+
+        if revoked_at.is_some() {
+            return Err(invalid_refresh_token("Revoked refresh token"));
+        }
+        if used_at.is_some() {
+            tracing::warn!(
+                "Refresh token reuse detected for user {}, revoking token family {}",
+                user_id,
+                family_id
+            );
+            revoke_family(&mut tx, family_id).await.map_err(|e| {
+                AppError::database_error("refresh token revocation", format!("Database error: {e}"))
+            })?;
+            tx.commit().await.map_err(|e| {
+                AppError::database_error("transaction commit", format!("Transaction error: {e}"))
+            })?;
+            return Err(invalid_refresh_token("Reused refresh token"));
+        }
+        if expires_at <= Utc::now() {
+            return Err(invalid_refresh_token("Expired refresh token"));
+        }
+
+        sqlx::query("UPDATE refresh_tokens SET used_at = CURRENT_TIMESTAMP WHERE id = $1")
+            .bind(token_id)
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| {
+                AppError::database_error("refresh token update", format!("Database error: {e}"))
+            })?;
+        let refresh_token = issue_refresh_token(&mut tx, user_id, family_id)
+            .await
+            .map_err(|e| {
+                AppError::database_error("refresh token creation", format!("Database error: {e}"))
+            })?;
+        touch_session(&mut tx, family_id, client)
+            .await
+            .map_err(|e| {
+                AppError::database_error("session update", format!("Database error: {e}"))
+            })?;
+
+        tx.commit().await.map_err(|e| {
+            AppError::database_error("transaction commit", format!("Transaction error: {e}"))
+        })?;
+
+        let token = generate_token(user_id, family_id)
+            .map_err(|e| AppError::internal_error(format!("Token generation error: {e}")))?;
+
+        Ok(TokenResponse {
+            token,
+            refresh_token,
+        })
+    }
+
+    /// Revoke a refresh token together with the rest of its family
+    ///
+    /// Unknown or already revoked tokens are ignored, so logging out twice
+    /// is not an error.
+    pub async fn revoke(&self, refresh_token: &str) -> Result<(), AppError> {
+        sqlx::query(
+            r#"
+            UPDATE refresh_tokens
+            SET revoked_at = CURRENT_TIMESTAMP
+            WHERE revoked_at IS NULL
+              AND family_id = (SELECT family_id FROM refresh_tokens WHERE token_hash = $1)
+            "#,
+        )
+        .bind(hash_token(refresh_token))
+        .execute(&self.pool)
+        .await
+        .map_err(|e| {
+            AppError::database_error("refresh token revocation", format!("Database error: {e}"))
+        })?;
+
+        Ok(())
+    }
+}
+
+/// Issue a new refresh token of the given family, returning the token
+///
+/// The family is the id of a session from `create_session`. Only the
+/// token's hash is stored. Expired tokens of the user are purged
+/// on the way.
+pub(crate) async fn issue_refresh_token(
+    conn: &mut PgConnection,
+    user_id: Uuid,
+    family_id: Uuid,
+) -> Result<String, sqlx::Error> {
+    sqlx::query("DELETE FROM refresh_tokens WHERE user_id = $1 AND expires_at < CURRENT_TIMESTAMP")
+        .bind(user_id)
+        .execute(&mut *conn)
+        .await?;
+
+    let refresh_token = generate_refresh_token();
+    sqlx::query(
+        r#"
+        INSERT INTO refresh_tokens (user_id, family_id, token_hash, expires_at)
+        VALUES ($1, $2, $3, CURRENT_TIMESTAMP + make_interval(days => $4))
+        "#,
+    )
+    .bind(user_id)
+    .bind(family_id)
+    .bind(hash_token(&refresh_token))
+    .bind(REFRESH_TOKEN_TTL_DAYS as i32)
+    .execute(&mut *conn)
+    .await?;
+
+    Ok(refresh_token)
+}
+
+/// Revoke every refresh token of a user, signing them out everywhere
+pub(crate) async fn revoke_user_refresh_tokens(
+    conn: &mut PgConnection,
+    user_id: Uuid,
+) -> Result<u64, sqlx::Error> {
+    let result = sqlx::query(
+        r#"
+        UPDATE refresh_tokens
+        SET revoked_at = CURRENT_TIMESTAMP
+        WHERE user_id = $1 AND revoked_at IS NULL
+        "#,
+    )
+    .bind(user_id)
+    .execute(conn)
+    .await?;
+
+    Ok(result.rows_affected())
+}
+
+async fn revoke_family(conn: &mut PgConnection, family_id: Uuid) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        r#"
+        UPDATE refresh_tokens
+        SET revoked_at = CURRENT_TIMESTAMP
+        WHERE family_id = $1 AND revoked_at IS NULL
+        "#,
+    )
+    .bind(family_id)
+    .execute(conn)
+    .await?;
+
+    Ok(())
+}
+
+fn invalid_refresh_token(reason: &str) -> AppError {
+    AppError::unauthorized_with_message(reason, "Your session has expired. Please sign in again.")
+}
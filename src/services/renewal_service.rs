@@ -0,0 +1,332 @@
+use chrono::{NaiveDate, Utc};
+use serde_json::json;
+use sqlx::{ConnectOptions, Connection, PgPool};
+use tokio::task::JoinHandle;
+use tokio::time::MissedTickBehavior;
+use uuid::Uuid;
+
+use crate::config::RenewalSchedulerConfig;
+use crate::models::{NewSubscriptionEvent, Subscription, SubscriptionEventType};
+use crate::services::NotificationService;
+use crate::services::event_service::record_events;
+use crate::services::subscription_service::subscription_from_row;
+use crate::services::webhook_service::queue_subscription_webhooks;
+use crate::utils::response::AppError;
+
+/// Postgres advisory lock key held for the duration of a renewal run, so that
+/// only one instance renews subscriptions at a time
+const RENEWAL_LOCK_KEY: i64 = 0x7375_6270_616c_0001;
+
+/// Outcome of a renewal run
+#[derive(Debug, Default)]
+pub struct RenewalSummary {
+    pub renewed: usize,
+    pub payments_created: u64,
+    /// Subscriptions that could not be renewed and are tried again next run
+    pub failed: usize,
+}
+
+/// Service rolling due subscriptions forward to their next billing cycle
+pub struct RenewalService {
+    pool: PgPool,
+}
+
+impl RenewalService {
+    /// Create a new RenewalService with the given database pool
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    /// Advance every active subscription whose next billing date has passed
+    ///
+    /// Each passed cycle can optionally be recorded as a projected payment.
+    /// Every subscription is renewed in a transaction of its own, so one that
+    /// fails is logged and skipped without undoing the others. Returns `None`
+    /// without doing anything when another instance is already running the
+    /// renewals.
+    pub async fn renew_due_subscriptions(
+        &self,
+        today: NaiveDate,
+        create_payments: bool,
+    ) -> Result<Option<RenewalSummary>, AppError> {
+        // The lock is held by a connection of its own outside the pool, so
+        // that the renewals below can use every pooled connection. It is
+        // released when the connection closes, even if the run is interrupted.
+        let mut lock = self.pool.connect_options().connect().await.map_err(|e| {
+            AppError::database_error("renewal lock", format!("Connection error: {e}"))
+        })?;
+        let locked: bool = sqlx::query_scalar("SELECT pg_try_advisory_lock($1)")
+            .bind(RENEWAL_LOCK_KEY)
+            .fetch_one(&mut lock)
+            .await
+            .map_err(|e| {
+                AppError::database_error("renewal lock", format!("Database error: {e}"))
+            })?;
+        if !locked {
+            return Ok(None);
+        }
+
+        let due: Vec<Uuid> = sqlx::query_scalar(
+            r#"
+            SELECT id FROM subscriptions
+            WHERE LOWER(status) = 'active' AND next_billing_date < $1
+            ORDER BY next_billing_date
+            "#,
+        )
+        .bind(today)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| {
+            AppError::database_error("due subscription lookup", format!("Database error: {e}"))
+        })?;
+
+        let mut summary = RenewalSummary::default();
+        for subscription_id in due {
+            match self
+                .renew_subscription(subscription_id, today, create_payments)
+                .await
+            {
+                Ok(Some(payments_created)) => {
+                    summary.renewed += 1;
+                    summary.payments_created += payments_created;
+                }
+                Ok(None) => {}
+                Err(e) => {
+                    tracing::error!(
+                        "Renewal of subscription {} failed, skipped: {}",
+                        subscription_id,
+                        e
+                    );
+                    summary.failed += 1;
+                }
+            }
+        }
+
+        if let Err(e) = lock.close().await {
+            tracing::warn!("Failed to release the renewal lock: {}", e);
+        }
+
+        Ok(Some(summary))
+    }
+
+    /// Renew one subscription if it is still due, returning the number of
+    /// projected payments created, or `None` when it was not renewed
+    async fn renew_subscription(
+        &self,
+        subscription_id: Uuid,
+        today: NaiveDate,
+        create_payments: bool,
+    ) -> Result<Option<u64>, AppError> {
+        let mut tx = self.pool.begin().await.map_err(|e| {
+            AppError::database_error("transaction start", format!("Transaction error: {e}"))
+        })?;
+
+        // Checked again under the row lock: the user may have changed it since
+        let Some(row) = sqlx::query(
+            r#"
+            SELECT id, user_id, name, description, amount,
+                   currency, billing_cycle_unit, billing_cycle_interval,
+                   start_date, next_billing_date, billing_anchor_date,
+                   status, paused_at, end_date, trial_end_date, category, color, created_at, updated_at
+            FROM subscriptions
+            WHERE id = $1 AND LOWER(status) = 'active' AND next_billing_date < $2
+            FOR UPDATE
+            "#,
+        )
+        .bind(subscription_id)
+        .bind(today)
+        .fetch_optional(&mut *tx)
+        .await
+        .map_err(|e| {
+            AppError::database_error("due subscription lookup", format!("Database error: {e}"))
+        })?
+        else {
+            return Ok(None);
+        };
+
+        let subscription = subscription_from_row(&row);
+        let Some((passed, next_billing_date)) = subscription.roll_forward(today) else {
+            tracing::warn!(
+                "Could not compute the next billing date of subscription {}",
+                subscription.id
+            );
+            return Ok(None);
+        };
+
+        let mut payments_created = 0;
+        if create_payments {
+            for (i, &cycle_start) in passed.iter().enumerate() {
+                let cycle_end = passed.get(i + 1).copied().unwrap_or(next_billing_date);
+                let result = sqlx::query(
+                    r#"
+                    INSERT INTO payment_records
+                    (subscription_id, user_id, amount, currency, paid_date,
+                     cycle_start, cycle_end, note, projected)
+                    VALUES ($1, $2, $3, $4, $5, $5, $6, $7, TRUE)
+                    ON CONFLICT (subscription_id, cycle_start) DO NOTHING
+                    "#,
+                )
+                .bind(subscription.id)
+                .bind(subscription.user_id)
+                .bind(&subscription.amount)
+                .bind(subscription.currency.as_str())
+                .bind(cycle_start)
+                .bind(cycle_end)
+                .bind("Projected by the renewal scheduler")
+                .execute(&mut *tx)
+                .await
+                .map_err(|e| {
+                    AppError::database_error(
+                        "projected payment creation",
+                        format!("Database error: {e}"),
+                    )
+                })?;
+                payments_created += result.rows_affected();
+            }
+        }
+
+        sqlx::query("UPDATE subscriptions SET next_billing_date = $2 WHERE id = $1")
+            .bind(subscription.id)
+            .bind(next_billing_date)
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| {
+                AppError::database_error("next billing date update", format!("Database error: {e}"))
+            })?;
+
+        let renewed = NewSubscriptionEvent {
+            event_type: SubscriptionEventType::Renewed,
+            before: Some(json!({ "next_billing_date": subscription.next_billing_date })),
+            after: Some(json!({ "next_billing_date": next_billing_date })),
+            note: Some(format!(
+                "Renewed by the scheduler after {} passed cycle(s)",
+                passed.len()
+            )),
+        };
+        let events = vec![renewed];
+        let user_id = subscription.user_id;
+        let renewed_subscription = Subscription {
+            next_billing_date,
+            ..subscription
+        };
+        queue_subscription_webhooks(&mut tx, &renewed_subscription, &events)
+            .await
+            .map_err(|e| {
+                AppError::database_error("webhook queueing", format!("Database error: {e}"))
+            })?;
+        record_events(&mut tx, subscription_id, user_id, events)
+            .await
+            .map_err(|e| {
+                AppError::database_error("event recording", format!("Database error: {e}"))
+            })?;
+
+        tx.commit().await.map_err(|e| {
+            AppError::database_error("transaction commit", format!("Transaction error: {e}"))
+        })?;
+
+        Ok(Some(payments_created))
+    }
+}
+
+/// Start the background renewal scheduler
+///
+/// Every run renews due subscriptions and then generates renewal and trial
+/// reminders. The first run happens right away so that renewals missed while
+/// no instance was running are caught up on startup. Returns `None` when the
+/// scheduler is disabled.
+pub fn spawn_renewal_scheduler(
+    pool: PgPool,
+    config: RenewalSchedulerConfig,
+) -> Option<JoinHandle<()>> {
+    if !config.enabled {
+        tracing::info!("Renewal scheduler is disabled");
+        return None;
+    }
+
+    tracing::info!(
+        "Starting renewal scheduler - interval: {:?}, create payments: {}",
+        config.interval,
+        config.create_payments
+    );
+
+    Some(tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(config.interval);
+        ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+        loop {
+            ticker.tick().await;
+
+            let renewal_service = RenewalService::new(pool.clone());
+            match renewal_service
+                .renew_due_subscriptions(Utc::now().naive_utc().date(), config.create_payments)
+                .await
+            {
+                Ok(Some(summary)) => tracing::info!(
+                    "Renewal run completed: {} subscription(s) renewed, {} projected payment(s) created, {} failed",
+                    summary.renewed,
+                    summary.payments_created,
+                    summary.failed
+                ),
+                Ok(None) => {
+                    tracing::info!("Renewal run skipped: another instance holds the renewal lock")
+                }
+                Err(e) => tracing::error!("Renewal run failed: {}", e),
+            }
+
+            // Reminders are deduplicated, so every instance may generate them
+            let notification_service = NotificationService::new(pool.clone());
+            match notification_service
+                .generate_reminders(Utc::now().naive_utc().date(), None)
+                .await
+            {
+                Ok(created) => tracing::info!(
+                    "Reminder run completed: {} notification(s) created",
+                    created
+                ),
+                Err(e) => tracing::error!("Reminder run failed: {}", e),
+            }
+        }
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{DatabaseConfig, create_pool};
+    use crate::services::SubscriptionService;
+    use crate::services::test_support::{create_user, subscription};
+    use chrono::Days;
+    use std::env;
+
+    #[tokio::test]
+    async fn test_renewals_run_on_a_single_connection() {
+        // This test requires a valid DATABASE_URL environment variable
+        if let Ok(url) = env::var("DATABASE_URL") {
+            let config = DatabaseConfig {
+                url,
+                max_connections: 1,
+                ..DatabaseConfig::default()
+            };
+            let pool = create_pool(&config).await.unwrap();
+            let (user_id, _) = create_user(&pool).await;
+            let today = Utc::now().naive_utc().date();
+            let due = SubscriptionService::new(pool.clone())
+                .create_subscription(subscription(user_id, today - Days::new(40)))
+                .await
+                .unwrap();
+
+            let summary = RenewalService::new(pool.clone())
+                .renew_due_subscriptions(today, false)
+                .await
+                .unwrap()
+                .unwrap();
+            assert!(summary.renewed >= 1);
+
+            let renewed = SubscriptionService::new(pool)
+                .get_subscription(user_id, due.id)
+                .await
+                .unwrap();
+            assert!(renewed.next_billing_date >= today);
+        }
+    }
+}
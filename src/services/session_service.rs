@@ -0,0 +1,430 @@
+use chrono::{DateTime, Utc};
+use sqlx::{PgConnection, PgPool, Row};
+use std::sync::Arc;
+use uuid::Uuid;
+
+use crate::models::{AuthResponse, Session};
+use crate::services::mailer::{EmailMessage, MailSender};
+use crate::services::refresh_token_service::revoke_user_refresh_tokens;
+use crate::utils::auth::hash_token;
+use crate::utils::client::ClientInfo;
+use crate::utils::email_template::NEW_DEVICE_TEMPLATE;
+use crate::utils::response::AppError;
+
+/// Service listing and signing out the devices a user is signed in on
+///
+/// A session is the refresh token family of one login. It stays active
+/// while it has a refresh token that was neither used, revoked nor expired.
+/// Signing out a session revokes its refresh tokens, after which the access
+/// tokens issued to it are rejected as well.
+pub struct SessionService {
+    pool: PgPool,
+}
+
+impl SessionService {
+    /// Create a new SessionService with the given database pool
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    /// List the active sessions of a user, most recently seen first
+    ///
+    /// `current` is the session of the request, which is marked in the list.
+    pub async fn list_sessions(
+        &self,
+        user_id: Uuid,
+        current: Option<Uuid>,
+    ) -> Result<Vec<Session>, AppError> {
+        let rows = sqlx::query(
+            r#"
+            SELECT s.id, s.user_agent, s.ip_address, s.created_at, s.last_seen_at
+            FROM user_sessions s
+            WHERE s.user_id = $1
+              AND EXISTS (
+                  SELECT 1 FROM refresh_tokens t
+                  WHERE t.family_id = s.id AND t.used_at IS NULL AND t.revoked_at IS NULL
+                    AND t.expires_at > CURRENT_TIMESTAMP
+              )
+            ORDER BY s.last_seen_at DESC
+            "#,
+        )
+        .bind(user_id)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| AppError::database_error("session listing", format!("Database error: {e}")))?;
+
+        Ok(rows
+            .iter()
+            .map(|row| {
+                let id: Uuid = row.get("id");
+                Session {
+                    id,
+                    user_agent: row.get("user_agent"),
+                    ip_address: row.get("ip_address"),
+                    created_at: row.get("created_at"),
+                    last_seen_at: row.get("last_seen_at"),
+                    current: current == Some(id),
+                }
+            })
+            .collect())
+    }
+
+    /// Sign out one session of a user
+    pub async fn revoke_session(&self, user_id: Uuid, session_id: Uuid) -> Result<(), AppError> {
+        let result = sqlx::query(
+            r#"
+            UPDATE refresh_tokens
+            SET revoked_at = CURRENT_TIMESTAMP
+            WHERE family_id = $1 AND user_id = $2 AND revoked_at IS NULL
+              AND expires_at > CURRENT_TIMESTAMP
+            "#,
+        )
+        .bind(session_id)
+        .bind(user_id)
+        .execute(&self.pool)
+        .await
+        .map_err(|e| {
+            AppError::database_error("session revocation", format!("Database error: {e}"))
+        })?;
+
+        if result.rows_affected() == 0 {
+            return Err(AppError::not_found(
+                "Session",
+                format!("Session {session_id} not found"),
+            ));
+        }
+
+        tracing::info!("Session {} signed out by user {}", session_id, user_id);
+        Ok(())
+    }
+
+    /// Sign out every session of a user, including the current one
+    pub async fn revoke_all_sessions(&self, user_id: Uuid) -> Result<(), AppError> {
+        let mut conn = self.pool.acquire().await.map_err(|e| {
+            AppError::database_error("connection acquire", format!("Database error: {e}"))
+        })?;
+        let revoked = revoke_user_refresh_tokens(&mut conn, user_id)
+            .await
+            .map_err(|e| {
+                AppError::database_error("session revocation", format!("Database error: {e}"))
+            })?;
+
+        tracing::info!(
+            "User {} signed out everywhere, {} refresh tokens revoked",
+            user_id,
+            revoked
+        );
+        Ok(())
+    }
+
+    /// Email the user in the background when a login came from a device
+    /// none of their other sessions used
+    ///
+    /// Devices are told apart by their user agent. The first login of an
+    /// account and logins without a user agent are not reported.
+    pub fn notify_new_device(
+        self,
+        auth: &AuthResponse,
+        mailer: Option<Arc<dyn MailSender>>,
+        app_url: String,
+    ) {
+        let refresh_token = auth.refresh_token.clone();
+        let email = auth.user.email.clone();
+        let name = auth.user.name.clone();
+        tokio::spawn(async move {
+            let Some(mailer) = mailer else {
+                return;
+            };
+
+            match self
+                .create_new_device_email(&refresh_token, &email, name, &app_url)
+                .await
+            {
+                Ok(Some(message)) => {
+                    if let Err(e) = mailer.send(&message).await {
+                        tracing::error!("Failed to send new device email: {}", e);
+                    }
+                }
+                Ok(None) => {}
+                Err(e) => tracing::error!("Failed to check for a new device: {}", e),
+            }
+        });
+    }
+
+    /// Render the new device email for the session of a refresh token,
+    /// returning `None` when the device is known
+    async fn create_new_device_email(
+        &self,
+        refresh_token: &str,
+        email: &str,
+        name: Option<String>,
+        app_url: &str,
+    ) -> Result<Option<EmailMessage>, AppError> {
+        let Some(row) = sqlx::query(
+            r#"
+            SELECT s.user_agent, s.ip_address, s.created_at,
+                   EXISTS (
+                       SELECT 1 FROM user_sessions o
+                       WHERE o.user_id = s.user_id AND o.id <> s.id
+                   ) AS has_other_sessions,
+                   EXISTS (
+                       SELECT 1 FROM user_sessions o
+                       WHERE o.user_id = s.user_id AND o.id <> s.id
+                         AND o.user_agent = s.user_agent
+                   ) AS known_device
+            FROM refresh_tokens t
+            JOIN user_sessions s ON s.id = t.family_id
+            WHERE t.token_hash = $1
+            "#,
+        )
+        .bind(hash_token(refresh_token))
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| AppError::database_error("session lookup", format!("Database error: {e}")))?
+        else {
+            return Ok(None);
+        };
+
+        let Some(user_agent) = row.get::<Option<String>, _>("user_agent") else {
+            return Ok(None);
+        };
+        if !row.get::<bool, _>("has_other_sessions") || row.get::<bool, _>("known_device") {
+            return Ok(None);
+        }
+
+        let name = name
+            .filter(|name| !name.trim().is_empty())
+            .unwrap_or_else(|| "there".to_string());
+        let ip_address = row
+            .get::<Option<String>, _>("ip_address")
+            .unwrap_or_else(|| "unknown".to_string());
+        let signed_in_at = row
+            .get::<DateTime<Utc>, _>("created_at")
+            .format("%Y-%m-%d %H:%M UTC")
+            .to_string();
+        let sessions_url = format!("{app_url}/profile");
+        let (text_body, html_body) = NEW_DEVICE_TEMPLATE.render(&[
+            ("name", &name),
+            ("device", &user_agent),
+            ("ip_address", &ip_address),
+            ("signed_in_at", &signed_in_at),
+            ("sessions_url", &sessions_url),
+        ]);
+
+        Ok(Some(EmailMessage {
+            to: email.to_string(),
+            subject: "New sign-in to your Sub-Pal account".to_string(),
+            text_body,
+            html_body,
+        }))
+    }
+}
+
+/// Whether a session of a user is still signed in, checked for every
+/// request made with one of its access tokens
+///
+/// A session is signed in while it has a refresh token that was neither
+/// used, revoked nor expired, as in `list_sessions`.
+pub(crate) async fn is_session_active(
+    pool: &PgPool,
+    user_id: Uuid,
+    session_id: Uuid,
+) -> Result<bool, sqlx::Error> {
+    sqlx::query_scalar(
+        r#"
+        SELECT EXISTS (
+            SELECT 1 FROM refresh_tokens
+            WHERE family_id = $1 AND user_id = $2 AND used_at IS NULL AND revoked_at IS NULL
+              AND expires_at > CURRENT_TIMESTAMP
+        )
+        "#,
+    )
+    .bind(session_id)
+    .bind(user_id)
+    .fetch_one(pool)
+    .await
+}
+
+/// Start a session for a login from the given client, returning its id
+///
+/// The id is the family of the session's refresh tokens. Sessions whose
+/// refresh tokens all expired are purged on the way.
+pub(crate) async fn create_session(
+    conn: &mut PgConnection,
+    user_id: Uuid,
+    client: &ClientInfo,
+) -> Result<Uuid, sqlx::Error> {
+    sqlx::query(
+        r#"
+        DELETE FROM user_sessions s
+        WHERE s.user_id = $1
+          AND NOT EXISTS (
+              SELECT 1 FROM refresh_tokens t
+              WHERE t.family_id = s.id AND t.expires_at >= CURRENT_TIMESTAMP
+          )
+        "#,
+    )
+    .bind(user_id)
+    .execute(&mut *conn)
+    .await?;
+
+    let session_id = sqlx::query(
+        r#"
+        INSERT INTO user_sessions (user_id, user_agent, ip_address)
+        VALUES ($1, $2, $3)
+        RETURNING id
+        "#,
+    )
+    .bind(user_id)
+    .bind(&client.user_agent)
+    .bind(&client.ip_address)
+    .fetch_one(&mut *conn)
+    .await?
+    .get("id");
+
+    Ok(session_id)
+}
+
+/// Record that a session was used by the given client
+pub(crate) async fn touch_session(
+    conn: &mut PgConnection,
+    session_id: Uuid,
+    client: &ClientInfo,
+) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        r#"
+        UPDATE user_sessions
+        SET last_seen_at = CURRENT_TIMESTAMP,
+            user_agent = COALESCE($2, user_agent),
+            ip_address = COALESCE($3, ip_address)
+        WHERE id = $1
+        "#,
+    )
+    .bind(session_id)
+    .bind(&client.user_agent)
+    .bind(&client.ip_address)
+    .execute(&mut *conn)
+    .await?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::RefreshTokenService;
+    use crate::services::refresh_token_service::issue_refresh_token;
+    use crate::services::test_support::{connect, create_user};
+    use crate::utils::auth::{extract_auth, generate_token};
+    use axum::http::{HeaderMap, header::AUTHORIZATION};
+    use std::env;
+
+    /// Sign a user in, returning the session id and its refresh token
+    async fn sign_in(pool: &PgPool, user_id: Uuid) -> (Uuid, String) {
+        let mut conn = pool.acquire().await.unwrap();
+        let client = ClientInfo {
+            user_agent: Some("test".to_string()),
+            ip_address: Some("203.0.113.7".to_string()),
+        };
+        let session_id = create_session(&mut conn, user_id, &client).await.unwrap();
+        let refresh_token = issue_refresh_token(&mut conn, user_id, session_id)
+            .await
+            .unwrap();
+        (session_id, refresh_token)
+    }
+
+    #[tokio::test]
+    async fn test_revoked_sessions_are_signed_out() {
+        // This test requires a valid DATABASE_URL environment variable
+        if let Ok(url) = env::var("DATABASE_URL") {
+            let pool = connect(url).await;
+            let (user_id, _) = create_user(&pool).await;
+            let (other_user_id, _) = create_user(&pool).await;
+            let service = SessionService::new(pool.clone());
+
+            let (current, _) = sign_in(&pool, user_id).await;
+            let (revoked, refresh_token) = sign_in(&pool, user_id).await;
+            let sessions = service.list_sessions(user_id, Some(current)).await.unwrap();
+            assert_eq!(sessions.len(), 2);
+            assert!(sessions.iter().any(|s| s.id == current && s.current));
+
+            // Sessions of other users cannot be signed out
+            assert!(matches!(
+                service.revoke_session(other_user_id, revoked).await,
+                Err(AppError::NotFound { .. })
+            ));
+
+            service.revoke_session(user_id, revoked).await.unwrap();
+            let sessions = service.list_sessions(user_id, Some(current)).await.unwrap();
+            assert_eq!(sessions.len(), 1);
+            assert_eq!(sessions[0].id, current);
+            assert!(matches!(
+                service.revoke_session(user_id, revoked).await,
+                Err(AppError::NotFound { .. })
+            ));
+
+            let error = RefreshTokenService::new(pool)
+                .refresh(&refresh_token, &ClientInfo::default())
+                .await
+                .unwrap_err();
+            assert!(error.to_string().contains("Revoked refresh token"));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_all_sessions_can_be_signed_out() {
+        // This test requires a valid DATABASE_URL environment variable
+        if let Ok(url) = env::var("DATABASE_URL") {
+            let pool = connect(url).await;
+            let (user_id, _) = create_user(&pool).await;
+            let service = SessionService::new(pool.clone());
+
+            sign_in(&pool, user_id).await;
+            sign_in(&pool, user_id).await;
+            service.revoke_all_sessions(user_id).await.unwrap();
+
+            assert!(
+                service
+                    .list_sessions(user_id, None)
+                    .await
+                    .unwrap()
+                    .is_empty()
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn test_access_tokens_of_signed_out_sessions_are_rejected() {
+        // This test requires a valid DATABASE_URL environment variable
+        if let Ok(url) = env::var("DATABASE_URL") {
+            let pool = connect(url).await;
+            let (user_id, _) = create_user(&pool).await;
+            let service = SessionService::new(pool.clone());
+            let authenticate = async |session_id: Uuid| {
+                let token = generate_token(user_id, session_id).unwrap();
+                let mut headers = HeaderMap::new();
+                headers.insert(AUTHORIZATION, format!("Bearer {token}").parse().unwrap());
+                extract_auth(&headers, &pool)
+                    .await
+                    .ok()
+                    .map(|auth| auth.user_id)
+            };
+
+            let (current, _) = sign_in(&pool, user_id).await;
+            let (revoked, _) = sign_in(&pool, user_id).await;
+            assert_eq!(authenticate(revoked).await, Some(user_id));
+
+            service.revoke_session(user_id, revoked).await.unwrap();
+            assert_eq!(authenticate(revoked).await, None);
+            assert_eq!(authenticate(current).await, Some(user_id));
+
+            // A session of another user is no session of this one
+            let (other_user_id, _) = create_user(&pool).await;
+            let (other, _) = sign_in(&pool, other_user_id).await;
+            assert_eq!(authenticate(other).await, None);
+
+            service.revoke_all_sessions(user_id).await.unwrap();
+            assert_eq!(authenticate(current).await, None);
+        }
+    }
+}
@@ -0,0 +1,466 @@
+use chrono::NaiveDate;
+use sqlx::{PgPool, Row};
+use uuid::Uuid;
+
+use crate::models::{
+    CategoryBreakdown, CurrencyTotals, MonthlyPayments, StatusBreakdown, SubscriptionStatistics,
+    TopSubscription, UpcomingRenewal,
+};
+use crate::utils::response::AppError;
+
+/// SQL expression normalizing a subscription's amount to a yearly amount.
+///
+/// Day and week cycles count from the mean Gregorian year of 365.25 days,
+/// so a weekly amount is billed 365.25 / 7 times a year. Kept in NUMERIC
+/// and divided last so no precision is lost before the final rounding.
+const YEARLY_AMOUNT_SQL: &str = r#"
+    (amount * CASE billing_cycle_unit
+        WHEN 'day' THEN 365.25
+        WHEN 'week' THEN 365.25
+        WHEN 'month' THEN 12
+        ELSE 1
+    END / (billing_cycle_interval * CASE billing_cycle_unit WHEN 'week' THEN 7 ELSE 1 END))
+"#;
+
+/// Service computing subscription statistics in SQL
+pub struct StatisticsService {
+    pool: PgPool,
+}
+
+impl StatisticsService {
+    /// Create a new StatisticsService with the given database pool
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    /// Compute all statistics for a user
+    pub async fn get_statistics(
+        &self,
+        user_id: Uuid,
+        top: i64,
+        today: NaiveDate,
+        upcoming_days: i32,
+        history_months: i32,
+    ) -> Result<SubscriptionStatistics, AppError> {
+        let (totals, by_category, by_status) = self.spend_breakdowns(user_id).await?;
+
+        Ok(SubscriptionStatistics {
+            totals,
+            by_category,
+            by_status,
+            top_subscriptions: self.top_subscriptions(user_id, top).await?,
+            upcoming_renewals: self
+                .upcoming_renewals(user_id, today, upcoming_days)
+                .await?,
+            payment_history: self.payment_history(user_id, today, history_months).await?,
+        })
+    }
+
+    /// Normalized spend per currency, per category and per status, from one
+    /// pass over the user's subscriptions
+    ///
+    /// Totals and categories cover active subscriptions, statuses cover all.
+    /// The grouping sets are told apart by `GROUPING(category, status)`.
+    async fn spend_breakdowns(
+        &self,
+        user_id: Uuid,
+    ) -> Result<
+        (
+            Vec<CurrencyTotals>,
+            Vec<CategoryBreakdown>,
+            Vec<StatusBreakdown>,
+        ),
+        AppError,
+    > {
+        let rows = sqlx::query(&format!(
+            r#"
+            WITH normalized AS (
+                SELECT currency,
+                       COALESCE(NULLIF(category, ''), 'Uncategorized') AS category,
+                       LOWER(status) AS status,
+                       {YEARLY_AMOUNT_SQL} AS yearly_amount
+                FROM subscriptions
+                WHERE user_id = $1
+            )
+            SELECT currency, category, status,
+                   GROUPING(category, status) AS grouping,
+                   COUNT(*) AS count,
+                   COUNT(*) FILTER (WHERE status = 'active') AS active_count,
+                   ROUND(SUM(yearly_amount) / 12, 2) AS monthly_spend,
+                   ROUND(SUM(yearly_amount) FILTER (WHERE status = 'active') / 12, 2)
+                       AS active_monthly_spend,
+                   ROUND(SUM(yearly_amount) FILTER (WHERE status = 'active'), 2)
+                       AS active_yearly_spend
+            FROM normalized
+            GROUP BY GROUPING SETS ((currency), (currency, category), (currency, status))
+            ORDER BY currency, active_monthly_spend DESC NULLS LAST, status
+            "#
+        ))
+        .bind(user_id)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| {
+            AppError::database_error("statistics breakdowns", format!("Database error: {e}"))
+        })?;
+
+        let mut totals = Vec::new();
+        let mut by_category = Vec::new();
+        let mut by_status = Vec::new();
+        for row in rows {
+            let currency = row.get::<String, _>("currency").into();
+            let active_count: i64 = row.get("active_count");
+            match row.get::<i32, _>("grouping") {
+                // Grouped by status
+                2 => by_status.push(StatusBreakdown {
+                    currency,
+                    status: row.get::<String, _>("status").into(),
+                    count: row.get("count"),
+                    monthly_spend: row.get("monthly_spend"),
+                }),
+                _ if active_count == 0 => {}
+                // Grouped by category
+                1 => by_category.push(CategoryBreakdown {
+                    currency,
+                    category: row.get("category"),
+                    count: active_count,
+                    monthly_spend: row.get("active_monthly_spend"),
+                }),
+                _ => totals.push(CurrencyTotals {
+                    currency,
+                    active_count,
+                    monthly_spend: row.get("active_monthly_spend"),
+                    yearly_spend: row.get("active_yearly_spend"),
+                }),
+            }
+        }
+
+        Ok((totals, by_category, by_status))
+    }
+
+    /// The `top` most expensive active subscriptions per currency, by monthly amount
+    async fn top_subscriptions(
+        &self,
+        user_id: Uuid,
+        top: i64,
+    ) -> Result<Vec<TopSubscription>, AppError> {
+        let rows = sqlx::query(&format!(
+            r#"
+            SELECT id, name, category, currency, amount, monthly_amount
+            FROM (
+                SELECT id, name, category, currency, amount,
+                       ROUND({YEARLY_AMOUNT_SQL} / 12, 2) AS monthly_amount,
+                       ROW_NUMBER() OVER (
+                           PARTITION BY currency
+                           ORDER BY {YEARLY_AMOUNT_SQL} DESC, name
+                       ) AS rank
+                FROM subscriptions
+                WHERE user_id = $1 AND LOWER(status) = 'active'
+            ) ranked
+            WHERE rank <= $2
+            ORDER BY currency, rank
+            "#
+        ))
+        .bind(user_id)
+        .bind(top)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| {
+            AppError::database_error(
+                "statistics top subscriptions",
+                format!("Database error: {e}"),
+            )
+        })?;
+
+        Ok(rows
+            .into_iter()
+            .map(|row| TopSubscription {
+                id: row.get("id"),
+                name: row.get("name"),
+                category: row.get("category"),
+                currency: row.get::<String, _>("currency").into(),
+                amount: row.get("amount"),
+                monthly_amount: row.get("monthly_amount"),
+            })
+            .collect())
+    }
+
+    /// Active subscriptions renewing between today and `days` days from now
+    async fn upcoming_renewals(
+        &self,
+        user_id: Uuid,
+        today: NaiveDate,
+        days: i32,
+    ) -> Result<Vec<UpcomingRenewal>, AppError> {
+        let rows = sqlx::query(
+            r#"
+            SELECT id, name, currency, amount, next_billing_date,
+                   (next_billing_date - $2::date) AS days_until
+            FROM subscriptions
+            WHERE user_id = $1
+              AND LOWER(status) = 'active'
+              AND next_billing_date >= $2::date
+              AND next_billing_date <= $2::date + $3::integer
+            ORDER BY next_billing_date, name
+            "#,
+        )
+        .bind(user_id)
+        .bind(today)
+        .bind(days)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| {
+            AppError::database_error(
+                "statistics upcoming renewals",
+                format!("Database error: {e}"),
+            )
+        })?;
+
+        Ok(rows
+            .into_iter()
+            .map(|row| UpcomingRenewal {
+                id: row.get("id"),
+                name: row.get("name"),
+                currency: row.get::<String, _>("currency").into(),
+                amount: row.get("amount"),
+                next_billing_date: row.get("next_billing_date"),
+                days_until: row.get("days_until"),
+            })
+            .collect())
+    }
+
+    /// Actual payments per month and currency over the last `months` months
+    ///
+    /// Projected payments recorded by the renewal scheduler are left out;
+    /// they were assumed, not paid.
+    async fn payment_history(
+        &self,
+        user_id: Uuid,
+        today: NaiveDate,
+        months: i32,
+    ) -> Result<Vec<MonthlyPayments>, AppError> {
+        let rows = sqlx::query(
+            r#"
+            SELECT currency,
+                   date_trunc('month', paid_date)::date AS month,
+                   COUNT(*) AS count,
+                   SUM(amount) AS total
+            FROM payment_records
+            WHERE user_id = $1
+              AND NOT projected
+              AND paid_date >= (date_trunc('month', $2::date)
+                                - make_interval(months => $3 - 1))::date
+              AND paid_date <= $2::date
+            GROUP BY currency, date_trunc('month', paid_date)
+            ORDER BY month, currency
+            "#,
+        )
+        .bind(user_id)
+        .bind(today)
+        .bind(months)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| {
+            AppError::database_error("statistics payment history", format!("Database error: {e}"))
+        })?;
+
+        Ok(rows
+            .into_iter()
+            .map(|row| MonthlyPayments {
+                currency: row.get::<String, _>("currency").into(),
+                month: row.get("month"),
+                count: row.get("count"),
+                total: row.get("total"),
+            })
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::subscription::{BillingCycleUnit, Currency, SubscriptionStatus};
+    use crate::services::SubscriptionService;
+    use crate::services::test_support::{connect, create_user, subscription};
+    use bigdecimal::BigDecimal;
+    use std::env;
+    use std::str::FromStr;
+
+    fn decimal(value: &str) -> BigDecimal {
+        BigDecimal::from_str(value).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_spend_is_normalized_per_currency() {
+        // This test requires a valid DATABASE_URL environment variable
+        if let Ok(url) = env::var("DATABASE_URL") {
+            let pool = connect(url).await;
+            let (user_id, _) = create_user(&pool).await;
+            let subscription_service = SubscriptionService::new(pool.clone());
+            let start = NaiveDate::from_ymd_opt(2025, 1, 1).unwrap();
+
+            for (name, amount, unit, interval, currency, category, status) in [
+                (
+                    "Weekly",
+                    "7",
+                    BillingCycleUnit::Week,
+                    1,
+                    Currency::Usd,
+                    "Food",
+                    SubscriptionStatus::Active,
+                ),
+                (
+                    "Monthly",
+                    "10",
+                    BillingCycleUnit::Month,
+                    1,
+                    Currency::Usd,
+                    "Video",
+                    SubscriptionStatus::Active,
+                ),
+                (
+                    "Yearly",
+                    "120",
+                    BillingCycleUnit::Year,
+                    1,
+                    Currency::Usd,
+                    "Video",
+                    SubscriptionStatus::Active,
+                ),
+                (
+                    "Every other day",
+                    "1",
+                    BillingCycleUnit::Day,
+                    2,
+                    Currency::Cny,
+                    "",
+                    SubscriptionStatus::Active,
+                ),
+                (
+                    "Paused",
+                    "30",
+                    BillingCycleUnit::Month,
+                    3,
+                    Currency::Usd,
+                    "Video",
+                    SubscriptionStatus::Paused,
+                ),
+            ] {
+                let mut new = subscription(user_id, start);
+                new.name = name.to_string();
+                new.amount = decimal(amount);
+                new.billing_cycle_unit = unit;
+                new.billing_cycle_interval = interval;
+                new.currency = currency;
+                new.category = Some(category.to_string());
+                if status == SubscriptionStatus::Paused {
+                    new.paused_at = Some(start);
+                }
+                new.status = status;
+                subscription_service.create_subscription(new).await.unwrap();
+            }
+
+            let stats = StatisticsService::new(pool)
+                .get_statistics(user_id, 5, start, 30, 12)
+                .await
+                .unwrap();
+
+            // 7 a week is 365.25 a year, 10 a month and 120 a year are 120 each
+            let totals: Vec<_> = stats
+                .totals
+                .iter()
+                .map(|t| {
+                    (
+                        t.currency.clone(),
+                        t.active_count,
+                        t.monthly_spend.clone(),
+                        t.yearly_spend.clone(),
+                    )
+                })
+                .collect();
+            assert_eq!(
+                totals,
+                vec![
+                    (Currency::Cny, 1, decimal("15.22"), decimal("182.63")),
+                    (Currency::Usd, 3, decimal("50.44"), decimal("605.25")),
+                ]
+            );
+
+            let by_category: Vec<_> = stats
+                .by_category
+                .iter()
+                .map(|c| {
+                    (
+                        c.currency.clone(),
+                        c.category.as_str(),
+                        c.count,
+                        c.monthly_spend.clone(),
+                    )
+                })
+                .collect();
+            assert_eq!(
+                by_category,
+                vec![
+                    (Currency::Cny, "Uncategorized", 1, decimal("15.22")),
+                    (Currency::Usd, "Food", 1, decimal("30.44")),
+                    (Currency::Usd, "Video", 2, decimal("20.00")),
+                ]
+            );
+
+            // Statuses cover subscriptions that are not active as well
+            let by_status: Vec<_> = stats
+                .by_status
+                .iter()
+                .map(|s| {
+                    (
+                        s.currency.clone(),
+                        s.status.as_str(),
+                        s.count,
+                        s.monthly_spend.clone(),
+                    )
+                })
+                .collect();
+            assert_eq!(
+                by_status,
+                vec![
+                    (Currency::Cny, "Active", 1, decimal("15.22")),
+                    (Currency::Usd, "Active", 3, decimal("50.44")),
+                    (Currency::Usd, "paused", 1, decimal("10.00")),
+                ]
+            );
+
+            // The most expensive subscriptions are ranked per currency
+            let top: Vec<_> = stats
+                .top_subscriptions
+                .iter()
+                .map(|t| (t.name.as_str(), t.monthly_amount.clone()))
+                .collect();
+            assert_eq!(
+                top,
+                vec![
+                    ("Every other day", decimal("15.22")),
+                    ("Weekly", decimal("30.44")),
+                    ("Monthly", decimal("10.00")),
+                    ("Yearly", decimal("10.00")),
+                ]
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn test_users_without_subscriptions_have_empty_statistics() {
+        // This test requires a valid DATABASE_URL environment variable
+        if let Ok(url) = env::var("DATABASE_URL") {
+            let pool = connect(url).await;
+            let (user_id, _) = create_user(&pool).await;
+            let today = NaiveDate::from_ymd_opt(2025, 1, 1).unwrap();
+
+            let stats = StatisticsService::new(pool)
+                .get_statistics(user_id, 5, today, 30, 12)
+                .await
+                .unwrap();
+            assert!(stats.totals.is_empty());
+            assert!(stats.by_category.is_empty());
+            assert!(stats.by_status.is_empty());
+        }
+    }
+}
@@ -1,7 +1,15 @@
-use sqlx::{Pool, Postgres, Row};
+use chrono::NaiveDate;
+use sqlx::{Pool, Postgres, Row, postgres::PgRow};
 use uuid::Uuid;
 
-use crate::models::{Subscription, SubscriptionListResponse};
+use crate::models::{
+    NewNotification, NewSubscriptionEvent, StatusTransition, Subscription, WebhookEvent,
+    WebhookEventType,
+};
+use crate::services::event_service::record_events;
+use crate::services::notification_service::record_notification;
+use crate::services::webhook_service::{queue_subscription_webhooks, queue_webhook_event};
+use crate::utils::response::AppError;
 
 pub struct SubscriptionService {
     pool: Pool<Postgres>,
@@ -13,19 +21,22 @@ impl SubscriptionService {
     }
 
     /// Create a new subscription
-    pub async fn create_subscription(
-        &self,
-        req: Subscription,
-    ) -> Result<Subscription, sqlx::Error> {
+    pub async fn create_subscription(&self, req: Subscription) -> Result<Subscription, AppError> {
+        let mut tx = self.pool.begin().await.map_err(|e| {
+            AppError::database_error("transaction start", format!("Transaction error: {e}"))
+        })?;
+
         let row = sqlx::query(
             r#"
             INSERT INTO subscriptions
-            (user_id, name, description, amount, currency, billing_cycle_days,
-             start_date, next_billing_date, status, category, color)
-            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11)
+            (user_id, name, description, amount, currency, billing_cycle_unit,
+             billing_cycle_interval, start_date, next_billing_date, billing_anchor_date,
+             status, paused_at, end_date, trial_end_date, category, color)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, $16)
             RETURNING id, user_id, name, description, amount,
-                     currency, billing_cycle_days, start_date, next_billing_date,
-                     status, category, color, created_at, updated_at
+                     currency, billing_cycle_unit, billing_cycle_interval,
+                     start_date, next_billing_date, billing_anchor_date,
+                     status, paused_at, end_date, trial_end_date, category, color, created_at, updated_at
             "#,
         )
         .bind(req.user_id)
@@ -33,31 +44,40 @@ impl SubscriptionService {
         .bind(req.description)
         .bind(req.amount)
         .bind(req.currency.as_str())
-        .bind(req.billing_cycle_days)
+        .bind(req.billing_cycle_unit.as_str())
+        .bind(req.billing_cycle_interval)
         .bind(req.start_date)
         .bind(req.next_billing_date)
+        .bind(req.billing_anchor_date)
         .bind(req.status.as_str())
+        .bind(req.paused_at)
+        .bind(req.end_date)
+        .bind(req.trial_end_date)
         .bind(req.category)
         .bind(req.color)
-        .fetch_one(&self.pool)
-        .await?;
-
-        Ok(Subscription {
-            id: row.get("id"),
-            user_id: row.get("user_id"),
-            name: row.get("name"),
-            description: row.get("description"),
-            amount: row.get("amount"),
-            currency: row.get::<String, _>("currency").into(),
-            billing_cycle_days: row.get("billing_cycle_days"),
-            start_date: row.get("start_date"),
-            next_billing_date: row.get("next_billing_date"),
-            status: row.get::<String, _>("status").into(),
-            category: row.get("category"),
-            color: row.get("color"),
-            created_at: row.get("created_at"),
-            updated_at: row.get("updated_at"),
-        })
+        .fetch_one(&mut *tx)
+        .await
+        .map_err(|e| {
+            AppError::database_error("subscription creation", format!("Database error: {e}"))
+        })?;
+        let subscription = subscription_from_row(&row);
+
+        let events = vec![NewSubscriptionEvent::created(&subscription)];
+        queue_subscription_webhooks(&mut tx, &subscription, &events)
+            .await
+            .map_err(|e| {
+                AppError::database_error("webhook queueing", format!("Database error: {e}"))
+            })?;
+        record_events(&mut tx, subscription.id, subscription.user_id, events)
+            .await
+            .map_err(|e| {
+                AppError::database_error("event recording", format!("Database error: {e}"))
+            })?;
+        tx.commit().await.map_err(|e| {
+            AppError::database_error("transaction commit", format!("Transaction error: {e}"))
+        })?;
+
+        Ok(subscription)
     }
 
     /// Get a subscription by id
@@ -65,49 +85,42 @@ impl SubscriptionService {
         &self,
         user_id: Uuid,
         subscription_id: Uuid,
-    ) -> Result<Subscription, sqlx::Error> {
+    ) -> Result<Subscription, AppError> {
         let row = sqlx::query(
             r#"
             SELECT id, user_id, name, description, amount,
-                   currency, billing_cycle_days, start_date, next_billing_date,
-                   status, category, color, created_at, updated_at
+                   currency, billing_cycle_unit, billing_cycle_interval,
+                   start_date, next_billing_date, billing_anchor_date,
+                   status, paused_at, end_date, trial_end_date, category, color, created_at, updated_at
             FROM subscriptions
             WHERE id = $1 AND user_id = $2
             "#,
         )
         .bind(subscription_id)
         .bind(user_id)
-        .fetch_one(&self.pool)
-        .await?;
-
-        Ok(Subscription {
-            id: row.get("id"),
-            user_id: row.get("user_id"),
-            name: row.get("name"),
-            description: row.get("description"),
-            amount: row.get("amount"),
-            currency: row.get::<String, _>("currency").into(),
-            billing_cycle_days: row.get("billing_cycle_days"),
-            start_date: row.get("start_date"),
-            next_billing_date: row.get("next_billing_date"),
-            status: row.get::<String, _>("status").into(),
-            category: row.get("category"),
-            color: row.get("color"),
-            created_at: row.get("created_at"),
-            updated_at: row.get("updated_at"),
-        })
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| {
+            AppError::database_error("subscription lookup", format!("Database error: {e}"))
+        })?
+        .ok_or_else(|| {
+            AppError::not_found(
+                "Subscription",
+                format!("Subscription {subscription_id} not found"),
+            )
+        })?;
+
+        Ok(subscription_from_row(&row))
     }
 
     /// Get all subscriptions for a user
-    pub async fn get_subscriptions(
-        &self,
-        user_id: Uuid,
-    ) -> Result<SubscriptionListResponse, sqlx::Error> {
+    pub async fn get_subscriptions(&self, user_id: Uuid) -> Result<Vec<Subscription>, AppError> {
         let rows = sqlx::query(
             r#"
             SELECT id, user_id, name, description, amount,
-                   currency, billing_cycle_days, start_date, next_billing_date,
-                   status, category, color, created_at, updated_at
+                   currency, billing_cycle_unit, billing_cycle_interval,
+                   start_date, next_billing_date, billing_anchor_date,
+                   status, paused_at, end_date, trial_end_date, category, color, created_at, updated_at
             FROM subscriptions
             WHERE user_id = $1
             ORDER BY created_at DESC
@@ -115,29 +128,14 @@ impl SubscriptionService {
         )
         .bind(user_id)
         .fetch_all(&self.pool)
-        .await?;
-
-        let subscriptions = rows
-            .into_iter()
-            .map(|row| Subscription {
-                id: row.get("id"),
-                user_id: row.get("user_id"),
-                name: row.get("name"),
-                description: row.get("description"),
-                amount: row.get("amount"),
-                currency: row.get::<String, _>("currency").into(),
-                billing_cycle_days: row.get("billing_cycle_days"),
-                start_date: row.get("start_date"),
-                next_billing_date: row.get("next_billing_date"),
-                status: row.get::<String, _>("status").into(),
-                category: row.get("category"),
-                color: row.get("color"),
-                created_at: row.get("created_at"),
-                updated_at: row.get("updated_at"),
-            })
-            .collect();
-
-        Ok(SubscriptionListResponse { subscriptions })
+        .await
+        .map_err(|e| {
+            AppError::database_error("subscription listing", format!("Database error: {e}"))
+        })?;
+
+        let subscriptions = rows.iter().map(subscription_from_row).collect();
+
+        Ok(subscriptions)
     }
 
     /// Update a subscription
@@ -145,18 +143,51 @@ impl SubscriptionService {
         &self,
         subscription_id: Uuid,
         req: Subscription,
-    ) -> Result<Subscription, sqlx::Error> {
+    ) -> Result<Subscription, AppError> {
+        let mut tx = self.pool.begin().await.map_err(|e| {
+            AppError::database_error("transaction start", format!("Transaction error: {e}"))
+        })?;
+
+        // Lock the current version so the recorded changes match what was overwritten
+        let before = sqlx::query(
+            r#"
+            SELECT id, user_id, name, description, amount,
+                   currency, billing_cycle_unit, billing_cycle_interval,
+                   start_date, next_billing_date, billing_anchor_date,
+                   status, paused_at, end_date, trial_end_date, category, color, created_at, updated_at
+            FROM subscriptions
+            WHERE id = $1
+            FOR UPDATE
+            "#,
+        )
+        .bind(subscription_id)
+        .fetch_optional(&mut *tx)
+        .await
+        .map_err(|e| {
+            AppError::database_error("subscription lookup", format!("Database error: {e}"))
+        })?
+        .ok_or_else(|| {
+            AppError::not_found(
+                "Subscription",
+                format!("Subscription {subscription_id} not found"),
+            )
+        })?;
+        let before = subscription_from_row(&before);
+
         // Update the subscription
         let row = sqlx::query(
             r#"
             UPDATE subscriptions
             SET name = $2, description = $3, amount = $4, currency = $5,
-                billing_cycle_days = $6, start_date = $7,
-                next_billing_date = $8, status = $9, category = $10, color = $11
+                billing_cycle_unit = $6, billing_cycle_interval = $7, start_date = $8,
+                next_billing_date = $9, billing_anchor_date = $10, status = $11,
+                paused_at = $12, end_date = $13, trial_end_date = $14, category = $15,
+                color = $16
             WHERE id = $1
             RETURNING id, user_id, name, description, amount,
-                     currency, billing_cycle_days, start_date, next_billing_date,
-                     status, category, color, created_at, updated_at
+                     currency, billing_cycle_unit, billing_cycle_interval,
+                     start_date, next_billing_date, billing_anchor_date,
+                     status, paused_at, end_date, trial_end_date, category, color, created_at, updated_at
             "#,
         )
         .bind(subscription_id)
@@ -164,31 +195,150 @@ impl SubscriptionService {
         .bind(req.description)
         .bind(req.amount)
         .bind(req.currency.as_str())
-        .bind(req.billing_cycle_days)
+        .bind(req.billing_cycle_unit.as_str())
+        .bind(req.billing_cycle_interval)
         .bind(req.start_date)
         .bind(req.next_billing_date)
+        .bind(req.billing_anchor_date)
         .bind(req.status.as_str())
+        .bind(req.paused_at)
+        .bind(req.end_date)
+        .bind(req.trial_end_date)
         .bind(req.category)
         .bind(req.color)
-        .fetch_one(&self.pool)
-        .await?;
-
-        Ok(Subscription {
-            id: row.get("id"),
-            user_id: row.get("user_id"),
-            name: row.get("name"),
-            description: row.get("description"),
-            amount: row.get("amount"),
-            currency: row.get::<String, _>("currency").into(),
-            billing_cycle_days: row.get("billing_cycle_days"),
-            start_date: row.get("start_date"),
-            next_billing_date: row.get("next_billing_date"),
-            status: row.get::<String, _>("status").into(),
-            category: row.get("category"),
-            color: row.get("color"),
-            created_at: row.get("created_at"),
-            updated_at: row.get("updated_at"),
-        })
+        .fetch_one(&mut *tx)
+        .await
+        .map_err(|e| {
+            AppError::database_error("subscription update", format!("Database error: {e}"))
+        })?;
+        let subscription = subscription_from_row(&row);
+
+        let events = NewSubscriptionEvent::changes(&before, &subscription);
+        queue_subscription_webhooks(&mut tx, &subscription, &events)
+            .await
+            .map_err(|e| {
+                AppError::database_error("webhook queueing", format!("Database error: {e}"))
+            })?;
+        record_events(&mut tx, subscription.id, subscription.user_id, events)
+            .await
+            .map_err(|e| {
+                AppError::database_error("event recording", format!("Database error: {e}"))
+            })?;
+        if let Some(notification) = NewNotification::price_changed(&before, &subscription) {
+            record_notification(&mut tx, subscription.user_id, notification)
+                .await
+                .map_err(|e| {
+                    AppError::database_error(
+                        "notification recording",
+                        format!("Database error: {e}"),
+                    )
+                })?;
+        }
+        tx.commit().await.map_err(|e| {
+            AppError::database_error("transaction commit", format!("Transaction error: {e}"))
+        })?;
+
+        Ok(subscription)
+    }
+
+    /// Pause, resume or cancel a subscription
+    ///
+    /// Transitions not allowed from the current status are rejected as a conflict.
+    pub async fn transition_subscription(
+        &self,
+        user_id: Uuid,
+        subscription_id: Uuid,
+        transition: StatusTransition,
+        today: NaiveDate,
+    ) -> Result<Subscription, AppError> {
+        let mut tx = self.pool.begin().await.map_err(|e| {
+            AppError::database_error("transaction start", format!("Transaction error: {e}"))
+        })?;
+
+        let row = sqlx::query(
+            r#"
+            SELECT id, user_id, name, description, amount,
+                   currency, billing_cycle_unit, billing_cycle_interval,
+                   start_date, next_billing_date, billing_anchor_date,
+                   status, paused_at, end_date, trial_end_date, category, color, created_at, updated_at
+            FROM subscriptions
+            WHERE id = $1 AND user_id = $2
+            FOR UPDATE
+            "#,
+        )
+        .bind(subscription_id)
+        .bind(user_id)
+        .fetch_optional(&mut *tx)
+        .await
+        .map_err(|e| {
+            AppError::database_error("subscription lookup", format!("Database error: {e}"))
+        })?
+        .ok_or_else(|| {
+            AppError::not_found(
+                "Subscription",
+                format!("Subscription {subscription_id} not found"),
+            )
+        })?;
+        let before = subscription_from_row(&row);
+
+        if let StatusTransition::Cancel {
+            effective_date: Some(date),
+        } = transition
+            && date < before.start_date
+        {
+            return Err(AppError::validation_error(
+                format!("Effective date {date} is before the start date"),
+                "The cancellation date cannot be before the subscription started.",
+            ));
+        }
+
+        let mut subscription = before.clone();
+        subscription
+            .apply_transition(transition, today)
+            .map_err(|e| AppError::conflict("Invalid status transition", e.to_string()))?;
+
+        let row = sqlx::query(
+            r#"
+            UPDATE subscriptions
+            SET status = $2, next_billing_date = $3, billing_anchor_date = $4,
+                paused_at = $5, end_date = $6
+            WHERE id = $1
+            RETURNING id, user_id, name, description, amount,
+                     currency, billing_cycle_unit, billing_cycle_interval,
+                     start_date, next_billing_date, billing_anchor_date,
+                     status, paused_at, end_date, trial_end_date, category, color, created_at, updated_at
+            "#,
+        )
+        .bind(subscription_id)
+        .bind(subscription.status.as_str())
+        .bind(subscription.next_billing_date)
+        .bind(subscription.billing_anchor_date)
+        .bind(subscription.paused_at)
+        .bind(subscription.end_date)
+        .fetch_one(&mut *tx)
+        .await
+        .map_err(|e| {
+            AppError::database_error("subscription status update", format!("Database error: {e}"))
+        })?;
+        let subscription = subscription_from_row(&row);
+
+        let events = NewSubscriptionEvent::changes(&before, &subscription);
+        queue_subscription_webhooks(&mut tx, &subscription, &events)
+            .await
+            .map_err(|e| {
+                AppError::database_error("webhook queueing", format!("Database error: {e}"))
+            })?;
+        record_events(&mut tx, subscription_id, user_id, events)
+            .await
+            .map_err(|e| {
+                AppError::database_error("event recording", format!("Database error: {e}"))
+            })?;
+
+        tx.commit().await.map_err(|e| {
+            AppError::database_error("transaction commit", format!("Transaction error: {e}"))
+        })?;
+
+        Ok(subscription)
     }
 
     /// Delete a subscription
@@ -196,18 +346,141 @@ impl SubscriptionService {
         &self,
         user_id: Uuid,
         subscription_id: Uuid,
-    ) -> Result<(), sqlx::Error> {
-        sqlx::query(
+    ) -> Result<(), AppError> {
+        let mut tx = self.pool.begin().await.map_err(|e| {
+            AppError::database_error("transaction start", format!("Transaction error: {e}"))
+        })?;
+
+        let row = sqlx::query(
             r#"
-            DELETE FROM subscriptions
+            SELECT id, user_id, name, description, amount,
+                   currency, billing_cycle_unit, billing_cycle_interval,
+                   start_date, next_billing_date, billing_anchor_date,
+                   status, paused_at, end_date, trial_end_date, category, color, created_at, updated_at
+            FROM subscriptions
             WHERE id = $1 AND user_id = $2
+            FOR UPDATE
             "#,
         )
         .bind(subscription_id)
         .bind(user_id)
-        .execute(&self.pool)
-        .await?;
+        .fetch_optional(&mut *tx)
+        .await
+        .map_err(|e| {
+            AppError::database_error("subscription lookup", format!("Database error: {e}"))
+        })?
+        .ok_or_else(|| {
+            AppError::not_found(
+                "Subscription",
+                format!("Subscription {subscription_id} not found"),
+            )
+        })?;
+        let subscription = subscription_from_row(&row);
+
+        // The history outlives the subscription, closed by an event naming it
+        record_events(
+            &mut tx,
+            subscription.id,
+            user_id,
+            vec![NewSubscriptionEvent::deleted(&subscription)],
+        )
+        .await
+        .map_err(|e| AppError::database_error("event recording", format!("Database error: {e}")))?;
+        sqlx::query("DELETE FROM subscriptions WHERE id = $1")
+            .bind(subscription_id)
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| {
+                AppError::database_error("subscription deletion", format!("Database error: {e}"))
+            })?;
+
+        let event =
+            WebhookEvent::subscription(WebhookEventType::SubscriptionDeleted, &subscription);
+        queue_webhook_event(&mut tx, user_id, &event)
+            .await
+            .map_err(|e| {
+                AppError::database_error("webhook queueing", format!("Database error: {e}"))
+            })?;
+        tx.commit().await.map_err(|e| {
+            AppError::database_error("transaction commit", format!("Transaction error: {e}"))
+        })?;
 
         Ok(())
     }
 }
+
+/// Map a row selected with the standard subscription columns to a model
+pub(crate) fn subscription_from_row(row: &PgRow) -> Subscription {
+    Subscription {
+        id: row.get("id"),
+        user_id: row.get("user_id"),
+        name: row.get("name"),
+        description: row.get("description"),
+        amount: row.get("amount"),
+        currency: row.get::<String, _>("currency").into(),
+        billing_cycle_unit: row.get::<String, _>("billing_cycle_unit").into(),
+        billing_cycle_interval: row.get("billing_cycle_interval"),
+        start_date: row.get("start_date"),
+        next_billing_date: row.get("next_billing_date"),
+        billing_anchor_date: row.get("billing_anchor_date"),
+        status: row.get::<String, _>("status").into(),
+        paused_at: row.get("paused_at"),
+        end_date: row.get("end_date"),
+        trial_end_date: row.get("trial_end_date"),
+        category: row.get("category"),
+        color: row.get("color"),
+        created_at: row.get("created_at"),
+        updated_at: row.get("updated_at"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::test_support::{connect, create_user, subscription};
+    use std::env;
+
+    #[tokio::test]
+    async fn test_history_outlives_a_deleted_subscription() {
+        // This test requires a valid DATABASE_URL environment variable
+        if let Ok(url) = env::var("DATABASE_URL") {
+            let pool = connect(url).await;
+            let (user_id, _) = create_user(&pool).await;
+            let service = SubscriptionService::new(pool.clone());
+            let start = NaiveDate::from_ymd_opt(2025, 1, 1).unwrap();
+            let created = service
+                .create_subscription(subscription(user_id, start))
+                .await
+                .unwrap();
+
+            service
+                .delete_subscription(user_id, created.id)
+                .await
+                .unwrap();
+
+            let rows = sqlx::query(
+                r#"
+                SELECT subscription_id, event_type, before
+                FROM subscription_events
+                WHERE user_id = $1
+                ORDER BY created_at
+                "#,
+            )
+            .bind(user_id)
+            .fetch_all(&pool)
+            .await
+            .unwrap();
+            let events: Vec<(Option<Uuid>, String)> = rows
+                .iter()
+                .map(|row| (row.get("subscription_id"), row.get("event_type")))
+                .collect();
+            assert_eq!(
+                events,
+                vec![(None, "created".to_string()), (None, "deleted".to_string())]
+            );
+            let before: serde_json::Value = rows[1].get("before");
+            assert_eq!(before["id"], serde_json::json!(created.id));
+            assert_eq!(before["name"], serde_json::json!("Test subscription"));
+        }
+    }
+}
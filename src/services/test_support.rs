@@ -0,0 +1,65 @@
+use bigdecimal::BigDecimal;
+use chrono::NaiveDate;
+use sqlx::{PgPool, Row};
+use uuid::Uuid;
+
+use crate::config::{DatabaseConfig, create_pool};
+use crate::models::Subscription;
+use crate::models::subscription::{BillingCycleUnit, Currency, SubscriptionStatus};
+use crate::services::mailer::EmailMessage;
+
+/// Connect to the database of a test and run the migrations
+pub(crate) async fn connect(url: String) -> PgPool {
+    let config = DatabaseConfig {
+        url,
+        ..DatabaseConfig::default()
+    };
+    create_pool(&config).await.unwrap()
+}
+
+/// Create a user with an address of its own, returning its id and email
+pub(crate) async fn create_user(pool: &PgPool) -> (Uuid, String) {
+    let email = format!("test-{}@example.com", Uuid::new_v4());
+    let user_id = sqlx::query(
+        "INSERT INTO users (email, password_hash) VALUES ($1, 'not a password hash') RETURNING id",
+    )
+    .bind(&email)
+    .fetch_one(pool)
+    .await
+    .unwrap()
+    .get("id");
+
+    (user_id, email)
+}
+
+/// An active monthly subscription of a user starting on the given date, to
+/// be adjusted and created by a test
+pub(crate) fn subscription(user_id: Uuid, start_date: NaiveDate) -> Subscription {
+    Subscription {
+        id: Uuid::nil(),
+        user_id,
+        name: "Test subscription".to_string(),
+        description: None,
+        amount: BigDecimal::from(10),
+        currency: Currency::Usd,
+        billing_cycle_unit: BillingCycleUnit::Month,
+        billing_cycle_interval: 1,
+        start_date,
+        next_billing_date: start_date,
+        billing_anchor_date: start_date,
+        status: SubscriptionStatus::Active,
+        paused_at: None,
+        end_date: None,
+        trial_end_date: None,
+        category: None,
+        color: None,
+        created_at: None,
+        updated_at: None,
+    }
+}
+
+/// The token of the link in an email
+pub(crate) fn link_token(message: &EmailMessage) -> String {
+    let (_, rest) = message.text_body.split_once("?token=").unwrap();
+    rest.split_whitespace().next().unwrap().to_string()
+}
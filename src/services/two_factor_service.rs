@@ -0,0 +1,656 @@
+use chrono::{DateTime, Utc};
+use sqlx::{PgConnection, PgPool, Row, postgres::PgRow};
+use uuid::Uuid;
+
+use crate::models::{RecoveryCodes, TwoFactorEnrollment, TwoFactorStatus};
+use crate::services::login_lockout_service::remaining;
+use crate::utils::auth::{generate_opaque_token, hash_token};
+use crate::utils::response::AppError;
+use crate::utils::totp::{
+    RECOVERY_CODE_COUNT, generate_recovery_code, generate_totp_secret, normalize_recovery_code,
+    otpauth_uri, verify_totp,
+};
+
+/// Minutes a login challenge can be completed
+pub const TWO_FACTOR_CHALLENGE_TTL_MINUTES: i64 = 5;
+/// Wrong codes after which a login challenge stops working
+pub const TWO_FACTOR_CHALLENGE_MAX_ATTEMPTS: i32 = 5;
+
+/// Outcome of a code entered for a login challenge
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChallengeOutcome {
+    /// The code was right and the user of the challenge is signed in
+    Passed(Uuid),
+    /// The code was wrong; it counts as a failed login of the user's account
+    WrongCode(Uuid),
+}
+
+/// Service managing TOTP two-factor authentication
+///
+/// Enrollment stores a secret that is only enabled once a code from the
+/// authenticator app confirms it. Wherever a code is asked for, an unused
+/// recovery code works as well; each recovery code works once.
+pub struct TwoFactorService {
+    pool: PgPool,
+}
+
+impl TwoFactorService {
+    /// Create a new TwoFactorService with the given database pool
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    /// Get the two-factor authentication state of a user
+    pub async fn status(&self, user_id: Uuid) -> Result<TwoFactorStatus, AppError> {
+        let row = sqlx::query(
+            r#"
+            SELECT u.two_factor_enabled_at,
+                   (SELECT COUNT(*) FROM two_factor_recovery_codes c
+                    WHERE c.user_id = u.id AND c.used_at IS NULL) AS recovery_codes_remaining
+            FROM users u
+            WHERE u.id = $1
+            "#,
+        )
+        .bind(user_id)
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| AppError::database_error("user lookup", format!("Database error: {e}")))?
+        .ok_or_else(|| AppError::not_found("User", format!("User {user_id} not found")))?;
+
+        let enabled_at: Option<DateTime<Utc>> = row.get("two_factor_enabled_at");
+        Ok(TwoFactorStatus {
+            enabled: enabled_at.is_some(),
+            enabled_at,
+            recovery_codes_remaining: row.get("recovery_codes_remaining"),
+        })
+    }
+
+    /// Start enrollment by generating a new secret
+    ///
+    /// Starting again replaces the secret of an unconfirmed enrollment.
+    pub async fn begin_enrollment(&self, user_id: Uuid) -> Result<TwoFactorEnrollment, AppError> {
+        let secret = generate_totp_secret();
+        let row = sqlx::query(
+            r#"
+            UPDATE users
+            SET totp_secret = $2
+            WHERE id = $1 AND two_factor_enabled_at IS NULL
+            RETURNING email
+            "#,
+        )
+        .bind(user_id)
+        .bind(&secret)
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| {
+            AppError::database_error("two-factor enrollment", format!("Database error: {e}"))
+        })?
+        .ok_or_else(|| already_enabled(user_id))?;
+
+        let email: String = row.get("email");
+        Ok(TwoFactorEnrollment {
+            otpauth_uri: otpauth_uri(&email, &secret),
+            secret,
+        })
+    }
+
+    /// Enable two-factor authentication with a code for the enrolled secret
+    ///
+    /// Returns the recovery codes, which are not shown again.
+    pub async fn confirm_enrollment(
+        &self,
+        user_id: Uuid,
+        code: &str,
+    ) -> Result<RecoveryCodes, AppError> {
+        let mut tx = self.pool.begin().await.map_err(|e| {
+            AppError::database_error("transaction start", format!("Transaction error: {e}"))
+        })?;
+
+        let user = lock_user(&mut tx, user_id).await?;
+        if user
+            .get::<Option<DateTime<Utc>>, _>("two_factor_enabled_at")
+            .is_some()
+        {
+            return Err(already_enabled(user_id));
+        }
+        let Some(secret) = user.get::<Option<String>, _>("totp_secret") else {
+            return Err(AppError::validation_error(
+                format!("User {user_id} has no pending two-factor enrollment"),
+                "Please start setting up two-factor authentication first.",
+            ));
+        };
+
+        // Recovery codes do not exist yet, so only the app can confirm
+        let step = verify_totp(&secret, code, Utc::now().timestamp())
+            .ok_or_else(|| incorrect_code("Two-factor enrollment code mismatch"))?;
+
+        sqlx::query(
+            r#"
+            UPDATE users
+            SET two_factor_enabled_at = CURRENT_TIMESTAMP, totp_last_used_step = $2
+            WHERE id = $1
+            "#,
+        )
+        .bind(user_id)
+        .bind(step)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| {
+            AppError::database_error("two-factor activation", format!("Database error: {e}"))
+        })?;
+
+        let recovery_codes = replace_recovery_codes(&mut tx, user_id).await?;
+
+        tx.commit().await.map_err(|e| {
+            AppError::database_error("transaction commit", format!("Transaction error: {e}"))
+        })?;
+
+        tracing::info!("Two-factor authentication enabled for user {}", user_id);
+        Ok(recovery_codes)
+    }
+
+    /// Disable two-factor authentication after checking a code
+    ///
+    /// The secret, the recovery codes and pending login challenges are
+    /// discarded.
+    pub async fn disable(&self, user_id: Uuid, code: &str) -> Result<(), AppError> {
+        let mut tx = self.pool.begin().await.map_err(|e| {
+            AppError::database_error("transaction start", format!("Transaction error: {e}"))
+        })?;
+
+        let user = lock_user(&mut tx, user_id).await?;
+        require_enabled(&user, user_id)?;
+        if !check_code(&mut tx, user_id, &user, code).await? {
+            return Err(incorrect_code("Two-factor disable code mismatch"));
+        }
+
+        sqlx::query(
+            r#"
+            UPDATE users
+            SET totp_secret = NULL, two_factor_enabled_at = NULL, totp_last_used_step = NULL
+            WHERE id = $1
+            "#,
+        )
+        .bind(user_id)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| {
+            AppError::database_error("two-factor deactivation", format!("Database error: {e}"))
+        })?;
+
+        sqlx::query("DELETE FROM two_factor_recovery_codes WHERE user_id = $1")
+            .bind(user_id)
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| {
+                AppError::database_error("recovery code deletion", format!("Database error: {e}"))
+            })?;
+
+        sqlx::query(
+            r#"
+            UPDATE two_factor_challenges
+            SET used_at = CURRENT_TIMESTAMP
+            WHERE user_id = $1 AND used_at IS NULL
+            "#,
+        )
+        .bind(user_id)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| {
+            AppError::database_error("login challenge update", format!("Database error: {e}"))
+        })?;
+
+        tx.commit().await.map_err(|e| {
+            AppError::database_error("transaction commit", format!("Transaction error: {e}"))
+        })?;
+
+        tracing::info!("Two-factor authentication disabled for user {}", user_id);
+        Ok(())
+    }
+
+    /// Replace all recovery codes after checking a code
+    pub async fn regenerate_recovery_codes(
+        &self,
+        user_id: Uuid,
+        code: &str,
+    ) -> Result<RecoveryCodes, AppError> {
+        let mut tx = self.pool.begin().await.map_err(|e| {
+            AppError::database_error("transaction start", format!("Transaction error: {e}"))
+        })?;
+
+        let user = lock_user(&mut tx, user_id).await?;
+        require_enabled(&user, user_id)?;
+        if !check_code(&mut tx, user_id, &user, code).await? {
+            return Err(incorrect_code("Recovery code regeneration code mismatch"));
+        }
+
+        let recovery_codes = replace_recovery_codes(&mut tx, user_id).await?;
+
+        tx.commit().await.map_err(|e| {
+            AppError::database_error("transaction commit", format!("Transaction error: {e}"))
+        })?;
+
+        Ok(recovery_codes)
+    }
+
+    /// Complete a login challenge with a code
+    ///
+    /// A challenge works once and stops working after too many wrong codes
+    /// or when the account gets locked.
+    pub async fn complete_challenge(
+        &self,
+        challenge_token: &str,
+        code: &str,
+    ) -> Result<ChallengeOutcome, AppError> {
+        let mut tx = self.pool.begin().await.map_err(|e| {
+            AppError::database_error("transaction start", format!("Transaction error: {e}"))
+        })?;
+
+        // Lock the challenge and the user so that a code cannot be used twice
+        let row = sqlx::query(
+            r#"
+            SELECT c.id, c.user_id, c.expires_at, c.failed_attempts, c.used_at,
+                   u.totp_secret, u.two_factor_enabled_at, u.totp_last_used_step, u.locked_until
+            FROM two_factor_challenges c
+            JOIN users u ON u.id = c.user_id
+            WHERE c.token_hash = $1
+            FOR UPDATE
+            "#,
+        )
+        .bind(hash_token(challenge_token))
+        .fetch_optional(&mut *tx)
+        .await
+        .map_err(|e| {
+            AppError::database_error("login challenge lookup", format!("Database error: {e}"))
+        })?
+        .ok_or_else(|| invalid_challenge("Unknown login challenge"))?;
+
+        let challenge_id: Uuid = row.get("id");
+        let user_id: Uuid = row.get("user_id");
+        let expires_at: DateTime<Utc> = row.get("expires_at");
+        let failed_attempts: i32 = row.get("failed_attempts");
+        let used_at: Option<DateTime<Utc>> = row.get("used_at");
+
+        if used_at.is_some() {
+            return Err(invalid_challenge("Used login challenge"));
+        }
+        if expires_at <= Utc::now() {
+            return Err(invalid_challenge("Expired login challenge"));
+        }
+        if failed_attempts >= TWO_FACTOR_CHALLENGE_MAX_ATTEMPTS {
+            return Err(invalid_challenge("Login challenge attempts exhausted"));
+        }
+        if row
+            .get::<Option<DateTime<Utc>>, _>("two_factor_enabled_at")
+            .is_none()
+        {
+            return Err(invalid_challenge("Two-factor authentication was disabled"));
+        }
+        if let Some(locked_until) = row
+            .get::<Option<DateTime<Utc>>, _>("locked_until")
+            .filter(|locked_until| *locked_until > Utc::now())
+        {
+            return Err(AppError::account_locked(remaining(locked_until)));
+        }
+
+        if !check_code(&mut tx, user_id, &row, code).await? {
+            sqlx::query(
+                "UPDATE two_factor_challenges SET failed_attempts = failed_attempts + 1 WHERE id = $1",
+            )
+            .bind(challenge_id)
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| {
+                AppError::database_error("login challenge update", format!("Database error: {e}"))
+            })?;
+            tx.commit().await.map_err(|e| {
+                AppError::database_error("transaction commit", format!("Transaction error: {e}"))
+            })?;
+
+            tracing::warn!("Wrong two-factor code for user {}", user_id);
+            return Ok(ChallengeOutcome::WrongCode(user_id));
+        }
+
+        sqlx::query("UPDATE two_factor_challenges SET used_at = CURRENT_TIMESTAMP WHERE id = $1")
+            .bind(challenge_id)
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| {
+                AppError::database_error("login challenge update", format!("Database error: {e}"))
+            })?;
+
+        tx.commit().await.map_err(|e| {
+            AppError::database_error("transaction commit", format!("Transaction error: {e}"))
+        })?;
+
+        Ok(ChallengeOutcome::Passed(user_id))
+    }
+}
+
+/// Create a login challenge for a user whose password was checked, returning its token
+pub(crate) async fn create_login_challenge(
+    conn: &mut PgConnection,
+    user_id: Uuid,
+) -> Result<String, sqlx::Error> {
+    let token = generate_opaque_token();
+    sqlx::query(
+        r#"
+        INSERT INTO two_factor_challenges (user_id, token_hash, expires_at)
+        VALUES ($1, $2, CURRENT_TIMESTAMP + make_interval(mins => $3))
+        "#,
+    )
+    .bind(user_id)
+    .bind(hash_token(&token))
+    .bind(TWO_FACTOR_CHALLENGE_TTL_MINUTES as i32)
+    .execute(conn)
+    .await?;
+
+    Ok(token)
+}
+
+async fn lock_user(conn: &mut PgConnection, user_id: Uuid) -> Result<PgRow, AppError> {
+    sqlx::query(
+        r#"
+        SELECT totp_secret, two_factor_enabled_at, totp_last_used_step
+        FROM users
+        WHERE id = $1
+        FOR UPDATE
+        "#,
+    )
+    .bind(user_id)
+    .fetch_optional(conn)
+    .await
+    .map_err(|e| AppError::database_error("user lookup", format!("Database error: {e}")))?
+    .ok_or_else(|| AppError::not_found("User", format!("User {user_id} not found")))
+}
+
+/// Check a TOTP or recovery code of a locked user row, consuming it on success
+///
+/// A TOTP code is rejected when its time step is not newer than the last
+/// accepted one, so an observed code cannot be replayed.
+async fn check_code(
+    conn: &mut PgConnection,
+    user_id: Uuid,
+    user: &PgRow,
+    code: &str,
+) -> Result<bool, AppError> {
+    let secret: Option<String> = user.get("totp_secret");
+    let last_used_step: Option<i64> = user.get("totp_last_used_step");
+
+    if let Some(step) = secret
+        .as_deref()
+        .and_then(|secret| verify_totp(secret, code, Utc::now().timestamp()))
+    {
+        if last_used_step.is_some_and(|last| step <= last) {
+            tracing::warn!("Replayed two-factor code for user {}", user_id);
+            return Ok(false);
+        }
+
+        sqlx::query("UPDATE users SET totp_last_used_step = $2 WHERE id = $1")
+            .bind(user_id)
+            .bind(step)
+            .execute(conn)
+            .await
+            .map_err(|e| {
+                AppError::database_error("two-factor code update", format!("Database error: {e}"))
+            })?;
+        return Ok(true);
+    }
+
+    let used = sqlx::query(
+        r#"
+        UPDATE two_factor_recovery_codes
+        SET used_at = CURRENT_TIMESTAMP
+        WHERE user_id = $1 AND code_hash = $2 AND used_at IS NULL
+        "#,
+    )
+    .bind(user_id)
+    .bind(hash_token(&normalize_recovery_code(code)))
+    .execute(conn)
+    .await
+    .map_err(|e| {
+        AppError::database_error("recovery code update", format!("Database error: {e}"))
+    })?;
+
+    if used.rows_affected() > 0 {
+        tracing::info!("Recovery code used by user {}", user_id);
+        return Ok(true);
+    }
+    Ok(false)
+}
+
+async fn replace_recovery_codes(
+    conn: &mut PgConnection,
+    user_id: Uuid,
+) -> Result<RecoveryCodes, AppError> {
+    sqlx::query("DELETE FROM two_factor_recovery_codes WHERE user_id = $1")
+        .bind(user_id)
+        .execute(&mut *conn)
+        .await
+        .map_err(|e| {
+            AppError::database_error("recovery code deletion", format!("Database error: {e}"))
+        })?;
+
+    let recovery_codes: Vec<String> = (0..RECOVERY_CODE_COUNT)
+        .map(|_| generate_recovery_code())
+        .collect();
+    let hashes: Vec<String> = recovery_codes.iter().map(|code| hash_token(code)).collect();
+    sqlx::query(
+        r#"
+        INSERT INTO two_factor_recovery_codes (user_id, code_hash)
+        SELECT $1, UNNEST($2::varchar[])
+        "#,
+    )
+    .bind(user_id)
+    .bind(&hashes)
+    .execute(&mut *conn)
+    .await
+    .map_err(|e| {
+        AppError::database_error("recovery code creation", format!("Database error: {e}"))
+    })?;
+
+    Ok(RecoveryCodes { recovery_codes })
+}
+
+fn require_enabled(user: &PgRow, user_id: Uuid) -> Result<(), AppError> {
+    if user
+        .get::<Option<DateTime<Utc>>, _>("two_factor_enabled_at")
+        .is_none()
+    {
+        return Err(AppError::validation_error(
+            format!("Two-factor authentication is not enabled for user {user_id}"),
+            "Two-factor authentication is not enabled for your account.",
+        ));
+    }
+    Ok(())
+}
+
+fn already_enabled(user_id: Uuid) -> AppError {
+    AppError::conflict(
+        "Two-factor authentication already enabled",
+        format!("Two-factor authentication is already enabled for user {user_id}"),
+    )
+}
+
+fn incorrect_code(message: &str) -> AppError {
+    tracing::warn!("{}", message);
+    AppError::field_validation_error(vec![("code", "Code is incorrect".to_string())])
+}
+
+fn invalid_challenge(message: &str) -> AppError {
+    tracing::warn!("{}", message);
+    AppError::unauthorized_with_message(
+        message.to_string(),
+        "Your sign-in has expired. Please sign in again.",
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::test_support::{connect, create_user};
+    use crate::utils::totp::{TOTP_PERIOD_SECS, totp_code};
+    use data_encoding::BASE32_NOPAD;
+    use std::env;
+
+    /// The code of a secret at a time step, as the authenticator app shows it
+    fn code(secret: &[u8], step: i64) -> String {
+        format!("{:06}", totp_code(secret, step))
+    }
+
+    /// Enable two-factor authentication for a user, returning the decoded
+    /// secret, the time step of the confirming code and the recovery codes
+    async fn enable(service: &TwoFactorService, user_id: Uuid) -> (Vec<u8>, i64, RecoveryCodes) {
+        let enrollment = service.begin_enrollment(user_id).await.unwrap();
+        let secret = BASE32_NOPAD.decode(enrollment.secret.as_bytes()).unwrap();
+        let step = Utc::now().timestamp().div_euclid(TOTP_PERIOD_SECS);
+        let recovery_codes = service
+            .confirm_enrollment(user_id, &code(&secret, step))
+            .await
+            .unwrap();
+        (secret, step, recovery_codes)
+    }
+
+    async fn challenge(pool: &PgPool, user_id: Uuid) -> String {
+        let mut conn = pool.acquire().await.unwrap();
+        create_login_challenge(&mut conn, user_id).await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_totp_codes_cannot_be_replayed() {
+        // This test requires a valid DATABASE_URL environment variable
+        if let Ok(url) = env::var("DATABASE_URL") {
+            let pool = connect(url).await;
+            let (user_id, _) = create_user(&pool).await;
+            let service = TwoFactorService::new(pool.clone());
+            let (secret, step, _) = enable(&service, user_id).await;
+
+            // The code that confirmed the enrollment was used
+            let token = challenge(&pool, user_id).await;
+            assert_eq!(
+                service
+                    .complete_challenge(&token, &code(&secret, step))
+                    .await
+                    .unwrap(),
+                ChallengeOutcome::WrongCode(user_id)
+            );
+            assert_eq!(
+                service
+                    .complete_challenge(&token, &code(&secret, step + 1))
+                    .await
+                    .unwrap(),
+                ChallengeOutcome::Passed(user_id)
+            );
+
+            // A challenge works once
+            let error = service
+                .complete_challenge(&token, &code(&secret, step + 2))
+                .await
+                .unwrap_err();
+            assert!(error.to_string().contains("Used login challenge"));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_recovery_codes_work_once() {
+        // This test requires a valid DATABASE_URL environment variable
+        if let Ok(url) = env::var("DATABASE_URL") {
+            let pool = connect(url).await;
+            let (user_id, _) = create_user(&pool).await;
+            let service = TwoFactorService::new(pool.clone());
+            let (_, _, recovery_codes) = enable(&service, user_id).await;
+            let recovery_code = &recovery_codes.recovery_codes[0];
+
+            let token = challenge(&pool, user_id).await;
+            assert_eq!(
+                service
+                    .complete_challenge(&token, &recovery_code.to_uppercase())
+                    .await
+                    .unwrap(),
+                ChallengeOutcome::Passed(user_id)
+            );
+
+            let token = challenge(&pool, user_id).await;
+            assert_eq!(
+                service
+                    .complete_challenge(&token, recovery_code)
+                    .await
+                    .unwrap(),
+                ChallengeOutcome::WrongCode(user_id)
+            );
+            assert_eq!(
+                service
+                    .status(user_id)
+                    .await
+                    .unwrap()
+                    .recovery_codes_remaining,
+                RECOVERY_CODE_COUNT as i64 - 1
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn test_challenges_stop_after_too_many_wrong_codes() {
+        // This test requires a valid DATABASE_URL environment variable
+        if let Ok(url) = env::var("DATABASE_URL") {
+            let pool = connect(url).await;
+            let (user_id, _) = create_user(&pool).await;
+            let service = TwoFactorService::new(pool.clone());
+            let (secret, step, _) = enable(&service, user_id).await;
+
+            let token = challenge(&pool, user_id).await;
+            for _ in 0..TWO_FACTOR_CHALLENGE_MAX_ATTEMPTS {
+                assert_eq!(
+                    service.complete_challenge(&token, "wrong").await.unwrap(),
+                    ChallengeOutcome::WrongCode(user_id)
+                );
+            }
+
+            let error = service
+                .complete_challenge(&token, &code(&secret, step + 1))
+                .await
+                .unwrap_err();
+            assert!(error.to_string().contains("attempts exhausted"));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_expired_challenges_and_locked_accounts_are_rejected() {
+        // This test requires a valid DATABASE_URL environment variable
+        if let Ok(url) = env::var("DATABASE_URL") {
+            let pool = connect(url).await;
+            let (user_id, _) = create_user(&pool).await;
+            let service = TwoFactorService::new(pool.clone());
+            let (secret, step, _) = enable(&service, user_id).await;
+
+            let token = challenge(&pool, user_id).await;
+            sqlx::query(
+                "UPDATE two_factor_challenges SET expires_at = CURRENT_TIMESTAMP - INTERVAL '1 minute' WHERE user_id = $1",
+            )
+            .bind(user_id)
+            .execute(&pool)
+            .await
+            .unwrap();
+            let error = service
+                .complete_challenge(&token, &code(&secret, step + 1))
+                .await
+                .unwrap_err();
+            assert!(error.to_string().contains("Expired login challenge"));
+
+            let token = challenge(&pool, user_id).await;
+            sqlx::query(
+                "UPDATE users SET locked_until = CURRENT_TIMESTAMP + INTERVAL '1 hour' WHERE id = $1",
+            )
+            .bind(user_id)
+            .execute(&pool)
+            .await
+            .unwrap();
+            assert!(matches!(
+                service
+                    .complete_challenge(&token, &code(&secret, step + 1))
+                    .await,
+                Err(AppError::TooManyRequests { .. })
+            ));
+        }
+    }
+}
@@ -2,9 +2,22 @@ use sqlx::{PgPool, Row};
 use tracing;
 use uuid::Uuid;
 
-use crate::models::{AuthResponse, LoginRequest, RegisterRequest, UserResponse};
+use crate::models::{
+    AuthResponse, ChangePasswordRequest, LoginRequest, LoginResponse, RegisterRequest,
+    TokenResponse, TwoFactorChallenge, UpdateProfileRequest, UserResponse,
+};
+use crate::services::refresh_token_service::{issue_refresh_token, revoke_user_refresh_tokens};
+use crate::services::session_service::create_session;
+use crate::services::two_factor_service::{
+    TWO_FACTOR_CHALLENGE_TTL_MINUTES, create_login_challenge,
+};
+use crate::utils::client::ClientInfo;
 use crate::utils::response::AppError;
-use crate::utils::{generate_refresh_token, generate_token, hash_password, verify_password};
+use crate::utils::{generate_token, hash_password, verify_password};
+
+/// Minutes after signing in that an account without a password can still
+/// confirm a sensitive change
+pub const REAUTHENTICATION_MAX_AGE_MINUTES: i64 = 10;
 
 /// Service for handling user-related operations
 pub struct UserService {
@@ -72,7 +85,8 @@ impl UserService {
             r#"
             INSERT INTO users (email, password_hash)
             VALUES ($1, $2)
-            RETURNING id, email, password_hash, created_at, updated_at
+            RETURNING id, email, password_hash, email_verified_at, two_factor_enabled_at,
+                      created_at, updated_at
             "#,
         )
         .bind(&request.email)
@@ -87,6 +101,8 @@ impl UserService {
             id: user_row.get("id"),
             email: user_row.get("email"),
             password_hash: user_row.get("password_hash"),
+            email_verified_at: user_row.get("email_verified_at"),
+            two_factor_enabled_at: user_row.get("two_factor_enabled_at"),
             created_at: user_row.get("created_at"),
             updated_at: user_row.get("updated_at"),
         };
@@ -130,7 +146,11 @@ impl UserService {
     }
 
     /// Login a user with optimized single query
-    pub async fn login(&self, request: LoginRequest) -> Result<AuthResponse, AppError> {
+    pub async fn login(
+        &self,
+        request: LoginRequest,
+        client: &ClientInfo,
+    ) -> Result<LoginResponse, AppError> {
         tracing::info!(
             "UserService::login - Starting login for email: {}",
             request.email
@@ -143,6 +163,8 @@ impl UserService {
                 u.id as user_id,
                 u.email,
                 u.password_hash,
+                u.email_verified_at,
+                u.two_factor_enabled_at,
                 u.created_at as user_created_at,
                 u.updated_at as user_updated_at,
                 p.id as profile_id,
@@ -189,6 +211,8 @@ impl UserService {
             id: user_id,
             email,
             password_hash: password_hash.clone(),
+            email_verified_at: result.get("email_verified_at"),
+            two_factor_enabled_at: result.get("two_factor_enabled_at"),
             created_at: user_created_at,
             updated_at: user_updated_at,
         };
@@ -228,35 +252,120 @@ impl UserService {
             None
         };
 
-        // Generate tokens
-        let token = generate_token(user.id).map_err(|e| {
-            tracing::error!(
-                "UserService::login - Token generation failed for user '{}': {}",
-                user.id,
-                e
+        let response = self
+            .sign_in(user.to_response(profile.as_ref()), client)
+            .await?;
+        if let LoginResponse::Authenticated(auth) = &response {
+            tracing::info!(
+                "UserService::login - Login successful for email: {}, user_id: {}",
+                request.email,
+                auth.user.id
             );
-            AppError::internal_error(format!("Token generation error: {e}"))
+        }
+        Ok(response)
+    }
+
+    /// Sign in a user whose identity was established by a password or a
+    /// single sign-on provider
+    ///
+    /// Accounts with two-factor authentication get a challenge instead of
+    /// tokens and finish signing in with a code.
+    pub async fn sign_in(
+        &self,
+        user: UserResponse,
+        client: &ClientInfo,
+    ) -> Result<LoginResponse, AppError> {
+        if user.two_factor_enabled {
+            let mut conn = self.pool.acquire().await.map_err(|e| {
+                AppError::database_error("connection acquire", format!("Database error: {e}"))
+            })?;
+            let challenge_token =
+                create_login_challenge(&mut conn, user.id)
+                    .await
+                    .map_err(|e| {
+                        AppError::database_error(
+                            "login challenge creation",
+                            format!("Database error: {e}"),
+                        )
+                    })?;
+
+            tracing::info!(
+                "UserService::sign_in - Two-factor code required for user_id: {}",
+                user.id
+            );
+            return Ok(LoginResponse::TwoFactorRequired(TwoFactorChallenge {
+                two_factor_required: true,
+                challenge_token,
+                expires_in: TWO_FACTOR_CHALLENGE_TTL_MINUTES * 60,
+            }));
+        }
+
+        Ok(LoginResponse::Authenticated(
+            self.start_session(user, client).await?,
+        ))
+    }
+
+    /// Sign in a user whose credentials were checked, starting a session of
+    /// the client and issuing its tokens
+    pub async fn start_session(
+        &self,
+        user: UserResponse,
+        client: &ClientInfo,
+    ) -> Result<AuthResponse, AppError> {
+        // Signing in during the deletion grace period keeps the account
+        let cancelled = sqlx::query(
+            "UPDATE users SET deletion_scheduled_at = NULL WHERE id = $1 AND deletion_scheduled_at IS NOT NULL",
+        )
+        .bind(user.id)
+        .execute(&self.pool)
+        .await
+        .map_err(|e| {
+            AppError::database_error("account deletion cancel", format!("Database error: {e}"))
         })?;
+        if cancelled.rows_affected() > 0 {
+            tracing::info!(
+                "UserService::start_session - Scheduled deletion cancelled for user: {}",
+                user.id
+            );
+        }
 
-        let refresh_token = generate_refresh_token(user.id).map_err(|e| {
+        // Every login starts a new session with its own refresh token family
+        let mut tx = self.pool.begin().await.map_err(|e| {
+            AppError::database_error("transaction start", format!("Transaction error: {e}"))
+        })?;
+        let session_id = create_session(&mut tx, user.id, client)
+            .await
+            .map_err(|e| {
+                AppError::database_error("session creation", format!("Database error: {e}"))
+            })?;
+        let refresh_token = issue_refresh_token(&mut tx, user.id, session_id)
+            .await
+            .map_err(|e| {
+                tracing::error!(
+                    "UserService::start_session - Refresh token creation failed for user '{}': {}",
+                    user.id,
+                    e
+                );
+                AppError::database_error("refresh token creation", format!("Database error: {e}"))
+            })?;
+        tx.commit().await.map_err(|e| {
+            AppError::database_error("transaction commit", format!("Transaction error: {e}"))
+        })?;
+
+        // Generate tokens
+        let token = generate_token(user.id, session_id).map_err(|e| {
             tracing::error!(
-                "UserService::login - Refresh token generation failed for user '{}': {}",
+                "UserService::start_session - Token generation failed for user '{}': {}",
                 user.id,
                 e
             );
-            AppError::internal_error(format!("Refresh token generation error: {e}"))
+            AppError::internal_error(format!("Token generation error: {e}"))
         })?;
 
-        // Return auth response
-        tracing::info!(
-            "UserService::login - Login successful for email: {}, user_id: {}",
-            request.email,
-            user.id
-        );
         Ok(AuthResponse {
             token,
             refresh_token,
-            user: user.to_response(profile.as_ref()),
+            user,
         })
     }
 
@@ -269,6 +378,8 @@ impl UserService {
                 u.id as user_id,
                 u.email,
                 u.password_hash,
+                u.email_verified_at,
+                u.two_factor_enabled_at,
                 u.created_at as user_created_at,
                 u.updated_at as user_updated_at,
                 p.id as profile_id,
@@ -299,6 +410,8 @@ impl UserService {
             id: user_id,
             email,
             password_hash,
+            email_verified_at: result.get("email_verified_at"),
+            two_factor_enabled_at: result.get("two_factor_enabled_at"),
             created_at: user_created_at,
             updated_at: user_updated_at,
         };
@@ -320,4 +433,191 @@ impl UserService {
         // Return user response
         Ok(user.to_response(profile.as_ref()))
     }
+
+    /// Update the name and preferences of a user's profile
+    ///
+    /// Preferences are merged into the stored ones; `null` values remove a
+    /// preference.
+    pub async fn update_profile(
+        &self,
+        user_id: Uuid,
+        request: UpdateProfileRequest,
+    ) -> Result<UserResponse, AppError> {
+        let preferences = serde_json::to_value(request.preferences.unwrap_or_default())
+            .map_err(|e| AppError::internal_error(format!("Preferences encoding error: {e}")))?;
+
+        sqlx::query(
+            r#"
+            UPDATE user_profiles
+            SET name = CASE WHEN $2 THEN NULLIF(TRIM($3), '') ELSE name END,
+                preferences = jsonb_strip_nulls(COALESCE(preferences, '{}'::jsonb) || $4)
+            WHERE user_id = $1
+            "#,
+        )
+        .bind(user_id)
+        .bind(request.name.is_some())
+        .bind(request.name)
+        .bind(preferences)
+        .execute(&self.pool)
+        .await
+        .map_err(|e| AppError::database_error("profile update", format!("Database error: {e}")))?;
+
+        self.get_user_by_id(user_id).await
+    }
+
+    /// Check a user's password before a sensitive change
+    ///
+    /// A wrong password is reported as an invalid `field`, not as 401, since
+    /// the caller is authenticated.
+    pub async fn check_password(
+        &self,
+        user_id: Uuid,
+        password: &str,
+        field: &str,
+    ) -> Result<(), AppError> {
+        let password_hash: String = sqlx::query("SELECT password_hash FROM users WHERE id = $1")
+            .bind(user_id)
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(|e| AppError::database_error("user lookup", format!("Database error: {e}")))?
+            .ok_or_else(|| AppError::not_found("User", format!("User {user_id} not found")))?
+            .get("password_hash");
+
+        let is_valid = verify_password(password, &password_hash)
+            .map_err(|e| AppError::internal_error(format!("Password verification error: {e}")))?;
+        if !is_valid {
+            tracing::warn!("Password check failed for user: {}", user_id);
+            return Err(AppError::field_validation_error(vec![(
+                field,
+                "Password is incorrect".to_string(),
+            )]));
+        }
+
+        Ok(())
+    }
+
+    /// Confirm that the user is present before deleting the account,
+    /// changing the email address or disabling two-factor authentication
+    ///
+    /// Users who set a password confirm with it. Accounts created by an OIDC
+    /// login have none, so their session must have started within
+    /// `REAUTHENTICATION_MAX_AGE_MINUTES`, which takes a fresh sign-in with
+    /// the provider.
+    pub async fn confirm_sensitive_change(
+        &self,
+        user_id: Uuid,
+        session_id: Option<Uuid>,
+        password: Option<&str>,
+    ) -> Result<(), AppError> {
+        let password_set: bool = sqlx::query("SELECT password_set FROM users WHERE id = $1")
+            .bind(user_id)
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(|e| AppError::database_error("user lookup", format!("Database error: {e}")))?
+            .ok_or_else(|| AppError::not_found("User", format!("User {user_id} not found")))?
+            .get("password_set");
+
+        if password_set {
+            let Some(password) = password else {
+                return Err(AppError::field_validation_error(vec![(
+                    "password",
+                    "Password is required".to_string(),
+                )]));
+            };
+            return self.check_password(user_id, password, "password").await;
+        }
+
+        let fresh = match session_id {
+            Some(session_id) => sqlx::query_scalar::<_, bool>(
+                r#"
+                SELECT EXISTS (
+                    SELECT 1 FROM user_sessions
+                    WHERE id = $1 AND user_id = $2
+                      AND created_at > CURRENT_TIMESTAMP - $3 * INTERVAL '1 minute'
+                )
+                "#,
+            )
+            .bind(session_id)
+            .bind(user_id)
+            .bind(REAUTHENTICATION_MAX_AGE_MINUTES)
+            .fetch_one(&self.pool)
+            .await
+            .map_err(|e| {
+                AppError::database_error("session lookup", format!("Database error: {e}"))
+            })?,
+            None => false,
+        };
+        if !fresh {
+            tracing::warn!("Sensitive change of user {} needs a fresh sign-in", user_id);
+            return Err(AppError::reauthentication_required(
+                REAUTHENTICATION_MAX_AGE_MINUTES,
+            ));
+        }
+
+        Ok(())
+    }
+
+    /// Change a user's password after checking the current one
+    ///
+    /// Every refresh token of the user is revoked, signing out other
+    /// sessions; the returned tokens start a new session for the client.
+    pub async fn change_password(
+        &self,
+        user_id: Uuid,
+        request: ChangePasswordRequest,
+        client: &ClientInfo,
+    ) -> Result<TokenResponse, AppError> {
+        self.check_password(user_id, &request.current_password, "current_password")
+            .await?;
+
+        let password_hash = hash_password(&request.new_password)
+            .map_err(|e| AppError::internal_error(format!("Password hashing error: {e}")))?;
+
+        let mut tx = self.pool.begin().await.map_err(|e| {
+            AppError::database_error("transaction start", format!("Transaction error: {e}"))
+        })?;
+
+        sqlx::query("UPDATE users SET password_hash = $2 WHERE id = $1")
+            .bind(user_id)
+            .bind(&password_hash)
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| {
+                AppError::database_error("password update", format!("Database error: {e}"))
+            })?;
+
+        let revoked = revoke_user_refresh_tokens(&mut tx, user_id)
+            .await
+            .map_err(|e| {
+                AppError::database_error("refresh token revocation", format!("Database error: {e}"))
+            })?;
+        let session_id = create_session(&mut tx, user_id, client)
+            .await
+            .map_err(|e| {
+                AppError::database_error("session creation", format!("Database error: {e}"))
+            })?;
+        let refresh_token = issue_refresh_token(&mut tx, user_id, session_id)
+            .await
+            .map_err(|e| {
+                AppError::database_error("refresh token creation", format!("Database error: {e}"))
+            })?;
+
+        tx.commit().await.map_err(|e| {
+            AppError::database_error("transaction commit", format!("Transaction error: {e}"))
+        })?;
+
+        tracing::info!(
+            "Password changed for user {}, {} refresh tokens revoked",
+            user_id,
+            revoked
+        );
+
+        let token = generate_token(user_id, session_id)
+            .map_err(|e| AppError::internal_error(format!("Token generation error: {e}")))?;
+
+        Ok(TokenResponse {
+            token,
+            refresh_token,
+        })
+    }
 }
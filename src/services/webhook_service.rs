@@ -0,0 +1,1080 @@
+use chrono::Utc;
+use hmac::{Hmac, Mac};
+use ipnet::IpNet;
+use rand::RngCore;
+use reqwest::Url;
+use reqwest::dns::{Addrs, Name, Resolve, Resolving};
+use serde_json::Value;
+use sha2::Sha256;
+use sqlx::{PgConnection, PgPool, Row, postgres::PgRow};
+use std::io;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::task::JoinHandle;
+use tokio::time::MissedTickBehavior;
+use uuid::Uuid;
+
+use crate::config::WebhookConfig;
+use crate::models::{
+    CreateWebhookRequest, MAX_WEBHOOK_ENDPOINTS, NewSubscriptionEvent, PaginatedResponse,
+    Pagination, PaginationQuery, Subscription, UpdateWebhookRequest, WebhookDelivery,
+    WebhookDeliveryAttempt, WebhookDeliveryDetail, WebhookDeliveryFilter, WebhookEndpoint,
+    WebhookEndpointWithSecret, WebhookEvent, WebhookEventType,
+};
+use crate::utils::backoff::exponential_backoff;
+use crate::utils::response::AppError;
+
+/// Header carrying the signature of a webhook request
+pub const SIGNATURE_HEADER: &str = "X-SubPal-Signature";
+/// Header carrying the event type of a webhook request
+pub const EVENT_HEADER: &str = "X-SubPal-Event";
+/// Header carrying the delivery id of a webhook request
+pub const DELIVERY_HEADER: &str = "X-SubPal-Delivery";
+
+/// Attempts after which a delivery is given up and marked as failed
+const MAX_DELIVERY_ATTEMPTS: i32 = 8;
+/// Deliveries claimed by one run
+const DELIVERY_BATCH_SIZE: i64 = 50;
+/// How long a claimed delivery is hidden from other runs while it is sent
+const DELIVERY_LEASE_SECS: i64 = 600;
+/// Delay before the first retry, doubled with every further attempt
+const RETRY_BASE_DELAY: Duration = Duration::from_secs(30);
+/// Longest delay between two attempts
+const RETRY_MAX_DELAY: Duration = Duration::from_secs(6 * 60 * 60);
+/// Longest part of a response body kept in the attempt log
+const MAX_LOGGED_RESPONSE_BYTES: usize = 1024;
+
+/// Outcome of a webhook delivery run
+#[derive(Debug, Default)]
+pub struct WebhookDeliverySummary {
+    pub delivered: usize,
+    pub retried: usize,
+    pub failed: usize,
+}
+
+/// Decides which addresses webhooks may be sent to
+///
+/// Webhook URLs are chosen by users, so without this check they could make
+/// the server send requests to itself or to other services on its network.
+/// Loopback, private, link-local, unique-local and other internal addresses
+/// are refused unless they are in one of the allowed networks. Also used as
+/// the DNS resolver of the delivery client, so a host name that resolves to
+/// an internal address after it was registered is not reached either.
+#[derive(Debug, Clone, Default)]
+pub struct WebhookAddressPolicy {
+    allowed_networks: Arc<[IpNet]>,
+}
+
+impl WebhookAddressPolicy {
+    pub fn new(config: &WebhookConfig) -> Self {
+        Self {
+            allowed_networks: config.allowed_networks.clone().into(),
+        }
+    }
+
+    /// Whether webhooks may be sent to an address
+    pub fn permits(&self, ip: IpAddr) -> bool {
+        let ip = ip.to_canonical();
+        !is_internal(ip)
+            || self
+                .allowed_networks
+                .iter()
+                .any(|network| network.contains(&ip))
+    }
+
+    /// The permitted addresses a host name resolves to
+    async fn lookup(&self, host: &str, port: u16) -> io::Result<Vec<SocketAddr>> {
+        let addrs: Vec<SocketAddr> = tokio::net::lookup_host((host, port))
+            .await?
+            .filter(|addr| self.permits(addr.ip()))
+            .collect();
+        if addrs.is_empty() {
+            return Err(io::Error::new(
+                io::ErrorKind::PermissionDenied,
+                format!("{host} does not resolve to a permitted address"),
+            ));
+        }
+        Ok(addrs)
+    }
+
+    /// Check that a webhook URL is an absolute http(s) URL whose host only
+    /// resolves to permitted addresses
+    pub async fn check_url(&self, url: &str) -> Result<(), AppError> {
+        let parsed = parse_webhook_url(url)?;
+        let refused = || {
+            AppError::validation_error(
+                format!("Webhook URL points to an internal address: {url}"),
+                "The webhook URL must point to a public address.",
+            )
+        };
+
+        let host = parsed.host_str().unwrap_or_default();
+        let addrs: Vec<IpAddr> = match host_address(host) {
+            Some(ip) => vec![ip],
+            None => {
+                let port = parsed.port_or_known_default().unwrap_or(80);
+                tokio::net::lookup_host((host, port))
+                    .await
+                    .map_err(|_| {
+                        AppError::validation_error(
+                            format!("Webhook host could not be resolved: {host}"),
+                            "The host of the webhook URL could not be resolved.",
+                        )
+                    })?
+                    .map(|addr| addr.ip())
+                    .collect()
+            }
+        };
+        if addrs.iter().all(|ip| self.permits(*ip)) {
+            Ok(())
+        } else {
+            Err(refused())
+        }
+    }
+
+    /// Check the host of a URL about to be requested when it is an address;
+    /// host names are checked when the client resolves them
+    fn check_literal_host(&self, url: &str) -> Result<(), String> {
+        let Some(ip) = Url::parse(url)
+            .ok()
+            .and_then(|url| url.host_str().and_then(host_address))
+        else {
+            return Ok(());
+        };
+        if self.permits(ip) {
+            Ok(())
+        } else {
+            Err(format!("Address {ip} is not permitted"))
+        }
+    }
+}
+
+impl Resolve for WebhookAddressPolicy {
+    fn resolve(&self, name: Name) -> Resolving {
+        let policy = self.clone();
+        Box::pin(async move {
+            let addrs = policy.lookup(name.as_str(), 0).await?;
+            Ok(Box::new(addrs.into_iter()) as Addrs)
+        })
+    }
+}
+
+/// The address of a URL host given as an address, e.g. `10.0.0.1` or `[::1]`
+fn host_address(host: &str) -> Option<IpAddr> {
+    host.trim_start_matches('[')
+        .trim_end_matches(']')
+        .parse()
+        .ok()
+}
+
+/// Whether an address belongs to the host itself or a non-public network
+fn is_internal(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => {
+            let [a, b, ..] = ip.octets();
+            ip.is_unspecified()
+                || ip.is_loopback()
+                || ip.is_private()
+                || ip.is_link_local()
+                || ip.is_broadcast()
+                || ip.is_multicast()
+                // "This network" and shared address space (RFC 6598)
+                || a == 0
+                || (a == 100 && (b & 0xc0) == 64)
+                // Benchmarking (RFC 2544) and reserved (RFC 1112)
+                || (a == 198 && (b & 0xfe) == 18)
+                || a >= 240
+        }
+        IpAddr::V6(ip) => {
+            if let Some(embedded) = embedded_ipv4(ip) {
+                return is_internal(IpAddr::V4(embedded));
+            }
+            let [first, second, third, ..] = ip.segments();
+            ip.is_unspecified()
+                || ip.is_loopback()
+                || ip.is_unique_local()
+                || ip.is_unicast_link_local()
+                || ip.is_multicast()
+                // Local-use NAT64 (RFC 8215)
+                || (first == 0x64 && second == 0xff9b && third == 1)
+        }
+    }
+}
+
+/// The IPv4 address carried by a NAT64 (RFC 6052) or 6to4 (RFC 3056)
+/// address, which reaches that IPv4 host through a gateway
+fn embedded_ipv4(ip: Ipv6Addr) -> Option<Ipv4Addr> {
+    match ip.segments() {
+        [0x64, 0xff9b, 0, 0, 0, 0, high, low] | [0x2002, high, low, ..] => {
+            Some(Ipv4Addr::from((u32::from(high) << 16) | u32::from(low)))
+        }
+        _ => None,
+    }
+}
+
+/// Service managing webhook endpoints and delivering events to them
+pub struct WebhookService {
+    pool: PgPool,
+    addresses: WebhookAddressPolicy,
+}
+
+impl WebhookService {
+    /// Create a new WebhookService with the given database pool
+    ///
+    /// Only public addresses are accepted as webhook targets; see
+    /// [`WebhookService::with_address_policy`].
+    pub fn new(pool: PgPool) -> Self {
+        Self {
+            pool,
+            addresses: WebhookAddressPolicy::default(),
+        }
+    }
+
+    /// Use the given policy to check the URLs of registered endpoints
+    pub fn with_address_policy(mut self, addresses: WebhookAddressPolicy) -> Self {
+        self.addresses = addresses;
+        self
+    }
+
+    /// List the webhook endpoints of a user
+    pub async fn list_endpoints(&self, user_id: Uuid) -> Result<Vec<WebhookEndpoint>, AppError> {
+        let rows = sqlx::query(
+            r#"
+            SELECT id, user_id, url, description, event_types, active, created_at, updated_at
+            FROM webhook_endpoints
+            WHERE user_id = $1
+            ORDER BY created_at
+            "#,
+        )
+        .bind(user_id)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| {
+            AppError::database_error("webhook endpoint listing", format!("Database error: {e}"))
+        })?;
+
+        Ok(rows.iter().map(endpoint_from_row).collect())
+    }
+
+    /// Get a webhook endpoint of a user
+    pub async fn get_endpoint(
+        &self,
+        user_id: Uuid,
+        endpoint_id: Uuid,
+    ) -> Result<WebhookEndpoint, AppError> {
+        let row = sqlx::query(
+            r#"
+            SELECT id, user_id, url, description, event_types, active, created_at, updated_at
+            FROM webhook_endpoints
+            WHERE id = $1 AND user_id = $2
+            "#,
+        )
+        .bind(endpoint_id)
+        .bind(user_id)
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| {
+            AppError::database_error("webhook endpoint lookup", format!("Database error: {e}"))
+        })?
+        .ok_or_else(|| endpoint_not_found(endpoint_id))?;
+
+        Ok(endpoint_from_row(&row))
+    }
+
+    /// Register a webhook endpoint with a new signing secret
+    pub async fn create_endpoint(
+        &self,
+        user_id: Uuid,
+        request: CreateWebhookRequest,
+    ) -> Result<WebhookEndpointWithSecret, AppError> {
+        self.addresses.check_url(&request.url).await?;
+
+        let mut tx = self.pool.begin().await.map_err(|e| {
+            AppError::database_error("transaction start", format!("Transaction error: {e}"))
+        })?;
+
+        // Lock the user so that concurrent requests cannot pass the limit together
+        sqlx::query("SELECT id FROM users WHERE id = $1 FOR UPDATE")
+            .bind(user_id)
+            .fetch_optional(&mut *tx)
+            .await
+            .map_err(|e| AppError::database_error("user lookup", format!("Database error: {e}")))?
+            .ok_or_else(|| AppError::not_found("User", format!("User {user_id} not found")))?;
+
+        let count: i64 =
+            sqlx::query_scalar("SELECT COUNT(*) FROM webhook_endpoints WHERE user_id = $1")
+                .bind(user_id)
+                .fetch_one(&mut *tx)
+                .await
+                .map_err(|e| {
+                    AppError::database_error(
+                        "webhook endpoint count",
+                        format!("Database error: {e}"),
+                    )
+                })?;
+        if count >= MAX_WEBHOOK_ENDPOINTS {
+            return Err(AppError::conflict(
+                "Webhook endpoint limit reached",
+                format!("A user can register at most {MAX_WEBHOOK_ENDPOINTS} webhook endpoints"),
+            ));
+        }
+
+        let secret = generate_webhook_secret();
+        let row = sqlx::query(
+            r#"
+            INSERT INTO webhook_endpoints (user_id, url, description, secret, event_types)
+            VALUES ($1, $2, $3, $4, $5)
+            RETURNING id, user_id, url, description, event_types, active, created_at, updated_at
+            "#,
+        )
+        .bind(user_id)
+        .bind(request.url.trim())
+        .bind(request.description)
+        .bind(&secret)
+        .bind(event_type_names(&request.event_types))
+        .fetch_one(&mut *tx)
+        .await
+        .map_err(|e| {
+            AppError::database_error("webhook endpoint creation", format!("Database error: {e}"))
+        })?;
+
+        tx.commit().await.map_err(|e| {
+            AppError::database_error("transaction commit", format!("Transaction error: {e}"))
+        })?;
+
+        Ok(WebhookEndpointWithSecret {
+            endpoint: endpoint_from_row(&row),
+            secret,
+        })
+    }
+
+    /// Update a webhook endpoint of a user
+    pub async fn update_endpoint(
+        &self,
+        user_id: Uuid,
+        endpoint_id: Uuid,
+        request: UpdateWebhookRequest,
+    ) -> Result<WebhookEndpoint, AppError> {
+        if let Some(url) = &request.url {
+            self.addresses.check_url(url).await?;
+        }
+
+        let row = sqlx::query(
+            r#"
+            UPDATE webhook_endpoints
+            SET url = COALESCE($3, url),
+                description = COALESCE($4, description),
+                event_types = COALESCE($5, event_types),
+                active = COALESCE($6, active)
+            WHERE id = $1 AND user_id = $2
+            RETURNING id, user_id, url, description, event_types, active, created_at, updated_at
+            "#,
+        )
+        .bind(endpoint_id)
+        .bind(user_id)
+        .bind(request.url.as_deref().map(str::trim))
+        .bind(request.description)
+        .bind(request.event_types.as_deref().map(event_type_names))
+        .bind(request.active)
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| {
+            AppError::database_error("webhook endpoint update", format!("Database error: {e}"))
+        })?
+        .ok_or_else(|| endpoint_not_found(endpoint_id))?;
+
+        Ok(endpoint_from_row(&row))
+    }
+
+    /// Replace the signing secret of a webhook endpoint
+    pub async fn rotate_secret(
+        &self,
+        user_id: Uuid,
+        endpoint_id: Uuid,
+    ) -> Result<WebhookEndpointWithSecret, AppError> {
+        let secret = generate_webhook_secret();
+        let row = sqlx::query(
+            r#"
+            UPDATE webhook_endpoints
+            SET secret = $3
+            WHERE id = $1 AND user_id = $2
+            RETURNING id, user_id, url, description, event_types, active, created_at, updated_at
+            "#,
+        )
+        .bind(endpoint_id)
+        .bind(user_id)
+        .bind(&secret)
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| {
+            AppError::database_error("webhook secret rotation", format!("Database error: {e}"))
+        })?
+        .ok_or_else(|| endpoint_not_found(endpoint_id))?;
+
+        Ok(WebhookEndpointWithSecret {
+            endpoint: endpoint_from_row(&row),
+            secret,
+        })
+    }
+
+    /// Delete a webhook endpoint of a user together with its deliveries
+    pub async fn delete_endpoint(&self, user_id: Uuid, endpoint_id: Uuid) -> Result<(), AppError> {
+        let result = sqlx::query("DELETE FROM webhook_endpoints WHERE id = $1 AND user_id = $2")
+            .bind(endpoint_id)
+            .bind(user_id)
+            .execute(&self.pool)
+            .await
+            .map_err(|e| {
+                AppError::database_error(
+                    "webhook endpoint deletion",
+                    format!("Database error: {e}"),
+                )
+            })?;
+
+        if result.rows_affected() == 0 {
+            return Err(endpoint_not_found(endpoint_id));
+        }
+        Ok(())
+    }
+
+    /// List the deliveries of a webhook endpoint, newest first
+    pub async fn list_deliveries(
+        &self,
+        user_id: Uuid,
+        endpoint_id: Uuid,
+        filter: &WebhookDeliveryFilter,
+        query: &PaginationQuery,
+    ) -> Result<PaginatedResponse<WebhookDelivery>, AppError> {
+        // Ensure the endpoint exists and belongs to the user
+        self.get_endpoint(user_id, endpoint_id).await?;
+
+        let status = filter.status.map(|status| status.as_str());
+        let total: i64 = sqlx::query_scalar(
+            r#"
+            SELECT COUNT(*) FROM webhook_deliveries
+            WHERE endpoint_id = $1 AND ($2::text IS NULL OR status = $2)
+            "#,
+        )
+        .bind(endpoint_id)
+        .bind(status)
+        .fetch_one(&self.pool)
+        .await
+        .map_err(|e| {
+            AppError::database_error("webhook delivery count", format!("Database error: {e}"))
+        })?;
+
+        let rows = sqlx::query(
+            r#"
+            SELECT id, endpoint_id, event_id, event_type, status, attempts,
+                   next_attempt_at, last_attempt_at, delivered_at, created_at
+            FROM webhook_deliveries
+            WHERE endpoint_id = $1 AND ($2::text IS NULL OR status = $2)
+            ORDER BY created_at DESC, id
+            LIMIT $3 OFFSET $4
+            "#,
+        )
+        .bind(endpoint_id)
+        .bind(status)
+        .bind(query.limit())
+        .bind(query.offset())
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| {
+            AppError::database_error("webhook delivery listing", format!("Database error: {e}"))
+        })?;
+
+        Ok(PaginatedResponse {
+            items: rows.iter().map(delivery_from_row).collect(),
+            pagination: Pagination::new(total, query),
+        })
+    }
+
+    /// Get a delivery of a webhook endpoint with its payload and attempt log
+    pub async fn get_delivery(
+        &self,
+        user_id: Uuid,
+        endpoint_id: Uuid,
+        delivery_id: Uuid,
+    ) -> Result<WebhookDeliveryDetail, AppError> {
+        let row = sqlx::query(
+            r#"
+            SELECT id, endpoint_id, event_id, event_type, status, attempts,
+                   next_attempt_at, last_attempt_at, delivered_at, created_at, payload
+            FROM webhook_deliveries
+            WHERE id = $1 AND endpoint_id = $2 AND user_id = $3
+            "#,
+        )
+        .bind(delivery_id)
+        .bind(endpoint_id)
+        .bind(user_id)
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| {
+            AppError::database_error("webhook delivery lookup", format!("Database error: {e}"))
+        })?
+        .ok_or_else(|| {
+            AppError::not_found(
+                "Webhook delivery",
+                format!("Webhook delivery with ID {delivery_id} not found"),
+            )
+        })?;
+
+        let attempts = sqlx::query(
+            r#"
+            SELECT id, attempt, response_status, response_body, error, duration_ms, created_at
+            FROM webhook_delivery_attempts
+            WHERE delivery_id = $1
+            ORDER BY attempt
+            "#,
+        )
+        .bind(delivery_id)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| {
+            AppError::database_error("webhook attempt listing", format!("Database error: {e}"))
+        })?;
+
+        Ok(WebhookDeliveryDetail {
+            delivery: delivery_from_row(&row),
+            payload: row.get("payload"),
+            attempt_log: attempts
+                .iter()
+                .map(|row| WebhookDeliveryAttempt {
+                    id: row.get("id"),
+                    attempt: row.get("attempt"),
+                    response_status: row.get("response_status"),
+                    response_body: row.get("response_body"),
+                    error: row.get("error"),
+                    duration_ms: row.get("duration_ms"),
+                    created_at: row.get("created_at"),
+                })
+                .collect(),
+        })
+    }
+
+    /// Send pending webhook deliveries that are due
+    ///
+    /// Deliveries are claimed with `SKIP LOCKED`, so several instances can run
+    /// this at the same time. Every request is logged as an attempt; a delivery
+    /// that did not get a 2xx response is retried with exponential backoff
+    /// until it has been attempted `MAX_DELIVERY_ATTEMPTS` times. Deliveries
+    /// of disabled endpoints wait until the endpoint is enabled again.
+    ///
+    /// The client must resolve host names with the [`WebhookAddressPolicy`]
+    /// of this service; URLs with an address as host are checked here.
+    pub async fn deliver_pending(
+        &self,
+        client: &reqwest::Client,
+    ) -> Result<WebhookDeliverySummary, AppError> {
+        let rows = sqlx::query(
+            r#"
+            UPDATE webhook_deliveries d
+            SET next_attempt_at = CURRENT_TIMESTAMP + make_interval(secs => $2)
+            FROM webhook_endpoints e
+            WHERE d.id IN (
+                SELECT d.id FROM webhook_deliveries d
+                JOIN webhook_endpoints e ON e.id = d.endpoint_id
+                WHERE d.status = 'pending' AND d.next_attempt_at <= CURRENT_TIMESTAMP
+                  AND e.active
+                ORDER BY d.next_attempt_at, d.created_at
+                LIMIT $1
+                FOR UPDATE OF d SKIP LOCKED
+            )
+              AND e.id = d.endpoint_id
+            RETURNING d.id, d.event_type, d.payload, d.attempts, e.url, e.secret
+            "#,
+        )
+        .bind(DELIVERY_BATCH_SIZE)
+        .bind(DELIVERY_LEASE_SECS as f64)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| {
+            AppError::database_error("webhook delivery claim", format!("Database error: {e}"))
+        })?;
+
+        let mut summary = WebhookDeliverySummary::default();
+        for row in rows {
+            let delivery_id: Uuid = row.get("id");
+            let attempt = row.get::<i32, _>("attempts") + 1;
+            let payload: Value = row.get("payload");
+            let event_type: String = row.get("event_type");
+            let url: String = row.get("url");
+            let secret: String = row.get("secret");
+
+            let body = payload.to_string();
+            let signature = sign_payload(&secret, Utc::now().timestamp(), &body);
+            let started = Instant::now();
+            let result = match self.addresses.check_literal_host(&url) {
+                Ok(()) => client
+                    .post(&url)
+                    .header(reqwest::header::CONTENT_TYPE, "application/json")
+                    .header(SIGNATURE_HEADER, signature)
+                    .header(EVENT_HEADER, &event_type)
+                    .header(DELIVERY_HEADER, delivery_id.to_string())
+                    .body(body)
+                    .send()
+                    .await
+                    .map_err(|e| format!("Request failed: {}", error_chain(&e))),
+                Err(e) => Err(format!("Request refused: {e}")),
+            };
+
+            let (response_status, response_body, error) = match result {
+                Ok(response) => {
+                    let status = response.status();
+                    let text = read_logged_body(response).await;
+                    let error =
+                        (!status.is_success()).then(|| format!("Endpoint responded with {status}"));
+                    (Some(status.as_u16() as i32), Some(text), error)
+                }
+                Err(e) => (None, None, Some(e)),
+            };
+            let duration_ms = started.elapsed().as_millis().min(i32::MAX as u128) as i32;
+
+            sqlx::query(
+                r#"
+                INSERT INTO webhook_delivery_attempts
+                (delivery_id, attempt, response_status, response_body, error, duration_ms)
+                VALUES ($1, $2, $3, $4, $5, $6)
+                "#,
+            )
+            .bind(delivery_id)
+            .bind(attempt)
+            .bind(response_status)
+            .bind(response_body)
+            .bind(&error)
+            .bind(duration_ms)
+            .execute(&self.pool)
+            .await
+            .map_err(|e| {
+                AppError::database_error("webhook attempt logging", format!("Database error: {e}"))
+            })?;
+
+            let status = match &error {
+                None => {
+                    summary.delivered += 1;
+                    "delivered"
+                }
+                Some(error) => {
+                    tracing::warn!(
+                        "Webhook delivery {} failed (attempt {}): {}",
+                        delivery_id,
+                        attempt,
+                        error
+                    );
+                    if attempt >= MAX_DELIVERY_ATTEMPTS {
+                        summary.failed += 1;
+                        "failed"
+                    } else {
+                        summary.retried += 1;
+                        "pending"
+                    }
+                }
+            };
+            let retry_in = exponential_backoff(RETRY_BASE_DELAY, attempt, RETRY_MAX_DELAY);
+
+            sqlx::query(
+                r#"
+                UPDATE webhook_deliveries
+                SET status = $2, attempts = $3, last_attempt_at = CURRENT_TIMESTAMP,
+                    delivered_at = CASE WHEN $2 = 'delivered' THEN CURRENT_TIMESTAMP END,
+                    next_attempt_at = CASE WHEN $2 = 'pending'
+                        THEN CURRENT_TIMESTAMP + make_interval(secs => $4) END
+                WHERE id = $1
+                "#,
+            )
+            .bind(delivery_id)
+            .bind(status)
+            .bind(attempt)
+            .bind(retry_in.as_secs_f64())
+            .execute(&self.pool)
+            .await
+            .map_err(|e| {
+                AppError::database_error("webhook delivery update", format!("Database error: {e}"))
+            })?;
+        }
+
+        Ok(summary)
+    }
+}
+
+/// Queue an event for every active webhook endpoint of a user subscribed to it
+///
+/// Runs with the given connection so that the event is only sent when the
+/// caller's transaction commits. Returns the number of queued deliveries.
+pub(crate) async fn queue_webhook_event(
+    conn: &mut PgConnection,
+    user_id: Uuid,
+    event: &WebhookEvent,
+) -> Result<u64, sqlx::Error> {
+    let result = sqlx::query(
+        r#"
+        INSERT INTO webhook_deliveries (endpoint_id, user_id, event_id, event_type, payload)
+        SELECT id, user_id, $2, $3, $4
+        FROM webhook_endpoints
+        WHERE user_id = $1 AND active
+          AND (cardinality(event_types) = 0 OR $3 = ANY(event_types))
+        "#,
+    )
+    .bind(user_id)
+    .bind(event.id)
+    .bind(event.event_type.as_str())
+    .bind(event.payload())
+    .execute(conn)
+    .await?;
+
+    Ok(result.rows_affected())
+}
+
+/// Queue the webhook events announcing recorded subscription events
+pub(crate) async fn queue_subscription_webhooks(
+    conn: &mut PgConnection,
+    subscription: &Subscription,
+    events: &[NewSubscriptionEvent],
+) -> Result<(), sqlx::Error> {
+    for event in WebhookEvent::for_changes(subscription, events) {
+        queue_webhook_event(&mut *conn, subscription.user_id, &event).await?;
+    }
+
+    Ok(())
+}
+
+/// Signature header value of a webhook body sent at `timestamp`
+///
+/// The value has the form `t=<unix timestamp>,v1=<hex HMAC-SHA256>`, where the
+/// HMAC is computed with the endpoint secret over `<timestamp>.<body>`.
+pub fn sign_payload(secret: &str, timestamp: i64, body: &str) -> String {
+    let mut mac =
+        Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC accepts keys of any length");
+    mac.update(format!("{timestamp}.{body}").as_bytes());
+    format!(
+        "t={timestamp},v1={}",
+        hex::encode(mac.finalize().into_bytes())
+    )
+}
+
+/// Start the background webhook delivery worker
+///
+/// Returns `None` when the worker is disabled.
+pub fn spawn_webhook_worker(pool: PgPool, config: WebhookConfig) -> Option<JoinHandle<()>> {
+    if !config.enabled {
+        tracing::info!("Webhook delivery worker is disabled");
+        return None;
+    }
+
+    let addresses = WebhookAddressPolicy::new(&config);
+    let client = match reqwest::Client::builder()
+        .dns_resolver(Arc::new(addresses.clone()))
+        .timeout(config.timeout)
+        .redirect(reqwest::redirect::Policy::none())
+        .user_agent(concat!("Sub-Pal-Webhooks/", env!("CARGO_PKG_VERSION")))
+        .build()
+    {
+        Ok(client) => client,
+        Err(e) => {
+            tracing::error!("Failed to create the webhook HTTP client: {}", e);
+            return None;
+        }
+    };
+
+    tracing::info!(
+        "Starting webhook delivery worker - interval: {:?}, timeout: {:?}",
+        config.interval,
+        config.timeout
+    );
+
+    Some(tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(config.interval);
+        ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+        loop {
+            ticker.tick().await;
+
+            let webhook_service =
+                WebhookService::new(pool.clone()).with_address_policy(addresses.clone());
+            match webhook_service.deliver_pending(&client).await {
+                Ok(summary) if summary.delivered + summary.retried + summary.failed > 0 => {
+                    tracing::info!(
+                        "Webhook run completed: {} delivered, {} to retry, {} failed",
+                        summary.delivered,
+                        summary.retried,
+                        summary.failed
+                    )
+                }
+                Ok(_) => {}
+                Err(e) => tracing::error!("Webhook run failed: {}", e),
+            }
+        }
+    }))
+}
+
+/// Parse a webhook URL, which must be an absolute http(s) URL
+fn parse_webhook_url(url: &str) -> Result<Url, AppError> {
+    let invalid = || {
+        AppError::validation_error(
+            format!("Invalid webhook URL: {url}"),
+            "The webhook URL must be an absolute http or https URL.",
+        )
+    };
+
+    let url = url.trim();
+    if url.len() > 2048 {
+        return Err(invalid());
+    }
+    let parsed = Url::parse(url).map_err(|_| invalid())?;
+    if !matches!(parsed.scheme(), "http" | "https") || parsed.host_str().is_none() {
+        return Err(invalid());
+    }
+    Ok(parsed)
+}
+
+/// The start of a response body, at most `MAX_LOGGED_RESPONSE_BYTES` long
+///
+/// Only as much of the body as is logged is read, so an endpoint sending a
+/// huge or endless response cannot exhaust the memory of the worker.
+async fn read_logged_body(mut response: reqwest::Response) -> String {
+    let mut body = Vec::new();
+    while body.len() <= MAX_LOGGED_RESPONSE_BYTES {
+        match response.chunk().await {
+            Ok(Some(chunk)) => body.extend_from_slice(&chunk),
+            Ok(None) | Err(_) => break,
+        }
+    }
+    // Keep the rest of a character cut at the limit, which `truncate` drops
+    body.truncate(MAX_LOGGED_RESPONSE_BYTES + 3);
+    truncate(
+        String::from_utf8_lossy(&body).into_owned(),
+        MAX_LOGGED_RESPONSE_BYTES,
+    )
+}
+
+/// An error with its sources, which reqwest leaves out of its own message
+fn error_chain(error: &dyn std::error::Error) -> String {
+    let mut message = error.to_string();
+    let mut source = error.source();
+    while let Some(error) = source {
+        message.push_str(&format!(": {error}"));
+        source = error.source();
+    }
+    message
+}
+
+/// Generate a random signing secret
+fn generate_webhook_secret() -> String {
+    let mut bytes = [0u8; 32];
+    rand::thread_rng().fill_bytes(&mut bytes);
+    format!("whsec_{}", hex::encode(bytes))
+}
+
+fn event_type_names(event_types: &[WebhookEventType]) -> Vec<String> {
+    let mut names: Vec<String> = event_types
+        .iter()
+        .map(|event_type| event_type.as_str().to_string())
+        .collect();
+    names.sort();
+    names.dedup();
+    names
+}
+
+/// Cut a string to at most `max` bytes without splitting a character
+fn truncate(mut text: String, max: usize) -> String {
+    if text.len() > max {
+        let mut end = max;
+        while !text.is_char_boundary(end) {
+            end -= 1;
+        }
+        text.truncate(end);
+    }
+    text
+}
+
+fn endpoint_not_found(endpoint_id: Uuid) -> AppError {
+    AppError::not_found(
+        "Webhook endpoint",
+        format!("Webhook endpoint with ID {endpoint_id} not found"),
+    )
+}
+
+pub(crate) fn endpoint_from_row(row: &PgRow) -> WebhookEndpoint {
+    WebhookEndpoint {
+        id: row.get("id"),
+        user_id: row.get("user_id"),
+        url: row.get("url"),
+        description: row.get("description"),
+        event_types: row
+            .get::<Vec<String>, _>("event_types")
+            .into_iter()
+            .map(Into::into)
+            .collect(),
+        active: row.get("active"),
+        created_at: row.get("created_at"),
+        updated_at: row.get("updated_at"),
+    }
+}
+
+fn delivery_from_row(row: &PgRow) -> WebhookDelivery {
+    WebhookDelivery {
+        id: row.get("id"),
+        endpoint_id: row.get("endpoint_id"),
+        event_id: row.get("event_id"),
+        event_type: row.get::<String, _>("event_type").into(),
+        status: row.get::<String, _>("status").into(),
+        attempts: row.get("attempts"),
+        next_attempt_at: row.get("next_attempt_at"),
+        last_attempt_at: row.get("last_attempt_at"),
+        delivered_at: row.get("delivered_at"),
+        created_at: row.get("created_at"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::test_support::{connect, create_user};
+    use std::env;
+
+    #[test]
+    fn test_sign_payload() {
+        let signature = sign_payload("secret", 1_700_000_000, r#"{"id":1}"#);
+        assert_eq!(
+            signature,
+            "t=1700000000,v1=3dd1b9aef568d75f6790a84bd2e5dfa1f44409eef3cbdbd3f10b837376100c11"
+        );
+        assert_ne!(
+            signature,
+            sign_payload("other", 1_700_000_000, r#"{"id":1}"#)
+        );
+    }
+
+    #[test]
+    fn test_parse_webhook_url() {
+        assert!(parse_webhook_url("https://example.com/hooks/sub-pal").is_ok());
+        assert!(parse_webhook_url("http://192.168.1.10:8123/api/webhook/abc").is_ok());
+        assert!(parse_webhook_url("ftp://example.com").is_err());
+        assert!(parse_webhook_url("example.com/hook").is_err());
+        assert!(parse_webhook_url("").is_err());
+    }
+
+    #[test]
+    fn test_internal_addresses_are_refused() {
+        let policy = WebhookAddressPolicy::default();
+        for ip in [
+            "127.0.0.1",
+            "10.1.2.3",
+            "172.16.0.1",
+            "192.168.1.10",
+            "169.254.169.254",
+            "100.64.0.1",
+            "0.0.0.0",
+            "::1",
+            "::",
+            "fd00::1",
+            "fe80::1",
+            "::ffff:127.0.0.1",
+            "198.18.0.1",
+            "198.19.255.254",
+            "240.0.0.1",
+            "64:ff9b::7f00:1",
+            "64:ff9b::10.1.2.3",
+            "64:ff9b:1::1",
+            "2002:7f00:1::",
+            "2002:a01:203::1",
+        ] {
+            assert!(!policy.permits(ip.parse().unwrap()), "{ip}");
+        }
+        assert!(policy.permits("93.184.215.14".parse().unwrap()));
+        assert!(policy.permits("198.20.0.1".parse().unwrap()));
+        assert!(policy.permits("64:ff9b::5db8:d70e".parse().unwrap()));
+        assert!(policy.permits("2002:5db8:d70e::1".parse().unwrap()));
+        assert!(policy.permits("2606:2800:21f:cb07:6820:80da:af6b:8b2c".parse().unwrap()));
+    }
+
+    #[tokio::test]
+    async fn test_allowed_networks_are_exempt() {
+        let policy = WebhookAddressPolicy::new(&WebhookConfig {
+            allowed_networks: vec!["192.168.1.0/24".parse().unwrap()],
+            ..WebhookConfig::default()
+        });
+
+        assert!(
+            policy
+                .check_url("http://192.168.1.10:8123/api/webhook/abc")
+                .await
+                .is_ok()
+        );
+        assert!(policy.check_url("http://192.168.2.10/hook").await.is_err());
+        assert!(policy.check_url("http://[::1]:8080/hook").await.is_err());
+        assert!(
+            policy
+                .check_literal_host("http://192.168.1.10/hook")
+                .is_ok()
+        );
+        assert!(policy.check_literal_host("http://127.0.0.1/hook").is_err());
+        assert!(
+            policy
+                .check_literal_host("https://example.com/hook")
+                .is_ok()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_only_the_logged_part_of_a_body_is_kept() {
+        let response = |body: String| reqwest::Response::from(axum::http::Response::new(body));
+
+        let text = read_logged_body(response("é".repeat(1000))).await;
+        assert_eq!(text, "é".repeat(MAX_LOGGED_RESPONSE_BYTES / 2));
+        let text = read_logged_body(response(format!("x{}", "é".repeat(1000)))).await;
+        assert_eq!(text.len(), MAX_LOGGED_RESPONSE_BYTES - 1);
+        assert_eq!(read_logged_body(response("ok".into())).await, "ok");
+    }
+
+    #[test]
+    fn test_truncate_keeps_characters_whole() {
+        assert_eq!(truncate("héllo".to_string(), 2), "h");
+        assert_eq!(truncate("hello".to_string(), 10), "hello");
+    }
+
+    #[tokio::test]
+    async fn test_concurrent_registrations_respect_the_endpoint_limit() {
+        // This test requires a valid DATABASE_URL environment variable
+        if let Ok(url) = env::var("DATABASE_URL") {
+            let pool = connect(url).await;
+            let (user_id, _) = create_user(&pool).await;
+
+            let tasks: Vec<_> = (0..MAX_WEBHOOK_ENDPOINTS + 5)
+                .map(|_| {
+                    let service = WebhookService::new(pool.clone());
+                    tokio::spawn(async move {
+                        service
+                            .create_endpoint(
+                                user_id,
+                                CreateWebhookRequest {
+                                    url: "https://93.184.215.14/hook".to_string(),
+                                    description: None,
+                                    event_types: Vec::new(),
+                                },
+                            )
+                            .await
+                    })
+                })
+                .collect();
+            let mut created = 0;
+            for task in tasks {
+                match task.await.unwrap() {
+                    Ok(_) => created += 1,
+                    Err(e) => assert!(matches!(e, AppError::Conflict { .. }), "{e}"),
+                }
+            }
+
+            assert_eq!(created, MAX_WEBHOOK_ENDPOINTS);
+            assert_eq!(
+                WebhookService::new(pool)
+                    .list_endpoints(user_id)
+                    .await
+                    .unwrap()
+                    .len() as i64,
+                MAX_WEBHOOK_ENDPOINTS
+            );
+        }
+    }
+}
@@ -0,0 +1,36 @@
+use axum::extract::FromRef;
+use sqlx::PgPool;
+use std::sync::Arc;
+
+use crate::config::{AccountConfig, LoginLockoutConfig};
+use crate::middleware::RateLimiter;
+use crate::services::mailer::MailSender;
+use crate::services::{OidcClient, WebhookAddressPolicy};
+
+/// State shared by all request handlers
+///
+/// Handlers that only need the database keep extracting `State<PgPool>`.
+#[derive(Clone)]
+pub struct AppState {
+    pub pool: PgPool,
+    /// Sender of account emails such as password resets, `None` when email is disabled
+    pub mailer: Option<Arc<dyn MailSender>>,
+    /// Base URL of the web app, used for links in emails
+    pub app_url: String,
+    /// Account deletion and notice settings
+    pub account: AccountConfig,
+    /// Lock of accounts after failed logins
+    pub lockout: LoginLockoutConfig,
+    /// Client of the single sign-on provider, `None` when single sign-on is disabled
+    pub oidc: Option<Arc<OidcClient>>,
+    /// Rate limit policies and the store counting requests against them
+    pub rate_limiter: Arc<RateLimiter>,
+    /// Addresses webhook endpoints may point to
+    pub webhook_addresses: WebhookAddressPolicy,
+}
+
+impl FromRef<AppState> for PgPool {
+    fn from_ref(state: &AppState) -> Self {
+        state.pool.clone()
+    }
+}
@@ -3,26 +3,121 @@ use argon2::{
     password_hash::{PasswordHash, PasswordHasher, PasswordVerifier, SaltString, rand_core::OsRng},
 };
 use axum::{
-    http::{HeaderMap, StatusCode, header::AUTHORIZATION},
+    extract::{FromRef, FromRequestParts},
+    http::{HeaderMap, StatusCode, header::AUTHORIZATION, request::Parts},
     response::{IntoResponse, Response},
 };
-use jsonwebtoken::{DecodingKey, EncodingKey, Header, Validation, decode, encode};
+use rand::RngCore;
 use serde::{Deserialize, Serialize};
-use std::env;
+use sha2::{Digest, Sha256};
+use sqlx::PgPool;
 use uuid::Uuid;
 
+use crate::models::ApiTokenScope;
+use crate::services::api_token_service::authenticate_api_token;
+use crate::services::session_service::is_session_active;
+use crate::utils::jwt::jwt_keys;
+use crate::utils::response::AppError;
+
 // Claims structure for JWT tokens
 #[derive(Debug, Serialize, Deserialize)]
 pub struct Claims {
     pub sub: String, // Subject (user ID)
     pub exp: usize,  // Expiration time
     pub iat: usize,  // Issued at
+    /// Session the token was issued to; missing in tokens issued before
+    /// sessions were tracked
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sid: Option<String>,
+}
+
+/// Prefix of personal API tokens, telling them apart from JWTs
+pub const API_TOKEN_PREFIX: &str = "sp_pat_";
+
+/// Kind of bearer token a request was authenticated with
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Credential {
+    /// Access token of an interactive login
+    Session,
+    /// Personal API token with its scope
+    ApiToken(ApiTokenScope),
 }
 
-// Authenticated user extracted from JWT
+// Authenticated user extracted from a JWT or an API token
 #[derive(Debug, Clone)]
 pub struct Auth {
     pub user_id: Uuid,
+    pub credential: Credential,
+    /// Session of the access token, `None` for API tokens
+    pub session_id: Option<Uuid>,
+}
+
+/// Extract the authenticated user in handlers, rejecting the request with
+/// `AppError::Unauthorized` when the bearer token is missing or invalid
+///
+/// Read-only API tokens are rejected with `AppError::Forbidden` on requests
+/// with a method that can change data.
+impl<S> FromRequestParts<S> for Auth
+where
+    PgPool: FromRef<S>,
+    S: Send + Sync,
+{
+    type Rejection = AppError;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        let pool = PgPool::from_ref(state);
+        let auth = extract_auth(&parts.headers, &pool)
+            .await
+            .map_err(|e| match e {
+                AuthError::MissingCredentials => AppError::unauthorized("Missing bearer token"),
+                AuthError::Unavailable => AppError::database_error(
+                    "credential lookup",
+                    "Database error while checking a bearer token",
+                ),
+                _ => AppError::unauthorized("Invalid or expired bearer token"),
+            })?;
+
+        if auth.credential == Credential::ApiToken(ApiTokenScope::Read) && !parts.method.is_safe() {
+            return Err(AppError::insufficient_scope(
+                "a read_write API token",
+                "This API token is read-only.",
+            ));
+        }
+
+        Ok(auth)
+    }
+}
+
+/// Authenticated user of an interactive login
+///
+/// Extracted by account management routes, which API tokens cannot use.
+#[derive(Debug, Clone)]
+pub struct SessionAuth {
+    pub user_id: Uuid,
+    pub session_id: Option<Uuid>,
+}
+
+impl<S> FromRequestParts<S> for SessionAuth
+where
+    PgPool: FromRef<S>,
+    S: Send + Sync,
+{
+    type Rejection = AppError;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        let auth = Auth::from_request_parts(parts, state).await?;
+        if auth.credential != Credential::Session {
+            return Err(AppError::insufficient_scope(
+                "a signed-in session",
+                "API tokens cannot manage your account. Please sign in to do this.",
+            ));
+        }
+
+        Ok(SessionAuth {
+            user_id: auth.user_id,
+            session_id: auth.session_id,
+        })
+    }
 }
 
 // Authentication error
@@ -33,6 +128,8 @@ pub enum AuthError {
     MissingCredentials,
     TokenCreation,
     InvalidToken,
+    /// The credentials could not be checked, e.g. because the database failed
+    Unavailable,
 }
 
 // Implement IntoResponse for AuthError
@@ -43,6 +140,10 @@ impl IntoResponse for AuthError {
             AuthError::MissingCredentials => (StatusCode::BAD_REQUEST, "Missing credentials"),
             AuthError::TokenCreation => (StatusCode::INTERNAL_SERVER_ERROR, "Token creation error"),
             AuthError::InvalidToken => (StatusCode::UNAUTHORIZED, "Invalid token"),
+            AuthError::Unavailable => (
+                StatusCode::SERVICE_UNAVAILABLE,
+                "Authentication unavailable",
+            ),
         };
 
         // Create a response with the status code and error message
@@ -51,11 +152,15 @@ impl IntoResponse for AuthError {
 }
 
 /// Extract authenticated user from request headers
-pub fn extract_auth(headers: &HeaderMap) -> Result<Auth, AuthError> {
+///
+/// The bearer token is either a JWT access token or a personal API token.
+/// Access tokens of a session that was signed out are rejected, so signing
+/// out takes effect before they expire.
+pub async fn extract_auth(headers: &HeaderMap, pool: &PgPool) -> Result<Auth, AuthError> {
     // Extract the token from the Authorization header
     let auth_header = headers
         .get(AUTHORIZATION)
-        .ok_or(AuthError::InvalidToken)?
+        .ok_or(AuthError::MissingCredentials)?
         .to_str()
         .map_err(|_| AuthError::InvalidToken)?;
 
@@ -67,10 +172,43 @@ pub fn extract_auth(headers: &HeaderMap) -> Result<Auth, AuthError> {
     // Extract the token
     let token = &auth_header[7..];
 
-    // Extract the user ID from the token
-    let user_id = extract_user_id_from_token(token)?;
+    if token.starts_with(API_TOKEN_PREFIX) {
+        let (user_id, scope) = authenticate_api_token(pool, token)
+            .await
+            .map_err(|e| {
+                tracing::error!("API token lookup failed: {}", e);
+                AuthError::Unavailable
+            })?
+            .ok_or(AuthError::InvalidToken)?;
 
-    Ok(Auth { user_id })
+        return Ok(Auth {
+            user_id,
+            credential: Credential::ApiToken(scope),
+            session_id: None,
+        });
+    }
+
+    // Extract the user and session IDs from the token
+    let (user_id, session_id) = extract_session_from_token(token)?;
+
+    // Tokens issued before sessions were tracked have no session to check
+    if let Some(session_id) = session_id {
+        let active = is_session_active(pool, user_id, session_id)
+            .await
+            .map_err(|e| {
+                tracing::error!("Session lookup failed: {}", e);
+                AuthError::Unavailable
+            })?;
+        if !active {
+            return Err(AuthError::InvalidToken);
+        }
+    }
+
+    Ok(Auth {
+        user_id,
+        credential: Credential::Session,
+        session_id,
+    })
 }
 
 // Hash a password
@@ -90,10 +228,11 @@ pub fn verify_password(password: &str, hash: &str) -> Result<bool, argon2::passw
     Ok(result.is_ok())
 }
 
-// Generate a JWT token
-pub fn generate_token(user_id: Uuid) -> Result<String, jsonwebtoken::errors::Error> {
-    let jwt_secret = env::var("JWT_SECRET").unwrap_or_else(|_| "secret".to_string());
-
+// Generate a JWT access token of a session, signed with the current key
+pub fn generate_token(
+    user_id: Uuid,
+    session_id: Uuid,
+) -> Result<String, jsonwebtoken::errors::Error> {
     // Create claims for the token
     let current_time = std::time::SystemTime::now()
         .duration_since(std::time::UNIX_EPOCH)
@@ -104,56 +243,52 @@ pub fn generate_token(user_id: Uuid) -> Result<String, jsonwebtoken::errors::Err
         sub: user_id.to_string(),
         exp: current_time + 3600, // Token expires in 1 hour
         iat: current_time,
+        sid: Some(session_id.to_string()),
     };
 
-    // Encode the token
-    encode(
-        &Header::default(),
-        &claims,
-        &EncodingKey::from_secret(jwt_secret.as_bytes()),
-    )
+    jwt_keys().sign(&claims)
 }
 
-// Generate a refresh token
-pub fn generate_refresh_token(user_id: Uuid) -> Result<String, jsonwebtoken::errors::Error> {
-    let jwt_secret = env::var("JWT_SECRET").unwrap_or_else(|_| "secret".to_string());
+/// Days a refresh token stays valid
+pub const REFRESH_TOKEN_TTL_DAYS: i64 = 30;
 
-    // Create claims for the refresh token
-    let current_time = std::time::SystemTime::now()
-        .duration_since(std::time::UNIX_EPOCH)
-        .unwrap()
-        .as_secs() as usize;
+// Generate an opaque refresh token; only its hash is stored
+pub fn generate_refresh_token() -> String {
+    generate_opaque_token()
+}
 
-    let claims = Claims {
-        sub: user_id.to_string(),
-        exp: current_time + 30 * 24 * 3600, // Refresh token expires in 30 days
-        iat: current_time,
-    };
+// Generate a random URL-safe token
+pub fn generate_opaque_token() -> String {
+    let mut bytes = [0u8; 32];
+    rand::thread_rng().fill_bytes(&mut bytes);
+    hex::encode(bytes)
+}
 
-    // Encode the token
-    encode(
-        &Header::default(),
-        &claims,
-        &EncodingKey::from_secret(jwt_secret.as_bytes()),
-    )
+// Generate a personal API token; only its hash is stored
+pub fn generate_api_token() -> String {
+    format!("{API_TOKEN_PREFIX}{}", generate_opaque_token())
 }
 
-// Extract user ID from JWT token
-pub fn extract_user_id_from_token(token: &str) -> Result<Uuid, AuthError> {
-    let jwt_secret = env::var("JWT_SECRET").unwrap_or_else(|_| "secret".to_string());
+// Hash an opaque token for storage and lookup
+pub fn hash_token(token: &str) -> String {
+    hex::encode(Sha256::digest(token.as_bytes()))
+}
 
-    // Decode and validate the token
-    let token_data = decode::<Claims>(
-        token,
-        &DecodingKey::from_secret(jwt_secret.as_bytes()),
-        &Validation::default(),
-    )
-    .map_err(|_| AuthError::InvalidToken)?;
+// Extract the user ID and the session ID, if any, from a JWT access token
+pub fn extract_session_from_token(token: &str) -> Result<(Uuid, Option<Uuid>), AuthError> {
+    // Decode and validate the token with the key it names
+    let claims: Claims = jwt_keys()
+        .verify(token)
+        .map_err(|_| AuthError::InvalidToken)?;
 
-    // Extract user ID from claims
-    let user_id = Uuid::parse_str(&token_data.claims.sub).map_err(|_| AuthError::InvalidToken)?;
+    // Extract user and session IDs from claims
+    let user_id = Uuid::parse_str(&claims.sub).map_err(|_| AuthError::InvalidToken)?;
+    let session_id = claims
+        .sid
+        .map(|sid| Uuid::parse_str(&sid).map_err(|_| AuthError::InvalidToken))
+        .transpose()?;
 
-    Ok(user_id)
+    Ok((user_id, session_id))
 }
 
 #[cfg(test)]
@@ -168,4 +303,68 @@ mod tests {
         assert!(verify_password(password, &hash).unwrap());
         assert!(!verify_password("wrong_password", &hash).unwrap());
     }
+
+    #[test]
+    fn test_refresh_tokens_are_opaque_and_hashed() {
+        let token = generate_refresh_token();
+        assert_eq!(token.len(), 64);
+        assert_ne!(token, generate_refresh_token());
+        // A refresh token is no JWT and cannot be used as an access token
+        assert!(extract_session_from_token(&token).is_err());
+
+        assert_eq!(hash_token(&token), hash_token(&token));
+        assert_ne!(hash_token(&token), token);
+        assert_eq!(
+            hash_token("abc"),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+    }
+
+    #[test]
+    fn test_api_tokens_are_prefixed_and_no_jwt() {
+        let token = generate_api_token();
+        assert!(token.starts_with(API_TOKEN_PREFIX));
+        assert_eq!(token.len(), API_TOKEN_PREFIX.len() + 64);
+        assert_ne!(token, generate_api_token());
+        assert!(extract_session_from_token(&token).is_err());
+    }
+
+    #[test]
+    fn test_access_token_round_trip() {
+        let user_id = Uuid::new_v4();
+        let session_id = Uuid::new_v4();
+        let token = generate_token(user_id, session_id).unwrap();
+        assert_eq!(
+            extract_session_from_token(&token).unwrap(),
+            (user_id, Some(session_id))
+        );
+
+        // Signed with the old fallback secret and without a key id
+        let forged = jsonwebtoken::encode(
+            &jsonwebtoken::Header::default(),
+            &Claims {
+                sub: user_id.to_string(),
+                exp: usize::MAX / 2,
+                iat: 0,
+                sid: None,
+            },
+            &jsonwebtoken::EncodingKey::from_secret(b"secret"),
+        )
+        .unwrap();
+        assert!(extract_session_from_token(&forged).is_err());
+
+        // Tokens issued before sessions were tracked have no session
+        let legacy = jwt_keys()
+            .sign(&Claims {
+                sub: user_id.to_string(),
+                exp: usize::MAX / 2,
+                iat: 0,
+                sid: None,
+            })
+            .unwrap();
+        assert_eq!(
+            extract_session_from_token(&legacy).unwrap(),
+            (user_id, None)
+        );
+    }
 }
@@ -0,0 +1,29 @@
+use std::time::Duration;
+
+/// Delay before retrying something that failed `attempts` times
+///
+/// Starts at `base` after the first failure and doubles with every further
+/// attempt, up to `max`.
+pub fn exponential_backoff(base: Duration, attempts: i32, max: Duration) -> Duration {
+    let exponent = attempts.clamp(1, 31) as u32 - 1;
+    base.saturating_mul(1 << exponent).min(max)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_exponential_backoff() {
+        let base = Duration::from_secs(60);
+        let max = Duration::from_secs(6 * 60 * 60);
+
+        assert_eq!(exponential_backoff(base, 1, max), Duration::from_secs(60));
+        assert_eq!(exponential_backoff(base, 2, max), Duration::from_secs(120));
+        assert_eq!(exponential_backoff(base, 4, max), Duration::from_secs(480));
+        // Capped at the maximum, also for absurd attempt counts
+        assert_eq!(exponential_backoff(base, 12, max), max);
+        assert_eq!(exponential_backoff(base, 1000, max), max);
+        assert_eq!(exponential_backoff(base, 0, max), base);
+    }
+}
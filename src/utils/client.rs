@@ -0,0 +1,299 @@
+use axum::{
+    extract::{ConnectInfo, FromRequestParts},
+    http::{Extensions, HeaderMap, header::USER_AGENT, request::Parts},
+};
+use ipnet::IpNet;
+use std::convert::Infallible;
+use std::net::{IpAddr, SocketAddr};
+
+use crate::config::ProxyConfig;
+use crate::config::proxy::ClientIpHeader;
+
+/// Longest user agent kept for a session, in characters
+const MAX_USER_AGENT_LEN: usize = 512;
+
+/// Device details of the client making a request, recorded with sessions
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ClientInfo {
+    pub user_agent: Option<String>,
+    pub ip_address: Option<String>,
+}
+
+impl<S> FromRequestParts<S> for ClientInfo
+where
+    S: Send + Sync,
+{
+    type Rejection = Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        let user_agent = parts
+            .headers
+            .get(USER_AGENT)
+            .and_then(|value| value.to_str().ok())
+            .map(str::trim)
+            .filter(|value| !value.is_empty())
+            .map(|value| value.chars().take(MAX_USER_AGENT_LEN).collect());
+        let ip_address = ClientIp::of(&parts.extensions).map(|ip| ip.to_string());
+
+        Ok(ClientInfo {
+            user_agent,
+            ip_address,
+        })
+    }
+}
+
+/// Address of the client making a request, resolved through trusted proxies
+///
+/// Stored in the request extensions by the `resolve_client_ip` middleware.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ClientIp(pub IpAddr);
+
+impl ClientIp {
+    /// The client address of a request, falling back to the address of the
+    /// connection when it was not resolved
+    pub fn of(extensions: &Extensions) -> Option<IpAddr> {
+        extensions
+            .get::<ClientIp>()
+            .map(|ClientIp(ip)| *ip)
+            .or_else(|| {
+                extensions
+                    .get::<ConnectInfo<SocketAddr>>()
+                    .map(|ConnectInfo(addr)| addr.ip().to_canonical())
+            })
+    }
+}
+
+/// Finds the address of the client behind the reverse proxies in front of
+/// the server
+///
+/// Forwarding headers are only believed on connections from a trusted
+/// proxy, and only as far back as the chain of trusted proxies reaches:
+/// the address list is read from the right, and the first address that is
+/// not a trusted proxy is the client. Addresses further left were written
+/// by the client itself and could be anything.
+#[derive(Debug, Clone)]
+pub struct ClientIpResolver {
+    trusted_proxies: Vec<IpNet>,
+    header: ClientIpHeader,
+}
+
+impl ClientIpResolver {
+    pub fn new(config: &ProxyConfig) -> Self {
+        Self {
+            trusted_proxies: config.trusted_proxies.clone(),
+            header: config.client_ip_header,
+        }
+    }
+
+    /// The address of the client of a request from the given peer
+    pub fn resolve(&self, peer: IpAddr, headers: &HeaderMap) -> IpAddr {
+        let peer = peer.to_canonical();
+        if !self.is_trusted(peer) {
+            return peer;
+        }
+
+        let mut client = peer;
+        for hop in self.forwarded_addresses(headers).into_iter().rev() {
+            // An address that cannot be read ends the chain that can be followed
+            let Some(hop) = hop else {
+                break;
+            };
+            client = hop;
+            if !self.is_trusted(hop) {
+                break;
+            }
+        }
+        client
+    }
+
+    fn is_trusted(&self, ip: IpAddr) -> bool {
+        self.trusted_proxies
+            .iter()
+            .any(|network| network.contains(&ip))
+    }
+
+    /// The addresses in the configured header, client first; `None` for
+    /// addresses that cannot be read
+    fn forwarded_addresses(&self, headers: &HeaderMap) -> Vec<Option<IpAddr>> {
+        let name = match self.header {
+            ClientIpHeader::XForwardedFor => "x-forwarded-for",
+            ClientIpHeader::Forwarded => "forwarded",
+            ClientIpHeader::XRealIp => "x-real-ip",
+        };
+        let mut addresses = Vec::new();
+        for value in headers.get_all(name) {
+            let Ok(value) = value.to_str() else {
+                addresses.push(None);
+                continue;
+            };
+            match self.header {
+                ClientIpHeader::XForwardedFor => {
+                    addresses.extend(value.split(',').map(parse_node));
+                }
+                ClientIpHeader::Forwarded => {
+                    addresses.extend(split_unquoted(value, ',').into_iter().map(|element| {
+                        split_unquoted(element, ';')
+                            .into_iter()
+                            .filter_map(|pair| pair.split_once('='))
+                            .find(|(name, _)| name.trim().eq_ignore_ascii_case("for"))
+                            .and_then(|(_, node)| parse_node(node))
+                    }));
+                }
+                // Only the last proxy's view is in the header
+                ClientIpHeader::XRealIp => addresses = vec![parse_node(value)],
+            }
+        }
+        addresses
+    }
+}
+
+/// Parse a forwarded node: an address, optionally quoted, bracketed or with
+/// a port, e.g. `203.0.113.7`, `203.0.113.7:4711` or `"[2001:db8::1]:4711"`
+///
+/// Obfuscated identifiers and `unknown` give `None`.
+fn parse_node(node: &str) -> Option<IpAddr> {
+    let node = node.trim().trim_matches('"');
+    let ip = if let Some(rest) = node.strip_prefix('[') {
+        rest.split_once(']')?.0.parse().ok()?
+    } else {
+        node.parse::<IpAddr>()
+            .ok()
+            .or_else(|| node.parse::<SocketAddr>().ok().map(|addr| addr.ip()))?
+    };
+    Some(IpAddr::to_canonical(&ip))
+}
+
+/// Split a header value at a separator outside quoted strings
+fn split_unquoted(value: &str, separator: char) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let mut start = 0;
+    let mut quoted = false;
+    let mut escaped = false;
+    for (index, c) in value.char_indices() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' if quoted => escaped = true,
+            '"' => quoted = !quoted,
+            c if c == separator && !quoted => {
+                parts.push(&value[start..index]);
+                start = index + c.len_utf8();
+            }
+            _ => {}
+        }
+    }
+    parts.push(&value[start..]);
+    parts
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::HeaderValue;
+
+    fn resolver(header: ClientIpHeader) -> ClientIpResolver {
+        ClientIpResolver::new(&ProxyConfig {
+            trusted_proxies: vec![
+                "10.0.0.0/8".parse().unwrap(),
+                "2001:db8:ffff::/48".parse().unwrap(),
+            ],
+            client_ip_header: header,
+        })
+    }
+
+    fn header_map(name: &'static str, values: &[&'static str]) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        for value in values {
+            headers.append(name, HeaderValue::from_static(value));
+        }
+        headers
+    }
+
+    fn ip(ip: &str) -> IpAddr {
+        ip.parse().unwrap()
+    }
+
+    #[test]
+    fn test_headers_from_untrusted_peers_are_ignored() {
+        let resolver = resolver(ClientIpHeader::XForwardedFor);
+        let headers = header_map("x-forwarded-for", &["198.51.100.1"]);
+
+        assert_eq!(
+            resolver.resolve(ip("203.0.113.7"), &headers),
+            ip("203.0.113.7")
+        );
+        assert_eq!(
+            resolver.resolve(ip("::ffff:203.0.113.7"), &headers),
+            ip("203.0.113.7")
+        );
+        assert_eq!(
+            resolver.resolve(ip("10.0.0.2"), &HeaderMap::new()),
+            ip("10.0.0.2")
+        );
+    }
+
+    #[test]
+    fn test_x_forwarded_for_is_followed_through_trusted_proxies() {
+        let resolver = resolver(ClientIpHeader::XForwardedFor);
+
+        // The client made up the first address; the proxies appended the rest
+        let headers = header_map(
+            "x-forwarded-for",
+            &["198.51.100.1, 203.0.113.7", "10.0.0.3"],
+        );
+        assert_eq!(
+            resolver.resolve(ip("10.0.0.2"), &headers),
+            ip("203.0.113.7")
+        );
+
+        // Only proxies in the chain: the first of them is the client
+        let headers = header_map("x-forwarded-for", &["10.0.0.4, 10.0.0.3"]);
+        assert_eq!(resolver.resolve(ip("10.0.0.2"), &headers), ip("10.0.0.4"));
+
+        // The chain cannot be followed past garbage
+        let headers = header_map("x-forwarded-for", &["203.0.113.7, nonsense, 10.0.0.3"]);
+        assert_eq!(resolver.resolve(ip("10.0.0.2"), &headers), ip("10.0.0.3"));
+
+        let headers = header_map("x-forwarded-for", &["203.0.113.7:4711"]);
+        assert_eq!(
+            resolver.resolve(ip("10.0.0.2"), &headers),
+            ip("203.0.113.7")
+        );
+    }
+
+    #[test]
+    fn test_forwarded_nodes_are_parsed() {
+        let resolver = resolver(ClientIpHeader::Forwarded);
+
+        let headers = header_map(
+            "forwarded",
+            &[
+                r#"for=198.51.100.1;proto=https, For="[2001:db8::17]:4711";by=10.0.0.9"#,
+                r#"for="[2001:db8:ffff::1]";host="example.com,evil""#,
+            ],
+        );
+        assert_eq!(
+            resolver.resolve(ip("10.0.0.2"), &headers),
+            ip("2001:db8::17")
+        );
+
+        let headers = header_map("forwarded", &["for=unknown, for=10.0.0.3"]);
+        assert_eq!(resolver.resolve(ip("10.0.0.2"), &headers), ip("10.0.0.3"));
+        let headers = header_map("forwarded", &["for=_hidden;proto=http"]);
+        assert_eq!(resolver.resolve(ip("10.0.0.2"), &headers), ip("10.0.0.2"));
+    }
+
+    #[test]
+    fn test_x_real_ip_is_the_last_value() {
+        let resolver = resolver(ClientIpHeader::XRealIp);
+
+        let headers = header_map("x-real-ip", &["198.51.100.1", "203.0.113.7"]);
+        assert_eq!(
+            resolver.resolve(ip("10.0.0.2"), &headers),
+            ip("203.0.113.7")
+        );
+
+        // Other headers are not looked at
+        let headers = header_map("x-forwarded-for", &["203.0.113.7"]);
+        assert_eq!(resolver.resolve(ip("10.0.0.2"), &headers), ip("10.0.0.2"));
+    }
+}
@@ -0,0 +1,194 @@
+/// An email template with a plain-text and an HTML body
+///
+/// Both bodies use `{{key}}` placeholders. Values are HTML-escaped when they
+/// are put into the HTML body.
+pub struct EmailTemplate {
+    pub text: &'static str,
+    pub html: &'static str,
+}
+
+/// Template of renewal and trial ending reminders
+pub const REMINDER_TEMPLATE: EmailTemplate = EmailTemplate {
+    text: include_str!("../templates/email/reminder.txt"),
+    html: include_str!("../templates/email/reminder.html"),
+};
+
+/// Template of password reset emails
+pub const PASSWORD_RESET_TEMPLATE: EmailTemplate = EmailTemplate {
+    text: include_str!("../templates/email/password_reset.txt"),
+    html: include_str!("../templates/email/password_reset.html"),
+};
+
+/// Template of email address verification emails
+pub const VERIFY_EMAIL_TEMPLATE: EmailTemplate = EmailTemplate {
+    text: include_str!("../templates/email/verify_email.txt"),
+    html: include_str!("../templates/email/verify_email.html"),
+};
+
+/// Template of emails about a login from a new device
+pub const NEW_DEVICE_TEMPLATE: EmailTemplate = EmailTemplate {
+    text: include_str!("../templates/email/new_device.txt"),
+    html: include_str!("../templates/email/new_device.html"),
+};
+
+/// Template of emails with a link unlocking an account locked after failed logins
+pub const UNLOCK_ACCOUNT_TEMPLATE: EmailTemplate = EmailTemplate {
+    text: include_str!("../templates/email/unlock_account.txt"),
+    html: include_str!("../templates/email/unlock_account.html"),
+};
+
+impl EmailTemplate {
+    /// Render the plain-text and HTML bodies with the given values
+    pub fn render(&self, values: &[(&str, &str)]) -> (String, String) {
+        (
+            fill(self.text, values, |value| value.to_string()),
+            fill(self.html, values, escape_html),
+        )
+    }
+}
+
+/// Replace the placeholders of a template in a single pass
+///
+/// Values are not searched for placeholders themselves, so a value that
+/// contains `{{key}}`, such as a user's name, cannot pull another value
+/// into the email. Unknown placeholders are left as they are.
+fn fill(template: &str, values: &[(&str, &str)], encode: impl Fn(&str) -> String) -> String {
+    let mut rendered = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find("{{") {
+        rendered.push_str(&rest[..start]);
+        let after = &rest[start + 2..];
+        let replacement = after.find("}}").and_then(|end| {
+            values
+                .iter()
+                .find(|(key, _)| *key == &after[..end])
+                .map(|(_, value)| (end, value))
+        });
+        match replacement {
+            Some((end, value)) => {
+                rendered.push_str(&encode(value));
+                rest = &after[end + 2..];
+            }
+            None => {
+                rendered.push_str("{{");
+                rest = after;
+            }
+        }
+    }
+    rendered.push_str(rest);
+    rendered
+}
+
+/// Escape the characters that have a special meaning in HTML
+fn escape_html(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_replaces_placeholders() {
+        let template = EmailTemplate {
+            text: "Hi {{name}}, {{message}} {{name}}",
+            html: "<p>Hi {{name}}, {{message}}</p>",
+        };
+
+        let (text, html) = template.render(&[("name", "Tom & Jerry"), ("message", "<b>soon</b>")]);
+        assert_eq!(text, "Hi Tom & Jerry, <b>soon</b> Tom & Jerry");
+        assert_eq!(html, "<p>Hi Tom &amp; Jerry, &lt;b&gt;soon&lt;/b&gt;</p>");
+    }
+
+    #[test]
+    fn test_values_are_not_rendered_again() {
+        let template = EmailTemplate {
+            text: "Hi {{name}}, open {{url}} {{unknown}}",
+            html: "<p>Hi {{name}}</p>",
+        };
+
+        let (text, html) = template.render(&[("name", "{{url}}"), ("url", "http://localhost/a")]);
+        assert_eq!(text, "Hi {{url}}, open http://localhost/a {{unknown}}");
+        assert_eq!(html, "<p>Hi {{url}}</p>");
+    }
+
+    #[test]
+    fn test_reminder_template_has_no_unknown_placeholders() {
+        let (text, html) = REMINDER_TEMPLATE.render(&[
+            ("name", "Alice"),
+            ("title", "Netflix renews soon"),
+            ("message", "Your Netflix subscription renews tomorrow."),
+            ("app_url", "http://localhost"),
+        ]);
+        assert!(!text.contains("{{"));
+        assert!(!html.contains("{{"));
+        assert!(text.contains("Your Netflix subscription renews tomorrow."));
+    }
+
+    #[test]
+    fn test_password_reset_template_has_no_unknown_placeholders() {
+        let (text, html) = PASSWORD_RESET_TEMPLATE.render(&[
+            ("name", "Alice"),
+            ("reset_url", "http://localhost/reset-password?token=abc"),
+            ("expires_minutes", "60"),
+        ]);
+        assert!(!text.contains("{{"));
+        assert!(!html.contains("{{"));
+        assert!(text.contains("http://localhost/reset-password?token=abc"));
+        assert!(html.contains("href=\"http://localhost/reset-password?token=abc\""));
+    }
+
+    #[test]
+    fn test_verify_email_template_has_no_unknown_placeholders() {
+        let (text, html) = VERIFY_EMAIL_TEMPLATE.render(&[
+            ("name", "Alice"),
+            ("email", "alice@example.com"),
+            ("verify_url", "http://localhost/verify-email?token=abc"),
+            ("expires_hours", "24"),
+        ]);
+        assert!(!text.contains("{{"));
+        assert!(!html.contains("{{"));
+        assert!(text.contains("http://localhost/verify-email?token=abc"));
+        assert!(html.contains("href=\"http://localhost/verify-email?token=abc\""));
+    }
+
+    #[test]
+    fn test_new_device_template_has_no_unknown_placeholders() {
+        let (text, html) = NEW_DEVICE_TEMPLATE.render(&[
+            ("name", "Alice"),
+            ("device", "Mozilla/5.0 <Firefox>"),
+            ("ip_address", "203.0.113.7"),
+            ("signed_in_at", "2025-09-05 09:00 UTC"),
+            ("sessions_url", "http://localhost/profile"),
+        ]);
+        assert!(!text.contains("{{"));
+        assert!(!html.contains("{{"));
+        assert!(text.contains("Mozilla/5.0 <Firefox>"));
+        assert!(html.contains("Mozilla/5.0 &lt;Firefox&gt;"));
+        assert!(html.contains("href=\"http://localhost/profile\""));
+    }
+
+    #[test]
+    fn test_unlock_account_template_has_no_unknown_placeholders() {
+        let (text, html) = UNLOCK_ACCOUNT_TEMPLATE.render(&[
+            ("name", "Alice"),
+            ("unlock_url", "http://localhost/unlock-account?token=abc"),
+            ("expires_hours", "24"),
+        ]);
+        assert!(!text.contains("{{"));
+        assert!(!html.contains("{{"));
+        assert!(text.contains("http://localhost/unlock-account?token=abc"));
+        assert!(html.contains("href=\"http://localhost/unlock-account?token=abc\""));
+    }
+}
@@ -0,0 +1,438 @@
+use data_encoding::BASE64URL_NOPAD;
+use jsonwebtoken::errors::{Error as JwtError, ErrorKind as JwtErrorKind};
+use jsonwebtoken::jwk::{
+    AlgorithmParameters, CommonParameters, EllipticCurve, EllipticCurveKeyParameters,
+    EllipticCurveKeyType, Jwk, JwkSet, KeyAlgorithm, OctetKeyPairParameters, OctetKeyPairType,
+    PublicKeyUse, RSAKeyParameters, RSAKeyType,
+};
+use jsonwebtoken::{
+    Algorithm, DecodingKey, EncodingKey, Header, Validation, decode, decode_header,
+};
+use rand::RngCore;
+use ring::rand::SystemRandom;
+use ring::signature::{self, KeyPair};
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+use std::str::FromStr;
+use std::sync::OnceLock;
+
+use crate::config::jwt::{JWT_SECRET_KID, JwtConfig, JwtKeySource, JwtKeySpec, MIN_JWT_SECRET_LEN};
+
+/// Secrets from examples and old defaults, never accepted in production
+const PLACEHOLDER_SECRETS: [&str; 3] = [
+    "secret",
+    "change_this_to_a_secure_random_string_in_production_minimum_32_chars",
+    "your_32_character_secret_key_here",
+];
+
+static JWT_KEYS: OnceLock<JwtKeys> = OnceLock::new();
+
+/// Install the keys access tokens are signed and verified with
+///
+/// Called once at startup; later calls are ignored.
+pub fn init_jwt_keys(keys: JwtKeys) {
+    if JWT_KEYS.set(keys).is_err() {
+        tracing::warn!("JWT keys were already initialized");
+    }
+}
+
+/// The keys of access tokens; a random key when none were installed (tests)
+pub fn jwt_keys() -> &'static JwtKeys {
+    JWT_KEYS.get_or_init(JwtKeys::ephemeral)
+}
+
+struct JwtKey {
+    kid: String,
+    algorithm: Algorithm,
+    encoding: EncodingKey,
+    decoding: DecodingKey,
+    /// Public key published in the JWKS, `None` for HMAC secrets
+    public_jwk: Option<Jwk>,
+}
+
+/// Keys access tokens are signed and verified with
+///
+/// Every token names its key in the `kid` header. The first key signs new
+/// tokens, the others only verify, so a key can be rotated out once the
+/// tokens it signed expired.
+pub struct JwtKeys {
+    keys: Vec<JwtKey>,
+}
+
+impl JwtKeys {
+    /// Load the configured keys
+    ///
+    /// Without keys, a random HS256 key is used outside production: tokens
+    /// then stop working on restart and clients renew them with their refresh
+    /// token. In production a missing key or a weak HMAC secret is an error.
+    pub fn from_config(config: &JwtConfig) -> Result<Self, String> {
+        if config.keys.is_empty() {
+            if config.production {
+                return Err(
+                    "No JWT key configured; set JWT_KEYS or JWT_SECRET in production".to_string(),
+                );
+            }
+            tracing::warn!(
+                "No JWT key configured, using a random key; tokens stop working on restart"
+            );
+            return Ok(Self::ephemeral());
+        }
+
+        let keys = config
+            .keys
+            .iter()
+            .map(|spec| load_key(spec, config.production))
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(Self { keys })
+    }
+
+    /// A single random HS256 key
+    pub fn ephemeral() -> Self {
+        let mut secret = [0u8; 32];
+        rand::thread_rng().fill_bytes(&mut secret);
+        Self {
+            keys: vec![hmac_key(JWT_SECRET_KID, Algorithm::HS256, &secret)],
+        }
+    }
+
+    /// Id and algorithm of the key signing new tokens
+    pub fn signing_key(&self) -> (&str, Algorithm) {
+        (&self.keys[0].kid, self.keys[0].algorithm)
+    }
+
+    /// Sign claims with the first key
+    pub fn sign<T: Serialize>(&self, claims: &T) -> Result<String, JwtError> {
+        let key = &self.keys[0];
+        let mut header = Header::new(key.algorithm);
+        header.kid = Some(key.kid.clone());
+        jsonwebtoken::encode(&header, claims, &key.encoding)
+    }
+
+    /// Verify a token with the key its `kid` header names
+    ///
+    /// The token must use the algorithm of that key; tokens without a key id
+    /// are rejected.
+    pub fn verify<T: DeserializeOwned>(&self, token: &str) -> Result<T, JwtError> {
+        let header = decode_header(token)?;
+        let key = header
+            .kid
+            .as_deref()
+            .and_then(|kid| self.keys.iter().find(|key| key.kid == kid))
+            .ok_or_else(|| JwtError::from(JwtErrorKind::InvalidToken))?;
+
+        let validation = Validation::new(key.algorithm);
+        Ok(decode::<T>(token, &key.decoding, &validation)?.claims)
+    }
+
+    /// Public keys of the asymmetric keys, for `/.well-known/jwks.json`
+    pub fn jwks(&self) -> JwkSet {
+        JwkSet {
+            keys: self
+                .keys
+                .iter()
+                .filter_map(|key| key.public_jwk.clone())
+                .collect(),
+        }
+    }
+}
+
+fn load_key(spec: &JwtKeySpec, production: bool) -> Result<JwtKey, String> {
+    let material = match &spec.source {
+        JwtKeySource::Inline(material) => material.clone().into_bytes(),
+        JwtKeySource::File(path) => std::fs::read(path).map_err(|e| {
+            format!(
+                "Failed to read JWT key {} from {}: {e}",
+                spec.kid,
+                path.display()
+            )
+        })?,
+    };
+    let invalid = |e: &dyn std::fmt::Display| format!("Invalid JWT key {}: {e}", spec.kid);
+
+    match spec.algorithm {
+        Algorithm::HS256 | Algorithm::HS384 | Algorithm::HS512 => {
+            let secret = String::from_utf8_lossy(&material).trim().to_string();
+            if production
+                && (secret.len() < MIN_JWT_SECRET_LEN
+                    || PLACEHOLDER_SECRETS.contains(&secret.as_str()))
+            {
+                return Err(format!(
+                    "JWT secret of key {} must be a random string of at least {MIN_JWT_SECRET_LEN} characters in production",
+                    spec.kid
+                ));
+            }
+            if secret.is_empty() {
+                return Err(invalid(&"the secret is empty"));
+            }
+            Ok(hmac_key(&spec.kid, spec.algorithm, secret.as_bytes()))
+        }
+        Algorithm::RS256
+        | Algorithm::RS384
+        | Algorithm::RS512
+        | Algorithm::PS256
+        | Algorithm::PS384
+        | Algorithm::PS512 => {
+            let pem = pem::parse(&material).map_err(|e| invalid(&e))?;
+            let key_pair = match pem.tag() {
+                "RSA PRIVATE KEY" => signature::RsaKeyPair::from_der(pem.contents()),
+                _ => signature::RsaKeyPair::from_pkcs8(pem.contents()),
+            }
+            .map_err(|e| invalid(&e))?;
+            let public: ring::rsa::PublicKeyComponents<Vec<u8>> = key_pair.public().into();
+
+            let encoding = EncodingKey::from_rsa_pem(&material).map_err(|e| invalid(&e))?;
+            asymmetric_key(
+                spec,
+                encoding,
+                AlgorithmParameters::RSA(RSAKeyParameters {
+                    key_type: RSAKeyType::RSA,
+                    n: BASE64URL_NOPAD.encode(&public.n),
+                    e: BASE64URL_NOPAD.encode(&public.e),
+                }),
+            )
+        }
+        Algorithm::ES256 | Algorithm::ES384 => {
+            let (signing_alg, curve) = if spec.algorithm == Algorithm::ES256 {
+                (
+                    &signature::ECDSA_P256_SHA256_FIXED_SIGNING,
+                    EllipticCurve::P256,
+                )
+            } else {
+                (
+                    &signature::ECDSA_P384_SHA384_FIXED_SIGNING,
+                    EllipticCurve::P384,
+                )
+            };
+            let pem = pem::parse(&material).map_err(|e| invalid(&e))?;
+            let key_pair = signature::EcdsaKeyPair::from_pkcs8(
+                signing_alg,
+                pem.contents(),
+                &SystemRandom::new(),
+            )
+            .map_err(|e| invalid(&e))?;
+            // Uncompressed point: 0x04, then x and y of equal length
+            let point = &key_pair.public_key().as_ref()[1..];
+            let (x, y) = point.split_at(point.len() / 2);
+
+            let encoding = EncodingKey::from_ec_pem(&material).map_err(|e| invalid(&e))?;
+            asymmetric_key(
+                spec,
+                encoding,
+                AlgorithmParameters::EllipticCurve(EllipticCurveKeyParameters {
+                    key_type: EllipticCurveKeyType::EC,
+                    curve,
+                    x: BASE64URL_NOPAD.encode(x),
+                    y: BASE64URL_NOPAD.encode(y),
+                }),
+            )
+        }
+        Algorithm::EdDSA => {
+            let pem = pem::parse(&material).map_err(|e| invalid(&e))?;
+            let key_pair = signature::Ed25519KeyPair::from_pkcs8_maybe_unchecked(pem.contents())
+                .map_err(|e| invalid(&e))?;
+
+            let encoding = EncodingKey::from_ed_pem(&material).map_err(|e| invalid(&e))?;
+            asymmetric_key(
+                spec,
+                encoding,
+                AlgorithmParameters::OctetKeyPair(OctetKeyPairParameters {
+                    key_type: OctetKeyPairType::OctetKeyPair,
+                    curve: EllipticCurve::Ed25519,
+                    x: BASE64URL_NOPAD.encode(key_pair.public_key().as_ref()),
+                }),
+            )
+        }
+    }
+}
+
+fn hmac_key(kid: &str, algorithm: Algorithm, secret: &[u8]) -> JwtKey {
+    JwtKey {
+        kid: kid.to_string(),
+        algorithm,
+        encoding: EncodingKey::from_secret(secret),
+        decoding: DecodingKey::from_secret(secret),
+        public_jwk: None,
+    }
+}
+
+fn asymmetric_key(
+    spec: &JwtKeySpec,
+    encoding: EncodingKey,
+    parameters: AlgorithmParameters,
+) -> Result<JwtKey, String> {
+    let jwk = Jwk {
+        common: CommonParameters {
+            public_key_use: Some(PublicKeyUse::Signature),
+            key_algorithm: KeyAlgorithm::from_str(&format!("{:?}", spec.algorithm)).ok(),
+            key_id: Some(spec.kid.clone()),
+            ..Default::default()
+        },
+        algorithm: parameters,
+    };
+    let decoding =
+        DecodingKey::from_jwk(&jwk).map_err(|e| format!("Invalid JWT key {}: {e}", spec.kid))?;
+
+    Ok(JwtKey {
+        kid: spec.kid.clone(),
+        algorithm: spec.algorithm,
+        encoding,
+        decoding,
+        public_jwk: Some(jwk),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde::Deserialize;
+
+    #[derive(Debug, Serialize, Deserialize, PartialEq)]
+    struct TestClaims {
+        sub: String,
+        exp: i64,
+    }
+
+    fn claims() -> TestClaims {
+        TestClaims {
+            sub: "user-1".to_string(),
+            exp: chrono::Utc::now().timestamp() + 300,
+        }
+    }
+
+    fn spec(kid: &str, algorithm: Algorithm, material: String) -> JwtKeySpec {
+        JwtKeySpec {
+            kid: kid.to_string(),
+            algorithm,
+            source: JwtKeySource::Inline(material),
+        }
+    }
+
+    fn keys(specs: Vec<JwtKeySpec>, production: bool) -> Result<JwtKeys, String> {
+        JwtKeys::from_config(&JwtConfig {
+            keys: specs,
+            production,
+        })
+    }
+
+    fn pkcs8_pem(document: &[u8]) -> String {
+        pem::encode(&pem::Pem::new("PRIVATE KEY", document.to_vec()))
+    }
+
+    #[test]
+    fn test_rotated_keys_keep_verifying_old_tokens() {
+        let old = spec("2025-07", Algorithm::HS256, "a".repeat(32));
+        let new = spec("2025-10", Algorithm::HS512, "b".repeat(64));
+
+        let old_token = keys(vec![old.clone()], true)
+            .unwrap()
+            .sign(&claims())
+            .unwrap();
+        let rotated = keys(vec![new.clone(), old], true).unwrap();
+        assert_eq!(rotated.signing_key(), ("2025-10", Algorithm::HS512));
+        assert_eq!(rotated.verify::<TestClaims>(&old_token).unwrap(), claims());
+
+        let new_token = rotated.sign(&claims()).unwrap();
+        assert_eq!(
+            decode_header(&new_token).unwrap().kid.as_deref(),
+            Some("2025-10")
+        );
+
+        // Once the old key is removed, its tokens stop working
+        let retired = keys(vec![new], true).unwrap();
+        assert!(retired.verify::<TestClaims>(&new_token).is_ok());
+        assert!(retired.verify::<TestClaims>(&old_token).is_err());
+        // HMAC keys are never published
+        assert!(retired.jwks().keys.is_empty());
+    }
+
+    #[test]
+    fn test_asymmetric_keys_are_published_in_jwks() {
+        let rng = SystemRandom::new();
+        let ed25519 = signature::Ed25519KeyPair::generate_pkcs8(&rng).unwrap();
+        let p256 = signature::EcdsaKeyPair::generate_pkcs8(
+            &signature::ECDSA_P256_SHA256_FIXED_SIGNING,
+            &rng,
+        )
+        .unwrap();
+
+        for key in [
+            spec(
+                "rsa",
+                Algorithm::RS256,
+                include_str!("../../testdata/rsa_test_key.pem").to_string(),
+            ),
+            spec("ed", Algorithm::EdDSA, pkcs8_pem(ed25519.as_ref())),
+            spec("ec", Algorithm::ES256, pkcs8_pem(p256.as_ref())),
+        ] {
+            let algorithm = key.algorithm;
+            let keys = keys(vec![key], true).unwrap();
+            let token = keys.sign(&claims()).unwrap();
+            assert_eq!(keys.verify::<TestClaims>(&token).unwrap(), claims());
+
+            // Anyone holding the published key can verify the token
+            let jwks = keys.jwks();
+            assert_eq!(jwks.keys.len(), 1);
+            let jwk = jwks.find(keys.signing_key().0).unwrap();
+            let decoding = DecodingKey::from_jwk(jwk).unwrap();
+            let verified = decode::<TestClaims>(&token, &decoding, &Validation::new(algorithm));
+            assert!(verified.is_ok(), "{algorithm:?}: {verified:?}");
+        }
+    }
+
+    #[test]
+    fn test_tokens_must_name_a_key_and_use_its_algorithm() {
+        let ed25519 = signature::Ed25519KeyPair::generate_pkcs8(&SystemRandom::new()).unwrap();
+        let keys = keys(
+            vec![spec("ed", Algorithm::EdDSA, pkcs8_pem(ed25519.as_ref()))],
+            true,
+        )
+        .unwrap();
+        let public_key = keys.jwks().keys[0].clone();
+        let AlgorithmParameters::OctetKeyPair(params) = &public_key.algorithm else {
+            panic!("Ed25519 key expected");
+        };
+
+        // HS256 token keyed with the public key, claiming the EdDSA key's id
+        let mut header = Header::new(Algorithm::HS256);
+        header.kid = Some("ed".to_string());
+        let confused = jsonwebtoken::encode(
+            &header,
+            &claims(),
+            &EncodingKey::from_secret(params.x.as_bytes()),
+        )
+        .unwrap();
+        assert!(keys.verify::<TestClaims>(&confused).is_err());
+
+        let mut header = Header::new(Algorithm::EdDSA);
+        header.kid = None;
+        let encoding = EncodingKey::from_ed_pem(pkcs8_pem(ed25519.as_ref()).as_bytes()).unwrap();
+        let without_kid = jsonwebtoken::encode(&header, &claims(), &encoding).unwrap();
+        assert!(keys.verify::<TestClaims>(&without_kid).is_err());
+    }
+
+    #[test]
+    fn test_production_requires_a_real_key() {
+        assert!(keys(vec![], true).is_err());
+        assert!(keys(vec![], false).is_ok());
+
+        for secret in ["secret", "too-short", PLACEHOLDER_SECRETS[1]] {
+            let spec = spec(JWT_SECRET_KID, Algorithm::HS256, secret.to_string());
+            assert!(keys(vec![spec.clone()], true).is_err(), "{secret}");
+            assert!(keys(vec![spec], false).is_ok(), "{secret}");
+        }
+
+        let unreadable = JwtKeySpec {
+            kid: "missing".to_string(),
+            algorithm: Algorithm::RS256,
+            source: JwtKeySource::File("/nonexistent/jwt.pem".into()),
+        };
+        assert!(keys(vec![unreadable], false).is_err());
+        assert!(
+            keys(
+                vec![spec("bad", Algorithm::EdDSA, "not a pem".to_string())],
+                false
+            )
+            .is_err()
+        );
+    }
+}
@@ -1,6 +1,12 @@
 pub mod auth;
+pub mod backoff;
+pub mod client;
+pub mod email_template;
+pub mod jwt;
 pub mod response;
 pub mod subscription_validation;
+pub mod totp;
+pub mod user_validation;
 
-pub use self::auth::{generate_refresh_token, generate_token, hash_password, verify_password};
+pub use self::auth::{generate_token, hash_password, verify_password};
 pub use self::subscription_validation::validate_subscription_request;
@@ -1,10 +1,12 @@
 use axum::{
     Json,
-    http::StatusCode,
+    extract::rejection::JsonRejection,
+    http::{HeaderValue, StatusCode, header::RETRY_AFTER},
     response::{IntoResponse, Response},
 };
 use serde::Serialize;
 use std::fmt;
+use std::time::Duration;
 use tracing;
 use uuid;
 
@@ -56,6 +58,8 @@ pub enum ErrorCategory {
     NotFound,
     /// Resource conflicts and business rule violations
     Conflict,
+    /// Too many requests or attempts
+    RateLimit,
     /// Server-side errors
     Server,
 }
@@ -77,6 +81,13 @@ pub enum AppError {
         user_message: String,
         suggestions: Vec<String>,
     },
+    /// Authenticated, but not allowed to perform the action
+    Forbidden {
+        message: String,
+        user_message: String,
+        reason: String,
+        suggestions: Vec<String>,
+    },
 
     /// Input validation failed
     ValidationError {
@@ -99,6 +110,14 @@ pub enum AppError {
         conflict_type: String,
         suggestions: Vec<String>,
     },
+    /// Too many requests or attempts; the client may retry after `retry_after`
+    TooManyRequests {
+        message: String,
+        user_message: String,
+        reason: String,
+        retry_after: Duration,
+        suggestions: Vec<String>,
+    },
     /// Internal server error
     InternalServerError {
         message: String,
@@ -116,9 +135,11 @@ impl fmt::Display for AppError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
             AppError::Unauthorized { message, .. } => write!(f, "Unauthorized: {message}"),
+            AppError::Forbidden { message, .. } => write!(f, "Forbidden: {message}"),
             AppError::ValidationError { message, .. } => write!(f, "Validation Error: {message}"),
             AppError::NotFound { message, .. } => write!(f, "Not Found: {message}"),
             AppError::Conflict { message, .. } => write!(f, "Conflict: {message}"),
+            AppError::TooManyRequests { message, .. } => write!(f, "Too Many Requests: {message}"),
             AppError::InternalServerError { message, .. } => {
                 write!(f, "Internal Server Error: {message}")
             }
@@ -156,6 +177,85 @@ impl AppError {
         }
     }
 
+    /// Create a forbidden error for actions that need a verified email address
+    pub fn email_not_verified(action: &str) -> Self {
+        Self::Forbidden {
+            message: format!("Email address must be verified to {action}"),
+            user_message: format!("Please verify your email address to {action}."),
+            reason: "email_not_verified".to_string(),
+            suggestions: vec![
+                "Follow the link in the verification email we sent you.".to_string(),
+                "Request a new verification email if the link expired.".to_string(),
+            ],
+        }
+    }
+
+    /// Create a forbidden error for a token used beyond its scope
+    pub fn insufficient_scope(required: &str, user_message: impl Into<String>) -> Self {
+        Self::Forbidden {
+            message: format!("This request needs {required}"),
+            user_message: user_message.into(),
+            reason: "insufficient_scope".to_string(),
+            suggestions: vec![
+                "Use an API token with the read_write scope to change data.".to_string(),
+                "Sign in to manage your account and API tokens.".to_string(),
+            ],
+        }
+    }
+
+    /// Create a forbidden error for a sensitive change of an account without
+    /// a password, made from a session that is not fresh
+    pub fn reauthentication_required(max_age_minutes: i64) -> Self {
+        Self::Forbidden {
+            message: format!("Session must have started within {max_age_minutes} minutes"),
+            user_message: "Please sign in again to confirm this change.".to_string(),
+            reason: "reauthentication_required".to_string(),
+            suggestions: vec![
+                "Sign in again with your provider, then repeat the change.".to_string(),
+                "Set a password with a password reset to confirm changes with it.".to_string(),
+            ],
+        }
+    }
+
+    /// Create a too many requests error for the limit named by `reason`
+    pub fn too_many_requests(
+        reason: impl Into<String>,
+        message: impl Into<String>,
+        user_message: impl Into<String>,
+        retry_after: Duration,
+    ) -> Self {
+        Self::TooManyRequests {
+            message: message.into(),
+            user_message: user_message.into(),
+            reason: reason.into(),
+            retry_after,
+            suggestions: vec![
+                "Wait a moment before trying again.".to_string(),
+                "Reduce how often your client sends requests.".to_string(),
+            ],
+        }
+    }
+
+    /// Create the error of a login to an account locked after failed logins
+    pub fn account_locked(retry_after: Duration) -> Self {
+        let minutes = retry_after.as_secs().div_ceil(60).max(1);
+        let wait = if minutes == 1 {
+            "a minute".to_string()
+        } else {
+            format!("{minutes} minutes")
+        };
+        Self::TooManyRequests {
+            message: "Account locked after too many failed logins".to_string(),
+            user_message: format!("Too many failed sign-in attempts. Please try again in {wait}."),
+            reason: "account_locked".to_string(),
+            retry_after,
+            suggestions: vec![
+                "Follow the link in the email we sent you to unlock your account now.".to_string(),
+                "Reset your password if you forgot it.".to_string(),
+            ],
+        }
+    }
+
     /// Create a validation error
     pub fn validation_error(message: impl Into<String>, user_message: impl Into<String>) -> Self {
         Self::ValidationError {
@@ -166,6 +266,33 @@ impl AppError {
         }
     }
 
+    /// Create a validation error with a message for every invalid field
+    ///
+    /// The messages are returned as `details.field_errors`, keyed by field name.
+    pub fn field_validation_error(field_errors: Vec<(&str, String)>) -> Self {
+        let mut fields: Vec<&str> = field_errors.iter().map(|(field, _)| *field).collect();
+        fields.dedup();
+        let user_message = match field_errors.as_slice() {
+            [(_, message)] => message.clone(),
+            _ => "Some fields are invalid. Please correct them and try again.".to_string(),
+        };
+
+        let mut errors = serde_json::Map::new();
+        for (field, message) in field_errors.iter() {
+            // Report the first problem of a field
+            errors
+                .entry(field.to_string())
+                .or_insert_with(|| serde_json::Value::String(message.clone()));
+        }
+
+        Self::ValidationError {
+            message: format!("Invalid fields: {}", fields.join(", ")),
+            user_message,
+            field_errors: Some(serde_json::Value::Object(errors)),
+            suggestions: vec!["Please check the form and correct any errors.".to_string()],
+        }
+    }
+
     /// Create a not found error
     pub fn not_found(resource_type: impl Into<String>, message: impl Into<String>) -> Self {
         let resource = resource_type.into();
@@ -222,6 +349,16 @@ impl AppError {
     }
 }
 
+/// Reject malformed JSON bodies with the standard error envelope
+impl From<JsonRejection> for AppError {
+    fn from(rejection: JsonRejection) -> Self {
+        AppError::validation_error(
+            rejection.body_text(),
+            "The request could not be read. Please check the submitted data.",
+        )
+    }
+}
+
 impl IntoResponse for AppError {
     fn into_response(self) -> Response {
         // Generate error ID for internal server errors
@@ -245,6 +382,11 @@ impl IntoResponse for AppError {
             AppError::Unauthorized { message, .. } => {
                 tracing::warn!("Unauthorized: {}", message);
             }
+            AppError::Forbidden {
+                message, reason, ..
+            } => {
+                tracing::warn!("Forbidden - {}: {}", reason, message);
+            }
             AppError::NotFound {
                 message,
                 resource_type,
@@ -262,7 +404,21 @@ impl IntoResponse for AppError {
             AppError::ValidationError { message, .. } => {
                 tracing::warn!("Validation Error: {}", message);
             }
+            AppError::TooManyRequests {
+                message, reason, ..
+            } => {
+                tracing::warn!("Too Many Requests - {}: {}", reason, message);
+            }
+        }
+
+        // Whole seconds, rounded up so that a retry is not too early
+        let retry_after = match &self {
+            AppError::TooManyRequests { retry_after, .. } => {
+                Some(retry_after.as_secs() + u64::from(retry_after.subsec_nanos() > 0))
+            }
+            _ => None,
         }
+        .map(|secs| secs.max(1));
 
         let (status, error_code, message, user_message, category, details, suggestions) = match self
         {
@@ -279,6 +435,20 @@ impl IntoResponse for AppError {
                 None,
                 suggestions,
             ),
+            AppError::Forbidden {
+                message,
+                user_message,
+                reason,
+                suggestions,
+            } => (
+                StatusCode::FORBIDDEN,
+                "AUTH_FORBIDDEN".to_string(),
+                message,
+                user_message,
+                ErrorCategory::Auth,
+                Some(serde_json::json!({ "reason": reason })),
+                suggestions,
+            ),
             AppError::ValidationError {
                 message,
                 user_message,
@@ -290,7 +460,7 @@ impl IntoResponse for AppError {
                 message,
                 user_message,
                 ErrorCategory::Validation,
-                field_errors,
+                field_errors.map(|errors| serde_json::json!({ "field_errors": errors })),
                 suggestions,
             ),
             AppError::NotFound {
@@ -335,6 +505,24 @@ impl IntoResponse for AppError {
                     suggestions,
                 )
             }
+            AppError::TooManyRequests {
+                message,
+                user_message,
+                reason,
+                suggestions,
+                ..
+            } => (
+                StatusCode::TOO_MANY_REQUESTS,
+                "RATE_LIMITED".to_string(),
+                message,
+                user_message,
+                ErrorCategory::RateLimit,
+                Some(serde_json::json!({
+                    "reason": reason,
+                    "retry_after": retry_after,
+                })),
+                suggestions,
+            ),
             AppError::InternalServerError {
                 message,
                 user_message,
@@ -390,6 +578,80 @@ impl IntoResponse for AppError {
             },
         });
 
-        (status, body).into_response()
+        let mut response = (status, body).into_response();
+        if let Some(secs) = retry_after {
+            response
+                .headers_mut()
+                .insert(RETRY_AFTER, HeaderValue::from(secs));
+        }
+        response
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_field_validation_error_keeps_first_error_per_field() {
+        let error = AppError::field_validation_error(vec![
+            ("name", "Subscription name cannot be empty".to_string()),
+            (
+                "start_date",
+                "Next billing date cannot be in the past".to_string(),
+            ),
+            ("start_date", "Another problem".to_string()),
+        ]);
+
+        let AppError::ValidationError {
+            message,
+            user_message,
+            field_errors,
+            ..
+        } = error
+        else {
+            panic!("expected a validation error");
+        };
+        assert_eq!(message, "Invalid fields: name, start_date");
+        assert_eq!(
+            user_message,
+            "Some fields are invalid. Please correct them and try again."
+        );
+        assert_eq!(
+            field_errors,
+            Some(serde_json::json!({
+                "name": "Subscription name cannot be empty",
+                "start_date": "Next billing date cannot be in the past",
+            }))
+        );
+    }
+
+    #[test]
+    fn test_single_field_error_is_the_user_message() {
+        let error =
+            AppError::field_validation_error(vec![("color", "Invalid color code".to_string())]);
+
+        let AppError::ValidationError { user_message, .. } = error else {
+            panic!("expected a validation error");
+        };
+        assert_eq!(user_message, "Invalid color code");
+    }
+
+    #[test]
+    fn test_too_many_requests_sets_retry_after() {
+        let response = AppError::account_locked(Duration::from_millis(90_500)).into_response();
+
+        assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(response.headers()[RETRY_AFTER], "91");
+
+        let AppError::TooManyRequests { user_message, .. } =
+            AppError::account_locked(Duration::from_secs(30))
+        else {
+            panic!("expected a too many requests error");
+        };
+        assert_eq!(
+            user_message,
+            "Too many failed sign-in attempts. Please try again in a minute."
+        );
     }
 }
@@ -1,35 +1,30 @@
-use axum::{Json, http::StatusCode};
 use bigdecimal::BigDecimal;
 use chrono::Utc;
-use serde_json::json;
 
 use crate::models::Subscription;
+use crate::utils::response::AppError;
 
 /// Validates a subscription request
-pub fn validate_subscription_request(
-    request: &Subscription,
-) -> Result<(), (StatusCode, Json<serde_json::Value>)> {
+///
+/// All invalid fields are reported at once in the error's `field_errors`.
+pub fn validate_subscription_request(request: &Subscription) -> Result<(), AppError> {
+    let mut errors: Vec<(&str, String)> = Vec::new();
+
     // Validate name
     if request.name.trim().is_empty() {
-        return Err((
-            StatusCode::BAD_REQUEST,
-            Json(json!({"error": "Subscription name cannot be empty"})),
-        ));
+        errors.push(("name", "Subscription name cannot be empty".to_string()));
     }
 
     // Validate amount
     if request.amount <= BigDecimal::from(0) {
-        return Err((
-            StatusCode::BAD_REQUEST,
-            Json(json!({"error": "Amount must be non-negative"})),
-        ));
+        errors.push(("amount", "Amount must be greater than zero".to_string()));
     }
 
-    // Validate billing cycle days
-    if request.billing_cycle_days <= 0 {
-        return Err((
-            StatusCode::BAD_REQUEST,
-            Json(json!({"error": "Billing cycle days must be positive"})),
+    // Validate billing cycle interval
+    if request.billing_cycle_interval <= 0 {
+        errors.push((
+            "billing_cycle_interval",
+            "Billing cycle interval must be positive".to_string(),
         ));
     }
 
@@ -41,41 +36,53 @@ pub fn validate_subscription_request(
 
     // Ensure next billing date is after start date
     if request.next_billing_date < request.start_date {
-        return Err((
-            StatusCode::BAD_REQUEST,
-            Json(json!({"error": "Next billing date must be on or after the start date"})),
+        errors.push((
+            "start_date",
+            "Next billing date must be on or after the start date".to_string(),
         ));
     }
 
     // If start date is in the future, next billing date should be the start date
     if request.start_date > today && request.next_billing_date != request.start_date {
-        return Err((
-            StatusCode::BAD_REQUEST,
-            Json(
-                json!({"error": "For future subscriptions, next billing date should be the start date"}),
-            ),
+        errors.push((
+            "start_date",
+            "For future subscriptions, next billing date should be the start date".to_string(),
         ));
     }
 
     // If start date is today or in the past, next billing should not be in the past
     if request.start_date <= today && request.next_billing_date < today {
-        return Err((
-            StatusCode::BAD_REQUEST,
-            Json(json!({"error": "Next billing date cannot be in the past"})),
+        errors.push((
+            "start_date",
+            "Next billing date cannot be in the past".to_string(),
+        ));
+    }
+
+    // A trial cannot end before the subscription starts
+    if let Some(trial_end_date) = request.trial_end_date
+        && trial_end_date < request.start_date
+    {
+        errors.push((
+            "trial_end_date",
+            "Trial end date must be on or after the start date".to_string(),
         ));
     }
 
     // Validate color if provided (should be a valid hex color code)
-    if let Some(color) = &request.color {
-        if !validate_color_code(color) {
-            return Err((
-                StatusCode::BAD_REQUEST,
-                Json(json!({"error": "Invalid color code format. Use #RRGGBB format."})),
-            ));
-        }
+    if let Some(color) = &request.color
+        && !validate_color_code(color)
+    {
+        errors.push((
+            "color",
+            "Invalid color code format. Use #RRGGBB format.".to_string(),
+        ));
     }
 
-    Ok(())
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(AppError::field_validation_error(errors))
+    }
 }
 
 /// Validates if a string is a valid hex color code
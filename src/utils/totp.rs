@@ -0,0 +1,169 @@
+use data_encoding::BASE32_NOPAD;
+use hmac::{Hmac, Mac};
+use rand::{Rng, RngCore};
+use sha1::Sha1;
+
+/// Issuer shown by authenticator apps
+pub const TOTP_ISSUER: &str = "Sub-Pal";
+/// Seconds a code is valid
+pub const TOTP_PERIOD_SECS: i64 = 30;
+/// Digits of a code
+pub const TOTP_DIGITS: u32 = 6;
+/// Time steps before and after the current one that are still accepted, to
+/// allow for clock drift
+const TOTP_SKEW_STEPS: i64 = 1;
+
+/// Number of recovery codes issued at once
+pub const RECOVERY_CODE_COUNT: usize = 10;
+const RECOVERY_CODE_ALPHABET: &[u8] = b"abcdefghjkmnpqrstuvwxyz23456789";
+
+// Generate a random 160-bit TOTP secret, base32 encoded as authenticator apps expect
+pub fn generate_totp_secret() -> String {
+    let mut bytes = [0u8; 20];
+    rand::thread_rng().fill_bytes(&mut bytes);
+    BASE32_NOPAD.encode(&bytes)
+}
+
+/// Build the `otpauth://` URI that authenticator apps import, usually from a QR code
+pub fn otpauth_uri(account: &str, secret: &str) -> String {
+    format!(
+        "otpauth://totp/{issuer}:{account}?secret={secret}&issuer={issuer}&algorithm=SHA1&digits={TOTP_DIGITS}&period={TOTP_PERIOD_SECS}",
+        issuer = percent_encode(TOTP_ISSUER),
+        account = percent_encode(account),
+    )
+}
+
+/// Compute the code of a secret for a time step (RFC 6238 with HMAC-SHA1)
+pub fn totp_code(secret: &[u8], step: i64) -> u32 {
+    let mut mac = Hmac::<Sha1>::new_from_slice(secret).expect("HMAC accepts keys of any length");
+    mac.update(&step.to_be_bytes());
+    let digest = mac.finalize().into_bytes();
+
+    // Dynamic truncation (RFC 4226, section 5.3)
+    let offset = (digest[digest.len() - 1] & 0x0f) as usize;
+    let binary = u32::from_be_bytes([
+        digest[offset] & 0x7f,
+        digest[offset + 1],
+        digest[offset + 2],
+        digest[offset + 3],
+    ]);
+    binary % 10u32.pow(TOTP_DIGITS)
+}
+
+/// Check a code against a base32 secret at the given Unix time
+///
+/// Returns the matched time step, so callers can reject a code that was
+/// already used. Spaces in the code are ignored.
+pub fn verify_totp(secret: &str, code: &str, unix_time: i64) -> Option<i64> {
+    let code: String = code.chars().filter(|c| !c.is_whitespace()).collect();
+    if code.len() != TOTP_DIGITS as usize || !code.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    let code: u32 = code.parse().ok()?;
+    let secret = BASE32_NOPAD.decode(secret.as_bytes()).ok()?;
+
+    let current = unix_time.div_euclid(TOTP_PERIOD_SECS);
+    (current - TOTP_SKEW_STEPS..=current + TOTP_SKEW_STEPS)
+        .find(|&step| totp_code(&secret, step) == code)
+}
+
+// Generate a recovery code like `k7m2q-x9fht`; only its hash is stored
+pub fn generate_recovery_code() -> String {
+    let mut rng = rand::thread_rng();
+    let mut code = String::with_capacity(11);
+    for i in 0..10 {
+        if i == 5 {
+            code.push('-');
+        }
+        let index = rng.gen_range(0..RECOVERY_CODE_ALPHABET.len());
+        code.push(RECOVERY_CODE_ALPHABET[index] as char);
+    }
+    code
+}
+
+/// Bring a recovery code as typed by a user into its generated form
+pub fn normalize_recovery_code(code: &str) -> String {
+    let code: String = code
+        .chars()
+        .filter(|c| c.is_ascii_alphanumeric())
+        .map(|c| c.to_ascii_lowercase())
+        .collect();
+    if code.len() == 10 {
+        format!("{}-{}", &code[..5], &code[5..])
+    } else {
+        code
+    }
+}
+
+fn percent_encode(value: &str) -> String {
+    value
+        .bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+                (b as char).to_string()
+            }
+            _ => format!("%{b:02X}"),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_totp_code_matches_rfc_6238_vectors() {
+        // SHA1 test vectors of RFC 6238, appendix B, truncated to six digits
+        let secret = b"12345678901234567890";
+        for (time, expected) in [
+            (59, 287082),
+            (1111111109, 81804),
+            (1111111111, 50471),
+            (1234567890, 5924),
+            (2000000000, 279037),
+            (20000000000, 353130),
+        ] {
+            assert_eq!(totp_code(secret, time / TOTP_PERIOD_SECS), expected);
+        }
+    }
+
+    #[test]
+    fn test_verify_totp_accepts_adjacent_steps_only() {
+        let secret = BASE32_NOPAD.encode(b"12345678901234567890");
+
+        assert_eq!(verify_totp(&secret, "081804", 1111111109), Some(37037036));
+        assert_eq!(
+            verify_totp(&secret, "081 804", 1111111109 + 30),
+            Some(37037036)
+        );
+        assert_eq!(verify_totp(&secret, "081804", 1111111109 + 60), None);
+        assert_eq!(verify_totp(&secret, "81804", 1111111109), None);
+        assert_eq!(verify_totp(&secret, "08180a", 1111111109), None);
+        assert_eq!(verify_totp("not base32!", "081804", 1111111109), None);
+    }
+
+    #[test]
+    fn test_generated_secret_and_otpauth_uri() {
+        let secret = generate_totp_secret();
+        assert_eq!(secret.len(), 32);
+        assert_eq!(BASE32_NOPAD.decode(secret.as_bytes()).unwrap().len(), 20);
+        assert_ne!(secret, generate_totp_secret());
+
+        assert_eq!(
+            otpauth_uri("a+b@example.com", "JBSWY3DPEHPK3PXP"),
+            "otpauth://totp/Sub-Pal:a%2Bb%40example.com?secret=JBSWY3DPEHPK3PXP&issuer=Sub-Pal&algorithm=SHA1&digits=6&period=30"
+        );
+    }
+
+    #[test]
+    fn test_recovery_codes() {
+        let code = generate_recovery_code();
+        assert_eq!(code.len(), 11);
+        assert_eq!(&code[5..6], "-");
+        assert_ne!(code, generate_recovery_code());
+
+        assert_eq!(normalize_recovery_code(&code), code);
+        assert_eq!(normalize_recovery_code(" K7M2Q X9FHT "), "k7m2q-x9fht");
+        assert_eq!(normalize_recovery_code("k7m2qx9fht"), "k7m2q-x9fht");
+    }
+}
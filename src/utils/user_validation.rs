@@ -0,0 +1,201 @@
+use chrono_tz::Tz;
+
+use crate::models::{MAX_REMINDER_LEAD_DAYS, UpdateProfileRequest};
+use crate::utils::response::AppError;
+
+/// Check that a string looks like a deliverable email address
+///
+/// This is no full RFC 5322 parser: it accepts `local@domain.tld` addresses
+/// with a dotted domain of letters, digits and hyphens, which covers the
+/// addresses people actually use. Whether the address exists is only known
+/// once the user follows the verification link.
+pub fn is_valid_email(email: &str) -> bool {
+    if email.len() > 254 {
+        return false;
+    }
+    let Some((local, domain)) = email.split_once('@') else {
+        return false;
+    };
+
+    let local_ok = !local.is_empty()
+        && local.len() <= 64
+        && !local.starts_with('.')
+        && !local.ends_with('.')
+        && !local.contains("..")
+        && local
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "!#$%&'*+-/=?^_`{|}~.".contains(c));
+
+    let labels: Vec<&str> = domain.split('.').collect();
+    let domain_ok = labels.len() >= 2
+        && labels.iter().all(|label| {
+            !label.is_empty()
+                && label.len() <= 63
+                && !label.starts_with('-')
+                && !label.ends_with('-')
+                && label.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')
+        })
+        && labels
+            .last()
+            .is_some_and(|tld| tld.len() >= 2 && tld.chars().all(|c| c.is_ascii_alphabetic()));
+
+    local_ok && domain_ok
+}
+
+/// Validates a profile update
+///
+/// All invalid fields are reported at once in the error's `field_errors`.
+pub fn validate_profile_update(request: &UpdateProfileRequest) -> Result<(), AppError> {
+    let mut errors: Vec<(&str, String)> = Vec::new();
+
+    if let Some(name) = &request.name
+        && name.trim().chars().count() > 255
+    {
+        errors.push(("name", "Name must be at most 255 characters".to_string()));
+    }
+
+    if let Some(preferences) = &request.preferences {
+        if let Some(Some(timezone)) = &preferences.timezone
+            && timezone.parse::<Tz>().is_err()
+        {
+            errors.push((
+                "preferences.timezone",
+                "Unknown time zone. Use an IANA name such as Europe/Berlin.".to_string(),
+            ));
+        }
+
+        if let Some(Some(days)) = preferences.reminder_lead_days
+            && !(0..=MAX_REMINDER_LEAD_DAYS).contains(&days)
+        {
+            errors.push((
+                "preferences.reminder_lead_days",
+                format!("Reminder lead days must be between 0 and {MAX_REMINDER_LEAD_DAYS}"),
+            ));
+        }
+
+        if let Some(Some(locale)) = &preferences.locale
+            && !is_valid_locale(locale)
+        {
+            errors.push((
+                "preferences.locale",
+                "Invalid locale. Use a language tag such as en-US.".to_string(),
+            ));
+        }
+    }
+
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(AppError::field_validation_error(errors))
+    }
+}
+
+/// Validates if a string is a BCP 47 style language tag such as `en` or `zh-Hans-CN`
+fn is_valid_locale(locale: &str) -> bool {
+    let mut subtags = locale.split('-');
+    let language_ok = subtags.next().is_some_and(|language| {
+        (2..=3).contains(&language.len()) && language.chars().all(|c| c.is_ascii_lowercase())
+    });
+
+    language_ok
+        && locale.len() <= 35
+        && subtags.all(|subtag| {
+            (2..=8).contains(&subtag.len()) && subtag.chars().all(|c| c.is_ascii_alphanumeric())
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::user::UserPreferences;
+
+    fn profile_update(preferences: serde_json::Value) -> UpdateProfileRequest {
+        UpdateProfileRequest {
+            name: None,
+            preferences: Some(serde_json::from_value::<UserPreferences>(preferences).unwrap()),
+        }
+    }
+
+    #[test]
+    fn test_accepts_common_addresses() {
+        assert!(is_valid_email("user@example.com"));
+        assert!(is_valid_email("first.last+tag@mail.example.co.uk"));
+        assert!(is_valid_email("o'brien@my-company.io"));
+    }
+
+    #[test]
+    fn test_rejects_malformed_addresses() {
+        for email in [
+            "",
+            "plainaddress",
+            "@example.com",
+            "user@",
+            "user@localhost",
+            "user@@example.com",
+            "user@exa mple.com",
+            " user@example.com",
+            "user.@example.com",
+            "us..er@example.com",
+            "user@-example.com",
+            "user@example.c",
+            "user@example.123",
+            "<script>@example.com",
+        ] {
+            assert!(!is_valid_email(email), "{email} should be invalid");
+        }
+    }
+
+    #[test]
+    fn test_accepts_valid_preferences() {
+        let request = profile_update(serde_json::json!({
+            "default_currency": "Usd",
+            "timezone": "Europe/Berlin",
+            "reminder_lead_days": 7,
+            "locale": "zh-Hans-CN",
+            "email_reminders": true,
+        }));
+        assert!(validate_profile_update(&request).is_ok());
+
+        // null resets a preference and is never invalid
+        let request = profile_update(serde_json::json!({ "timezone": null, "locale": null }));
+        assert!(validate_profile_update(&request).is_ok());
+    }
+
+    #[test]
+    fn test_reports_every_invalid_preference() {
+        let request = profile_update(serde_json::json!({
+            "timezone": "Mars/Olympus",
+            "reminder_lead_days": 400,
+            "locale": "english",
+        }));
+        let AppError::ValidationError { field_errors, .. } =
+            validate_profile_update(&request).unwrap_err()
+        else {
+            panic!("expected a validation error");
+        };
+        let field_errors = field_errors.unwrap();
+        assert!(field_errors.get("preferences.timezone").is_some());
+        assert!(field_errors.get("preferences.reminder_lead_days").is_some());
+        assert!(field_errors.get("preferences.locale").is_some());
+    }
+
+    #[test]
+    fn test_preferences_reject_unknown_fields_and_wrong_types() {
+        assert!(
+            serde_json::from_value::<UserPreferences>(serde_json::json!({ "theme": "dark" }))
+                .is_err()
+        );
+        assert!(
+            serde_json::from_value::<UserPreferences>(
+                serde_json::json!({ "reminder_lead_days": "7" })
+            )
+            .is_err()
+        );
+        assert!(
+            serde_json::from_value::<UserPreferences>(
+                serde_json::json!({ "default_currency": "EUR" })
+            )
+            .is_err()
+        );
+    }
+}
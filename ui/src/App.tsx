@@ -3,10 +3,16 @@ import { BrowserRouter as Router, Routes, Route, Navigate, useNavigate, useLocat
 import { AuthProvider, useAuth } from './lib/auth-context';
 import { LoginPage } from './pages/login-page';
 import { RegisterPage } from './pages/register-page';
+import { ForgotPasswordPage } from './pages/forgot-password-page';
+import { ResetPasswordPage } from './pages/reset-password-page';
+import { VerifyEmailPage } from './pages/verify-email-page';
+import { UnlockAccountPage } from './pages/unlock-account-page';
+import { OidcCallbackPage } from './pages/oidc-callback-page';
 import { DashboardPage } from './pages/dashboard-page';
 import { SubscriptionPage } from './pages/subscription-page';
 import { AddSubscriptionPage } from './pages/add-subscription-page';
 import { StatisticsPage } from './pages/statistics-page';
+import { ProfilePage } from './pages/profile-page';
 import { ProtectedRoute } from './components/auth/protected-route';
 import { ErrorBoundary } from './components/ui/error-boundary';
 
@@ -78,6 +84,11 @@ function App() {
               {/* Public routes */}
               <Route path="/login" element={<LoginPage />} />
               <Route path="/register" element={<RegisterPage />} />
+              <Route path="/forgot-password" element={<ForgotPasswordPage />} />
+              <Route path="/reset-password" element={<ResetPasswordPage />} />
+              <Route path="/verify-email" element={<VerifyEmailPage />} />
+              <Route path="/unlock-account" element={<UnlockAccountPage />} />
+              <Route path="/oidc/callback" element={<OidcCallbackPage />} />
 
               {/* Protected routes */}
               <Route element={<ProtectedRoute />}>
@@ -86,6 +97,7 @@ function App() {
                 <Route path="/subscriptions/edit/:id" element={<AddSubscriptionPage />} />
                 <Route path="/subscriptions/:id" element={<AddSubscriptionPage />} />
                 <Route path="/statistics" element={<StatisticsPage />} />
+                <Route path="/profile" element={<ProfilePage />} />
                 {/* Legacy dashboard route */}
                 <Route path="/legacy-dashboard" element={<DashboardPage />} />
               </Route>
@@ -0,0 +1,89 @@
+import { useState } from 'react';
+import { useForm } from 'react-hook-form';
+import { z } from 'zod';
+import { zodResolver } from '@hookform/resolvers/zod';
+import { Link } from 'react-router-dom';
+import { authApi } from '../../lib/api';
+import { ErrorHandler } from '../../lib/error-handler';
+import { ErrorDisplay } from '../ui/error-display';
+import type { ApiError } from '@/types';
+
+// Form validation schema
+const forgotPasswordSchema = z.object({
+  email: z.string().email('Please enter a valid email address'),
+});
+
+type ForgotPasswordFormValues = z.infer<typeof forgotPasswordSchema>;
+
+export function ForgotPasswordForm() {
+  const [error, setError] = useState<ApiError | null>(null);
+  const [submitted, setSubmitted] = useState(false);
+
+  const {
+    register,
+    handleSubmit,
+    formState: { errors, isSubmitting },
+  } = useForm<ForgotPasswordFormValues>({
+    resolver: zodResolver(forgotPasswordSchema),
+  });
+
+  const onSubmit = async (data: ForgotPasswordFormValues) => {
+    setError(null);
+
+    try {
+      await authApi.requestPasswordReset(data.email);
+      setSubmitted(true);
+    } catch (err) {
+      setError(ErrorHandler.extractError(err));
+    }
+  };
+
+  return (
+    <div className="mx-auto w-full max-w-md space-y-6 p-6">
+      <div className="space-y-2 text-center">
+        <h1 className="text-3xl font-bold">Forgot password</h1>
+        <p className="text-muted-foreground">We will email you a link to choose a new password</p>
+      </div>
+
+      {error && <ErrorDisplay error={error} showSuggestions={true} className="mb-4" />}
+
+      {submitted ? (
+        <p className="text-center text-sm">
+          If an account exists for this email, a password reset link is on its way. The link expires in one hour.
+        </p>
+      ) : (
+        <form onSubmit={handleSubmit(onSubmit)} className="space-y-4">
+          <div className="space-y-2">
+            <label htmlFor="email" className="text-sm font-medium leading-none peer-disabled:cursor-not-allowed peer-disabled:opacity-70">
+              Email
+            </label>
+            <input
+              {...register('email')}
+              id="email"
+              type="email"
+              className="flex h-10 w-full rounded-md border border-input bg-background px-3 py-2 text-sm ring-offset-background file:border-0 file:bg-transparent file:text-sm file:font-medium placeholder:text-muted-foreground focus-visible:outline-none focus-visible:ring-2 focus-visible:ring-ring focus-visible:ring-offset-2 disabled:cursor-not-allowed disabled:opacity-50"
+              placeholder="m@example.com"
+            />
+            {errors.email && (
+              <p className="text-sm text-destructive">{errors.email.message}</p>
+            )}
+          </div>
+
+          <button
+            type="submit"
+            disabled={isSubmitting}
+            className="inline-flex h-10 w-full items-center justify-center rounded-md bg-primary px-4 py-2 text-sm font-medium text-primary-foreground ring-offset-background transition-colors hover:bg-primary/90 focus-visible:outline-none focus-visible:ring-2 focus-visible:ring-ring focus-visible:ring-offset-2 disabled:pointer-events-none disabled:opacity-50"
+          >
+            {isSubmitting ? 'Sending...' : 'Send reset link'}
+          </button>
+        </form>
+      )}
+
+      <div className="text-center text-sm">
+        <Link to="/login" className="text-primary hover:underline">
+          Back to login
+        </Link>
+      </div>
+    </div>
+  );
+}
@@ -3,7 +3,8 @@ import { useForm } from 'react-hook-form';
 import { z } from 'zod';
 import { zodResolver } from '@hookform/resolvers/zod';
 import { useAuth } from '../../lib/auth-context';
-import { Link, useNavigate } from 'react-router-dom';
+import { authApi } from '../../lib/api';
+import { Link, useLocation, useNavigate } from 'react-router-dom';
 import { ErrorDisplay } from '../ui/error-display';
 
 // Form validation schema
@@ -14,10 +15,25 @@ const loginSchema = z.object({
 
 type LoginFormValues = z.infer<typeof loginSchema>;
 
+const twoFactorSchema = z.object({
+  code: z.string().trim().min(6, 'Please enter the code from your authenticator app'),
+});
+
+type TwoFactorFormValues = z.infer<typeof twoFactorSchema>;
+
 export function LoginForm() {
-  const { login, error: authError, clearError, user } = useAuth();
+  const { login, completeTwoFactorLogin, error: authError, clearError, user } = useAuth();
   const navigate = useNavigate();
+  // Passed by the page that sent the user here: a notice, e.g. after deleting
+  // the account, or the challenge of a single sign-on login needing a code
+  const locationState = useLocation().state as { message?: string; challengeToken?: string } | null;
+  const notice = locationState?.message;
   const [formError, setFormError] = useState<string | null>(null);
+  // Set once the password is accepted for an account with two-factor authentication
+  const [challengeToken, setChallengeToken] = useState<string | null>(locationState?.challengeToken ?? null);
+  // Name of the single sign-on provider, when one is configured
+  const [oidcProvider, setOidcProvider] = useState<string | null>(null);
+  const [redirecting, setRedirecting] = useState(false);
 
   const {
     register,
@@ -27,6 +43,10 @@ export function LoginForm() {
     resolver: zodResolver(loginSchema),
   });
 
+  const twoFactorForm = useForm<TwoFactorFormValues>({
+    resolver: zodResolver(twoFactorSchema),
+  });
+
   // Redirect to dashboard if already logged in
   useEffect(() => {
     if (user) {
@@ -34,6 +54,13 @@ export function LoginForm() {
     }
   }, [user, navigate]);
 
+  useEffect(() => {
+    authApi
+      .getOidcProvider()
+      .then((provider) => setOidcProvider(provider.enabled ? provider.provider_name : null))
+      .catch(() => setOidcProvider(null));
+  }, []);
+
   // Debug: Log errors whenever they change
   useEffect(() => {
     console.log('Auth Error:', authError);
@@ -46,7 +73,11 @@ export function LoginForm() {
     clearError();
 
     try {
-      await login(data.email, data.password);
+      const challenge = await login(data.email, data.password);
+      if (challenge) {
+        setChallengeToken(challenge.challenge_token);
+        return;
+      }
       // If login is successful, the user state will be updated
       // and the above useEffect will handle the redirection
     } catch (err: any) {
@@ -56,6 +87,36 @@ export function LoginForm() {
     }
   };
 
+  const onSubmitCode = async (data: TwoFactorFormValues) => {
+    setFormError(null);
+    clearError();
+
+    try {
+      await completeTwoFactorLogin(challengeToken!, data.code);
+    } catch (err: any) {
+      setFormError(err.message || 'Login failed. Please try again.');
+    }
+  };
+
+  const startOidcLogin = async () => {
+    setFormError(null);
+    clearError();
+    setRedirecting(true);
+
+    try {
+      window.location.assign(await authApi.startOidcLogin());
+    } catch (err: any) {
+      setRedirecting(false);
+      setFormError(err.message || 'Single sign-on is not available right now.');
+    }
+  };
+
+  const startOver = () => {
+    setChallengeToken(null);
+    setFormError(null);
+    clearError();
+    twoFactorForm.reset();
+  };
 
   // Display either auth context error or form error
   const displayError = authError || (formError ? {
@@ -74,6 +135,10 @@ export function LoginForm() {
         <p className="text-muted-foreground">Enter your credentials to access your account</p>
       </div>
 
+      {notice && !displayError && (
+        <p className="rounded-md border bg-muted px-4 py-3 text-center text-sm">{notice}</p>
+      )}
+
       {displayError && (
         <ErrorDisplay
           error={displayError}
@@ -82,58 +147,114 @@ export function LoginForm() {
         />
       )}
 
-      <form onSubmit={handleSubmit(onSubmit)} className="space-y-4">
-        <div className="space-y-2">
-          <label htmlFor="email" className="text-sm font-medium leading-none peer-disabled:cursor-not-allowed peer-disabled:opacity-70">
-            Email
-          </label>
-          <input
-            {...register('email')}
-            id="email"
-            type="email"
-            className="flex h-10 w-full rounded-md border border-input bg-background px-3 py-2 text-sm ring-offset-background file:border-0 file:bg-transparent file:text-sm file:font-medium placeholder:text-muted-foreground focus-visible:outline-none focus-visible:ring-2 focus-visible:ring-ring focus-visible:ring-offset-2 disabled:cursor-not-allowed disabled:opacity-50"
-            placeholder="m@example.com"
-          />
-          {errors.email && (
-            <p className="text-sm text-destructive">{errors.email.message}</p>
-          )}
-        </div>
+      {challengeToken ? (
+        <form onSubmit={twoFactorForm.handleSubmit(onSubmitCode)} className="space-y-4">
+          <div className="space-y-2">
+            <label htmlFor="code" className="text-sm font-medium leading-none peer-disabled:cursor-not-allowed peer-disabled:opacity-70">
+              Authentication code
+            </label>
+            <input
+              {...twoFactorForm.register('code')}
+              id="code"
+              type="text"
+              autoComplete="one-time-code"
+              autoFocus
+              className="flex h-10 w-full rounded-md border border-input bg-background px-3 py-2 text-sm ring-offset-background file:border-0 file:bg-transparent file:text-sm file:font-medium placeholder:text-muted-foreground focus-visible:outline-none focus-visible:ring-2 focus-visible:ring-ring focus-visible:ring-offset-2 disabled:cursor-not-allowed disabled:opacity-50"
+              placeholder="123456"
+            />
+            <p className="text-sm text-muted-foreground">
+              Enter the code from your authenticator app, or one of your recovery codes.
+            </p>
+            {twoFactorForm.formState.errors.code && (
+              <p className="text-sm text-destructive">{twoFactorForm.formState.errors.code.message}</p>
+            )}
+          </div>
+
+          <button
+            type="submit"
+            disabled={twoFactorForm.formState.isSubmitting}
+            className="inline-flex h-10 w-full items-center justify-center rounded-md bg-primary px-4 py-2 text-sm font-medium text-primary-foreground ring-offset-background transition-colors hover:bg-primary/90 focus-visible:outline-none focus-visible:ring-2 focus-visible:ring-ring focus-visible:ring-offset-2 disabled:pointer-events-none disabled:opacity-50"
+          >
+            {twoFactorForm.formState.isSubmitting ? 'Verifying...' : 'Verify'}
+          </button>
 
-        <div className="space-y-2">
-          <div className="flex items-center justify-between">
-            <label htmlFor="password" className="text-sm font-medium leading-none peer-disabled:cursor-not-allowed peer-disabled:opacity-70">
-              Password
+          <button type="button" onClick={startOver} className="w-full text-center text-sm text-primary hover:underline">
+            Back to login
+          </button>
+        </form>
+      ) : (
+        <form onSubmit={handleSubmit(onSubmit)} className="space-y-4">
+          <div className="space-y-2">
+            <label htmlFor="email" className="text-sm font-medium leading-none peer-disabled:cursor-not-allowed peer-disabled:opacity-70">
+              Email
             </label>
-            <Link to="/forgot-password" className="text-sm text-primary hover:underline">
-              Forgot password?
-            </Link>
+            <input
+              {...register('email')}
+              id="email"
+              type="email"
+              className="flex h-10 w-full rounded-md border border-input bg-background px-3 py-2 text-sm ring-offset-background file:border-0 file:bg-transparent file:text-sm file:font-medium placeholder:text-muted-foreground focus-visible:outline-none focus-visible:ring-2 focus-visible:ring-ring focus-visible:ring-offset-2 disabled:cursor-not-allowed disabled:opacity-50"
+              placeholder="m@example.com"
+            />
+            {errors.email && (
+              <p className="text-sm text-destructive">{errors.email.message}</p>
+            )}
           </div>
-          <input
-            {...register('password')}
-            id="password"
-            type="password"
-            className="flex h-10 w-full rounded-md border border-input bg-background px-3 py-2 text-sm ring-offset-background file:border-0 file:bg-transparent file:text-sm file:font-medium placeholder:text-muted-foreground focus-visible:outline-none focus-visible:ring-2 focus-visible:ring-ring focus-visible:ring-offset-2 disabled:cursor-not-allowed disabled:opacity-50"
-          />
-          {errors.password && (
-            <p className="text-sm text-destructive">{errors.password.message}</p>
-          )}
-        </div>
-
-        <button
-          type="submit"
-          disabled={isSubmitting}
-          className="inline-flex h-10 w-full items-center justify-center rounded-md bg-primary px-4 py-2 text-sm font-medium text-primary-foreground ring-offset-background transition-colors hover:bg-primary/90 focus-visible:outline-none focus-visible:ring-2 focus-visible:ring-ring focus-visible:ring-offset-2 disabled:pointer-events-none disabled:opacity-50"
-        >
-          {isSubmitting ? (
+
+          <div className="space-y-2">
+            <div className="flex items-center justify-between">
+              <label htmlFor="password" className="text-sm font-medium leading-none peer-disabled:cursor-not-allowed peer-disabled:opacity-70">
+                Password
+              </label>
+              <Link to="/forgot-password" className="text-sm text-primary hover:underline">
+                Forgot password?
+              </Link>
+            </div>
+            <input
+              {...register('password')}
+              id="password"
+              type="password"
+              className="flex h-10 w-full rounded-md border border-input bg-background px-3 py-2 text-sm ring-offset-background file:border-0 file:bg-transparent file:text-sm file:font-medium placeholder:text-muted-foreground focus-visible:outline-none focus-visible:ring-2 focus-visible:ring-ring focus-visible:ring-offset-2 disabled:cursor-not-allowed disabled:opacity-50"
+            />
+            {errors.password && (
+              <p className="text-sm text-destructive">{errors.password.message}</p>
+            )}
+          </div>
+
+          <button
+            type="submit"
+            disabled={isSubmitting}
+            className="inline-flex h-10 w-full items-center justify-center rounded-md bg-primary px-4 py-2 text-sm font-medium text-primary-foreground ring-offset-background transition-colors hover:bg-primary/90 focus-visible:outline-none focus-visible:ring-2 focus-visible:ring-ring focus-visible:ring-offset-2 disabled:pointer-events-none disabled:opacity-50"
+          >
+            {isSubmitting ? (
+              <>
+                <span className="mr-2 h-4 w-4 animate-spin rounded-full border-2 border-background border-t-transparent"></span>
+                Logging in...
+              </>
+            ) : (
+              'Login'
+            )}
+          </button>
+
+          {oidcProvider && (
             <>
-              <span className="mr-2 h-4 w-4 animate-spin rounded-full border-2 border-background border-t-transparent"></span>
-              Logging in...
+              <div className="flex items-center gap-2 text-xs uppercase text-muted-foreground">
+                <span className="h-px flex-1 bg-border" />
+                or
+                <span className="h-px flex-1 bg-border" />
+              </div>
+
+              <button
+                type="button"
+                onClick={startOidcLogin}
+                disabled={redirecting}
+                className="inline-flex h-10 w-full items-center justify-center rounded-md border border-input bg-background px-4 py-2 text-sm font-medium ring-offset-background transition-colors hover:bg-accent focus-visible:outline-none focus-visible:ring-2 focus-visible:ring-ring focus-visible:ring-offset-2 disabled:pointer-events-none disabled:opacity-50"
+              >
+                {redirecting ? 'Redirecting...' : `Sign in with ${oidcProvider}`}
+              </button>
             </>
-          ) : (
-            'Login'
           )}
-        </button>
-      </form>
+        </form>
+      )}
 
       <div className="text-center text-sm">
         Don't have an account?{' '}
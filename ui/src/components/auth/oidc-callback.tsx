@@ -0,0 +1,68 @@
+import { useEffect, useRef, useState } from 'react';
+import { Link, useNavigate, useSearchParams } from 'react-router-dom';
+import { OIDC_STATE_KEY } from '../../lib/api';
+import { useAuth } from '../../lib/auth-context';
+import { ErrorHandler } from '../../lib/error-handler';
+import { ErrorDisplay } from '../ui/error-display';
+import type { ApiError } from '@/types';
+
+export function OidcCallback() {
+  const [searchParams] = useSearchParams();
+  const code = searchParams.get('code') ?? '';
+  const state = searchParams.get('state') ?? '';
+  // Set by the provider when the user cancelled or was not allowed to sign in
+  const providerError = searchParams.get('error_description') ?? searchParams.get('error');
+  // Only callbacks of a login this tab started are completed, so a link with
+  // someone else's code cannot sign the user into their account
+  const [issuedState] = useState(() => sessionStorage.getItem(OIDC_STATE_KEY));
+  const foreignState = !!state && state !== issuedState;
+  const { completeOidcLogin } = useAuth();
+  const navigate = useNavigate();
+  const [error, setError] = useState<ApiError | null>(null);
+  // The state works once, so StrictMode's double effect must not submit it twice
+  const submitted = useRef(false);
+
+  useEffect(() => {
+    if (!code || !state || foreignState || submitted.current) {
+      return;
+    }
+    submitted.current = true;
+    sessionStorage.removeItem(OIDC_STATE_KEY);
+
+    completeOidcLogin(code, state)
+      .then((challenge) => {
+        if (challenge) {
+          navigate('/login', { replace: true, state: { challengeToken: challenge.challenge_token } });
+        } else {
+          navigate('/dashboard', { replace: true });
+        }
+      })
+      .catch((err) => setError(ErrorHandler.extractError(err)));
+  }, [code, state, foreignState, completeOidcLogin, navigate]);
+
+  return (
+    <div className="mx-auto w-full max-w-md space-y-6 p-6">
+      <div className="space-y-2 text-center">
+        <h1 className="text-3xl font-bold">Signing in</h1>
+      </div>
+
+      {error && <ErrorDisplay error={error} showSuggestions={false} className="mb-4" />}
+
+      {providerError ? (
+        <p className="text-center text-sm">Single sign-on did not complete: {providerError}</p>
+      ) : !code || !state ? (
+        <p className="text-center text-sm">This sign-in link is invalid. Please sign in again.</p>
+      ) : foreignState ? (
+        <p className="text-center text-sm">This sign-in was not started in this browser. Please sign in again.</p>
+      ) : (
+        !error && <p className="text-center text-sm text-muted-foreground">Completing your sign-in...</p>
+      )}
+
+      <div className="text-center text-sm">
+        <Link to="/login" className="text-primary hover:underline">
+          Back to login
+        </Link>
+      </div>
+    </div>
+  );
+}
@@ -0,0 +1,104 @@
+import { useState } from 'react';
+import { useForm } from 'react-hook-form';
+import { z } from 'zod';
+import { zodResolver } from '@hookform/resolvers/zod';
+import { Link, useSearchParams } from 'react-router-dom';
+import { authApi } from '../../lib/api';
+import { ErrorHandler } from '../../lib/error-handler';
+import { ErrorDisplay } from '../ui/error-display';
+import type { ApiError } from '@/types';
+
+// Form validation schema
+const resetPasswordSchema = z
+  .object({
+    password: z.string().min(8, 'Password must be at least 8 characters'),
+    confirmPassword: z.string(),
+  })
+  .refine((data) => data.password === data.confirmPassword, {
+    message: 'Passwords do not match',
+    path: ['confirmPassword'],
+  });
+
+type ResetPasswordFormValues = z.infer<typeof resetPasswordSchema>;
+
+export function ResetPasswordForm() {
+  const [searchParams] = useSearchParams();
+  const token = searchParams.get('token') ?? '';
+  const [error, setError] = useState<ApiError | null>(null);
+  const [done, setDone] = useState(false);
+
+  const {
+    register,
+    handleSubmit,
+    formState: { errors, isSubmitting },
+  } = useForm<ResetPasswordFormValues>({
+    resolver: zodResolver(resetPasswordSchema),
+  });
+
+  const onSubmit = async (data: ResetPasswordFormValues) => {
+    setError(null);
+
+    try {
+      await authApi.resetPassword(token, data.password);
+      setDone(true);
+    } catch (err) {
+      setError(ErrorHandler.extractError(err));
+    }
+  };
+
+  const inputClassName =
+    'flex h-10 w-full rounded-md border border-input bg-background px-3 py-2 text-sm ring-offset-background file:border-0 file:bg-transparent file:text-sm file:font-medium placeholder:text-muted-foreground focus-visible:outline-none focus-visible:ring-2 focus-visible:ring-ring focus-visible:ring-offset-2 disabled:cursor-not-allowed disabled:opacity-50';
+
+  return (
+    <div className="mx-auto w-full max-w-md space-y-6 p-6">
+      <div className="space-y-2 text-center">
+        <h1 className="text-3xl font-bold">Choose a new password</h1>
+        <p className="text-muted-foreground">You will be signed out on all devices</p>
+      </div>
+
+      {error && <ErrorDisplay error={error} showSuggestions={true} className="mb-4" />}
+
+      {done ? (
+        <p className="text-center text-sm">Your password has been changed. You can now log in with it.</p>
+      ) : !token ? (
+        <p className="text-center text-sm">This password reset link is invalid. Please request a new one.</p>
+      ) : (
+        <form onSubmit={handleSubmit(onSubmit)} className="space-y-4">
+          <div className="space-y-2">
+            <label htmlFor="password" className="text-sm font-medium leading-none peer-disabled:cursor-not-allowed peer-disabled:opacity-70">
+              New password
+            </label>
+            <input {...register('password')} id="password" type="password" className={inputClassName} />
+            {errors.password && (
+              <p className="text-sm text-destructive">{errors.password.message}</p>
+            )}
+          </div>
+
+          <div className="space-y-2">
+            <label htmlFor="confirmPassword" className="text-sm font-medium leading-none peer-disabled:cursor-not-allowed peer-disabled:opacity-70">
+              Confirm new password
+            </label>
+            <input {...register('confirmPassword')} id="confirmPassword" type="password" className={inputClassName} />
+            {errors.confirmPassword && (
+              <p className="text-sm text-destructive">{errors.confirmPassword.message}</p>
+            )}
+          </div>
+
+          <button
+            type="submit"
+            disabled={isSubmitting}
+            className="inline-flex h-10 w-full items-center justify-center rounded-md bg-primary px-4 py-2 text-sm font-medium text-primary-foreground ring-offset-background transition-colors hover:bg-primary/90 focus-visible:outline-none focus-visible:ring-2 focus-visible:ring-ring focus-visible:ring-offset-2 disabled:pointer-events-none disabled:opacity-50"
+          >
+            {isSubmitting ? 'Saving...' : 'Set new password'}
+          </button>
+        </form>
+      )}
+
+      <div className="text-center text-sm">
+        <Link to={done ? '/login' : '/forgot-password'} className="text-primary hover:underline">
+          {done ? 'Go to login' : 'Request a new link'}
+        </Link>
+      </div>
+    </div>
+  );
+}
@@ -0,0 +1,44 @@
+import { useState } from 'react';
+import { useAuth } from '../../lib/auth-context';
+import { authApi } from '../../lib/api';
+import { ErrorHandler } from '../../lib/error-handler';
+
+export function VerifyEmailBanner() {
+  const { user } = useAuth();
+  const [status, setStatus] = useState<'idle' | 'sending' | 'sent'>('idle');
+  const [error, setError] = useState<string | null>(null);
+
+  if (!user || user.email_verified_at) {
+    return null;
+  }
+
+  const handleResend = async () => {
+    setStatus('sending');
+    setError(null);
+
+    try {
+      await authApi.resendVerification();
+      setStatus('sent');
+    } catch (err) {
+      setError(ErrorHandler.getUserMessage(err));
+      setStatus('idle');
+    }
+  };
+
+  return (
+    <div className="rounded-md border border-amber-300 bg-amber-50 px-4 py-3 text-sm text-amber-900 dark:border-amber-700 dark:bg-amber-950 dark:text-amber-100">
+      <p>
+        Please confirm your email address {user.email} to receive reminder emails. Check your inbox for the verification link.
+      </p>
+      {error && <p className="mt-1 text-destructive">{error}</p>}
+      <button
+        type="button"
+        onClick={handleResend}
+        disabled={status !== 'idle'}
+        className="mt-2 font-medium underline disabled:no-underline disabled:opacity-70"
+      >
+        {status === 'sent' ? 'Verification email sent' : status === 'sending' ? 'Sending...' : 'Resend verification email'}
+      </button>
+    </div>
+  );
+}
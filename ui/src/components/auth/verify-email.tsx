@@ -0,0 +1,51 @@
+import { useEffect, useRef, useState } from 'react';
+import { Link, useSearchParams } from 'react-router-dom';
+import { authApi } from '../../lib/api';
+import { ErrorHandler } from '../../lib/error-handler';
+import { ErrorDisplay } from '../ui/error-display';
+import type { ApiError } from '@/types';
+
+export function VerifyEmail() {
+  const [searchParams] = useSearchParams();
+  const token = searchParams.get('token') ?? '';
+  const [error, setError] = useState<ApiError | null>(null);
+  const [done, setDone] = useState(false);
+  // The token works once, so StrictMode's double effect must not submit it twice
+  const submitted = useRef(false);
+
+  useEffect(() => {
+    if (!token || submitted.current) {
+      return;
+    }
+    submitted.current = true;
+
+    authApi
+      .verifyEmail(token)
+      .then(() => setDone(true))
+      .catch((err) => setError(ErrorHandler.extractError(err)));
+  }, [token]);
+
+  return (
+    <div className="mx-auto w-full max-w-md space-y-6 p-6">
+      <div className="space-y-2 text-center">
+        <h1 className="text-3xl font-bold">Confirm your email</h1>
+      </div>
+
+      {error && <ErrorDisplay error={error} showSuggestions={true} className="mb-4" />}
+
+      {done ? (
+        <p className="text-center text-sm">Your email address has been verified. Thank you!</p>
+      ) : !token ? (
+        <p className="text-center text-sm">This verification link is invalid. Please request a new one.</p>
+      ) : (
+        !error && <p className="text-center text-sm text-muted-foreground">Verifying your email address...</p>
+      )}
+
+      <div className="text-center text-sm">
+        <Link to="/dashboard" className="text-primary hover:underline">
+          Go to dashboard
+        </Link>
+      </div>
+    </div>
+  );
+}
@@ -0,0 +1,161 @@
+import { useEffect, useState } from 'react';
+import { useForm } from 'react-hook-form';
+import { z } from 'zod';
+import { zodResolver } from '@hookform/resolvers/zod';
+import { userApi } from '../../lib/api';
+import { ErrorHandler } from '../../lib/error-handler';
+import { ErrorDisplay } from '../ui/error-display';
+import { inputClassName, labelClassName, submitClassName } from './form-styles';
+import type { ApiError, ApiToken, ApiTokenWithSecret } from '@/types';
+
+// Form validation schema
+const apiTokenSchema = z.object({
+  name: z.string().trim().min(1, 'Please enter a name').max(100, 'Name must be at most 100 characters'),
+  scope: z.enum(['read', 'read_write']),
+  // Empty for tokens that do not expire
+  expires_in_days: z.string(),
+});
+
+type ApiTokenFormValues = z.infer<typeof apiTokenSchema>;
+
+const formatDate = (value: string | null) => (value ? new Date(value).toLocaleDateString() : 'Never');
+
+export function ApiTokensForm() {
+  const [tokens, setTokens] = useState<ApiToken[]>([]);
+  // The new token is only returned once, right after it is created
+  const [created, setCreated] = useState<ApiTokenWithSecret | null>(null);
+  const [error, setError] = useState<ApiError | null>(null);
+
+  const {
+    register,
+    handleSubmit,
+    reset,
+    setError: setFieldError,
+    formState: { errors, isSubmitting },
+  } = useForm<ApiTokenFormValues>({
+    resolver: zodResolver(apiTokenSchema),
+    defaultValues: { scope: 'read', expires_in_days: '90' },
+  });
+
+  const loadTokens = async () => {
+    try {
+      setTokens(await userApi.getApiTokens());
+    } catch (err) {
+      setError(ErrorHandler.extractError(err));
+    }
+  };
+
+  useEffect(() => {
+    void loadTokens();
+  }, []);
+
+  const onSubmit = async (data: ApiTokenFormValues) => {
+    setError(null);
+
+    try {
+      const token = await userApi.createApiToken({
+        name: data.name,
+        scope: data.scope,
+        expires_in_days: data.expires_in_days ? Number(data.expires_in_days) : undefined,
+      });
+      reset();
+      setCreated(token);
+      await loadTokens();
+    } catch (err) {
+      const fieldErrors = ErrorHandler.getFieldErrors(err);
+      if (fieldErrors.name) {
+        setFieldError('name', { message: fieldErrors.name });
+      } else {
+        setError(ErrorHandler.extractError(err));
+      }
+    }
+  };
+
+  const handleRevoke = async (id: string) => {
+    setError(null);
+
+    try {
+      await userApi.revokeApiToken(id);
+      if (created?.id === id) {
+        setCreated(null);
+      }
+      await loadTokens();
+    } catch (err) {
+      setError(ErrorHandler.extractError(err));
+    }
+  };
+
+  return (
+    <div className="space-y-6">
+      {error && <ErrorDisplay error={error} showSuggestions={false} />}
+
+      {created && (
+        <div className="space-y-2 rounded-md border bg-muted px-4 py-3">
+          <p className="text-sm font-medium">Copy your new token "{created.name}"</p>
+          <p className="text-sm text-muted-foreground">It will not be shown again.</p>
+          <p className="break-all font-mono text-sm">{created.token}</p>
+        </div>
+      )}
+
+      {tokens.length > 0 && (
+        <ul className="divide-y rounded-md border">
+          {tokens.map((token) => (
+            <li key={token.id} className="flex items-center justify-between gap-4 px-4 py-3 text-sm">
+              <div className="min-w-0">
+                <p className="font-medium">
+                  {token.name}{' '}
+                  <span className="text-muted-foreground">
+                    ({token.scope === 'read' ? 'read-only' : 'read and write'})
+                  </span>
+                </p>
+                <p className="text-muted-foreground">
+                  <span className="font-mono">{token.token_prefix}…</span> · Expires {formatDate(token.expires_at)} · Last used{' '}
+                  {formatDate(token.last_used_at)}
+                </p>
+              </div>
+              <button
+                type="button"
+                onClick={() => handleRevoke(token.id)}
+                className="shrink-0 text-sm text-destructive hover:underline"
+              >
+                Revoke
+              </button>
+            </li>
+          ))}
+        </ul>
+      )}
+
+      <form onSubmit={handleSubmit(onSubmit)} className="space-y-4">
+        <div className="space-y-2">
+          <label htmlFor="tokenName" className={labelClassName}>Name</label>
+          <input {...register('name')} id="tokenName" placeholder="e.g. Budget script" className={inputClassName} />
+          {errors.name && <p className="text-sm text-destructive">{errors.name.message}</p>}
+        </div>
+
+        <div className="grid gap-4 sm:grid-cols-2">
+          <div className="space-y-2">
+            <label htmlFor="tokenScope" className={labelClassName}>Access</label>
+            <select {...register('scope')} id="tokenScope" className={inputClassName}>
+              <option value="read">Read-only</option>
+              <option value="read_write">Read and write</option>
+            </select>
+          </div>
+
+          <div className="space-y-2">
+            <label htmlFor="tokenExpiry" className={labelClassName}>Expires</label>
+            <select {...register('expires_in_days')} id="tokenExpiry" className={inputClassName}>
+              <option value="30">In 30 days</option>
+              <option value="90">In 90 days</option>
+              <option value="365">In a year</option>
+              <option value="">Never</option>
+            </select>
+          </div>
+        </div>
+
+        <button type="submit" disabled={isSubmitting} className={submitClassName}>
+          {isSubmitting ? 'Creating...' : 'Create token'}
+        </button>
+      </form>
+    </div>
+  );
+}
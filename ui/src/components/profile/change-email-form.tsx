@@ -0,0 +1,81 @@
+import { useState } from 'react';
+import { useForm } from 'react-hook-form';
+import { z } from 'zod';
+import { zodResolver } from '@hookform/resolvers/zod';
+import { userApi } from '../../lib/api';
+import { ErrorHandler } from '../../lib/error-handler';
+import { ErrorDisplay } from '../ui/error-display';
+import { inputClassName, labelClassName, submitClassName } from './form-styles';
+import type { ApiError } from '@/types';
+
+// Form validation schema
+const changeEmailSchema = z.object({
+  newEmail: z.string().email('Please enter a valid email address'),
+  password: z.string().min(1, 'Please enter your password'),
+});
+
+type ChangeEmailFormValues = z.infer<typeof changeEmailSchema>;
+
+export function ChangeEmailForm() {
+  const [error, setError] = useState<ApiError | null>(null);
+  const [sentTo, setSentTo] = useState<string | null>(null);
+
+  const {
+    register,
+    handleSubmit,
+    reset,
+    setError: setFieldError,
+    formState: { errors, isSubmitting },
+  } = useForm<ChangeEmailFormValues>({
+    resolver: zodResolver(changeEmailSchema),
+  });
+
+  const onSubmit = async (data: ChangeEmailFormValues) => {
+    setError(null);
+    setSentTo(null);
+
+    try {
+      await userApi.changeEmail(data.newEmail, data.password);
+      reset();
+      setSentTo(data.newEmail);
+    } catch (err) {
+      const fieldErrors = ErrorHandler.getFieldErrors(err);
+      if (fieldErrors.password) {
+        setFieldError('password', { message: fieldErrors.password });
+      } else if (fieldErrors.new_email) {
+        setFieldError('newEmail', { message: fieldErrors.new_email });
+      } else {
+        setError(ErrorHandler.extractError(err));
+      }
+    }
+  };
+
+  return (
+    <form onSubmit={handleSubmit(onSubmit)} className="space-y-4">
+      {error && <ErrorDisplay error={error} showSuggestions={false} />}
+
+      <div className="space-y-2">
+        <label htmlFor="newEmail" className={labelClassName}>New email</label>
+        <input {...register('newEmail')} id="newEmail" type="email" className={inputClassName} />
+        {errors.newEmail && <p className="text-sm text-destructive">{errors.newEmail.message}</p>}
+      </div>
+
+      <div className="space-y-2">
+        <label htmlFor="emailPassword" className={labelClassName}>Password</label>
+        <input {...register('password')} id="emailPassword" type="password" className={inputClassName} />
+        {errors.password && <p className="text-sm text-destructive">{errors.password.message}</p>}
+      </div>
+
+      <div className="flex items-center gap-4">
+        <button type="submit" disabled={isSubmitting} className={submitClassName}>
+          {isSubmitting ? 'Sending...' : 'Change email'}
+        </button>
+        {sentTo && (
+          <p className="text-sm text-muted-foreground">
+            We sent a confirmation link to {sentTo}. Your email changes once you open it.
+          </p>
+        )}
+      </div>
+    </form>
+  );
+}
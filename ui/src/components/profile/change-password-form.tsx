@@ -0,0 +1,91 @@
+import { useState } from 'react';
+import { useForm } from 'react-hook-form';
+import { z } from 'zod';
+import { zodResolver } from '@hookform/resolvers/zod';
+import { userApi } from '../../lib/api';
+import { ErrorHandler } from '../../lib/error-handler';
+import { ErrorDisplay } from '../ui/error-display';
+import { inputClassName, labelClassName, submitClassName } from './form-styles';
+import type { ApiError } from '@/types';
+
+// Form validation schema
+const changePasswordSchema = z
+  .object({
+    currentPassword: z.string().min(1, 'Please enter your current password'),
+    newPassword: z.string().min(8, 'Password must be at least 8 characters'),
+    confirmPassword: z.string(),
+  })
+  .refine((data) => data.newPassword === data.confirmPassword, {
+    message: 'Passwords do not match',
+    path: ['confirmPassword'],
+  });
+
+type ChangePasswordFormValues = z.infer<typeof changePasswordSchema>;
+
+export function ChangePasswordForm() {
+  const [error, setError] = useState<ApiError | null>(null);
+  const [done, setDone] = useState(false);
+
+  const {
+    register,
+    handleSubmit,
+    reset,
+    setError: setFieldError,
+    formState: { errors, isSubmitting },
+  } = useForm<ChangePasswordFormValues>({
+    resolver: zodResolver(changePasswordSchema),
+  });
+
+  const onSubmit = async (data: ChangePasswordFormValues) => {
+    setError(null);
+    setDone(false);
+
+    try {
+      await userApi.changePassword(data.currentPassword, data.newPassword);
+      reset();
+      setDone(true);
+    } catch (err) {
+      const fieldErrors = ErrorHandler.getFieldErrors(err);
+      if (fieldErrors.current_password) {
+        setFieldError('currentPassword', { message: fieldErrors.current_password });
+      } else {
+        setError(ErrorHandler.extractError(err));
+      }
+    }
+  };
+
+  return (
+    <form onSubmit={handleSubmit(onSubmit)} className="space-y-4">
+      {error && <ErrorDisplay error={error} showSuggestions={false} />}
+
+      <div className="space-y-2">
+        <label htmlFor="currentPassword" className={labelClassName}>Current password</label>
+        <input {...register('currentPassword')} id="currentPassword" type="password" className={inputClassName} />
+        {errors.currentPassword && (
+          <p className="text-sm text-destructive">{errors.currentPassword.message}</p>
+        )}
+      </div>
+
+      <div className="space-y-2">
+        <label htmlFor="newPassword" className={labelClassName}>New password</label>
+        <input {...register('newPassword')} id="newPassword" type="password" className={inputClassName} />
+        {errors.newPassword && <p className="text-sm text-destructive">{errors.newPassword.message}</p>}
+      </div>
+
+      <div className="space-y-2">
+        <label htmlFor="confirmPassword" className={labelClassName}>Confirm new password</label>
+        <input {...register('confirmPassword')} id="confirmPassword" type="password" className={inputClassName} />
+        {errors.confirmPassword && (
+          <p className="text-sm text-destructive">{errors.confirmPassword.message}</p>
+        )}
+      </div>
+
+      <div className="flex items-center gap-4">
+        <button type="submit" disabled={isSubmitting} className={submitClassName}>
+          {isSubmitting ? 'Saving...' : 'Change password'}
+        </button>
+        {done && <p className="text-sm text-muted-foreground">Password changed. Other devices have been signed out.</p>}
+      </div>
+    </form>
+  );
+}
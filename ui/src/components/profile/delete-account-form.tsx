@@ -0,0 +1,114 @@
+import { useState } from 'react';
+import { useForm } from 'react-hook-form';
+import { z } from 'zod';
+import { zodResolver } from '@hookform/resolvers/zod';
+import { useNavigate } from 'react-router-dom';
+import { useAuth } from '../../lib/auth-context';
+import { userApi } from '../../lib/api';
+import { ErrorHandler } from '../../lib/error-handler';
+import { ErrorDisplay } from '../ui/error-display';
+import { inputClassName, labelClassName } from './form-styles';
+import type { ApiError } from '@/types';
+
+// Form validation schema
+const deleteAccountSchema = z.object({
+  password: z.string().min(1, 'Please enter your password'),
+  confirm: z.literal(true, { errorMap: () => ({ message: 'Please confirm that you want to delete your account' }) }),
+});
+
+type DeleteAccountFormValues = z.infer<typeof deleteAccountSchema>;
+
+export function DeleteAccountForm() {
+  const { logout } = useAuth();
+  const navigate = useNavigate();
+  const [error, setError] = useState<ApiError | null>(null);
+  const [exporting, setExporting] = useState(false);
+
+  const {
+    register,
+    handleSubmit,
+    setError: setFieldError,
+    formState: { errors, isSubmitting },
+  } = useForm<DeleteAccountFormValues>({
+    resolver: zodResolver(deleteAccountSchema),
+  });
+
+  const handleExport = async () => {
+    setError(null);
+    setExporting(true);
+
+    try {
+      await userApi.exportData();
+    } catch (err) {
+      setError(ErrorHandler.extractError(err));
+    } finally {
+      setExporting(false);
+    }
+  };
+
+  const onSubmit = async (data: DeleteAccountFormValues) => {
+    setError(null);
+
+    try {
+      const deletion = await userApi.deleteAccount(data.password);
+      logout();
+      const message = deletion.deleted
+        ? 'Your account has been deleted.'
+        : `Your account will be deleted on ${new Date(deletion.deletion_scheduled_at!).toLocaleDateString()}. Log in before then to keep it.`;
+      navigate('/login', { state: { message } });
+    } catch (err) {
+      const fieldErrors = ErrorHandler.getFieldErrors(err);
+      if (fieldErrors.password) {
+        setFieldError('password', { message: fieldErrors.password });
+      } else {
+        setError(ErrorHandler.extractError(err));
+      }
+    }
+  };
+
+  return (
+    <div className="space-y-6">
+      {error && <ErrorDisplay error={error} showSuggestions={false} />}
+
+      <div className="space-y-2">
+        <p className="text-sm text-muted-foreground">
+          Download your profile, subscriptions, payments, notifications and history as a JSON file.
+        </p>
+        <button
+          type="button"
+          onClick={handleExport}
+          disabled={exporting}
+          className="inline-flex h-10 items-center justify-center rounded-md border border-input bg-background px-4 py-2 text-sm font-medium hover:bg-accent disabled:pointer-events-none disabled:opacity-50"
+        >
+          {exporting ? 'Preparing...' : 'Download my data'}
+        </button>
+      </div>
+
+      <form onSubmit={handleSubmit(onSubmit)} className="space-y-4 border-t pt-6">
+        <p className="text-sm text-muted-foreground">
+          Deleting your account removes all of your data. This cannot be undone.
+        </p>
+
+        <div className="space-y-2">
+          <label htmlFor="deletePassword" className={labelClassName}>Password</label>
+          <input {...register('password')} id="deletePassword" type="password" className={inputClassName} />
+          {errors.password && <p className="text-sm text-destructive">{errors.password.message}</p>}
+        </div>
+
+        <label className="flex items-center gap-2 text-sm">
+          <input {...register('confirm')} type="checkbox" />
+          I understand that my account and all of its data will be deleted
+        </label>
+        {errors.confirm && <p className="text-sm text-destructive">{errors.confirm.message}</p>}
+
+        <button
+          type="submit"
+          disabled={isSubmitting}
+          className="inline-flex h-10 items-center justify-center rounded-md bg-destructive px-4 py-2 text-sm font-medium text-destructive-foreground hover:bg-destructive/90 disabled:pointer-events-none disabled:opacity-50"
+        >
+          {isSubmitting ? 'Deleting...' : 'Delete account'}
+        </button>
+      </form>
+    </div>
+  );
+}
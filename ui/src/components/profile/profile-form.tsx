@@ -0,0 +1,128 @@
+import { useState } from 'react';
+import { useForm } from 'react-hook-form';
+import { z } from 'zod';
+import { zodResolver } from '@hookform/resolvers/zod';
+import { useAuth } from '../../lib/auth-context';
+import { userApi } from '../../lib/api';
+import { ErrorHandler } from '../../lib/error-handler';
+import { ErrorDisplay } from '../ui/error-display';
+import { inputClassName, labelClassName, submitClassName } from './form-styles';
+import type { ApiError, UserPreferences } from '@/types';
+
+// Form validation schema; empty fields reset the preference to its default
+const profileSchema = z.object({
+  name: z.string().max(255, 'Name must be at most 255 characters'),
+  default_currency: z.enum(['', 'Usd', 'Cny']),
+  timezone: z.string(),
+  reminder_lead_days: z.string().regex(/^\d{0,3}$/, 'Enter a number of days'),
+  locale: z.string(),
+  email_reminders: z.boolean().optional(),
+});
+
+type ProfileFormValues = z.infer<typeof profileSchema>;
+
+export function ProfileForm() {
+  const { user, updateUser } = useAuth();
+  const [error, setError] = useState<ApiError | null>(null);
+  const [saved, setSaved] = useState(false);
+  const preferences = (user?.preferences ?? {}) as UserPreferences;
+
+  const {
+    register,
+    handleSubmit,
+    setError: setFieldError,
+    formState: { errors, isSubmitting },
+  } = useForm<ProfileFormValues>({
+    resolver: zodResolver(profileSchema),
+    defaultValues: {
+      name: user?.name ?? '',
+      default_currency: preferences.default_currency ?? '',
+      timezone: preferences.timezone ?? '',
+      reminder_lead_days: preferences.reminder_lead_days?.toString() ?? '',
+      locale: preferences.locale ?? '',
+      email_reminders: preferences.email_reminders ?? false,
+    },
+  });
+
+  const onSubmit = async (data: ProfileFormValues) => {
+    setError(null);
+    setSaved(false);
+
+    try {
+      const updated = await userApi.updateProfile({
+        name: data.name,
+        preferences: {
+          default_currency: data.default_currency || null,
+          timezone: data.timezone.trim() || null,
+          reminder_lead_days: data.reminder_lead_days ? Number(data.reminder_lead_days) : null,
+          locale: data.locale.trim() || null,
+          email_reminders: data.email_reminders,
+        },
+      });
+      updateUser(updated);
+      setSaved(true);
+    } catch (err) {
+      const fieldErrors = ErrorHandler.getFieldErrors(err);
+      for (const [field, message] of Object.entries(fieldErrors)) {
+        setFieldError(field.replace('preferences.', '') as keyof ProfileFormValues, { message });
+      }
+      setError(ErrorHandler.extractError(err));
+    }
+  };
+
+  return (
+    <form onSubmit={handleSubmit(onSubmit)} className="space-y-4">
+      {error && <ErrorDisplay error={error} showSuggestions={false} />}
+
+      <div className="space-y-2">
+        <label htmlFor="name" className={labelClassName}>Name</label>
+        <input {...register('name')} id="name" className={inputClassName} />
+        {errors.name && <p className="text-sm text-destructive">{errors.name.message}</p>}
+      </div>
+
+      <div className="grid gap-4 sm:grid-cols-2">
+        <div className="space-y-2">
+          <label htmlFor="default_currency" className={labelClassName}>Default currency</label>
+          <select {...register('default_currency')} id="default_currency" className={inputClassName}>
+            <option value="">No default</option>
+            <option value="Usd">USD</option>
+            <option value="Cny">CNY</option>
+          </select>
+        </div>
+
+        <div className="space-y-2">
+          <label htmlFor="reminder_lead_days" className={labelClassName}>Reminder lead days</label>
+          <input {...register('reminder_lead_days')} id="reminder_lead_days" inputMode="numeric" placeholder="3" className={inputClassName} />
+          {errors.reminder_lead_days && (
+            <p className="text-sm text-destructive">{errors.reminder_lead_days.message}</p>
+          )}
+        </div>
+
+        <div className="space-y-2">
+          <label htmlFor="timezone" className={labelClassName}>Time zone</label>
+          <input {...register('timezone')} id="timezone" placeholder="Europe/Berlin" className={inputClassName} />
+          {errors.timezone && <p className="text-sm text-destructive">{errors.timezone.message}</p>}
+        </div>
+
+        <div className="space-y-2">
+          <label htmlFor="locale" className={labelClassName}>Locale</label>
+          <input {...register('locale')} id="locale" placeholder="en-US" className={inputClassName} />
+          {errors.locale && <p className="text-sm text-destructive">{errors.locale.message}</p>}
+        </div>
+      </div>
+
+      <label className="flex items-center gap-2 text-sm">
+        <input {...register('email_reminders')} type="checkbox" disabled={!user?.email_verified_at} />
+        Email me renewal and trial reminders
+        {!user?.email_verified_at && <span className="text-muted-foreground">(verify your email first)</span>}
+      </label>
+
+      <div className="flex items-center gap-4">
+        <button type="submit" disabled={isSubmitting} className={submitClassName}>
+          {isSubmitting ? 'Saving...' : 'Save profile'}
+        </button>
+        {saved && <p className="text-sm text-muted-foreground">Profile saved.</p>}
+      </div>
+    </form>
+  );
+}
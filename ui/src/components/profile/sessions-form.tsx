@@ -0,0 +1,100 @@
+import { useEffect, useState } from 'react';
+import { useNavigate } from 'react-router-dom';
+import { useAuth } from '../../lib/auth-context';
+import { userApi } from '../../lib/api';
+import { ErrorHandler } from '../../lib/error-handler';
+import { ErrorDisplay } from '../ui/error-display';
+import { submitClassName } from './form-styles';
+import type { ApiError, Session } from '@/types';
+
+const formatDateTime = (value: string) => new Date(value).toLocaleString();
+
+export function SessionsForm() {
+  const { logout } = useAuth();
+  const navigate = useNavigate();
+  const [sessions, setSessions] = useState<Session[]>([]);
+  const [error, setError] = useState<ApiError | null>(null);
+  const [signingOut, setSigningOut] = useState(false);
+
+  const loadSessions = async () => {
+    try {
+      setSessions(await userApi.getSessions());
+    } catch (err) {
+      setError(ErrorHandler.extractError(err));
+    }
+  };
+
+  useEffect(() => {
+    void loadSessions();
+  }, []);
+
+  // Signing out this browser ends the login here as well
+  const signOutHere = (message: string) => {
+    logout();
+    navigate('/login', { state: { message } });
+  };
+
+  const handleRevoke = async (session: Session) => {
+    setError(null);
+
+    try {
+      await userApi.revokeSession(session.id);
+      if (session.current) {
+        signOutHere('You have been signed out.');
+        return;
+      }
+      await loadSessions();
+    } catch (err) {
+      setError(ErrorHandler.extractError(err));
+    }
+  };
+
+  const handleRevokeAll = async () => {
+    setError(null);
+    setSigningOut(true);
+
+    try {
+      await userApi.revokeAllSessions();
+      signOutHere('You have been signed out on all devices.');
+    } catch (err) {
+      setError(ErrorHandler.extractError(err));
+      setSigningOut(false);
+    }
+  };
+
+  return (
+    <div className="space-y-6">
+      {error && <ErrorDisplay error={error} showSuggestions={false} />}
+
+      {sessions.length > 0 && (
+        <ul className="divide-y rounded-md border">
+          {sessions.map((session) => (
+            <li key={session.id} className="flex items-center justify-between gap-4 px-4 py-3 text-sm">
+              <div className="min-w-0">
+                <p className="truncate font-medium">
+                  {session.user_agent ?? 'Unknown device'}{' '}
+                  {session.current && <span className="text-muted-foreground">(this device)</span>}
+                </p>
+                <p className="text-muted-foreground">
+                  {session.ip_address ?? 'Unknown IP address'} · Signed in {formatDateTime(session.created_at)} · Last
+                  active {formatDateTime(session.last_seen_at)}
+                </p>
+              </div>
+              <button
+                type="button"
+                onClick={() => handleRevoke(session)}
+                className="shrink-0 text-sm text-destructive hover:underline"
+              >
+                Sign out
+              </button>
+            </li>
+          ))}
+        </ul>
+      )}
+
+      <button type="button" onClick={handleRevokeAll} disabled={signingOut} className={submitClassName}>
+        {signingOut ? 'Signing out...' : 'Sign out everywhere'}
+      </button>
+    </div>
+  );
+}
@@ -0,0 +1,242 @@
+import { useEffect, useState } from 'react';
+import { useForm } from 'react-hook-form';
+import { z } from 'zod';
+import { zodResolver } from '@hookform/resolvers/zod';
+import { useAuth } from '../../lib/auth-context';
+import { userApi } from '../../lib/api';
+import { ErrorHandler } from '../../lib/error-handler';
+import { ErrorDisplay } from '../ui/error-display';
+import { inputClassName, labelClassName, submitClassName } from './form-styles';
+import type { ApiError, TwoFactorEnrollment, TwoFactorStatus } from '@/types';
+
+// Form validation schemas
+const codeSchema = z.object({
+  code: z.string().trim().min(6, 'Please enter a code'),
+});
+
+const disableSchema = codeSchema.extend({
+  password: z.string().min(1, 'Please enter your password'),
+});
+
+type CodeFormValues = z.infer<typeof codeSchema>;
+type DisableFormValues = z.infer<typeof disableSchema>;
+
+const secondaryButtonClassName =
+  'inline-flex h-10 items-center justify-center rounded-md border border-input bg-background px-4 py-2 text-sm font-medium hover:bg-accent disabled:pointer-events-none disabled:opacity-50';
+
+export function TwoFactorForm() {
+  const { user, updateUser } = useAuth();
+  const [status, setStatus] = useState<TwoFactorStatus | null>(null);
+  const [enrollment, setEnrollment] = useState<TwoFactorEnrollment | null>(null);
+  // Recovery codes are only returned once, right after they are generated
+  const [recoveryCodes, setRecoveryCodes] = useState<string[] | null>(null);
+  const [error, setError] = useState<ApiError | null>(null);
+  const [starting, setStarting] = useState(false);
+
+  const codeForm = useForm<CodeFormValues>({ resolver: zodResolver(codeSchema) });
+  const disableForm = useForm<DisableFormValues>({ resolver: zodResolver(disableSchema) });
+
+  const loadStatus = async () => {
+    try {
+      setStatus(await userApi.getTwoFactorStatus());
+    } catch (err) {
+      setError(ErrorHandler.extractError(err));
+    }
+  };
+
+  useEffect(() => {
+    void loadStatus();
+  }, []);
+
+  const setEnabled = (enabled: boolean) => {
+    if (user) {
+      updateUser({ ...user, two_factor_enabled: enabled });
+    }
+  };
+
+  const handleCodeError = (err: unknown) => {
+    const fieldErrors = ErrorHandler.getFieldErrors(err);
+    if (fieldErrors.code) {
+      codeForm.setError('code', { message: fieldErrors.code });
+    } else {
+      setError(ErrorHandler.extractError(err));
+    }
+  };
+
+  const startEnrollment = async () => {
+    setError(null);
+    setStarting(true);
+
+    try {
+      setEnrollment(await userApi.enrollTwoFactor());
+    } catch (err) {
+      setError(ErrorHandler.extractError(err));
+    } finally {
+      setStarting(false);
+    }
+  };
+
+  const onConfirm = async (data: CodeFormValues) => {
+    setError(null);
+
+    try {
+      const result = await userApi.confirmTwoFactor(data.code);
+      codeForm.reset();
+      setEnrollment(null);
+      setRecoveryCodes(result.recovery_codes);
+      setEnabled(true);
+      await loadStatus();
+    } catch (err) {
+      handleCodeError(err);
+    }
+  };
+
+  const onRegenerate = async (data: CodeFormValues) => {
+    setError(null);
+
+    try {
+      const result = await userApi.regenerateRecoveryCodes(data.code);
+      codeForm.reset();
+      setRecoveryCodes(result.recovery_codes);
+      await loadStatus();
+    } catch (err) {
+      handleCodeError(err);
+    }
+  };
+
+  const onDisable = async (data: DisableFormValues) => {
+    setError(null);
+
+    try {
+      await userApi.disableTwoFactor(data.password, data.code);
+      disableForm.reset();
+      setRecoveryCodes(null);
+      setEnabled(false);
+      await loadStatus();
+    } catch (err) {
+      const fieldErrors = ErrorHandler.getFieldErrors(err);
+      if (fieldErrors.password || fieldErrors.code) {
+        if (fieldErrors.password) disableForm.setError('password', { message: fieldErrors.password });
+        if (fieldErrors.code) disableForm.setError('code', { message: fieldErrors.code });
+      } else {
+        setError(ErrorHandler.extractError(err));
+      }
+    }
+  };
+
+  if (!status) {
+    return error ? <ErrorDisplay error={error} showSuggestions={false} /> : null;
+  }
+
+  return (
+    <div className="space-y-6">
+      {error && <ErrorDisplay error={error} showSuggestions={false} />}
+
+      {recoveryCodes && (
+        <div className="space-y-2 rounded-md border bg-muted px-4 py-3">
+          <p className="text-sm font-medium">Save your recovery codes</p>
+          <p className="text-sm text-muted-foreground">
+            Each code signs you in once if you lose your authenticator app. They will not be shown again.
+          </p>
+          <ul className="grid grid-cols-2 gap-1 font-mono text-sm">
+            {recoveryCodes.map((code) => (
+              <li key={code}>{code}</li>
+            ))}
+          </ul>
+          <button type="button" onClick={() => setRecoveryCodes(null)} className={secondaryButtonClassName}>
+            I have saved them
+          </button>
+        </div>
+      )}
+
+      {!status.enabled && !enrollment && (
+        <div className="space-y-2">
+          <p className="text-sm text-muted-foreground">
+            Protect your account with a code from an authenticator app in addition to your password.
+          </p>
+          <button type="button" onClick={startEnrollment} disabled={starting} className={submitClassName}>
+            {starting ? 'Starting...' : 'Enable two-factor authentication'}
+          </button>
+        </div>
+      )}
+
+      {!status.enabled && enrollment && (
+        <form onSubmit={codeForm.handleSubmit(onConfirm)} className="space-y-4">
+          <div className="space-y-2 text-sm">
+            <p>
+              Add Sub-Pal to your authenticator app by{' '}
+              <a href={enrollment.otpauth_uri} className="text-primary hover:underline">opening this link</a>{' '}
+              on your phone, or enter this key:
+            </p>
+            <p className="break-all rounded-md bg-muted px-3 py-2 font-mono">{enrollment.secret}</p>
+          </div>
+
+          <div className="space-y-2">
+            <label htmlFor="enrollCode" className={labelClassName}>Code from the app</label>
+            <input {...codeForm.register('code')} id="enrollCode" autoComplete="one-time-code" className={inputClassName} />
+            {codeForm.formState.errors.code && (
+              <p className="text-sm text-destructive">{codeForm.formState.errors.code.message}</p>
+            )}
+          </div>
+
+          <div className="flex gap-2">
+            <button type="submit" disabled={codeForm.formState.isSubmitting} className={submitClassName}>
+              {codeForm.formState.isSubmitting ? 'Verifying...' : 'Turn on'}
+            </button>
+            <button type="button" onClick={() => setEnrollment(null)} className={secondaryButtonClassName}>
+              Cancel
+            </button>
+          </div>
+        </form>
+      )}
+
+      {status.enabled && (
+        <>
+          <form onSubmit={codeForm.handleSubmit(onRegenerate)} className="space-y-4">
+            <p className="text-sm text-muted-foreground">
+              Two-factor authentication is on. {status.recovery_codes_remaining} unused recovery codes left.
+            </p>
+
+            <div className="space-y-2">
+              <label htmlFor="regenerateCode" className={labelClassName}>Authentication code</label>
+              <input {...codeForm.register('code')} id="regenerateCode" autoComplete="one-time-code" className={inputClassName} />
+              {codeForm.formState.errors.code && (
+                <p className="text-sm text-destructive">{codeForm.formState.errors.code.message}</p>
+              )}
+            </div>
+
+            <button type="submit" disabled={codeForm.formState.isSubmitting} className={secondaryButtonClassName}>
+              {codeForm.formState.isSubmitting ? 'Generating...' : 'Generate new recovery codes'}
+            </button>
+          </form>
+
+          <form onSubmit={disableForm.handleSubmit(onDisable)} className="space-y-4 border-t pt-6">
+            <div className="space-y-2">
+              <label htmlFor="disablePassword" className={labelClassName}>Password</label>
+              <input {...disableForm.register('password')} id="disablePassword" type="password" className={inputClassName} />
+              {disableForm.formState.errors.password && (
+                <p className="text-sm text-destructive">{disableForm.formState.errors.password.message}</p>
+              )}
+            </div>
+
+            <div className="space-y-2">
+              <label htmlFor="disableCode" className={labelClassName}>Authentication or recovery code</label>
+              <input {...disableForm.register('code')} id="disableCode" autoComplete="one-time-code" className={inputClassName} />
+              {disableForm.formState.errors.code && (
+                <p className="text-sm text-destructive">{disableForm.formState.errors.code.message}</p>
+              )}
+            </div>
+
+            <button
+              type="submit"
+              disabled={disableForm.formState.isSubmitting}
+              className="inline-flex h-10 items-center justify-center rounded-md bg-destructive px-4 py-2 text-sm font-medium text-destructive-foreground hover:bg-destructive/90 disabled:pointer-events-none disabled:opacity-50"
+            >
+              {disableForm.formState.isSubmitting ? 'Turning off...' : 'Turn off two-factor authentication'}
+            </button>
+          </form>
+        </>
+      )}
+    </div>
+  );
+}
@@ -9,14 +9,17 @@ import type {
   AddSubscriptionFormProps
 } from '@/types';
 import { COLOR_OPTIONS } from '@/types';
+import { useAuth } from "@/lib/auth-context";
 
 export function AddSubscriptionForm({ subscription, onSubmit, onCancel }: AddSubscriptionFormProps) {
+  const { user } = useAuth();
+  const preferredCurrency = (user?.preferences?.default_currency as string | undefined)?.toUpperCase();
   const [isSubmitting, setIsSubmitting] = useState(false);
   const [formData, setFormData] = useState<SubscriptionFormValues>({
     name: subscription?.name || "",
     description: subscription?.description || "",
     amount: subscription?.amount?.toString() || "",
-    currency: subscription?.currency || "USD",
+    currency: subscription?.currency || preferredCurrency || "USD",
     billing_cycle: subscription?.billingCycle || "monthly",
     start_date: subscription?.startDate?.toISOString().split('T')[0] || new Date().toISOString().split('T')[0],
     category: subscription?.category || "",
@@ -1,4 +1,4 @@
-import { AlertCircle, AlertTriangle, Info, Server, WifiOff, CheckCircle } from 'lucide-react';
+import { AlertCircle, AlertTriangle, Clock, Info, Server, WifiOff, CheckCircle } from 'lucide-react';
 import { ApiError } from '@/types/api.types';
 
 interface ErrorDisplayProps {
@@ -36,6 +36,8 @@ export function ErrorDisplay({
         return <Info className="h-5 w-5 text-blue-500" />;
       case 'conflict':
         return <AlertTriangle className="h-5 w-5 text-orange-500" />;
+      case 'rate_limit':
+        return <Clock className="h-5 w-5 text-amber-500" />;
       case 'server':
         return <Server className="h-5 w-5 text-red-600" />;
       case 'network':
@@ -55,6 +57,8 @@ export function ErrorDisplay({
         return 'bg-blue-50 border-blue-200 text-blue-800';
       case 'conflict':
         return 'bg-orange-50 border-orange-200 text-orange-800';
+      case 'rate_limit':
+        return 'bg-amber-50 border-amber-200 text-amber-800';
       case 'server':
         return 'bg-red-50 border-red-200 text-red-800';
       case 'network':
@@ -74,6 +78,8 @@ export function ErrorDisplay({
         return 'Not Found';
       case 'conflict':
         return 'Conflict';
+      case 'rate_limit':
+        return 'Too Many Attempts';
       case 'server':
         return 'Server Error';
       case 'network':
@@ -1,8 +1,21 @@
-import axios, { AxiosInstance, AxiosResponse } from 'axios';
+import axios, { AxiosInstance, AxiosResponse, InternalAxiosRequestConfig } from 'axios';
 import { getSecureToken, setSecureToken, removeSecureToken } from './secure-storage';
 import type {
   User,
+  UpdateProfileRequest,
+  AccountDeletion,
   AuthResponse,
+  LoginResponse,
+  OidcProviderInfo,
+  OidcAuthorization,
+  TokenResponse,
+  TwoFactorStatus,
+  TwoFactorEnrollment,
+  RecoveryCodes,
+  ApiToken,
+  ApiTokenWithSecret,
+  CreateApiTokenRequest,
+  Session,
   RegisterRequest,
   LoginRequest,
   Subscription,
@@ -10,17 +23,33 @@ import type {
   SubscriptionStats,
   SubscriptionStatus,
   ApiSubscriptionsResponse,
+  ApiSubscriptionResponse,
   ApiUserResponse,
   ApiAuthResponse,
   ApiStatsResponse,
   SubscriptionFormValues,
-  BillingCycle
+  BillingCycle,
+  BillingCycleUnit,
+  Notification,
+  NotificationListParams,
+  WebhookEndpoint,
+  WebhookEndpointWithSecret,
+  CreateWebhookRequest,
+  UpdateWebhookRequest,
+  WebhookDelivery,
+  WebhookDeliveryDetail,
+  WebhookDeliveryListParams,
+  ApiPaginatedResponse,
+  ApiResponse
 } from '@/types';
 
 // ======== API Configuration ========
 
 const API_URL = import.meta.env.VITE_API_URL || '/api/v1';
 
+// Session storage key of the state of the single sign-on login this tab started
+export const OIDC_STATE_KEY = 'oidc_state';
+
 // Create API instance
 const api: AxiosInstance = axios.create({
   baseURL: API_URL,
@@ -29,6 +58,16 @@ const api: AxiosInstance = axios.create({
   },
 });
 
+// ======== Utility Functions ========
+
+// Generic response handler
+const handleResponse = <T>(response: AxiosResponse): T => {
+  if (response.data.data) {
+    return response.data.data;
+  }
+  return response.data;
+};
+
 // ======== Interceptors ========
 
 // Request interceptor: Add authentication token
@@ -43,25 +82,55 @@ api.interceptors.request.use(
   (error) => Promise.reject(error)
 );
 
-// Response interceptor: Handle errors
+// Store a freshly issued token pair
+const storeTokens = (tokens: TokenResponse): void => {
+  setSecureToken('token', tokens.token, { maxAge: 3600 }); // 1 hour
+  setSecureToken('refresh_token', tokens.refresh_token, { maxAge: 30 * 24 * 3600 }); // 30 days
+};
+
+// Refresh tokens are single use, so concurrent 401s share one refresh
+let refreshPromise: Promise<string> | null = null;
+
+const refreshAccessToken = (): Promise<string> => {
+  if (!refreshPromise) {
+    refreshPromise = (async () => {
+      const refreshToken = getSecureToken('refresh_token');
+      if (!refreshToken) {
+        throw new Error('No refresh token');
+      }
+      // Plain axios so that a failing refresh does not trigger this interceptor again
+      const response = await axios.post(`${API_URL}/auth/refresh`, { refresh_token: refreshToken });
+      const tokens = handleResponse<TokenResponse>(response);
+      storeTokens(tokens);
+      return tokens.token;
+    })().finally(() => {
+      refreshPromise = null;
+    });
+  }
+  return refreshPromise;
+};
+
+// Response interceptor: Refresh the access token once on 401, then handle errors
 api.interceptors.response.use(
   (response) => response,
-  (error) => {
+  async (error) => {
+    const original = error.config as (InternalAxiosRequestConfig & { _retried?: boolean }) | undefined;
+    if (error.response?.status === 401 && original && !original._retried && !original.url?.startsWith('/auth/')) {
+      original._retried = true;
+      try {
+        const token = await refreshAccessToken();
+        original.headers.Authorization = `Bearer ${token}`;
+        return api(original);
+      } catch {
+        removeSecureToken('token');
+        removeSecureToken('refresh_token');
+      }
+    }
     console.error('API Error:', error);
     return Promise.reject(error);
   }
 );
 
-// ======== Utility Functions ========
-
-// Generic response handler
-const handleResponse = <T>(response: AxiosResponse): T => {
-  if (response.data.data) {
-    return response.data.data;
-  }
-  return response.data;
-};
-
 // ======== Authentication API Service ========
 
 export const authApi = {
@@ -78,25 +147,145 @@ export const authApi = {
   /**
    * Login user
    * @param data Login credentials
+   * @returns Authentication response with tokens, or a challenge when the
+   * account uses two-factor authentication
+   */
+  login: async (data: LoginRequest): Promise<LoginResponse> => {
+    const response = await api.post<ApiResponse<LoginResponse>>('/auth/login', data);
+    const loginData = handleResponse<LoginResponse>(response);
+
+    // Store tokens securely
+    if ('token' in loginData) {
+      storeTokens(loginData);
+    }
+
+    return loginData;
+  },
+
+  /**
+   * Complete a login with the challenge token and a two-factor code
+   * @param challengeToken Token returned by login
+   * @param code Code from the authenticator app, or a recovery code
    * @returns Authentication response with tokens
    */
-  login: async (data: LoginRequest): Promise<AuthResponse> => {
-    const response = await api.post<ApiAuthResponse>('/auth/login', data);
+  loginTwoFactor: async (challengeToken: string, code: string): Promise<AuthResponse> => {
+    const response = await api.post<ApiAuthResponse>('/auth/login/2fa', {
+      challenge_token: challengeToken,
+      code,
+    });
     const authData = handleResponse<AuthResponse>(response);
 
     // Store tokens securely
-    setSecureToken('token', authData.token, { maxAge: 3600 }); // 1 hour
-    setSecureToken('refresh_token', authData.refresh_token, { maxAge: 30 * 24 * 3600 }); // 30 days
+    storeTokens(authData);
 
     return authData;
   },
 
   /**
-   * Logout user and clear tokens
+   * Get the single sign-on provider shown on the login page
+   */
+  getOidcProvider: async (): Promise<OidcProviderInfo> => {
+    const response = await api.get<ApiResponse<OidcProviderInfo>>('/auth/oidc');
+    return handleResponse<OidcProviderInfo>(response);
+  },
+
+  /**
+   * Start a single sign-on login
+   * @returns URL of the provider's login page
+   */
+  startOidcLogin: async (): Promise<string> => {
+    // The response sets the cookie the callback is checked against
+    const response = await api.post<ApiResponse<OidcAuthorization>>('/auth/oidc/authorize', undefined, {
+      withCredentials: true,
+    });
+    const authorization = handleResponse<OidcAuthorization>(response);
+    sessionStorage.setItem(OIDC_STATE_KEY, authorization.state);
+    return authorization.authorization_url;
+  },
+
+  /**
+   * Complete a single sign-on login with the parameters the provider returned
+   * @returns Authentication response with tokens, or a challenge when the
+   * account uses two-factor authentication
+   */
+  completeOidcLogin: async (code: string, state: string): Promise<LoginResponse> => {
+    const response = await api.post<ApiResponse<LoginResponse>>(
+      '/auth/oidc/callback',
+      { code, state },
+      { withCredentials: true },
+    );
+    const loginData = handleResponse<LoginResponse>(response);
+
+    // Store tokens securely
+    if ('token' in loginData) {
+      storeTokens(loginData);
+    }
+
+    return loginData;
+  },
+
+  /**
+   * Exchange the stored refresh token for a new token pair
+   * @returns The new access token
    */
-  logout: (): void => {
+  refresh: async (): Promise<string> => {
+    return refreshAccessToken();
+  },
+
+  /**
+   * Logout user: revoke the refresh token and clear tokens
+   */
+  logout: async (): Promise<void> => {
+    const refreshToken = getSecureToken('refresh_token');
     removeSecureToken('token');
     removeSecureToken('refresh_token');
+    if (refreshToken) {
+      try {
+        await api.post('/auth/logout', { refresh_token: refreshToken });
+      } catch (error) {
+        console.error('Logout request failed:', error);
+      }
+    }
+  },
+
+  /**
+   * Request a password reset email
+   * @param email Email address of the account
+   */
+  requestPasswordReset: async (email: string): Promise<void> => {
+    await api.post('/auth/password-reset/request', { email });
+  },
+
+  /**
+   * Set a new password with the token from a password reset email
+   * @param token Token from the reset link
+   * @param password New password
+   */
+  resetPassword: async (token: string, password: string): Promise<void> => {
+    await api.post('/auth/password-reset/confirm', { token, password });
+  },
+
+  /**
+   * Verify the email address with the token from a verification email
+   * @param token Token from the verification link
+   */
+  verifyEmail: async (token: string): Promise<void> => {
+    await api.post('/auth/verify-email', { token });
+  },
+
+  /**
+   * Unlock an account locked after failed logins
+   * @param token Token from the unlock link
+   */
+  unlockAccount: async (token: string): Promise<void> => {
+    await api.post('/auth/unlock', { token });
+  },
+
+  /**
+   * Send a new verification email to the current user
+   */
+  resendVerification: async (): Promise<void> => {
+    await api.post('/auth/verify-email/resend');
   },
 
   /**
@@ -109,6 +298,167 @@ export const authApi = {
   },
 };
 
+// ======== User API Service ========
+
+export const userApi = {
+  /**
+   * Update the name and preferences of the current user
+   * @param data Fields to change
+   * @returns Updated user
+   */
+  updateProfile: async (data: UpdateProfileRequest): Promise<User> => {
+    const response = await api.patch<ApiUserResponse>('/users/me', data);
+    return handleResponse<User>(response);
+  },
+
+  /**
+   * Change the password of the current user; other sessions are signed out
+   * @param currentPassword Current password
+   * @param newPassword New password
+   */
+  changePassword: async (currentPassword: string, newPassword: string): Promise<void> => {
+    const response = await api.put<ApiResponse<TokenResponse>>('/users/me/password', {
+      current_password: currentPassword,
+      new_password: newPassword,
+    });
+    storeTokens(handleResponse<TokenResponse>(response));
+  },
+
+  /**
+   * Send a verification link to a new email address; it takes effect once confirmed
+   * @param newEmail New email address
+   * @param password Current password
+   */
+  changeEmail: async (newEmail: string, password: string): Promise<void> => {
+    await api.post('/users/me/email', { new_email: newEmail, password });
+  },
+
+  /**
+   * Download all personal data of the current user as a JSON file
+   */
+  exportData: async (): Promise<void> => {
+    const response = await api.get<Blob>('/users/me/export', { responseType: 'blob' });
+    const disposition = response.headers['content-disposition'] as string | undefined;
+    const filename = disposition?.match(/filename="([^"]+)"/)?.[1] ?? 'sub-pal-export.json';
+
+    const url = URL.createObjectURL(response.data);
+    const link = document.createElement('a');
+    link.href = url;
+    link.download = filename;
+    link.click();
+    URL.revokeObjectURL(url);
+  },
+
+  /**
+   * Delete the account of the current user; tokens are discarded afterwards
+   * @param password Current password
+   * @returns Whether the account is gone or when it will be purged
+   */
+  deleteAccount: async (password: string): Promise<AccountDeletion> => {
+    const response = await api.delete<ApiResponse<AccountDeletion>>('/users/me', { data: { password } });
+    const deletion = handleResponse<AccountDeletion>(response);
+    removeSecureToken('token');
+    removeSecureToken('refresh_token');
+    return deletion;
+  },
+
+  /**
+   * Get the two-factor authentication state of the current user
+   */
+  getTwoFactorStatus: async (): Promise<TwoFactorStatus> => {
+    const response = await api.get<ApiResponse<TwoFactorStatus>>('/users/me/2fa');
+    return handleResponse<TwoFactorStatus>(response);
+  },
+
+  /**
+   * Start enabling two-factor authentication
+   * @returns Secret and otpauth URI for the authenticator app
+   */
+  enrollTwoFactor: async (): Promise<TwoFactorEnrollment> => {
+    const response = await api.post<ApiResponse<TwoFactorEnrollment>>('/users/me/2fa/enroll');
+    return handleResponse<TwoFactorEnrollment>(response);
+  },
+
+  /**
+   * Enable two-factor authentication with a code from the authenticator app
+   * @param code Code from the authenticator app
+   * @returns Recovery codes, shown only once
+   */
+  confirmTwoFactor: async (code: string): Promise<RecoveryCodes> => {
+    const response = await api.post<ApiResponse<RecoveryCodes>>('/users/me/2fa/confirm', { code });
+    return handleResponse<RecoveryCodes>(response);
+  },
+
+  /**
+   * Disable two-factor authentication
+   * @param password Password of the current user
+   * @param code Code from the authenticator app, or a recovery code
+   */
+  disableTwoFactor: async (password: string, code: string): Promise<void> => {
+    await api.post('/users/me/2fa/disable', { password, code });
+  },
+
+  /**
+   * Replace the recovery codes of the current user
+   * @param code Code from the authenticator app, or a recovery code
+   * @returns New recovery codes, shown only once
+   */
+  regenerateRecoveryCodes: async (code: string): Promise<RecoveryCodes> => {
+    const response = await api.post<ApiResponse<RecoveryCodes>>('/users/me/2fa/recovery-codes', { code });
+    return handleResponse<RecoveryCodes>(response);
+  },
+
+  /**
+   * List the API tokens of the current user
+   */
+  getApiTokens: async (): Promise<ApiToken[]> => {
+    const response = await api.get<ApiResponse<ApiToken[]>>('/users/me/tokens');
+    return handleResponse<ApiToken[]>(response);
+  },
+
+  /**
+   * Create an API token
+   * @param data Name, scope and optional expiry
+   * @returns The token, shown only once
+   */
+  createApiToken: async (data: CreateApiTokenRequest): Promise<ApiTokenWithSecret> => {
+    const response = await api.post<ApiResponse<ApiTokenWithSecret>>('/users/me/tokens', data);
+    return handleResponse<ApiTokenWithSecret>(response);
+  },
+
+  /**
+   * Revoke an API token
+   * @param id Token ID
+   */
+  revokeApiToken: async (id: string): Promise<void> => {
+    await api.delete(`/users/me/tokens/${id}`);
+  },
+
+  /**
+   * Get the devices the current user is signed in on
+   * @returns Active sessions, most recently seen first
+   */
+  getSessions: async (): Promise<Session[]> => {
+    const response = await api.get<ApiResponse<Session[]>>('/users/me/sessions');
+    return handleResponse<Session[]>(response);
+  },
+
+  /**
+   * Sign out one device
+   * @param id Session ID
+   */
+  revokeSession: async (id: string): Promise<void> => {
+    await api.delete(`/users/me/sessions/${id}`);
+  },
+
+  /**
+   * Sign out every device, this one included
+   */
+  revokeAllSessions: async (): Promise<void> => {
+    await api.delete('/users/me/sessions');
+  },
+};
+
 // ======== Subscription API Service ========
 
 export const subscriptionApi = {
@@ -118,7 +468,7 @@ export const subscriptionApi = {
    */
   getAll: async (): Promise<Subscription[]> => {
     const response = await api.get<ApiSubscriptionsResponse>('/subscriptions');
-    return response.data.subscriptions;
+    return handleResponse<Subscription[]>(response);
   },
 
   /**
@@ -127,8 +477,8 @@ export const subscriptionApi = {
    * @returns Subscription details
    */
   getById: async (id: string): Promise<Subscription> => {
-    const response = await api.get<Subscription>(`/subscriptions/${id}`);
-    return response.data;
+    const response = await api.get<ApiSubscriptionResponse>(`/subscriptions/${id}`);
+    return handleResponse<Subscription>(response);
   },
 
   /**
@@ -137,8 +487,8 @@ export const subscriptionApi = {
    * @returns Created subscription
    */
   create: async (data: CreateSubscriptionRequest): Promise<Subscription> => {
-    const response = await api.post<Subscription>('/subscriptions', data);
-    return response.data;
+    const response = await api.post<ApiSubscriptionResponse>('/subscriptions', data);
+    return handleResponse<Subscription>(response);
   },
 
   /**
@@ -149,8 +499,8 @@ export const subscriptionApi = {
    */
   update: async (id: string, data: Partial<CreateSubscriptionRequest>): Promise<Subscription> => {
     console.log("update data", data);
-    const response = await api.put<Subscription>(`/subscriptions/${id}`, data);
-    return response.data;
+    const response = await api.put<ApiSubscriptionResponse>(`/subscriptions/${id}`, data);
+    return handleResponse<Subscription>(response);
   },
 
   /**
@@ -162,25 +512,174 @@ export const subscriptionApi = {
   },
 
   /**
-   * Get subscription statistics
-   * @returns Subscription statistics
+   * Pause an active subscription, freezing its billing cycle
+   * @param id Subscription ID
+   * @returns Paused subscription
+   */
+  pause: async (id: string): Promise<Subscription> => {
+    const response = await api.post<ApiSubscriptionResponse>(`/subscriptions/${id}/pause`);
+    return handleResponse<Subscription>(response);
+  },
+
+  /**
+   * Resume a paused subscription, shifting its next billing date by the paused duration
+   * @param id Subscription ID
+   * @returns Resumed subscription
+   */
+  resume: async (id: string): Promise<Subscription> => {
+    const response = await api.post<ApiSubscriptionResponse>(`/subscriptions/${id}/resume`);
+    return handleResponse<Subscription>(response);
+  },
+
+  /**
+   * Cancel a subscription
+   * @param id Subscription ID
+   * @param effectiveDate Optional last day the subscription is in effect (YYYY-MM-DD)
+   * @returns Cancelled subscription
    */
-  getStats: async (): Promise<SubscriptionStats> => {
-    const response = await api.get<ApiStatsResponse>('/subscriptions/stats');
+  cancel: async (id: string, effectiveDate?: string): Promise<Subscription> => {
+    const response = await api.post<ApiSubscriptionResponse>(
+      `/subscriptions/${id}/cancel`,
+      effectiveDate ? { effective_date: effectiveDate } : undefined
+    );
+    return handleResponse<Subscription>(response);
+  },
+
+  /**
+   * Get subscription statistics computed on the server
+   * @param params Optional number of top subscriptions and renewal window in days
+   * @returns Subscription statistics grouped by currency
+   */
+  getStats: async (params?: { top?: number; upcoming_days?: number }): Promise<SubscriptionStats> => {
+    const response = await api.get<ApiStatsResponse>('/subscriptions/stats', { params });
     return handleResponse<SubscriptionStats>(response);
   },
 };
 
+// ======== Notification API Service ========
+
+export const notificationApi = {
+  /**
+   * Get notifications of the current user, newest first
+   * @param params Optional read filter and pagination
+   * @returns Notifications with pagination metadata
+   */
+  getAll: async (params?: NotificationListParams) => {
+    const response = await api.get<ApiPaginatedResponse<Notification>>('/notifications', { params });
+    return response.data.data;
+  },
+
+  /**
+   * Mark a notification as read
+   * @param id Notification ID
+   * @returns Updated notification
+   */
+  markRead: async (id: string): Promise<Notification> => {
+    const response = await api.post<ApiResponse<Notification>>(`/notifications/${id}/read`);
+    return handleResponse<Notification>(response);
+  },
+
+  /**
+   * Mark all notifications as read
+   * @returns Number of notifications that were unread
+   */
+  markAllRead: async (): Promise<number> => {
+    const response = await api.post<ApiResponse<{ count: number }>>('/notifications/read-all');
+    return response.data.data.count;
+  },
+};
+
+export const webhookApi = {
+  /**
+   * Get the webhook endpoints of the current user
+   * @returns Webhook endpoints
+   */
+  getAll: async (): Promise<WebhookEndpoint[]> => {
+    const response = await api.get<ApiResponse<WebhookEndpoint[]>>('/webhooks');
+    return handleResponse<WebhookEndpoint[]>(response);
+  },
+
+  /**
+   * Register a webhook endpoint
+   * @param data Endpoint URL, description and events
+   * @returns Created endpoint with its signing secret
+   */
+  create: async (data: CreateWebhookRequest): Promise<WebhookEndpointWithSecret> => {
+    const response = await api.post<ApiResponse<WebhookEndpointWithSecret>>('/webhooks', data);
+    return handleResponse<WebhookEndpointWithSecret>(response);
+  },
+
+  /**
+   * Update a webhook endpoint
+   * @param id Endpoint ID
+   * @param data Fields to change
+   * @returns Updated endpoint
+   */
+  update: async (id: string, data: UpdateWebhookRequest): Promise<WebhookEndpoint> => {
+    const response = await api.patch<ApiResponse<WebhookEndpoint>>(`/webhooks/${id}`, data);
+    return handleResponse<WebhookEndpoint>(response);
+  },
+
+  /**
+   * Delete a webhook endpoint
+   * @param id Endpoint ID
+   */
+  delete: async (id: string): Promise<void> => {
+    await api.delete(`/webhooks/${id}`);
+  },
+
+  /**
+   * Replace the signing secret of a webhook endpoint
+   * @param id Endpoint ID
+   * @returns Endpoint with its new signing secret
+   */
+  rotateSecret: async (id: string): Promise<WebhookEndpointWithSecret> => {
+    const response = await api.post<ApiResponse<WebhookEndpointWithSecret>>(
+      `/webhooks/${id}/rotate-secret`
+    );
+    return handleResponse<WebhookEndpointWithSecret>(response);
+  },
+
+  /**
+   * Get the deliveries of a webhook endpoint, newest first
+   * @param id Endpoint ID
+   * @param params Optional status filter and pagination
+   * @returns Deliveries with pagination metadata
+   */
+  getDeliveries: async (id: string, params?: WebhookDeliveryListParams) => {
+    const response = await api.get<ApiPaginatedResponse<WebhookDelivery>>(
+      `/webhooks/${id}/deliveries`,
+      { params }
+    );
+    return response.data.data;
+  },
+
+  /**
+   * Get a delivery with its payload and attempt log
+   * @param id Endpoint ID
+   * @param deliveryId Delivery ID
+   * @returns Delivery details
+   */
+  getDelivery: async (id: string, deliveryId: string): Promise<WebhookDeliveryDetail> => {
+    const response = await api.get<ApiResponse<WebhookDeliveryDetail>>(
+      `/webhooks/${id}/deliveries/${deliveryId}`
+    );
+    return handleResponse<WebhookDeliveryDetail>(response);
+  },
+};
+
 // ======== Data Transformation Utilities ========
 
-const getBillingCycleDays = (billingCycle: BillingCycle): number => {
+const getBillingCycleParts = (
+  billingCycle: BillingCycle
+): { billing_cycle_unit: BillingCycleUnit; billing_cycle_interval: number } => {
   switch (billingCycle) {
-    case "daily": return 1;
-    case "weekly": return 7;
-    case "monthly": return 30;
-    case "quarterly": return 90;
-    case "yearly": return 365;
-    default: return 30;
+    case "daily": return { billing_cycle_unit: "Day", billing_cycle_interval: 1 };
+    case "weekly": return { billing_cycle_unit: "Week", billing_cycle_interval: 1 };
+    case "quarterly": return { billing_cycle_unit: "Month", billing_cycle_interval: 3 };
+    case "yearly": return { billing_cycle_unit: "Year", billing_cycle_interval: 1 };
+    case "monthly":
+    default: return { billing_cycle_unit: "Month", billing_cycle_interval: 1 };
   }
 };
 export const subscriptionUtils = {
@@ -194,7 +693,7 @@ export const subscriptionUtils = {
     description: formData.description || undefined,
     amount: String(formData.amount),
     currency: formData.currency === "USD" ? "Usd" : "Cny",
-    billing_cycle_days: getBillingCycleDays(formData.billing_cycle),
+    ...getBillingCycleParts(formData.billing_cycle),
     category: formData.category || undefined,
     status: formData.status as SubscriptionStatus | "Active",
     start_date: formData.start_date,
@@ -208,12 +707,11 @@ export const subscriptionUtils = {
    */
   apiToComponent: (apiSubscription: Subscription): any => {
 
-    const getBillingCycleFromDays = (days: number): BillingCycle => {
-      if (days === 1) return "daily";
-      if (days === 7) return "weekly";
-      if (days === 30) return "monthly";
-      if (days === 90) return "quarterly";
-      if (days === 365) return "yearly";
+    const getBillingCycleFromApi = (unit: BillingCycleUnit, interval: number): BillingCycle => {
+      if (unit === "Day" && interval === 1) return "daily";
+      if (unit === "Week" && interval === 1) return "weekly";
+      if (unit === "Month" && interval === 3) return "quarterly";
+      if (unit === "Year" && interval === 1) return "yearly";
       return "monthly";
     };
 
@@ -223,7 +721,10 @@ export const subscriptionUtils = {
       description: apiSubscription.description,
       amount: apiSubscription.amount,
       currency: apiSubscription.currency === "Usd" ? "USD" : "CNY",
-      billingCycle: getBillingCycleFromDays(apiSubscription.billing_cycle_days),
+      billingCycle: getBillingCycleFromApi(
+        apiSubscription.billing_cycle_unit,
+        apiSubscription.billing_cycle_interval
+      ),
       nextBillingDate: new Date(apiSubscription.next_billing_date),
       startDate: apiSubscription.start_date ? new Date(apiSubscription.start_date) : undefined,
       status: apiSubscription.status,
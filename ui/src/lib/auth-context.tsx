@@ -2,7 +2,7 @@ import React, { createContext, useContext, useState, useEffect } from 'react';
 import { authApi } from './api';
 import { getSecureToken, removeSecureToken } from './secure-storage';
 import { ErrorHandler } from './error-handler';
-import type { AuthContextType, User, ApiError } from '@/types';
+import type { AuthContextType, User, ApiError, TwoFactorChallenge } from '@/types';
 
 const AuthContext = createContext<AuthContextType | undefined>(undefined);
 
@@ -24,7 +24,11 @@ export const AuthProvider: React.FC<{ children: React.ReactNode }> = ({ children
     const checkAuth = async () => {
       try {
         const token = getSecureToken('token');
-        if (token) {
+        if (!token && getSecureToken('refresh_token')) {
+          // The access token expired but the session can still be renewed
+          await authApi.refresh();
+        }
+        if (getSecureToken('token')) {
           const userData = await authApi.getCurrentUser();
           setUser(userData);
         }
@@ -43,13 +47,16 @@ export const AuthProvider: React.FC<{ children: React.ReactNode }> = ({ children
 
   const clearError = () => setError(null);
 
-  const login = async (email: string, password: string): Promise<void> => {
+  const login = async (email: string, password: string): Promise<TwoFactorChallenge | void> => {
     setIsLoading(true);
     setError(null);
 
     try {
-      const authData = await authApi.login({ email, password });
-      setUser(authData.user);
+      const loginData = await authApi.login({ email, password });
+      if ('two_factor_required' in loginData) {
+        return loginData;
+      }
+      setUser(loginData.user);
     } catch (err: any) {
       console.error('Login error details:', err);
 
@@ -62,6 +69,47 @@ export const AuthProvider: React.FC<{ children: React.ReactNode }> = ({ children
     }
   };
 
+  const completeTwoFactorLogin = async (challengeToken: string, code: string): Promise<void> => {
+    setIsLoading(true);
+    setError(null);
+
+    try {
+      const authData = await authApi.loginTwoFactor(challengeToken, code);
+      setUser(authData.user);
+    } catch (err: any) {
+      console.error('Two-factor login error details:', err);
+
+      const apiError = ErrorHandler.extractError(err);
+      setError(apiError);
+
+      throw new Error(apiError.user_message);
+    } finally {
+      setIsLoading(false);
+    }
+  };
+
+  const completeOidcLogin = async (code: string, state: string): Promise<TwoFactorChallenge | void> => {
+    setIsLoading(true);
+    setError(null);
+
+    try {
+      const loginData = await authApi.completeOidcLogin(code, state);
+      if ('two_factor_required' in loginData) {
+        return loginData;
+      }
+      setUser(loginData.user);
+    } catch (err: any) {
+      console.error('Single sign-on error details:', err);
+
+      const apiError = ErrorHandler.extractError(err);
+      setError(apiError);
+
+      throw new Error(apiError.user_message);
+    } finally {
+      setIsLoading(false);
+    }
+  };
+
   const register = async (email: string, password: string, name?: string) => {
     setIsLoading(true);
     setError(null);
@@ -81,12 +129,12 @@ export const AuthProvider: React.FC<{ children: React.ReactNode }> = ({ children
   };
 
   const logout = () => {
-    authApi.logout();
+    void authApi.logout();
     setUser(null);
   };
 
   return (
-    <AuthContext.Provider value={{ user, login, register, logout, isLoading, error, clearError }}>
+    <AuthContext.Provider value={{ user, login, completeTwoFactorLogin, completeOidcLogin, register, updateUser: setUser, logout, isLoading, error, clearError }}>
       {children}
     </AuthContext.Provider>
   );
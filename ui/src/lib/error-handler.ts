@@ -67,6 +67,17 @@ export class ErrorHandler {
     return apiError.suggestions;
   }
 
+  /**
+   * Get the validation messages of individual fields, keyed by field name
+   */
+  static getFieldErrors(error: AxiosError | Error | unknown): Record<string, string> {
+    const apiError = this.extractError(error);
+    const fieldErrors = apiError.details?.field_errors;
+    return fieldErrors && typeof fieldErrors === 'object'
+      ? (fieldErrors as Record<string, string>)
+      : {};
+  }
+
   /**
    * Check if error is an Axios error
    */
@@ -86,6 +97,7 @@ export class ErrorHandler {
       validation: 'validation',
       not_found: 'not_found',
       conflict: 'conflict',
+      rate_limit: 'rate_limit',
       server: 'server',
       network: 'network',
     };
@@ -157,7 +169,7 @@ export class ErrorHandler {
           break;
 
         case 429:
-          category = 'server';
+          category = 'rate_limit';
           code = 'RATE_LIMITED';
           userMessage = 'Too many requests. Please wait and try again.';
           suggestions = [
@@ -1,4 +1,4 @@
-import { Subscription } from '@/types';
+import { Subscription, BillingCycleUnit } from '@/types';
 
 export interface CategoryData {
   name: string;
@@ -40,7 +40,7 @@ export const calculateStatistics = (
 
   // Calculate costs normalized to monthly amounts
   const monthlyTotalCost = activeSubscriptions.reduce((total, sub) => {
-    const monthlyAmount = normalizeToMonthly(sub.amount, sub.billing_cycle_days);
+    const monthlyAmount = normalizeToMonthly(sub.amount, sub.billing_cycle_unit, sub.billing_cycle_interval);
     return total + monthlyAmount;
   }, 0);
 
@@ -48,7 +48,7 @@ export const calculateStatistics = (
   const categoryMap = new Map<string, { total: number; count: number }>();
   activeSubscriptions.forEach(sub => {
     const category = sub.category || 'Uncategorized';
-    const monthlyAmount = normalizeToMonthly(sub.amount, sub.billing_cycle_days);
+    const monthlyAmount = normalizeToMonthly(sub.amount, sub.billing_cycle_unit, sub.billing_cycle_interval);
 
     if (categoryMap.has(category)) {
       const existing = categoryMap.get(category)!;
@@ -71,7 +71,7 @@ export const calculateStatistics = (
   const topSubscriptions: TopSubscription[] = activeSubscriptions
     .map(sub => ({
       name: sub.name,
-      cost: normalizeToMonthly(sub.amount, sub.billing_cycle_days),
+      cost: normalizeToMonthly(sub.amount, sub.billing_cycle_unit, sub.billing_cycle_interval),
       category: sub.category
     }))
     .sort((a, b) => b.cost - a.cost)
@@ -83,7 +83,7 @@ export const calculateStatistics = (
   // Status breakdown
   const statusMap = new Map<string, { count: number; cost: number }>();
   subscriptions.forEach(sub => {
-    const monthlyAmount = normalizeToMonthly(sub.amount, sub.billing_cycle_days);
+    const monthlyAmount = normalizeToMonthly(sub.amount, sub.billing_cycle_unit, sub.billing_cycle_interval);
 
     if (statusMap.has(sub.status)) {
       const existing = statusMap.get(sub.status)!;
@@ -121,10 +121,19 @@ export const calculateStatistics = (
 /**
  * Normalize subscription amount to monthly cost
  */
-const normalizeToMonthly = (amount: number, billingCycleDays: number): number => {
-  // Convert billing cycle to monthly equivalent
-  const monthlyMultiplier = 30 / billingCycleDays;
-  return amount * monthlyMultiplier;
+const normalizeToMonthly = (
+  amount: number,
+  unit: BillingCycleUnit,
+  interval: number
+): number => {
+  // Convert billing cycle to monthly equivalent via the number of cycles per year
+  const cyclesPerYear: Record<BillingCycleUnit, number> = {
+    Day: 365,
+    Week: 52,
+    Month: 12,
+    Year: 1,
+  };
+  return (amount * cyclesPerYear[unit]) / (12 * interval);
 };
 
 /**
@@ -165,7 +174,7 @@ const generateMonthlyTrendData = (
 
       // Check if subscription was active during this month
       if (startDate <= date && (!endDate || endDate >= date) && sub.status === 'Active') {
-        return total + normalizeToMonthly(sub.amount, sub.billing_cycle_days);
+        return total + normalizeToMonthly(sub.amount, sub.billing_cycle_unit, sub.billing_cycle_interval);
       }
       return total;
     }, 0);
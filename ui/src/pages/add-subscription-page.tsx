@@ -6,6 +6,7 @@ import { AddSubscriptionForm } from "@/components/subscription/add-subscription-
 import { Button } from "@/components/ui/button";
 import { ChevronLeft } from "lucide-react";
 import { subscriptionApi, subscriptionUtils } from "@/lib/api";
+import { ErrorHandler } from "@/lib/error-handler";
 import type { 
   SubscriptionDisplay, 
   Subscription as ApiSubscription,
@@ -60,13 +61,12 @@ export function AddSubscriptionPage() {
       }
       navigate("/dashboard");
     } catch (err: any) {
-      setError(err.response?.data?.message || 'Failed to save subscription');
+      const fieldErrors = Object.values(ErrorHandler.getFieldErrors(err));
+      setError(fieldErrors.length > 1 ? fieldErrors.join(' ') : ErrorHandler.getUserMessage(err));
       console.error('Error saving subscription:', err);
     }
   };
 
-  // (removed) legacy helper left intentionally blank; using form's billing_cycle_days directly
-
   const handleCancel = () => {
     navigate(-1);
   };
@@ -0,0 +1,11 @@
+import { ForgotPasswordForm } from '../components/auth/forgot-password-form';
+
+export function ForgotPasswordPage() {
+  return (
+    <div className="flex min-h-screen items-center justify-center bg-background">
+      <div className="w-full max-w-md rounded-lg border bg-card p-8 shadow-sm">
+        <ForgotPasswordForm />
+      </div>
+    </div>
+  );
+}
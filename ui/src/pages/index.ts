@@ -1,6 +1,11 @@
 export { LoginPage } from './login-page';
 export { RegisterPage } from './register-page';
+export { ForgotPasswordPage } from './forgot-password-page';
+export { ResetPasswordPage } from './reset-password-page';
+export { VerifyEmailPage } from './verify-email-page';
+export { OidcCallbackPage } from './oidc-callback-page';
 export { DashboardPage } from './dashboard-page';
 export { SubscriptionPage } from './subscription-page';
 export { AddSubscriptionPage } from './add-subscription-page';
 export { StatisticsPage } from './statistics-page';
+export { ProfilePage } from './profile-page';
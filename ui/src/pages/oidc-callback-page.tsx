@@ -0,0 +1,11 @@
+import { OidcCallback } from '../components/auth/oidc-callback';
+
+export function OidcCallbackPage() {
+  return (
+    <div className="flex min-h-screen items-center justify-center bg-background">
+      <div className="w-full max-w-md rounded-lg border bg-card p-8 shadow-sm">
+        <OidcCallback />
+      </div>
+    </div>
+  );
+}
@@ -0,0 +1,99 @@
+import { Header } from "@/components/subscription/header";
+import { Navigation } from "@/components/subscription/navigation";
+import { Card, CardContent, CardDescription, CardHeader, CardTitle } from "@/components/ui/card";
+import { VerifyEmailBanner } from "@/components/auth/verify-email-banner";
+import { ProfileForm } from "@/components/profile/profile-form";
+import { ChangePasswordForm } from "@/components/profile/change-password-form";
+import { ChangeEmailForm } from "@/components/profile/change-email-form";
+import { TwoFactorForm } from "@/components/profile/two-factor-form";
+import { ApiTokensForm } from "@/components/profile/api-tokens-form";
+import { SessionsForm } from "@/components/profile/sessions-form";
+import { DeleteAccountForm } from "@/components/profile/delete-account-form";
+import { useAuth } from "@/lib/auth-context";
+
+export function ProfilePage() {
+  const { user } = useAuth();
+
+  return (
+    <div className="flex min-h-screen flex-col">
+      <Header />
+
+      <main className="flex-1 w-full max-w-3xl mx-auto px-4 sm:px-6 lg:px-8 py-6 space-y-6 pb-16">
+        <VerifyEmailBanner />
+
+        <Card>
+          <CardHeader>
+            <CardTitle>Profile</CardTitle>
+            <CardDescription>Your name and preferences</CardDescription>
+          </CardHeader>
+          <CardContent>
+            {/* Remount when the user loads so the form picks up the saved values */}
+            <ProfileForm key={user?.id} />
+          </CardContent>
+        </Card>
+
+        <Card>
+          <CardHeader>
+            <CardTitle>Email</CardTitle>
+            <CardDescription>Currently {user?.email}</CardDescription>
+          </CardHeader>
+          <CardContent>
+            <ChangeEmailForm />
+          </CardContent>
+        </Card>
+
+        <Card>
+          <CardHeader>
+            <CardTitle>Password</CardTitle>
+            <CardDescription>Changing your password signs out your other devices</CardDescription>
+          </CardHeader>
+          <CardContent>
+            <ChangePasswordForm />
+          </CardContent>
+        </Card>
+
+        <Card>
+          <CardHeader>
+            <CardTitle>Two-factor authentication</CardTitle>
+            <CardDescription>Require a code from an authenticator app when signing in</CardDescription>
+          </CardHeader>
+          <CardContent>
+            <TwoFactorForm />
+          </CardContent>
+        </Card>
+
+        <Card>
+          <CardHeader>
+            <CardTitle>Devices</CardTitle>
+            <CardDescription>Where you are signed in</CardDescription>
+          </CardHeader>
+          <CardContent>
+            <SessionsForm />
+          </CardContent>
+        </Card>
+
+        <Card>
+          <CardHeader>
+            <CardTitle>API tokens</CardTitle>
+            <CardDescription>Let scripts and automation access your subscriptions</CardDescription>
+          </CardHeader>
+          <CardContent>
+            <ApiTokensForm />
+          </CardContent>
+        </Card>
+
+        <Card>
+          <CardHeader>
+            <CardTitle>Your data</CardTitle>
+            <CardDescription>Take your data with you or delete your account</CardDescription>
+          </CardHeader>
+          <CardContent>
+            <DeleteAccountForm />
+          </CardContent>
+        </Card>
+      </main>
+
+      <Navigation />
+    </div>
+  );
+}
@@ -0,0 +1,11 @@
+import { ResetPasswordForm } from '../components/auth/reset-password-form';
+
+export function ResetPasswordPage() {
+  return (
+    <div className="flex min-h-screen items-center justify-center bg-background">
+      <div className="w-full max-w-md rounded-lg border bg-card p-8 shadow-sm">
+        <ResetPasswordForm />
+      </div>
+    </div>
+  );
+}
@@ -7,14 +7,12 @@ import { SubscriptionTable } from "@/components/subscription/subscription-table"
 import { SubscriptionCards } from "@/components/subscription/subscription-cards";
 import { Button } from "@/components/ui/button";
 import { ConfirmDeleteDialog } from "@/components/ui/confirm-delete-dialog";
+import { VerifyEmailBanner } from "@/components/auth/verify-email-banner";
 import { Plus, BarChart3 } from "lucide-react";
 import { subscriptionApi, subscriptionUtils } from "@/lib/api";
 import type {
-  SubscriptionCurrency,
-  SubscriptionStatus,
   Subscription as ApiSubscription,
-  SubscriptionDisplay,
-  SubscriptionFormValues
+  SubscriptionDisplay
 } from "@/types";
 
 // Use utility function for mapping API subscription to component format
@@ -94,23 +92,10 @@ export function SubscriptionPage() {
       const subscription = subscriptions.find(sub => sub.id === id);
       if (!subscription) return;
 
-      // Convert component status to API status and create proper update request
-      const newStatus = subscription.status === "Active" ? "Paused" : "Active";
-      console.log("newStatus, subscription", newStatus, subscription);
-      const formData = {
-        name: subscription.name,
-        description: subscription.description,
-        amount: subscription.amount.toString(),
-        currency: subscription.currency as SubscriptionCurrency,
-        billing_cycle: subscription.billingCycle,
-        category: subscription.category,
-        status: newStatus as SubscriptionStatus,
-        start_date: subscription.startDate?.toISOString().split('T')[0],
-        color: subscription.color,
-      } as SubscriptionFormValues;
-      const updateData = subscriptionUtils.formDataToApi(formData);
-
-      const updatedApiSubscription = await subscriptionApi.update(id, updateData);
+      // Status changes go through the dedicated pause/resume actions
+      const updatedApiSubscription = subscription.status === "Active"
+        ? await subscriptionApi.pause(id)
+        : await subscriptionApi.resume(id);
       console.log("updatedApiSubscription", updatedApiSubscription);
       const updatedSubscription = mapApiSubscriptionToComponent(updatedApiSubscription);
 
@@ -118,7 +103,7 @@ export function SubscriptionPage() {
         sub.id === id ? updatedSubscription : sub
       ));
     } catch (err: any) {
-      setError(err.response?.data?.message || 'Failed to update subscription status');
+      setError(err.response?.data?.error?.message || 'Failed to update subscription status');
       console.error('Error updating subscription status:', err);
     }
   };
@@ -159,6 +144,8 @@ export function SubscriptionPage() {
       <Header />
 
       <main className="flex-1 w-full max-w-7xl mx-auto px-4 sm:px-6 lg:px-8 py-6 space-y-6 pb-16">
+        <VerifyEmailBanner />
+
         <div className="flex flex-col sm:flex-row sm:items-center sm:justify-between gap-4">
           <h1 className="text-3xl font-bold tracking-tight">Subscriptions</h1>
 
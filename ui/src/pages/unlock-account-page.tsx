@@ -0,0 +1,11 @@
+import { UnlockAccount } from '../components/auth/unlock-account';
+
+export function UnlockAccountPage() {
+  return (
+    <div className="flex min-h-screen items-center justify-center bg-background">
+      <div className="w-full max-w-md rounded-lg border bg-card p-8 shadow-sm">
+        <UnlockAccount />
+      </div>
+    </div>
+  );
+}
@@ -0,0 +1,11 @@
+import { VerifyEmail } from '../components/auth/verify-email';
+
+export function VerifyEmailPage() {
+  return (
+    <div className="flex min-h-screen items-center justify-center bg-background">
+      <div className="w-full max-w-md rounded-lg border bg-card p-8 shadow-sm">
+        <VerifyEmail />
+      </div>
+    </div>
+  );
+}
@@ -9,7 +9,7 @@ export interface ApiError {
   message: string;
   user_message: string;
   code: string;
-  category: 'auth' | 'validation' | 'not_found' | 'conflict' | 'server' | 'network';
+  category: 'auth' | 'validation' | 'not_found' | 'conflict' | 'rate_limit' | 'server' | 'network';
   details?: Record<string, unknown>;
   suggestions: string[];
 }
@@ -21,5 +21,14 @@ import type { Subscription, SubscriptionStats } from './subscription.types';
 export type ApiUserResponse = ApiResponse<User>;
 export type ApiAuthResponse = ApiResponse<AuthResponse>;
 export type ApiSubscriptionResponse = ApiResponse<Subscription>;
-export type ApiSubscriptionsResponse = { success: boolean; subscriptions: Subscription[] };
+export type ApiSubscriptionsResponse = ApiResponse<Subscription[]>;
 export type ApiStatsResponse = ApiResponse<SubscriptionStats>;
+
+export interface Pagination {
+  total: number;
+  page: number;
+  limit: number;
+  pages: number;
+}
+
+export type ApiPaginatedResponse<T> = ApiResponse<{ items: T[]; pagination: Pagination }>;
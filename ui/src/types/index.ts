@@ -2,5 +2,7 @@
 export * from './api.types';
 export * from './subscription.types';
 export * from './user.types';
+export * from './notification.types';
+export * from './webhook.types';
 export * from './ui.types';
 export * from './form.types';
\ No newline at end of file
@@ -0,0 +1,22 @@
+// ======== Notification Types ========
+
+export type NotificationType = 'RenewalUpcoming' | 'TrialEnding' | 'PriceChanged';
+
+export interface Notification {
+  id: string;
+  user_id: string;
+  subscription_id?: string | null;
+  notification_type: NotificationType;
+  title: string;
+  message: string;
+  due_date?: string | null;
+  read: boolean;
+  read_at?: string | null;
+  created_at: string;
+}
+
+export interface NotificationListParams {
+  read?: boolean;
+  page?: number;
+  limit?: number;
+}
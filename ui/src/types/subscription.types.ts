@@ -3,6 +3,7 @@
 export type SubscriptionCurrency = 'Usd' | 'Cny' | 'USD' | 'CNY';
 export type SubscriptionStatus = 'Active' | 'Paused' | 'Cancelled' | 'Trial';
 export type BillingCycle = 'daily' | 'weekly' | 'monthly' | 'quarterly' | 'yearly';
+export type BillingCycleUnit = 'Day' | 'Week' | 'Month' | 'Year';
 
 // ======== API Subscription Types ========
 
@@ -12,12 +13,16 @@ export interface Subscription {
   description?: string;
   amount: number;
   currency: SubscriptionCurrency;
-  billing_cycle_days: number;
+  billing_cycle_unit: BillingCycleUnit;
+  billing_cycle_interval: number;
   category?: string;
   status: SubscriptionStatus;
   start_date: string;
   next_billing_date: string;
-  end_date?: string;
+  billing_anchor_date: string;
+  paused_at?: string | null;
+  end_date?: string | null;
+  trial_end_date?: string | null;
   website?: string;
   notes?: string;
   created_at: string;
@@ -30,13 +35,15 @@ export interface CreateSubscriptionRequest {
   description?: string;
   amount: string; // Use string for BigDecimal safety
   currency: SubscriptionCurrency;
-  billing_cycle_days: number;
+  billing_cycle_unit: BillingCycleUnit;
+  billing_cycle_interval: number;
   category?: string;
   status: SubscriptionStatus;
   start_date: string;
   color?: string;
   next_billing_date?: string;
   end_date?: string;
+  trial_end_date?: string | null;
   website?: string;
   notes?: string;
 }
@@ -45,16 +52,102 @@ export interface UpdateSubscriptionRequest extends Partial<CreateSubscriptionReq
   id: string;
 }
 
+export interface CurrencyTotals {
+  currency: SubscriptionCurrency;
+  active_count: number;
+  monthly_spend: string;
+  yearly_spend: string;
+}
+
+export interface CategoryBreakdown {
+  currency: SubscriptionCurrency;
+  category: string;
+  count: number;
+  monthly_spend: string;
+}
+
+export interface StatusBreakdown {
+  currency: SubscriptionCurrency;
+  status: SubscriptionStatus;
+  count: number;
+  monthly_spend: string;
+}
+
+export interface TopSubscription {
+  id: string;
+  name: string;
+  category?: string;
+  currency: SubscriptionCurrency;
+  amount: string;
+  monthly_amount: string;
+}
+
+export interface UpcomingRenewal {
+  id: string;
+  name: string;
+  currency: SubscriptionCurrency;
+  amount: string;
+  next_billing_date: string;
+  days_until: number;
+}
+
+export interface MonthlyPayments {
+  currency: SubscriptionCurrency;
+  month: string;
+  count: number;
+  total: string;
+}
+
+// Amounts are decimal strings grouped by currency (see GET /subscriptions/stats)
 export interface SubscriptionStats {
-  total_active: number;
-  total_monthly: number;
-  total_yearly: number;
-  total_weekly: number;
-  total_custom: number;
-  monthly_cost: number;
-  yearly_cost: number;
-  weekly_cost: number;
-  custom_cost: number;
+  totals: CurrencyTotals[];
+  by_category: CategoryBreakdown[];
+  by_status: StatusBreakdown[];
+  top_subscriptions: TopSubscription[];
+  upcoming_renewals: UpcomingRenewal[];
+  payment_history: MonthlyPayments[];
+}
+
+export interface PaymentRecord {
+  id: string;
+  subscription_id: string;
+  user_id: string;
+  amount: string;
+  currency: SubscriptionCurrency;
+  paid_date: string;
+  cycle_start: string;
+  cycle_end: string;
+  note?: string;
+  projected: boolean;
+  created_at: string;
+}
+
+export type SubscriptionEventType =
+  | 'Created'
+  | 'Updated'
+  | 'Repriced'
+  | 'Paused'
+  | 'Resumed'
+  | 'Cancelled'
+  | 'Renewed';
+
+export interface SubscriptionEvent {
+  id: string;
+  subscription_id: string;
+  user_id: string;
+  event_type: SubscriptionEventType;
+  before: Record<string, unknown> | null;
+  after: Record<string, unknown> | null;
+  note?: string;
+  created_at: string;
+}
+
+export interface CreatePaymentRequest {
+  amount?: string;
+  currency?: SubscriptionCurrency;
+  paid_date?: string;
+  cycle_start?: string;
+  note?: string;
 }
 
 // ======== Frontend Subscription Types (for components) ========
@@ -3,16 +3,110 @@
 export interface User {
   id: string;
   email: string;
+  email_verified_at?: string | null;
+  two_factor_enabled?: boolean;
   name?: string;
   preferences?: Record<string, unknown>;
 }
 
+export interface UserPreferences {
+  default_currency?: 'Usd' | 'Cny' | null;
+  timezone?: string | null;
+  reminder_lead_days?: number | null;
+  locale?: string | null;
+  email_reminders?: boolean | null;
+}
+
+// Omitted fields stay unchanged; null preferences reset to their default
+export interface UpdateProfileRequest {
+  name?: string;
+  preferences?: UserPreferences;
+}
+
+export interface AccountDeletion {
+  deleted: boolean;
+  deletion_scheduled_at: string | null;
+}
+
 export interface AuthResponse {
   token: string;
   refresh_token: string;
   user: User;
 }
 
+// Returned by login instead of tokens when the account uses two-factor authentication
+export interface TwoFactorChallenge {
+  two_factor_required: true;
+  challenge_token: string;
+  expires_in: number;
+}
+
+export type LoginResponse = AuthResponse | TwoFactorChallenge;
+
+export interface OidcProviderInfo {
+  enabled: boolean;
+  provider_name: string | null;
+}
+
+export interface OidcAuthorization {
+  authorization_url: string;
+  state: string;
+}
+
+export interface TwoFactorStatus {
+  enabled: boolean;
+  enabled_at: string | null;
+  recovery_codes_remaining: number;
+}
+
+export interface TwoFactorEnrollment {
+  secret: string;
+  otpauth_uri: string;
+}
+
+export interface RecoveryCodes {
+  recovery_codes: string[];
+}
+
+export type ApiTokenScope = 'read' | 'read_write';
+
+export interface ApiToken {
+  id: string;
+  name: string;
+  scope: ApiTokenScope;
+  token_prefix: string;
+  expires_at: string | null;
+  last_used_at: string | null;
+  created_at: string;
+}
+
+// The token itself is only returned when it is created
+export interface ApiTokenWithSecret extends ApiToken {
+  token: string;
+}
+
+export interface CreateApiTokenRequest {
+  name: string;
+  scope: ApiTokenScope;
+  expires_in_days?: number;
+}
+
+// A device the user is signed in on
+export interface Session {
+  id: string;
+  user_agent: string | null;
+  ip_address: string | null;
+  created_at: string;
+  last_seen_at: string;
+  // Whether this is the session of the current browser
+  current: boolean;
+}
+
+export interface TokenResponse {
+  token: string;
+  refresh_token: string;
+}
+
 export interface RegisterRequest {
   email: string;
   password: string;
@@ -29,8 +123,13 @@ import type { ApiError } from './api.types';
 
 export interface AuthContextType {
   user: User | null;
-  login: (email: string, password: string) => Promise<void>;
+  // Resolves with a challenge when a two-factor code is still needed
+  login: (email: string, password: string) => Promise<TwoFactorChallenge | void>;
+  completeTwoFactorLogin: (challengeToken: string, code: string) => Promise<void>;
+  // Resolves with a challenge when a two-factor code is still needed
+  completeOidcLogin: (code: string, state: string) => Promise<TwoFactorChallenge | void>;
   register: (email: string, password: string, name?: string) => Promise<void>;
+  updateUser: (user: User) => void;
   logout: () => void;
   isLoading: boolean;
   error: ApiError | null;
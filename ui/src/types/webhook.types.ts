@@ -0,0 +1,78 @@
+// ======== Webhook Types ========
+
+export type WebhookEventType =
+  | 'subscription.created'
+  | 'subscription.updated'
+  | 'subscription.paused'
+  | 'subscription.resumed'
+  | 'subscription.cancelled'
+  | 'subscription.renewed'
+  | 'subscription.deleted'
+  | 'subscription.renewal_upcoming'
+  | 'subscription.trial_ending';
+
+export type WebhookDeliveryStatus = 'Pending' | 'Delivered' | 'Failed';
+
+export interface WebhookEndpoint {
+  id: string;
+  user_id: string;
+  url: string;
+  description?: string | null;
+  // empty for all events
+  event_types: WebhookEventType[];
+  active: boolean;
+  created_at: string;
+  updated_at: string;
+}
+
+// Only returned when an endpoint is created or its secret is rotated
+export interface WebhookEndpointWithSecret extends WebhookEndpoint {
+  secret: string;
+}
+
+export interface CreateWebhookRequest {
+  url: string;
+  description?: string;
+  event_types?: WebhookEventType[];
+}
+
+export interface UpdateWebhookRequest {
+  url?: string;
+  description?: string;
+  event_types?: WebhookEventType[];
+  active?: boolean;
+}
+
+export interface WebhookDelivery {
+  id: string;
+  endpoint_id: string;
+  event_id: string;
+  event_type: WebhookEventType;
+  status: WebhookDeliveryStatus;
+  attempts: number;
+  next_attempt_at?: string | null;
+  last_attempt_at?: string | null;
+  delivered_at?: string | null;
+  created_at: string;
+}
+
+export interface WebhookDeliveryAttempt {
+  id: string;
+  attempt: number;
+  response_status?: number | null;
+  response_body?: string | null;
+  error?: string | null;
+  duration_ms: number;
+  created_at: string;
+}
+
+export interface WebhookDeliveryDetail extends WebhookDelivery {
+  payload: Record<string, unknown>;
+  attempt_log: WebhookDeliveryAttempt[];
+}
+
+export interface WebhookDeliveryListParams {
+  status?: WebhookDeliveryStatus;
+  page?: number;
+  limit?: number;
+}